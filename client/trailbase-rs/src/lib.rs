@@ -9,11 +9,16 @@ use eventsource_stream::Eventsource;
 pub use futures::Stream;
 use futures::StreamExt;
 use parking_lot::RwLock;
-use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::multipart::{Form, Part};
 use reqwest::Method;
 use std::borrow::Cow;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
 
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -24,12 +29,18 @@ pub enum Error {
   Reqwest(#[from] reqwest::Error),
   #[error("JSON: {0}")]
   Json(#[from] serde_json::Error),
+  #[error("MessagePack: {0}")]
+  MessagePack(#[from] rmp_serde::decode::Error),
+  #[error("CSV: {0}")]
+  Csv(#[from] csv::Error),
   #[error("JWT: {0}")]
   Jwt(#[from] jsonwebtoken::errors::Error),
   #[error("Url: {0}")]
   Url(#[from] url::ParseError),
   #[error("Precondition: {0}")]
   Precondition(&'static str),
+  #[error("HTTP status {0}: {1}")]
+  HttpStatusWithBody(reqwest::StatusCode, serde_json::Value),
 }
 
 /// Represents the currently logged-in user.
@@ -55,12 +66,102 @@ pub struct Pagination {
   pub limit: Option<usize>,
 }
 
+/// Arguments for listing records, e.g.:
+///
+///   ListArguments::new().with_order(&["-created"]).with_search("rust")
+#[derive(Clone, Debug, Default)]
+pub struct ListArguments<'a> {
+  pagination: Pagination,
+  order: &'a [&'a str],
+  filters: &'a [&'a str],
+  search: Option<&'a str>,
+}
+
+impl<'a> ListArguments<'a> {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  pub fn with_pagination(mut self, pagination: Pagination) -> Self {
+    self.pagination = pagination;
+    return self;
+  }
+
+  /// Column names to order by, optionally prefixed by "-" for descending order.
+  pub fn with_order(mut self, order: &'a [&'a str]) -> Self {
+    self.order = order;
+    return self;
+  }
+
+  /// Filters of the form: "name[op]=value".
+  pub fn with_filters(mut self, filters: &'a [&'a str]) -> Self {
+    self.filters = filters;
+    return self;
+  }
+
+  /// Full-text search query. Requires the table to have an FTS index.
+  pub fn with_search(mut self, query: &'a str) -> Self {
+    self.search = Some(query);
+    return self;
+  }
+}
+
+/// Arguments for creating records.
+#[derive(Clone, Debug, Default)]
+pub struct CreateArguments {
+  /// Whether the server should respond with the full record rather than only its id.
+  pub return_record: bool,
+}
+
+/// Arguments for updating records, e.g.:
+///
+///   UpdateArguments::new().with_etag(&etag)
+#[derive(Clone, Debug, Default)]
+pub struct UpdateArguments<'a> {
+  etag: Option<&'a str>,
+}
+
+impl<'a> UpdateArguments<'a> {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  /// Only update if the record's current ETag matches, e.g. as returned by `read_with_etag`.
+  /// Otherwise the update fails with "412 Precondition Failed".
+  pub fn with_etag(mut self, etag: &'a str) -> Self {
+    self.etag = Some(etag);
+    return self;
+  }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DbEvent {
   Update(Option<serde_json::Value>),
   Insert(Option<serde_json::Value>),
   Delete(Option<serde_json::Value>),
   Error(String),
+  /// Emitted by `RecordApi::subscribe_resilient` after re-establishing a dropped connection.
+  Reconnected,
+}
+
+/// Reconnect behavior of `RecordApi::subscribe_resilient`.
+#[derive(Clone, Debug)]
+pub struct SubscribeOptions {
+  pub initial_reconnect_delay: Duration,
+  pub max_reconnect_delay: Duration,
+  /// Number of consecutive failed connection attempts after which the stream ends. Retries
+  /// indefinitely if unset.
+  pub max_retries: Option<u32>,
+}
+
+impl Default for SubscribeOptions {
+  fn default() -> Self {
+    return Self {
+      initial_reconnect_delay: Duration::from_millis(500),
+      max_reconnect_delay: Duration::from_secs(30),
+      max_retries: None,
+    };
+  }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -69,6 +170,34 @@ pub struct ListResponse<T> {
   pub records: Vec<T>,
 }
 
+/// Single entry of a record's audit trail as returned by `RecordApi::history`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuditEntry<T = serde_json::Value> {
+  pub id: i64,
+  /// One of "INSERT", "UPDATE" or "DELETE".
+  pub operation: String,
+  pub old_data: Option<T>,
+  pub new_data: Option<T>,
+  /// Id of the user who made the change, if known.
+  pub changed_by: Option<String>,
+  pub changed_at: i64,
+}
+
+/// Outcome of a CSV import as returned by `RecordApi::import_csv`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImportResult {
+  pub inserted: usize,
+  pub failed: usize,
+  pub errors: Vec<ImportError>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImportError {
+  /// 1-based index of the failed data row not counting the header. Zero refers to the header.
+  pub row: usize,
+  pub message: String,
+}
+
 pub trait RecordId<'a> {
   fn serialized_id(self) -> Cow<'a, str>;
 }
@@ -132,6 +261,27 @@ impl ThinClient {
 
     return Ok(self.client.execute(request).await?);
   }
+
+  async fn fetch_multipart(
+    &self,
+    path: &str,
+    headers: HeaderMap,
+    form: Form,
+  ) -> Result<reqwest::Response, Error> {
+    assert!(path.starts_with("/"));
+
+    let mut url = self.url.clone();
+    url.set_path(path);
+
+    let request = self
+      .client
+      .request(Method::POST, url)
+      .headers(headers)
+      .multipart(form)
+      .build()?;
+
+    return Ok(self.client.execute(request).await?);
+  }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -153,21 +303,49 @@ fn decode_auth_token<T: DeserializeOwned>(token: &str) -> Result<T, Error> {
   return Ok(jsonwebtoken::decode::<T>(token, &decoding_key, &validation).map(|data| data.claims)?);
 }
 
+/// MIME type for requesting MessagePack-encoded records, see [RecordApi::with_accept].
+pub const MIME_MSGPACK: &str = "application/msgpack";
+/// MIME type for requesting CSV-encoded records, see [RecordApi::with_accept].
+pub const MIME_CSV: &str = "text/csv";
+
 #[derive(Clone)]
 pub struct RecordApi {
   client: Arc<ClientState>,
   name: String,
+  accept: Option<&'static str>,
 }
 
 impl RecordApi {
   // TODO: add subscription APIs.
 
+  /// Requests listed and read records in the given encoding, e.g. [MIME_MSGPACK] for more compact
+  /// responses. Responses are decoded accordingly.
+  ///
+  /// NOTE: CSV listings don't contain a pagination cursor.
+  pub fn with_accept(mut self, mime: &'static str) -> Self {
+    self.accept = Some(mime);
+    return self;
+  }
+
+  fn accept_headers(&self) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(mime) = self.accept {
+      headers.insert(ACCEPT, HeaderValue::from_static(mime));
+    }
+    return headers;
+  }
+
   pub async fn list<T: DeserializeOwned>(
     &self,
-    pagination: Pagination,
-    order: &[&str],
-    filters: &[&str],
+    args: ListArguments<'_>,
   ) -> Result<ListResponse<T>, Error> {
+    let ListArguments {
+      pagination,
+      order,
+      filters,
+      search,
+    } = args;
+
     let mut params: Vec<(Cow<'static, str>, Cow<'static, str>)> = vec![];
     if let Some(cursor) = pagination.cursor {
       params.push((Cow::Borrowed("cursor"), Cow::Owned(cursor)));
@@ -192,54 +370,125 @@ impl RecordApi {
       ));
     }
 
+    if let Some(search) = search {
+      params.push((Cow::Borrowed("search"), Cow::Owned(search.to_string())));
+    }
+
     let response = self
       .client
-      .fetch(
+      .fetch_with_headers(
         &format!("/{RECORD_API}/{}", self.name),
+        self.accept_headers(),
         Method::GET,
         None::<&()>,
         Some(&params),
       )
       .await?;
 
-    return Ok(response.json().await?);
+    if content_type(&response).starts_with(MIME_CSV) {
+      return Ok(ListResponse {
+        cursor: None,
+        records: decode_csv(response).await?,
+      });
+    }
+    return decode_response(response).await;
   }
 
   pub async fn read<'a, T: DeserializeOwned>(&self, id: impl RecordId<'a>) -> Result<T, Error> {
+    return Ok(self.read_with_etag(id).await?.0);
+  }
+
+  /// Reads a record alongside its ETag, which can be used for conditional updates.
+  pub async fn read_with_etag<'a, T: DeserializeOwned>(
+    &self,
+    id: impl RecordId<'a>,
+  ) -> Result<(T, Option<String>), Error> {
     let response = self
       .client
-      .fetch(
+      .fetch_with_headers(
         &format!(
           "/{RECORD_API}/{name}/{id}",
           name = self.name,
           id = id.serialized_id()
         ),
+        self.accept_headers(),
         Method::GET,
         None::<&()>,
         None,
       )
       .await?;
 
-    return Ok(response.json().await?);
+    let etag = response
+      .headers()
+      .get(reqwest::header::ETAG)
+      .and_then(|v| v.to_str().ok())
+      .map(|v| v.to_string());
+
+    if content_type(&response).starts_with(MIME_CSV) {
+      let Some(record) = decode_csv(response).await?.into_iter().next() else {
+        return Err(Error::Precondition("Empty CSV response"));
+      };
+      return Ok((record, etag));
+    }
+    return Ok((decode_response(response).await?, etag));
   }
 
   pub async fn create<T: Serialize>(&self, record: T) -> Result<String, Error> {
+    let (id, _) = self
+      .create_with_args::<T, serde_json::Value>(record, CreateArguments::default())
+      .await?;
+    return Ok(id);
+  }
+
+  /// Creates a record and returns its id alongside the record as stored, e.g. including defaults,
+  /// saving a subsequent `read`.
+  pub async fn create_returning<T: Serialize + DeserializeOwned>(
+    &self,
+    record: T,
+  ) -> Result<(String, T), Error> {
+    let (id, record) = self
+      .create_with_args::<T, T>(
+        record,
+        CreateArguments {
+          return_record: true,
+        },
+      )
+      .await?;
+    return Ok((
+      id,
+      record.ok_or(Error::Precondition("Missing record in response"))?,
+    ));
+  }
+
+  async fn create_with_args<T: Serialize, R: DeserializeOwned>(
+    &self,
+    record: T,
+    args: CreateArguments,
+  ) -> Result<(String, Option<R>), Error> {
+    let params: Vec<(Cow<'static, str>, Cow<'static, str>)> = if args.return_record {
+      vec![(Cow::Borrowed("return"), Cow::Borrowed("representation"))]
+    } else {
+      vec![]
+    };
+
     let response = self
       .client
       .fetch(
         &format!("/{RECORD_API}/{name}", name = self.name),
         Method::POST,
         Some(&record),
-        None,
+        Some(&params),
       )
       .await?;
 
     #[derive(Deserialize)]
-    pub struct RecordIdResponse {
+    pub struct RecordIdResponse<R> {
       pub id: String,
+      pub record: Option<R>,
     }
 
-    return Ok(response.json::<RecordIdResponse>().await?.id);
+    let response = response.json::<RecordIdResponse<R>>().await?;
+    return Ok((response.id, response.record));
   }
 
   pub async fn update<'a, T: Serialize>(
@@ -247,7 +496,19 @@ impl RecordApi {
     id: impl RecordId<'a>,
     record: T,
   ) -> Result<(), Error> {
-    self
+    return self
+      .update_with_args(id, record, UpdateArguments::default())
+      .await;
+  }
+
+  /// Updates a record and returns the record as stored, saving a subsequent `read`.
+  pub async fn update_returning<'a, T: Serialize + DeserializeOwned>(
+    &self,
+    id: impl RecordId<'a>,
+    record: T,
+  ) -> Result<T, Error> {
+    let params = [(Cow::Borrowed("return"), Cow::Borrowed("representation"))];
+    let response = self
       .client
       .fetch(
         &format!(
@@ -257,6 +518,38 @@ impl RecordApi {
         ),
         Method::PATCH,
         Some(&record),
+        Some(&params),
+      )
+      .await?;
+
+    return Ok(response.json().await?);
+  }
+
+  pub async fn update_with_args<'a, T: Serialize>(
+    &self,
+    id: impl RecordId<'a>,
+    record: T,
+    args: UpdateArguments<'_>,
+  ) -> Result<(), Error> {
+    let mut headers = HeaderMap::new();
+    if let Some(etag) = args.etag {
+      headers.insert(
+        reqwest::header::IF_MATCH,
+        HeaderValue::from_str(etag).map_err(|_| Error::Precondition("Invalid ETag"))?,
+      );
+    }
+
+    self
+      .client
+      .fetch_with_headers(
+        &format!(
+          "/{RECORD_API}/{name}/{id}",
+          name = self.name,
+          id = id.serialized_id()
+        ),
+        headers,
+        Method::PATCH,
+        Some(&record),
         None,
       )
       .await?;
@@ -264,6 +557,65 @@ impl RecordApi {
     return Ok(());
   }
 
+  /// Updates multiple records in a single transaction, i.e. either all or none are updated.
+  /// Returns the number of updated records.
+  ///
+  /// NOTE: Assumes the table's primary key column to be named "id".
+  pub async fn update_bulk<'a, T: Serialize>(
+    &self,
+    records: &[(impl RecordId<'a> + Clone, T)],
+  ) -> Result<usize, Error> {
+    let mut body: Vec<serde_json::Value> = Vec::with_capacity(records.len());
+    for (id, record) in records {
+      let serde_json::Value::Object(mut record) = serde_json::to_value(record)? else {
+        return Err(Error::Precondition("Record is not an object"));
+      };
+      record.insert(
+        "id".to_string(),
+        serde_json::Value::String(id.clone().serialized_id().to_string()),
+      );
+      body.push(serde_json::Value::Object(record));
+    }
+
+    let response = self
+      .client
+      .fetch(
+        &format!("/{RECORD_API}/{name}", name = self.name),
+        Method::PATCH,
+        Some(&body),
+        None,
+      )
+      .await?;
+
+    #[derive(Deserialize)]
+    struct UpdateBulkResponse {
+      updated: usize,
+    }
+
+    return Ok(response.json::<UpdateBulkResponse>().await?.updated);
+  }
+
+  /// Bulk-creates records from CSV `data`, whose first row contains the column names. The data is
+  /// streamed to the server, which inserts rows in batches and reports failing rows.
+  pub async fn import_csv(
+    &self,
+    data: impl AsyncRead + Send + Sync + 'static,
+  ) -> Result<ImportResult, Error> {
+    let part = Part::stream(reqwest::Body::wrap_stream(ReaderStream::new(data)))
+      .file_name("import.csv")
+      .mime_str("text/csv")?;
+
+    let response = self
+      .client
+      .fetch_multipart(
+        &format!("/{RECORD_API}/{name}/import", name = self.name),
+        Form::new().part("file", part),
+      )
+      .await?;
+
+    return Ok(response.json().await?);
+  }
+
   pub async fn delete<'a>(&self, id: impl RecordId<'a>) -> Result<(), Error> {
     self
       .client
@@ -282,10 +634,83 @@ impl RecordApi {
     return Ok(());
   }
 
+  /// Restores a soft-deleted record. Requires the API to be configured with `soft_delete`.
+  pub async fn restore<'a>(&self, id: impl RecordId<'a>) -> Result<(), Error> {
+    self
+      .client
+      .fetch(
+        &format!(
+          "/{RECORD_API}/{name}/{id}/restore",
+          name = self.name,
+          id = id.serialized_id()
+        ),
+        Method::POST,
+        None::<&()>,
+        None,
+      )
+      .await?;
+
+    return Ok(());
+  }
+
+  /// Lists the changes made to a record, oldest first. Requires the API to be configured with
+  /// `audit_trail`.
+  pub async fn history<'a, T: DeserializeOwned>(
+    &self,
+    id: impl RecordId<'a>,
+    args: ListArguments<'_>,
+  ) -> Result<ListResponse<AuditEntry<T>>, Error> {
+    let mut params: Vec<(Cow<'static, str>, Cow<'static, str>)> = vec![];
+    if let Some(cursor) = args.pagination.cursor {
+      params.push((Cow::Borrowed("cursor"), Cow::Owned(cursor)));
+    }
+
+    if let Some(limit) = args.pagination.limit {
+      params.push((Cow::Borrowed("limit"), Cow::Owned(limit.to_string())));
+    }
+
+    let response = self
+      .client
+      .fetch(
+        &format!(
+          "/{RECORD_API}/{name}/{id}/history",
+          name = self.name,
+          id = id.serialized_id()
+        ),
+        Method::GET,
+        None::<&()>,
+        Some(&params),
+      )
+      .await?;
+
+    return Ok(response.json().await?);
+  }
+
   pub async fn subscribe<'a>(
     &self,
     id: impl RecordId<'a>,
   ) -> Result<impl Stream<Item = DbEvent>, Error> {
+    return Ok(
+      self
+        .subscribe_since(id, None)
+        .await?
+        .map(|(_event_id, db_event)| db_event),
+    );
+  }
+
+  /// Like `subscribe` but also yields each event's id. Passing the id of the last received event
+  /// as `since_id` when re-subscribing replays events missed in the meantime, as far as they're
+  /// still buffered by the server.
+  pub async fn subscribe_since<'a>(
+    &self,
+    id: impl RecordId<'a>,
+    since_id: Option<i64>,
+  ) -> Result<impl Stream<Item = (Option<i64>, DbEvent)>, Error> {
+    let params: Vec<(Cow<'static, str>, Cow<'static, str>)> = since_id
+      .map(|since_id| (Cow::Borrowed("since_id"), Cow::Owned(since_id.to_string())))
+      .into_iter()
+      .collect();
+
     // TODO: Might have to add HeaderValue::from_static("text/event-stream").
     let response = self
       .client
@@ -297,26 +722,146 @@ impl RecordApi {
         ),
         Method::GET,
         None::<&()>,
-        None,
+        Some(&params),
       )
       .await?;
 
-    return Ok(
-      response
-        .bytes_stream()
-        .eventsource()
-        .filter_map(|event_or| async {
-          if let Ok(event) = event_or {
-            if let Ok(db_event) = serde_json::from_str::<DbEvent>(&event.data) {
-              return Some(db_event);
+    return Ok(decode_db_events(response.bytes_stream()));
+  }
+
+  /// Like `subscribe` but transparently re-connects with exponential backoff when the connection
+  /// drops, e.g. due to network issues or server restarts. Events missed in the meantime are
+  /// replayed as far as they're still buffered by the server. Successful re-connects are signaled
+  /// by `DbEvent::Reconnected`.
+  pub fn subscribe_resilient<'a>(
+    &self,
+    id: impl RecordId<'a>,
+    opts: SubscribeOptions,
+  ) -> impl Stream<Item = DbEvent> {
+    let state = ResilientSubscription {
+      api: self.clone(),
+      id: id.serialized_id().into_owned(),
+      opts,
+      stream: None,
+      last_event_id: None,
+    };
+
+    return futures::stream::unfold(state, |state| state.next());
+  }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = (Option<i64>, DbEvent)> + Send>>;
+
+struct ResilientSubscription {
+  api: RecordApi,
+  id: String,
+  opts: SubscribeOptions,
+  stream: Option<EventStream>,
+  last_event_id: Option<i64>,
+}
+
+impl ResilientSubscription {
+  async fn next(mut self) -> Option<(DbEvent, Self)> {
+    let mut reconnecting = false;
+    loop {
+      if let Some(ref mut stream) = self.stream {
+        match stream.next().await {
+          Some((event_id, event)) => {
+            if event_id.is_some() {
+              self.last_event_id = event_id;
             }
+            return Some((event, self));
           }
-          return None;
-        }),
-    );
+          None => {
+            self.stream = None;
+            reconnecting = true;
+          }
+        }
+      }
+
+      self.connect(reconnecting).await?;
+      if reconnecting {
+        return Some((DbEvent::Reconnected, self));
+      }
+    }
+  }
+
+  async fn connect(&mut self, reconnecting: bool) -> Option<()> {
+    let mut attempt: u32 = 0;
+    loop {
+      if reconnecting || attempt > 0 {
+        tokio::time::sleep(self.backoff(attempt)).await;
+      }
+
+      match self
+        .api
+        .subscribe_since(self.id.clone(), self.last_event_id)
+        .await
+      {
+        Ok(stream) => {
+          self.stream = Some(Box::pin(stream));
+          return Some(());
+        }
+        Err(err) => {
+          if is_permanent(&err) {
+            log::warn!("Subscription failed permanently: {err}");
+            return None;
+          }
+
+          attempt += 1;
+          if self.opts.max_retries.is_some_and(|max| attempt > max) {
+            log::warn!("Giving up on subscription after {attempt} attempts: {err}");
+            return None;
+          }
+        }
+      }
+    }
+  }
+
+  /// Exponential backoff with jitter, i.e. a random delay within [delay/2, delay].
+  fn backoff(&self, attempt: u32) -> Duration {
+    use std::hash::{BuildHasher, Hasher};
+
+    let delay = self
+      .opts
+      .initial_reconnect_delay
+      .saturating_mul(2_u32.saturating_pow(attempt))
+      .min(self.opts.max_reconnect_delay);
+    let random = std::collections::hash_map::RandomState::new()
+      .build_hasher()
+      .finish();
+    return delay / 2 + delay.mul_f64((random % 1000) as f64 / 2000.0);
   }
 }
 
+/// Client errors, e.g. the record having been deleted or access having been revoked, won't go away
+/// by retrying.
+fn is_permanent(err: &Error) -> bool {
+  let status = match err {
+    Error::HttpStatusWithBody(status, _) => Some(*status),
+    Error::Reqwest(err) => err.status(),
+    _ => None,
+  };
+  return status.is_some_and(|status| {
+    status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+  });
+}
+
+/// Decodes a stream of server-sent events into `DbEvent`s alongside their ids. Comments, e.g. the
+/// server's keep-alives, and malformed events are skipped.
+fn decode_db_events<B: AsRef<[u8]>, E>(
+  stream: impl Stream<Item = Result<B, E>>,
+) -> impl Stream<Item = (Option<i64>, DbEvent)> {
+  return stream.eventsource().filter_map(|event_or| async {
+    if let Ok(event) = event_or {
+      if let Ok(db_event) = serde_json::from_str::<DbEvent>(&event.data) {
+        return Some((event.id.parse().ok(), db_event));
+      }
+    }
+    return None;
+  });
+}
+
 #[derive(Clone, Debug)]
 struct TokenState {
   state: Option<(Tokens, JwtTokenClaims)>,
@@ -354,18 +899,53 @@ impl ClientState {
     body: Option<&T>,
     query_params: Option<&[(Cow<'static, str>, Cow<'static, str>)]>,
   ) -> Result<reqwest::Response, Error> {
-    let (mut headers, refresh_token) = self.extract_headers_and_refresh_token_if_exp();
-    if let Some(refresh_token) = refresh_token {
-      let new_tokens = ClientState::refresh_tokens(&self.client, headers, refresh_token).await?;
+    return self
+      .fetch_with_headers(path, HeaderMap::new(), method, body, query_params)
+      .await;
+  }
 
-      headers = new_tokens.headers.clone();
-      *self.tokens.write() = new_tokens;
-    }
+  async fn fetch_with_headers<T: Serialize>(
+    &self,
+    path: &str,
+    extra_headers: HeaderMap,
+    method: Method,
+    body: Option<&T>,
+    query_params: Option<&[(Cow<'static, str>, Cow<'static, str>)]>,
+  ) -> Result<reqwest::Response, Error> {
+    let mut headers = self.current_headers().await?;
+    headers.extend(extra_headers);
 
-    return self
+    let response = self
       .client
       .fetch(path, headers, method, body, query_params)
-      .await;
+      .await?;
+
+    return error_for_status(response).await;
+  }
+
+  async fn fetch_multipart(&self, path: &str, form: Form) -> Result<reqwest::Response, Error> {
+    let mut headers = self.current_headers().await?;
+    // Let reqwest set the content type including the multipart boundary.
+    headers.remove(CONTENT_TYPE);
+
+    let response = self.client.fetch_multipart(path, headers, form).await?;
+
+    return error_for_status(response).await;
+  }
+
+  /// Returns the headers for the next request, refreshing the tokens first if they're about to
+  /// expire.
+  async fn current_headers(&self) -> Result<HeaderMap, Error> {
+    let (headers, refresh_token) = self.extract_headers_and_refresh_token_if_exp();
+    let Some(refresh_token) = refresh_token else {
+      return Ok(headers);
+    };
+
+    let new_tokens = ClientState::refresh_tokens(&self.client, headers, refresh_token).await?;
+    let headers = new_tokens.headers.clone();
+    *self.tokens.write() = new_tokens;
+
+    return Ok(headers);
   }
 
   #[inline]
@@ -474,6 +1054,7 @@ impl Client {
     return RecordApi {
       client: self.state.clone(),
       name: api_name.to_string(),
+      accept: None,
     };
   }
 
@@ -592,6 +1173,46 @@ fn build_headers(tokens: Option<&Tokens>) -> HeaderMap {
   return base;
 }
 
+/// Like [reqwest::Response::error_for_status] but preserves JSON error bodies, which e.g. contain
+/// the server's `request_id` for correlation with its logs.
+async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+  let status = response.status();
+  let Err(err) = response.error_for_status_ref() else {
+    return Ok(response);
+  };
+
+  let body = response.bytes().await?;
+  return Err(match serde_json::from_slice(&body) {
+    Ok(value) => Error::HttpStatusWithBody(status, value),
+    Err(_) => Error::Reqwest(err),
+  });
+}
+
+fn content_type(response: &reqwest::Response) -> &str {
+  return response
+    .headers()
+    .get(CONTENT_TYPE)
+    .and_then(|v| v.to_str().ok())
+    .unwrap_or_default();
+}
+
+/// Decodes JSON or MessagePack responses depending on their content type.
+async fn decode_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+  if content_type(&response).starts_with(MIME_MSGPACK) {
+    return Ok(rmp_serde::from_slice(&response.bytes().await?)?);
+  }
+  return Ok(response.json().await?);
+}
+
+async fn decode_csv<T: DeserializeOwned>(response: reqwest::Response) -> Result<Vec<T>, Error> {
+  let body = response.bytes().await?;
+  return Ok(
+    csv::Reader::from_reader(body.as_ref())
+      .deserialize()
+      .collect::<Result<Vec<T>, _>>()?,
+  );
+}
+
 fn now() -> u64 {
   return std::time::SystemTime::now()
     .duration_since(std::time::UNIX_EPOCH)
@@ -623,4 +1244,78 @@ mod tests {
       .unwrap();
     }
   }
+
+  #[tokio::test]
+  async fn subscribe_resilient_test() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    // Serves a single event per connection and then drops it.
+    let server = tokio::spawn(async move {
+      let mut requests = vec![];
+      for id in 1..=2 {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buffer = vec![0; 4096];
+        let len = socket.read(&mut buffer).await.unwrap();
+        requests.push(String::from_utf8_lossy(&buffer[..len]).to_string());
+
+        let response = format!(
+          "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+           id: {id}\ndata: {{\"Insert\":{{\"id\":{id}}}}}\n\n"
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+      }
+      return requests;
+    });
+
+    let client = Client::new(&format!("http://{address}"), None).unwrap();
+    let events: Vec<DbEvent> = tokio::spawn(async move {
+      return client
+        .records("api")
+        .subscribe_resilient(
+          "*".to_string(),
+          SubscribeOptions {
+            initial_reconnect_delay: Duration::from_millis(10),
+            max_reconnect_delay: Duration::from_millis(10),
+            max_retries: Some(0),
+          },
+        )
+        .collect()
+        .await;
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(
+      events,
+      vec![
+        DbEvent::Insert(Some(serde_json::json!({"id": 1}))),
+        DbEvent::Reconnected,
+        DbEvent::Insert(Some(serde_json::json!({"id": 2}))),
+      ]
+    );
+
+    let requests = server.await.unwrap();
+    assert!(!requests[0].contains("since_id"), "{}", requests[0]);
+    assert!(requests[1].contains("since_id=1"), "{}", requests[1]);
+  }
+
+  #[tokio::test]
+  async fn keepalive_is_skipped_test() {
+    let chunks: Vec<Result<&[u8], std::convert::Infallible>> = vec![
+      Ok(b": keepalive\n\n"),
+      Ok(b"id: 7\ndata: {\"Insert\":{\"id\":1}}\n\n"),
+      Ok(b": keepalive\n\n"),
+    ];
+
+    let events: Vec<_> = decode_db_events(futures::stream::iter(chunks))
+      .collect()
+      .await;
+    assert_eq!(
+      events,
+      vec![(Some(7), DbEvent::Insert(Some(serde_json::json!({"id": 1}))))]
+    );
+  }
 }
@@ -1,7 +1,9 @@
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use trailbase_client::{Client, DbEvent, Pagination};
+use trailbase_client::{
+  Client, DbEvent, Error, ListArguments, Pagination, UpdateArguments, MIME_CSV, MIME_MSGPACK,
+};
 
 struct Server {
   child: std::process::Child,
@@ -123,7 +125,7 @@ async fn records_test() {
     let filter = format!("text_not_null={}", messages[0]);
     let filters = vec![filter.as_str()];
     let response = api
-      .list::<serde_json::Value>(Pagination::default(), &[], filters.as_slice())
+      .list::<serde_json::Value>(ListArguments::new().with_filters(filters.as_slice()))
       .await
       .unwrap();
 
@@ -131,12 +133,12 @@ async fn records_test() {
 
     let second_response = api
       .list::<serde_json::Value>(
-        Pagination {
-          cursor: response.cursor,
-          ..Default::default()
-        },
-        &[],
-        filters.as_slice(),
+        ListArguments::new()
+          .with_pagination(Pagination {
+            cursor: response.cursor,
+            ..Default::default()
+          })
+          .with_filters(filters.as_slice()),
       )
       .await
       .unwrap();
@@ -148,7 +150,11 @@ async fn records_test() {
     // List all the messages
     let filter = format!("text_not_null[like]=% =?&{now}");
     let records_ascending: Vec<SimpleStrict> = api
-      .list(Pagination::default(), &["+text_not_null"], &[&filter])
+      .list(
+        ListArguments::new()
+          .with_order(&["+text_not_null"])
+          .with_filters(&[&filter]),
+      )
       .await
       .unwrap()
       .records;
@@ -160,7 +166,11 @@ async fn records_test() {
     assert_eq!(messages, messages_ascending);
 
     let records_descending: Vec<SimpleStrict> = api
-      .list(Pagination::default(), &["-text_not_null"], &[&filter])
+      .list(
+        ListArguments::new()
+          .with_order(&["-text_not_null"])
+          .with_filters(&[&filter]),
+      )
       .await
       .unwrap()
       .records;
@@ -182,6 +192,31 @@ async fn records_test() {
     assert_eq!(record.text_not_null, messages[0]);
   }
 
+  {
+    // Read and list using MessagePack and CSV encodings. NOTE: CSV doesn't distinguish empty
+    // strings from NULL, thus only ids and non-null columns are compared.
+    let key = |r: &SimpleStrict| (r.id.clone(), r.text_not_null.clone());
+    let json: SimpleStrict = api.read(&ids[0]).await.unwrap();
+    for mime in [MIME_MSGPACK, MIME_CSV] {
+      let encoded_api = client.records("simple_strict_table").with_accept(mime);
+
+      let record: SimpleStrict = encoded_api.read(&ids[0]).await.unwrap();
+      assert_eq!(key(&json), key(&record), "{mime}");
+
+      let filter = format!("text_not_null={}", messages[0]);
+      let records = encoded_api
+        .list::<SimpleStrict>(ListArguments::new().with_filters(&[&filter]))
+        .await
+        .unwrap()
+        .records;
+      assert_eq!(
+        vec![key(&json)],
+        records.iter().map(key).collect::<Vec<_>>(),
+        "{mime}"
+      );
+    }
+  }
+
   {
     // Update
     let updated_message = format!("rust client updated test 0: {now}");
@@ -194,6 +229,93 @@ async fn records_test() {
     assert_eq!(record.text_not_null, updated_message);
   }
 
+  {
+    // Create & update returning the stored record.
+    let message = format!("rust client returning test: {now}");
+    let (id, created) = api
+      .create_returning(json!({"text_not_null": message}))
+      .await
+      .unwrap();
+    assert_eq!(created["text_not_null"], message);
+
+    let record: serde_json::Value = api.read(&id).await.unwrap();
+    assert_eq!(created, record);
+
+    let updated_message = format!("rust client updated returning test: {now}");
+    let updated = api
+      .update_returning(&id, json!({"text_not_null": updated_message}))
+      .await
+      .unwrap();
+    assert_eq!(updated["text_not_null"], updated_message);
+    assert_eq!(updated["id"], record["id"]);
+  }
+
+  {
+    // Bulk update.
+    let updated_messages: Vec<_> = ids
+      .iter()
+      .map(|id| {
+        (
+          id,
+          json!({"text_not_null": format!("rust client bulk update: {id}")}),
+        )
+      })
+      .collect();
+    let updated = api.update_bulk(&updated_messages).await.unwrap();
+    assert_eq!(updated, ids.len());
+
+    for id in &ids {
+      let record: SimpleStrict = api.read(id).await.unwrap();
+      assert_eq!(
+        record.text_not_null,
+        format!("rust client bulk update: {id}")
+      );
+    }
+  }
+
+  {
+    // Conditional update: the second update based on a stale ETag fails.
+    let (_, etag): (SimpleStrict, _) = api.read_with_etag(&ids[0]).await.unwrap();
+    let etag = etag.unwrap();
+
+    let args = || UpdateArguments::new().with_etag(&etag);
+    api
+      .update_with_args(&ids[0], json!({"text_not_null": "first"}), args())
+      .await
+      .unwrap();
+
+    let Err(Error::HttpStatusWithBody(status, body)) = api
+      .update_with_args(&ids[0], json!({"text_not_null": "second"}), args())
+      .await
+    else {
+      panic!("expected precondition failure");
+    };
+    assert_eq!(status, reqwest::StatusCode::PRECONDITION_FAILED);
+    assert!(body["request_id"].is_string(), "{body}");
+  }
+
+  {
+    // CSV import.
+    let csv = std::iter::once("text_not_null".to_string())
+      .chain((0..500).map(|i| format!("rust client csv import {i}: {now}")))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let result = api
+      .import_csv(std::io::Cursor::new(csv.into_bytes()))
+      .await
+      .unwrap();
+    assert_eq!(result.inserted, 500);
+    assert_eq!(result.failed, 0);
+
+    let filter = format!("text_not_null=rust client csv import 499: {now}");
+    let filters = vec![filter.as_str()];
+    let response = api
+      .list::<SimpleStrict>(ListArguments::new().with_filters(filters.as_slice()))
+      .await
+      .unwrap();
+    assert_eq!(response.records.len(), 1);
+  }
+
   {
     // Delete
     api.delete(&ids[0]).await.unwrap();
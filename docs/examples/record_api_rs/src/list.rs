@@ -1,16 +1,17 @@
-use trailbase_client::{Client, ListResponse, Pagination};
+use trailbase_client::{Client, ListArguments, ListResponse, Pagination};
 
 pub async fn list(client: &Client) -> anyhow::Result<ListResponse<serde_json::Value>> {
   Ok(
     client
       .records("movies")
       .list(
-        Pagination {
-          limit: Some(3),
-          ..Default::default()
-        },
-        &["rank"],
-        &["watch_time[lt]=120", "description[like]=%love%"],
+        ListArguments::new()
+          .with_pagination(Pagination {
+            limit: Some(3),
+            ..Default::default()
+          })
+          .with_order(&["rank"])
+          .with_filters(&["watch_time[lt]=120", "description[like]=%love%"]),
       )
       .await?,
   )
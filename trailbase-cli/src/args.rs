@@ -1,6 +1,7 @@
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
 use trailbase::api::JsonSchemaMode;
+use trailbase::records::DataFormat;
 use trailbase::DataDir;
 
 #[derive(ValueEnum, Clone, Copy, Debug)]
@@ -11,6 +12,8 @@ pub enum JsonSchemaModeArg {
   Select,
   /// Update mode.
   Update,
+  /// Insert mode including rules derived from CHECK constraints.
+  Strict,
 }
 
 impl From<JsonSchemaModeArg> for JsonSchemaMode {
@@ -19,6 +22,27 @@ impl From<JsonSchemaModeArg> for JsonSchemaMode {
       JsonSchemaModeArg::Insert => Self::Insert,
       JsonSchemaModeArg::Select => Self::Select,
       JsonSchemaModeArg::Update => Self::Update,
+      JsonSchemaModeArg::Strict => Self::Strict,
+    }
+  }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DataFormatArg {
+  /// JSON array of objects.
+  Json,
+  /// One JSON object per line.
+  Ndjson,
+  /// Comma-separated values with a header row.
+  Csv,
+}
+
+impl From<DataFormatArg> for DataFormat {
+  fn from(value: DataFormatArg) -> Self {
+    match value {
+      DataFormatArg::Json => Self::Json,
+      DataFormatArg::Ndjson => Self::NdJson,
+      DataFormatArg::Csv => Self::Csv,
     }
   }
 }
@@ -56,6 +80,11 @@ pub enum SubCommands {
     /// Optional suffix used for the generated migration file: U<timetamp>__<suffix>.sql.
     suffix: Option<String>,
   },
+  /// Inspect and apply pending migrations of the main database.
+  Migrate {
+    #[command(subcommand)]
+    cmd: Option<MigrateSubCommands>,
+  },
   /// Simple admin management (use dashboard for everything else).
   Admin {
     #[command(subcommand)]
@@ -66,8 +95,21 @@ pub enum SubCommands {
     #[command(subcommand)]
     cmd: Option<UserSubCommands>,
   },
+  /// Manage API keys, i.e. static bearer tokens for non-interactive clients.
+  ApiKey {
+    #[command(subcommand)]
+    cmd: Option<ApiKeySubCommands>,
+  },
   /// Programmatically send emails.
   Email(EmailArgs),
+  /// Creates a consistent copy of the main database. Safe to use while the server is running.
+  Backup(BackupArgs),
+  /// Replaces the main database with a backup. The server must be stopped first.
+  Restore(RestoreArgs),
+  /// Exports a table's rows to a file.
+  Export(ExportArgs),
+  /// Imports rows from a file into a table.
+  Import(ImportArgs),
 }
 
 #[derive(Args, Clone, Debug)]
@@ -79,6 +121,15 @@ pub struct ServerArgs {
   #[arg(long, env)]
   pub admin_address: Option<String>,
 
+  /// Only accept IPv6 connections when binding IPv6 addresses. By default, "[::]" is dual-stack,
+  /// i.e. accepts both IPv4 and IPv6 connections.
+  #[arg(long, env, default_value_t = false)]
+  pub ipv6_only: bool,
+
+  /// Listen on the given Unix domain socket instead of `address`, e.g. behind a reverse proxy.
+  #[arg(long, env)]
+  pub unix_socket: Option<std::path::PathBuf>,
+
   /// Optional path to static assets that will be served at the HTTP root.
   #[arg(long, env)]
   pub public_dir: Option<String>,
@@ -95,13 +146,66 @@ pub struct ServerArgs {
   #[arg(long, default_value_t = false)]
   pub disable_auth_ui: bool,
 
+  /// Export Prometheus metrics at "/api/metrics".
+  #[arg(long, default_value_t = false)]
+  pub enable_metrics: bool,
+
+  /// Serve the OpenAPI spec at "/openapi.json" and "/openapi.yaml". Defaults to true in dev mode.
+  #[arg(long)]
+  pub enable_openapi: Option<bool>,
+
+  /// OTLP/HTTP endpoint to export traces to, e.g. "http://localhost:4318/v1/traces".
+  #[arg(long, env)]
+  pub otlp_endpoint: Option<String>,
+
+  /// Interval in seconds for sending keep-alives on realtime subscriptions (0 to disable).
+  #[arg(long, default_value_t = 30)]
+  pub sse_keepalive_secs: u64,
+
+  /// Number of recent realtime events buffered for replaying to re-connecting subscribers.
+  #[arg(long, default_value_t = 128)]
+  pub sse_replay_buffer_size: usize,
+
+  /// Log request bodies (truncated to 4KiB) for debugging. Requires --dev.
+  #[arg(long, default_value_t = false)]
+  pub log_request_bodies: bool,
+
+  /// Log response bodies (truncated to 4KiB) for debugging. Requires --dev.
+  #[arg(long, default_value_t = false)]
+  pub log_response_bodies: bool,
+
   /// Limit the set of allowed origins the HTTP server will answer to.
   #[arg(long, default_value = "*")]
   pub cors_allowed_origins: Vec<String>,
 
+  /// Content-Security-Policy for HTML responses. "{nonce}" is replaced with a per-request nonce.
+  #[arg(long, env)]
+  pub csp_policy: Option<String>,
+
+  /// Max age in seconds of the Strict-Transport-Security header (Default: none).
+  #[arg(long, env)]
+  pub hsts_max_age: Option<u64>,
+
+  /// Source of client IPs, e.g. "RightmostXForwardedFor" behind a trusted reverse proxy
+  /// (Default: the connection's peer address).
+  #[arg(long, env)]
+  pub client_ip_source: Option<String>,
+
   /// Number of JavaScript isolates/workers to start (Default: #cpus).
   #[arg(long, env)]
   pub js_runtime_threads: Option<usize>,
+
+  /// Maximum heap size per JavaScript isolate in MB (Default: V8's default).
+  #[arg(long, env)]
+  pub js_heap_limit: Option<u32>,
+
+  /// Stack size of JavaScript isolates in KB (Default: V8's default).
+  #[arg(long, env)]
+  pub js_stack_size: Option<u32>,
+
+  /// Time in milliseconds after which JavaScript HTTP handlers are interrupted (Default: none).
+  #[arg(long, env)]
+  pub js_timeout: Option<u64>,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -130,6 +234,65 @@ pub struct EmailArgs {
   pub body: String,
 }
 
+#[derive(Args, Clone, Debug)]
+pub struct BackupArgs {
+  /// Path of the backup file or "-" to write an uncompressed backup to stdout [Default:
+  /// trailbase_backup_<timestamp>.sqlite[.gz]].
+  #[arg(long)]
+  pub output: Option<std::path::PathBuf>,
+
+  /// Produce a gzip-compressed backup.
+  #[arg(long, default_value_t = false)]
+  pub compress: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct RestoreArgs {
+  /// Path of the backup file to restore.
+  #[arg(long)]
+  pub from: std::path::PathBuf,
+
+  /// Backup file is gzip-compressed.
+  #[arg(long, default_value_t = false)]
+  pub compress: bool,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ExportArgs {
+  /// Name of the table to export.
+  #[arg(long)]
+  pub table: String,
+
+  #[arg(long, default_value = "ndjson")]
+  pub format: DataFormatArg,
+
+  /// Path of the output file.
+  #[arg(long)]
+  pub output: std::path::PathBuf,
+
+  /// Optional SQL expression to only export matching rows, e.g. "created > 1700000000".
+  #[arg(long = "where")]
+  pub filter: Option<String>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct ImportArgs {
+  /// Name of the table to import into.
+  #[arg(long)]
+  pub table: String,
+
+  #[arg(long, default_value = "ndjson")]
+  pub format: DataFormatArg,
+
+  /// Path of the input file.
+  #[arg(long)]
+  pub input: std::path::PathBuf,
+
+  /// Number of rows inserted per transaction.
+  #[arg(long, default_value_t = 1000)]
+  pub batch_size: usize,
+}
+
 #[cfg(feature = "openapi")]
 #[derive(Subcommand, Debug, Clone)]
 pub enum OpenApiSubCommands {
@@ -138,6 +301,38 @@ pub enum OpenApiSubCommands {
     #[arg(long, default_value_t = 4004)]
     port: u16,
   },
+  /// Writes the full spec including the configured record APIs.
+  Export {
+    #[arg(long, value_enum, default_value_t = OpenApiFormatArg::Json)]
+    format: OpenApiFormatArg,
+
+    /// Path of the output file. Defaults to stdout.
+    #[arg(long)]
+    output: Option<std::path::PathBuf>,
+  },
+}
+
+#[cfg(feature = "openapi")]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OpenApiFormatArg {
+  Json,
+  Yaml,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum MigrateSubCommands {
+  /// Lists applied and pending migrations.
+  Status,
+  /// Applies pending migrations.
+  Up {
+    /// Print the SQL of pending migrations without applying them. Exits with code 1 if there are
+    /// any, e.g. for use in CI.
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    /// Only apply the next N pending migrations.
+    #[arg(long)]
+    steps: Option<usize>,
+  },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -163,9 +358,35 @@ pub enum UserSubCommands {
   ResetPassword {
     /// E-mail of the user who's password is being reset.
     email: String,
-    /// Password to set.
+    /// Temporary password to set. The user will have to change it upon their next login.
     password: String,
   },
   /// Mint auth tokens for the given user.
   MintToken { email: String },
 }
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ApiKeySubCommands {
+  /// Creates a new API key for the given user.
+  Create {
+    /// E-mail of the user the key is created for.
+    email: String,
+    /// Name to identify the key by.
+    name: String,
+    /// Optional time-to-live in seconds. Keys without TTL never expire.
+    #[arg(long)]
+    ttl_sec: Option<i64>,
+  },
+  /// Lists the given user's API keys.
+  List {
+    /// E-mail of the user who's keys are listed.
+    email: String,
+  },
+  /// Revokes an API key.
+  Revoke {
+    /// E-mail of the user owning the key.
+    email: String,
+    /// Id of the key to revoke as shown by `list`.
+    id: String,
+  },
+}
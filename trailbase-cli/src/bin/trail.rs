@@ -13,11 +13,14 @@ use tracing_subscriber::{filter, prelude::*};
 use trailbase::{
   api::{self, init_app_state, Email, InitArgs, TokenClaims},
   constants::USER_TABLE,
+  records,
+  util::{b64_to_uuid, id_to_b64},
   DataDir, Server, ServerOptions,
 };
 
 use trailbase_cli::{
-  AdminSubCommands, DefaultCommandLineArgs, JsonSchemaModeArg, SubCommands, UserSubCommands,
+  AdminSubCommands, ApiKeySubCommands, DefaultCommandLineArgs, JsonSchemaModeArg,
+  MigrateSubCommands, SubCommands, UserSubCommands,
 };
 
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
@@ -81,15 +84,39 @@ async fn async_main() -> Result<(), BoxError> {
 
       let app = Server::init(ServerOptions {
         data_dir,
+        on_first_init_sql: None,
         address: cmd.address,
         admin_address: cmd.admin_address,
+        ipv6_only: cmd.ipv6_only,
+        unix_socket: cmd.unix_socket,
         public_dir: cmd.public_dir.map(|p| p.into()),
+        extra_public_dirs: vec![],
+        static_cache_max_age: None,
+        custom_error_pages: Default::default(),
+        virtual_hosts: vec![],
         dev: cmd.dev,
         disable_auth_ui: cmd.disable_auth_ui,
         cors_allowed_origins: cmd.cors_allowed_origins,
         js_runtime_threads: cmd.js_runtime_threads,
+        js_heap_limit_mb: cmd.js_heap_limit,
+        js_stack_size_kb: cmd.js_stack_size,
+        js_timeout_ms: cmd.js_timeout,
         tls_key: None,
         tls_cert: None,
+        rate_limit: None,
+        rate_limit_routes: vec![],
+        enable_compression: true,
+        compression_level: 0,
+        enable_metrics: cmd.enable_metrics,
+        enable_openapi: cmd.enable_openapi,
+        otlp_endpoint: cmd.otlp_endpoint,
+        sse_keepalive_secs: cmd.sse_keepalive_secs,
+        sse_replay_buffer_size: cmd.sse_replay_buffer_size,
+        log_request_bodies: cmd.log_request_bodies,
+        log_response_bodies: cmd.log_response_bodies,
+        csp_policy: cmd.csp_policy,
+        hsts_max_age: cmd.hsts_max_age,
+        client_ip_source: cmd.client_ip_source,
       })
       .await?;
 
@@ -99,9 +126,12 @@ async fn async_main() -> Result<(), BoxError> {
       // by the env_logger above.
       // FIXME: Without the sqlite logger here, logging is broken despite us trying to initialize
       // in app.server() as well.
-      let layer = tracing_subscriber::registry().with(
-        trailbase::logging::SqliteLogLayer::new(app.state()).with_filter(filter::LevelFilter::INFO),
-      );
+      let layer = tracing_subscriber::registry()
+        .with(
+          trailbase::logging::SqliteLogLayer::new(app.state())
+            .with_filter(filter::LevelFilter::INFO),
+        )
+        .with(app.otel_layer());
 
       if stderr_logging {
         let _ = layer
@@ -126,7 +156,7 @@ async fn async_main() -> Result<(), BoxError> {
     Some(SubCommands::OpenApi { cmd }) => {
       init_logger(false);
 
-      use trailbase_cli::OpenApiSubCommands;
+      use trailbase_cli::{OpenApiFormatArg, OpenApiSubCommands};
       use utoipa::OpenApi;
       use utoipa_swagger_ui::SwaggerUi;
 
@@ -150,6 +180,20 @@ async fn async_main() -> Result<(), BoxError> {
         Some(OpenApiSubCommands::Run { port }) => {
           run_server(port).await;
         }
+        Some(OpenApiSubCommands::Export { format, output }) => {
+          let (_new_db, state) = init_app_state(data_dir, None, InitArgs::default()).await?;
+
+          let doc = trailbase::openapi::build(&state);
+          let spec = match format {
+            OpenApiFormatArg::Json => doc.to_pretty_json()?,
+            OpenApiFormatArg::Yaml => doc.to_yaml()?,
+          };
+
+          match output {
+            Some(output) => std::fs::write(output, spec)?,
+            None => println!("{spec}"),
+          };
+        }
         None => {
           run_server(4004).await;
         }
@@ -198,6 +242,67 @@ async fn async_main() -> Result<(), BoxError> {
 
       println!("Created empty migration file: {path:?}");
     }
+    Some(SubCommands::Migrate { cmd }) => {
+      init_logger(false);
+
+      let mut conn = api::connect_sqlite(Some(data_dir.main_db_path()), None)?;
+      let migrations_path = Some(data_dir.migrations_path());
+
+      match cmd {
+        Some(MigrateSubCommands::Status) => {
+          let status = api::migration_status(&mut conn, migrations_path)?;
+
+          println!("version\tname\tapplied\tchecksum");
+          for migration in status {
+            println!(
+              "{}\t{}\t{}\t{}",
+              migration.version,
+              migration.name,
+              migration.applied_on.as_deref().unwrap_or("pending"),
+              migration.checksum,
+            );
+          }
+        }
+        Some(MigrateSubCommands::Up { dry_run, steps }) => {
+          if dry_run {
+            let pending: Vec<_> = api::migration_status(&mut conn, migrations_path)?
+              .into_iter()
+              .filter(|m| m.is_pending())
+              .take(steps.unwrap_or(usize::MAX))
+              .collect();
+
+            for migration in &pending {
+              println!(
+                "-- {version}: {name}\n{sql}",
+                version = migration.version,
+                name = migration.name,
+                sql = migration.sql.as_deref().unwrap_or_default(),
+              );
+            }
+
+            if !pending.is_empty() {
+              std::process::exit(1);
+            }
+            println!("No pending migrations");
+            return Ok(());
+          }
+
+          let applied = api::apply_pending_migrations(&mut conn, migrations_path, steps)?;
+          for migration in &applied {
+            println!(
+              "Applied migration {}: {}",
+              migration.version, migration.name
+            );
+          }
+          println!("Applied {} migration(s)", applied.len());
+        }
+        None => {
+          DefaultCommandLineArgs::command()
+            .find_subcommand_mut("migrate")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
     Some(SubCommands::Admin { cmd }) => {
       init_logger(false);
 
@@ -294,6 +399,62 @@ async fn async_main() -> Result<(), BoxError> {
         }
       };
     }
+    Some(SubCommands::ApiKey { cmd }) => {
+      init_logger(false);
+
+      let conn = trailbase_sqlite::Connection::from_conn(api::connect_sqlite(
+        Some(data_dir.main_db_path()),
+        None,
+      )?)?;
+
+      match cmd {
+        Some(ApiKeySubCommands::Create {
+          email,
+          name,
+          ttl_sec,
+        }) => {
+          let user = get_user_by_email(&conn, &email).await?;
+          let expires_at = ttl_sec.map(|ttl| chrono::Utc::now().timestamp() + ttl);
+
+          let api::NewApiKey { id, key } =
+            api::create_api_key(&conn, user.uuid(), name, expires_at).await?;
+
+          println!(
+            "Created API key '{}' for '{email}':",
+            id_to_b64(&id.into_bytes())
+          );
+          println!("{key}");
+        }
+        Some(ApiKeySubCommands::List { email }) => {
+          let user = get_user_by_email(&conn, &email).await?;
+          let keys = api::list_api_keys(&conn, user.uuid()).await?;
+
+          println!("{: >24}\tname\tprefix\tcreated\tlast_used\texpires", "id");
+          for key in keys {
+            println!(
+              "{}\t{}\t{}\t{created:?}\t{last_used:?}\t{expires:?}",
+              key.id,
+              key.name,
+              key.prefix,
+              created = chrono::Utc.timestamp_opt(key.created_at, 0),
+              last_used = key.last_used_at.map(|t| chrono::Utc.timestamp_opt(t, 0)),
+              expires = key.expires_at.map(|t| chrono::Utc.timestamp_opt(t, 0)),
+            );
+          }
+        }
+        Some(ApiKeySubCommands::Revoke { email, id }) => {
+          let user = get_user_by_email(&conn, &email).await?;
+          api::revoke_api_key(&conn, user.uuid(), b64_to_uuid(&id)?).await?;
+
+          println!("API key '{id}' has been revoked");
+        }
+        None => {
+          DefaultCommandLineArgs::command()
+            .find_subcommand_mut("api-key")
+            .map(|cmd| cmd.print_help());
+        }
+      };
+    }
     Some(SubCommands::Email(cmd)) => {
       init_logger(false);
 
@@ -314,6 +475,82 @@ async fn async_main() -> Result<(), BoxError> {
         }
       };
     }
+    Some(SubCommands::Backup(cmd)) => {
+      init_logger(false);
+
+      let conn = trailbase_sqlite::Connection::from_conn(api::connect_sqlite(
+        Some(data_dir.main_db_path()),
+        None,
+      )?)?;
+
+      if cmd
+        .output
+        .as_ref()
+        .is_some_and(|output| output.as_os_str() == "-")
+      {
+        if cmd.compress {
+          return Err("Compression isn't supported when writing to stdout".into());
+        }
+        conn.backup_to_writer(tokio::io::stdout()).await?;
+        return Ok(());
+      }
+
+      let output = cmd
+        .output
+        .unwrap_or_else(|| api::default_backup_filename(cmd.compress).into());
+      api::backup(&conn, output.clone(), cmd.compress).await?;
+
+      println!("Created backup: {output:?}");
+    }
+    Some(SubCommands::Restore(cmd)) => {
+      init_logger(false);
+
+      api::restore(&cmd.from, &data_dir.main_db_path(), cmd.compress)?;
+
+      println!("Restored {:?} from {:?}", data_dir.main_db_path(), cmd.from);
+    }
+    Some(SubCommands::Export(cmd)) => {
+      init_logger(false);
+
+      let conn = trailbase_sqlite::Connection::from_conn(api::connect_sqlite(
+        Some(data_dir.main_db_path()),
+        None,
+      )?)?;
+      let table_metadata = api::TableMetadataCache::new(conn.clone()).await?;
+      let Some(table) = table_metadata.get(&cmd.table) else {
+        return Err(format!("Could not find table: '{}'", cmd.table).into());
+      };
+
+      let mut writer = std::io::BufWriter::new(std::fs::File::create(&cmd.output)?);
+      let count = records::export_table(
+        &conn,
+        &table,
+        cmd.format.into(),
+        cmd.filter.as_deref(),
+        &mut writer,
+      )
+      .await?;
+
+      println!("Exported {count} rows to {:?}", cmd.output);
+    }
+    Some(SubCommands::Import(cmd)) => {
+      init_logger(false);
+
+      let conn = trailbase_sqlite::Connection::from_conn(api::connect_sqlite(
+        Some(data_dir.main_db_path()),
+        None,
+      )?)?;
+      let table_metadata = api::TableMetadataCache::new(conn.clone()).await?;
+      let Some(table) = table_metadata.get(&cmd.table) else {
+        return Err(format!("Could not find table: '{}'", cmd.table).into());
+      };
+
+      let reader = std::io::BufReader::new(std::fs::File::open(&cmd.input)?);
+      let count =
+        records::import_table(&conn, &table, cmd.format.into(), reader, cmd.batch_size).await?;
+
+      println!("Imported {count} rows from {:?}", cmd.input);
+    }
     None => {
       let _ = DefaultCommandLineArgs::command().print_help();
     }
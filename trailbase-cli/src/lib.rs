@@ -3,9 +3,10 @@
 mod args;
 
 pub use args::{
-  AdminSubCommands, DefaultCommandLineArgs, EmailArgs, JsonSchemaModeArg, SubCommands,
-  UserSubCommands,
+  AdminSubCommands, ApiKeySubCommands, BackupArgs, DataFormatArg, DefaultCommandLineArgs,
+  EmailArgs, ExportArgs, ImportArgs, JsonSchemaModeArg, MigrateSubCommands, RestoreArgs,
+  SubCommands, UserSubCommands,
 };
 
 #[cfg(feature = "openapi")]
-pub use args::OpenApiSubCommands;
+pub use args::{OpenApiFormatArg, OpenApiSubCommands};
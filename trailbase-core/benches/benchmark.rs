@@ -137,6 +137,7 @@ async fn setup_app() -> Result<SetupResult, anyhow::Error> {
       password: password.to_string(),
       verified: true,
       admin: false,
+      force_password_reset: false,
     }),
   )
   .await?
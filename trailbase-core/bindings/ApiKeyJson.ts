@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ApiKeyJson = { id: string, name: string, prefix: string, created_at: bigint, last_used_at: bigint | null, expires_at: bigint | null, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type AuditLogEntryJson = { id: bigint, 
+/**
+ * Url-safe Base64 encoded id of the admin who performed the operation.
+ */
+admin_user_id: string, action: string, resource_type: string | null, resource_id: string | null, changes: Object | undefined, ip: string | null, 
+/**
+ * Unix timestamp in seconds.
+ */
+timestamp: bigint, };
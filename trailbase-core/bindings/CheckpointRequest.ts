@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CheckpointMode } from "./CheckpointMode";
+
+export type CheckpointRequest = { 
+/**
+ * Defaults to "passive", which doesn't block concurrent readers or writers.
+ */
+mode: CheckpointMode | null, };
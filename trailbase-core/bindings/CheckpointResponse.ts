@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CheckpointResponse = { 
+/**
+ * Number of pages in the WAL.
+ */
+wal_pages: number, 
+/**
+ * Number of WAL pages moved into the main database file.
+ */
+checkpointed_pages: number, };
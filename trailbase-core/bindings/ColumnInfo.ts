@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnDataType } from "./ColumnDataType";
+import type { ColumnOption } from "./ColumnOption";
+
+export type ColumnInfo = { name: string, data_type: ColumnDataType, primary_key: boolean, not_null: boolean, options: Array<ColumnOption>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateApiKeyRequest = { name: string, 
+/**
+ * Optional time-to-live in seconds. Keys without TTL never expire.
+ */
+ttl_sec: bigint | null, };
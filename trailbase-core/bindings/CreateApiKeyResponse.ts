@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateApiKeyResponse = { id: string, 
+/**
+ * The full API key. It's only ever returned once and cannot be recovered.
+ */
+key: string, };
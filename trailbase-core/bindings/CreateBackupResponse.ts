@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateBackupResponse = { 
+/**
+ * Name of the compressed backup file within the "backups/" directory.
+ */
+filename: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateFtsIndexRequest = { table_name: string, 
+/**
+ * Columns to be indexed for full-text search.
+ */
+columns: Array<string>, dry_run: boolean | null, };
@@ -1,3 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type CreateUserRequest = { email: string, password: string, verified: boolean, admin: boolean, };
+export type CreateUserRequest = { email: string, password: string, verified: boolean, admin: boolean, 
+/**
+ * Requires the user to change their (temporary) password before accessing any record APIs.
+ */
+force_password_reset: boolean, };
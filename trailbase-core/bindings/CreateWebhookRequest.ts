@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CreateWebhookRequest = { name: string, url: string, 
+/**
+ * Secret for signing payloads. A random one is generated if absent.
+ */
+secret: string | null, 
+/**
+ * Subscribed operations, i.e. "INSERT", "UPDATE" and/or "DELETE".
+ */
+events: Array<string>, 
+/**
+ * Name of the observed table.
+ */
+table_filter: string, active: boolean | null, };
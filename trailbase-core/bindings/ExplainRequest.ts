@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ExplainRequest = { sql: string, 
+/**
+ * Positional parameters bound to the statement.
+ */
+params: Object[] | null, };
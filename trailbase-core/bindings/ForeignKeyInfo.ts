@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ReferentialAction } from "./ReferentialAction";
+
+export type ForeignKeyInfo = { name: string | null, 
+/**
+ * Referencing columns of this table.
+ */
+columns: Array<string>, foreign_table: string, 
+/**
+ * Referenced columns of the foreign table.
+ */
+referred_columns: Array<string>, on_delete: ReferentialAction | null, on_update: ReferentialAction | null, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnOrder } from "./ColumnOrder";
+
+export type IndexInfo = { name: string, columns: Array<ColumnOrder>, unique: boolean, predicate: string | null, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type JobEntry = { name: string, 
+/**
+ * Cron expression or interval.
+ */
+schedule: string, 
+/**
+ * Unix timestamps in seconds.
+ */
+last_run_at: bigint | null, next_run_at: bigint | null, error_count: bigint, last_error: string | null, };
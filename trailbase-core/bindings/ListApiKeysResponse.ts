@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApiKeyJson } from "./ApiKeyJson";
+
+export type ListApiKeysResponse = { keys: Array<ApiKeyJson>, };
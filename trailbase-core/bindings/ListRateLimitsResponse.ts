@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RateLimitEntry } from "./RateLimitEntry";
+
+export type ListRateLimitsResponse = { 
+/**
+ * Clients closest to or over their rate limit first.
+ */
+entries: Array<RateLimitEntry>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PreviewScheduleResponse = { 
+/**
+ * Unix timestamps in seconds of the next runs.
+ */
+next_runs: Array<bigint>, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type QueryPlanEntry = { id: bigint, 
+/**
+ * Id of the parent entry or zero for top-level entries.
+ */
+parent: bigint, detail: string, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RateLimitEntry = { 
+/**
+ * Client IP address, user id or "global" depending on the rate limit configuration.
+ */
+key: string, 
+/**
+ * Tokens left in the bucket as of the last request.
+ */
+tokens: number, 
+/**
+ * Seconds passed since the last request.
+ */
+idle_seconds: number, };
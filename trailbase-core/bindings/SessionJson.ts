@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * An active session. The refresh token itself is never exposed.
+ */
+export type SessionJson = { id: bigint, 
+/**
+ * Unix timestamp of when the session was created. Absent for legacy sessions.
+ */
+created: bigint | null, updated: bigint, 
+/**
+ * Unix timestamp after which the session can no longer be refreshed.
+ */
+expires: bigint, user_agent: string | null, ip_address: string | null, };
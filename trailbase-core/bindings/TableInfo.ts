@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnInfo } from "./ColumnInfo";
+import type { ForeignKeyInfo } from "./ForeignKeyInfo";
+import type { IndexInfo } from "./IndexInfo";
+
+export type TableInfo = { name: string, columns: Array<ColumnInfo>, indexes: Array<IndexInfo>, 
+/**
+ * Both column-level and table-level foreign keys.
+ */
+foreign_keys: Array<ForeignKeyInfo>, };
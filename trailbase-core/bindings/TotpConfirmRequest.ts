@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TotpConfirmRequest = { totp: string, 
+/**
+ * Refresh token of the pending session. Defaults to the token provided via header or cookie.
+ */
+refresh_token: string | null, };
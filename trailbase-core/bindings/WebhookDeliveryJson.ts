@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WebhookDeliveryJson = { id: bigint, event: string, payload: string, 
+/**
+ * One of "pending", "delivered" or "failed".
+ */
+status: string, attempts: bigint, 
+/**
+ * Unix timestamp in seconds of the next attempt for pending deliveries.
+ */
+next_attempt: bigint, 
+/**
+ * HTTP status code of the last attempt.
+ */
+response_status: bigint | null, error: string | null, 
+/**
+ * Unix timestamp in seconds.
+ */
+created: bigint, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type WebhookJson = { id: bigint, name: string, url: string, 
+/**
+ * Subscribed operations, i.e. "INSERT", "UPDATE" and/or "DELETE".
+ */
+events: Array<string>, table_filter: string, active: boolean, 
+/**
+ * Unix timestamp in seconds.
+ */
+created: bigint, };
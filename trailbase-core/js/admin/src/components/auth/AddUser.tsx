@@ -23,6 +23,7 @@ export function AddUser(props: {
       password: "",
       verified: true,
       admin: false,
+      force_password_reset: false,
     },
     onSubmit: async ({ value }) => {
       createUser(value)
@@ -78,6 +79,16 @@ export function AddUser(props: {
               ),
             })}
           </form.Field>
+
+          <form.Field name="force_password_reset">
+            {buildBoolFormField({
+              label: () => (
+                <L>
+                  <div class="text-right">Force Password Reset</div>
+                </L>
+              ),
+            })}
+          </form.Field>
         </div>
 
         <SheetFooter>
@@ -0,0 +1,112 @@
+import { createSignal, For } from "solid-js";
+
+import { Button } from "@/components/ui/button";
+import { Checkbox } from "@/components/ui/checkbox";
+import { Label } from "@/components/ui/label";
+import { SheetHeader, SheetTitle, SheetFooter } from "@/components/ui/sheet";
+import { showToast } from "@/components/ui/toast";
+
+import { createFtsIndex } from "@/lib/table";
+import type { Table } from "@/lib/bindings";
+import { SheetContainer } from "@/components/SafeSheet";
+
+export function CreateFtsIndexForm(props: {
+  close: () => void;
+  markDirty: () => void;
+  schemaRefetch: () => void;
+  table: Table;
+}) {
+  const [columns, setColumns] = createSignal<Set<string>>(new Set());
+  const [sql, setSql] = createSignal<string | undefined>();
+
+  const onSubmit = async (dryRun: boolean) => {
+    try {
+      const response = await createFtsIndex({
+        table_name: props.table.name,
+        columns: props.table.columns
+          .map((c) => c.name)
+          .filter((name) => columns().has(name)),
+        dry_run: dryRun,
+      });
+      console.debug(`CreateFtsIndexResponse [dry: ${dryRun}]:`, response);
+
+      if (dryRun) {
+        setSql(response.sql);
+        return;
+      }
+
+      props.schemaRefetch();
+      props.close();
+    } catch (err) {
+      showToast({
+        title: "Uncaught Error",
+        description: `${err}`,
+        variant: "error",
+      });
+    }
+  };
+
+  return (
+    <SheetContainer>
+      <SheetHeader>
+        <SheetTitle>
+          Create Full-Text Search Index for "{props.table.name}" Table
+        </SheetTitle>
+      </SheetHeader>
+
+      <div class="flex flex-col items-start gap-4 pr-4 my-4">
+        <p class="text-sm">
+          Creates a "{props.table.name}_fts" table, which enables the{" "}
+          <code>?search=</code> parameter for the table's record APIs.
+        </p>
+
+        <For each={props.table.columns}>
+          {(column) => (
+            <div class="flex items-center gap-2">
+              <Checkbox
+                checked={columns().has(column.name)}
+                onChange={(value: boolean) => {
+                  const next = new Set(columns());
+                  if (value) {
+                    next.add(column.name);
+                  } else {
+                    next.delete(column.name);
+                  }
+                  setColumns(next);
+                  props.markDirty();
+                }}
+              />
+              <Label>{column.name}</Label>
+            </div>
+          )}
+        </For>
+
+        {sql() && <pre class="overflow-auto text-sm">{sql()}</pre>}
+      </div>
+
+      <SheetFooter>
+        <div class="flex gap-4 items-center">
+          <Button
+            disabled={columns().size === 0}
+            variant="outline"
+            onClick={() => {
+              onSubmit(true).catch(console.error);
+            }}
+          >
+            Dry Run
+          </Button>
+
+          <Button
+            disabled={columns().size === 0}
+            variant="default"
+            onClick={() => {
+              onSubmit(false).catch(console.error);
+            }}
+          >
+            Submit
+          </Button>
+        </div>
+      </SheetFooter>
+    </SheetContainer>
+  );
+}
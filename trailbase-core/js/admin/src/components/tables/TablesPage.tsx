@@ -53,6 +53,7 @@ import {
 
 import { CreateAlterTableForm } from "@/components/tables/CreateAlterTable";
 import { CreateAlterIndexForm } from "@/components/tables/CreateAlterIndex";
+import { CreateFtsIndexForm } from "@/components/tables/CreateFtsIndex";
 import {
   DataTable,
   defaultPaginationState,
@@ -1022,6 +1023,30 @@ function TablePane(props: {
                   }}
                 />
 
+                <SafeSheet
+                  children={(sheet) => {
+                    return (
+                      <>
+                        <SheetContent class={sheetMaxWidth}>
+                          <CreateFtsIndexForm
+                            schemaRefetch={props.schemaRefetch}
+                            table={table() as Table}
+                            {...sheet}
+                          />
+                        </SheetContent>
+
+                        <SheetTrigger
+                          as={(props: DialogTriggerProps) => (
+                            <Button variant="outline" {...props}>
+                              Add Full-Text Index
+                            </Button>
+                          )}
+                        />
+                      </>
+                    );
+                  }}
+                />
+
                 <Button
                   variant="destructive"
                   disabled={selectedIndexes().size == 0}
@@ -1,10 +1,14 @@
 export type * from "@bindings/AlterIndexRequest";
 export type * from "@bindings/AlterTableRequest";
 export type * from "@bindings/Column";
+export type * from "@bindings/ColumnInfo";
 export type * from "@bindings/ColumnDataType";
 export type * from "@bindings/ColumnOption";
 export type * from "@bindings/ColumnOrder";
 export type * from "@bindings/ConfiguredOAuthProvidersResponse";
+export type * from "@bindings/CreateBackupResponse";
+export type * from "@bindings/CreateFtsIndexRequest";
+export type * from "@bindings/CreateFtsIndexResponse";
 export type * from "@bindings/CreateIndexRequest";
 export type * from "@bindings/CreateIndexResponse";
 export type * from "@bindings/CreateTableRequest";
@@ -15,10 +19,15 @@ export type * from "@bindings/DeleteRowsRequest";
 export type * from "@bindings/DropIndexRequest";
 export type * from "@bindings/DropTableRequest";
 export type * from "@bindings/ForeignKey";
+export type * from "@bindings/ForeignKeyInfo";
 export type * from "@bindings/GeneratedExpressionMode";
+export type * from "@bindings/IndexInfo";
+export type * from "@bindings/JobEntry";
 export type * from "@bindings/JsonSchema";
+export type * from "@bindings/ListJobsResponse";
 export type * from "@bindings/ListJsonSchemasResponse";
 export type * from "@bindings/ListLogsResponse";
+export type * from "@bindings/ListRateLimitsResponse";
 export type * from "@bindings/ListRowsResponse";
 export type * from "@bindings/ListSchemasResponse";
 export type * from "@bindings/ListUsersResponse";
@@ -30,13 +39,16 @@ export type * from "@bindings/OAuthProviderEntry";
 export type * from "@bindings/OAuthProviderResponse";
 export type * from "@bindings/ParseRequest";
 export type * from "@bindings/ParseResponse";
+export type * from "@bindings/PreviewScheduleResponse";
 export type * from "@bindings/QueryRequest";
 export type * from "@bindings/QueryResponse";
+export type * from "@bindings/RateLimitEntry";
 export type * from "@bindings/ReadFilesRequest";
 export type * from "@bindings/ReferentialAction";
 export type * from "@bindings/Stats";
 export type * from "@bindings/Table";
 export type * from "@bindings/TableIndex";
+export type * from "@bindings/TableInfo";
 export type * from "@bindings/TableTrigger";
 export type * from "@bindings/View";
 export type * from "@bindings/UniqueConstraint";
@@ -2,6 +2,8 @@ import { adminFetch } from "@/lib/fetch";
 import type {
   AlterIndexRequest,
   AlterTableRequest,
+  CreateFtsIndexRequest,
+  CreateFtsIndexResponse,
   CreateIndexRequest,
   CreateIndexResponse,
   CreateTableRequest,
@@ -29,6 +31,19 @@ export async function createIndex(
   return await response.json();
 }
 
+export async function createFtsIndex(
+  request: CreateFtsIndexRequest,
+): Promise<CreateFtsIndexResponse> {
+  const response = await adminFetch("/fts_index", {
+    method: "POST",
+    headers: {
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify(request),
+  });
+  return await response.json();
+}
+
 export async function createTable(
   request: CreateTableRequest,
 ): Promise<CreateTableResponse> {
@@ -0,0 +1,6 @@
+--
+-- Correlates log entries with the "X-Request-ID" header of the responses.
+--
+ALTER TABLE _logs ADD COLUMN request_id TEXT DEFAULT '' NOT NULL;
+
+CREATE INDEX IF NOT EXISTS __logs__request_id_index ON _logs (request_id);
@@ -0,0 +1,6 @@
+--
+-- Request and response bodies, only captured in dev mode when explicitly
+-- enabled to help debugging. Truncated to 4KiB.
+--
+ALTER TABLE _logs ADD COLUMN request_body TEXT;
+ALTER TABLE _logs ADD COLUMN response_body TEXT;
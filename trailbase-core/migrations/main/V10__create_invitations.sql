@@ -0,0 +1,17 @@
+--
+-- One-time invitations to register, e.g. when public registration is disabled.
+--
+CREATE TABLE _invitations (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  -- Normalized address of the invitee.
+  email                        TEXT NOT NULL,
+  -- SHA-256 hash of the token. The token itself is only ever sent by e-mail.
+  token_hash                   BLOB NOT NULL,
+  -- JSON array of ids of the groups the new user is added to.
+  group_ids                    TEXT DEFAULT '[]' NOT NULL CHECK(json_valid(group_ids)),
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  expires                      INTEGER NOT NULL,
+  used_at                      INTEGER
+) STRICT;
+
+CREATE UNIQUE INDEX __invitations__token_hash_index ON _invitations (token_hash);
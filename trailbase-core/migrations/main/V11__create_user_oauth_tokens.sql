@@ -0,0 +1,18 @@
+--
+-- Tokens issued by external OAuth providers, e.g. to call provider APIs on the user's behalf.
+--
+CREATE TABLE _user_oauth_tokens (
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- Name of the configured provider, e.g. "google".
+  provider                     TEXT NOT NULL,
+  -- Tokens are encrypted at rest with a key derived from the server's private key.
+  access_token                 BLOB NOT NULL,
+  refresh_token                BLOB,
+  -- Expiration of the access token, if known.
+  expires                      INTEGER,
+  updated                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+
+  PRIMARY KEY (user, provider)
+) STRICT;
+
+CREATE INDEX __user_oauth_tokens__expires_index ON _user_oauth_tokens (expires);
@@ -0,0 +1,6 @@
+--
+-- Forced password resets.
+--
+-- Set for users who need to pick a new password before they can access any
+-- record APIs, e.g. users created by an admin with a temporary password.
+ALTER TABLE _user ADD COLUMN must_change_password INTEGER DEFAULT FALSE NOT NULL;
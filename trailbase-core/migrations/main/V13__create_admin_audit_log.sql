@@ -0,0 +1,27 @@
+--
+-- Append-only log of admin API operations.
+--
+CREATE TABLE _admin_audit_log (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  admin_user_id                BLOB NOT NULL,
+  -- E.g. "create_user" or "update_config".
+  action                       TEXT NOT NULL,
+  resource_type                TEXT,
+  resource_id                  TEXT,
+  -- JSON object with the affected resource's "before" and/or "after" state, if available.
+  changes                      TEXT CHECK(changes IS NULL OR json_valid(changes)),
+  ip                           TEXT,
+  timestamp                    INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE INDEX __admin_audit_log__admin_user_id_index ON _admin_audit_log (admin_user_id);
+
+CREATE TRIGGER __admin_audit_log__immutable_update BEFORE UPDATE ON _admin_audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'admin audit log is immutable');
+END;
+
+CREATE TRIGGER __admin_audit_log__immutable_delete BEFORE DELETE ON _admin_audit_log
+BEGIN
+  SELECT RAISE(ABORT, 'admin audit log is immutable');
+END;
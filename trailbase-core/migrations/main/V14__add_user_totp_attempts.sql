@@ -0,0 +1,12 @@
+--
+-- TOTP replay protection and brute-force lockout.
+--
+-- Time step of the last accepted code. Codes for the same or earlier steps are
+-- rejected, such that an observed code cannot be used twice.
+ALTER TABLE _user_totp ADD COLUMN last_used_step INTEGER;
+
+-- Failed verifications since the last lockout or accepted code.
+ALTER TABLE _user_totp ADD COLUMN failed_count INTEGER DEFAULT 0 NOT NULL;
+
+-- Unix timestamp until which further codes are rejected.
+ALTER TABLE _user_totp ADD COLUMN locked_until INTEGER;
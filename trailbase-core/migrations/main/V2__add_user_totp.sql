@@ -0,0 +1,17 @@
+--
+-- TOTP (RFC 6238) second factor.
+--
+-- Set once a user has successfully verified their enrolled TOTP secret. Logins
+-- for such users require a second step to confirm a TOTP code.
+ALTER TABLE _user ADD COLUMN require_totp INTEGER DEFAULT FALSE NOT NULL;
+
+-- Sessions minted by a first-factor login of a user requiring TOTP, which
+-- cannot be refreshed until the second factor has been confirmed.
+ALTER TABLE _session ADD COLUMN totp_pending INTEGER DEFAULT FALSE NOT NULL;
+
+CREATE TABLE _user_totp (
+  user                         BLOB PRIMARY KEY NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- Shared secret encrypted at rest: nonce || ciphertext.
+  secret                       BLOB NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
@@ -0,0 +1,18 @@
+--
+-- API keys: long-lived, static bearer tokens for non-interactive clients.
+--
+CREATE TABLE _api_keys (
+  id                           BLOB PRIMARY KEY NOT NULL CHECK(is_uuid_v7(id)) DEFAULT (uuid_v7()),
+  user_id                      BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  name                         TEXT NOT NULL,
+  -- Public part of the key used for lookups.
+  prefix                       TEXT NOT NULL,
+  -- Argon2 hash of the secret suffix salted with the key's id.
+  hash                         BLOB NOT NULL,
+  created_at                   INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  last_used_at                 INTEGER,
+  expires_at                   INTEGER
+) STRICT;
+
+CREATE UNIQUE INDEX __api_keys__prefix_index ON _api_keys (prefix);
+CREATE INDEX __api_keys__user_id_index ON _api_keys (user_id);
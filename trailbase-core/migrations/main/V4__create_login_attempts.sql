@@ -0,0 +1,12 @@
+--
+-- Failed login attempts used to lock out brute-force attacks.
+--
+CREATE TABLE _login_attempts (
+  email                        TEXT NOT NULL,
+  ip_address                   TEXT NOT NULL,
+  failed_count                 INTEGER DEFAULT 0 NOT NULL,
+  -- Unix timestamp until which further login attempts are rejected.
+  locked_until                 INTEGER,
+
+  PRIMARY KEY (email, ip_address)
+) STRICT;
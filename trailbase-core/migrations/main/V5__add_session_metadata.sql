@@ -0,0 +1,17 @@
+--
+-- Session metadata to let users identify and revoke individual sessions.
+--
+-- NOTE: NULL for sessions created prior to this migration.
+ALTER TABLE _session ADD COLUMN created INTEGER;
+ALTER TABLE _session ADD COLUMN user_agent TEXT;
+ALTER TABLE _session ADD COLUMN ip_address TEXT;
+-- Revoked sessions can no longer be refreshed. They're retained until expiry.
+ALTER TABLE _session ADD COLUMN revoked INTEGER DEFAULT FALSE NOT NULL;
+
+-- Only touch the updated session rather than all of the user's sessions, since
+-- individual sessions may now be updated, e.g. revoked.
+DROP TRIGGER __session__updated_trigger;
+CREATE TRIGGER __session__updated_trigger AFTER UPDATE ON _session FOR EACH ROW
+  BEGIN
+    UPDATE _session SET updated = UNIXEPOCH() WHERE id = OLD.id;
+  END;
@@ -0,0 +1,15 @@
+--
+-- One-time tokens for passwordless, e-mail based login.
+--
+CREATE TABLE _magic_link_tokens (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- SHA-256 hash of the token. The token itself is only ever sent by e-mail.
+  token_hash                   BLOB NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  expires                      INTEGER NOT NULL,
+  used                         INTEGER DEFAULT FALSE NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __magic_link_tokens__token_hash_index ON _magic_link_tokens (token_hash);
+CREATE INDEX __magic_link_tokens__user_index ON _magic_link_tokens (user);
@@ -0,0 +1,39 @@
+--
+-- Outbound webhooks notifying external services about record changes.
+--
+CREATE TABLE _webhooks (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  name                         TEXT NOT NULL,
+  url                          TEXT NOT NULL,
+  -- Shared secret for signing payloads. Needs to be kept in plain text to compute HMACs.
+  secret                       TEXT NOT NULL,
+  -- JSON array of subscribed operations, i.e. "INSERT", "UPDATE" and/or "DELETE".
+  events                       TEXT NOT NULL CHECK(json_valid(events)),
+  -- Name of the observed table.
+  table_filter                 TEXT NOT NULL,
+  active                       INTEGER DEFAULT TRUE NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __webhooks__name_index ON _webhooks (name);
+
+--
+-- Outbox of webhook deliveries populated by triggers on the observed tables.
+--
+CREATE TABLE _webhook_deliveries (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  webhook_id                   INTEGER NOT NULL REFERENCES _webhooks(id) ON DELETE CASCADE,
+  event                        TEXT NOT NULL,
+  payload                      TEXT NOT NULL,
+  -- One of "pending", "delivered" or "failed".
+  status                       TEXT DEFAULT 'pending' NOT NULL,
+  attempts                     INTEGER DEFAULT 0 NOT NULL,
+  next_attempt                 INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+  -- HTTP status code of the last attempt, if any.
+  response_status              INTEGER,
+  error                        TEXT,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE INDEX __webhook_deliveries__webhook_id_index ON _webhook_deliveries (webhook_id);
+CREATE INDEX __webhook_deliveries__status_index ON _webhook_deliveries (status, next_attempt);
@@ -0,0 +1,16 @@
+--
+-- Device tokens for sending push notifications to users via FCM or APNs.
+--
+CREATE TABLE _push_tokens (
+  id                           INTEGER PRIMARY KEY NOT NULL,
+  user_id                      BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- Either "fcm" or "apns".
+  provider                     TEXT NOT NULL CHECK(provider IN ('fcm', 'apns')),
+  token                        TEXT NOT NULL,
+  -- Optional client-chosen device identifier. Re-registering a device replaces its old token.
+  device_id                    TEXT,
+  created_at                   INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX __push_tokens__provider_token_index ON _push_tokens (provider, token);
+CREATE INDEX __push_tokens__user_id_index ON _push_tokens (user_id);
@@ -0,0 +1,21 @@
+--
+-- Groups of users, e.g. teams or tenants, to grant access to.
+--
+CREATE TABLE _groups (
+  -- Either chosen by an admin, e.g. "admin", or a random url-safe Base64 id.
+  id                           TEXT PRIMARY KEY NOT NULL,
+  name                         TEXT NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL
+) STRICT;
+
+CREATE TABLE _group_members (
+  group_id                     TEXT NOT NULL REFERENCES _groups(id) ON DELETE CASCADE,
+  user                         BLOB NOT NULL REFERENCES _user(id) ON DELETE CASCADE,
+  -- Owners can add further members.
+  owner                        INTEGER DEFAULT FALSE NOT NULL,
+  created                      INTEGER DEFAULT (UNIXEPOCH()) NOT NULL,
+
+  PRIMARY KEY (group_id, user)
+) STRICT;
+
+CREATE INDEX __group_members__user_index ON _group_members (user);
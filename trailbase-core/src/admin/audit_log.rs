@@ -0,0 +1,320 @@
+use axum::extract::{Query, Request, State};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Json;
+use lazy_static::lazy_static;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use trailbase_sqlite::{named_params, params};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::auth::User;
+use crate::constants::ADMIN_AUDIT_LOG_TABLE;
+use crate::extract::client_ip;
+use crate::listing::limit_or_default;
+use crate::util::id_to_b64;
+
+tokio::task_local! {
+  static AUDIT_EVENT: RefCell<Option<AuditEvent>>;
+}
+
+/// Details of an admin operation recorded in the audit log. Handlers can provide them using
+/// [annotate_audit_event], otherwise the request's method and path are recorded as the action.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditEvent {
+  /// E.g. "create_user".
+  pub action: String,
+  pub resource_type: Option<String>,
+  pub resource_id: Option<String>,
+  /// JSON object with the resource's "before" and/or "after" state.
+  pub changes: Option<serde_json::Value>,
+}
+
+/// Annotates the admin request currently being handled with details for its audit log entry.
+/// No-op outside of admin requests, e.g. when handlers are called directly.
+pub(crate) fn annotate_audit_event(event: AuditEvent) {
+  let _ = AUDIT_EVENT.try_with(|current| *current.borrow_mut() = Some(event));
+}
+
+/// Records successful, mutating admin requests, i.e. anything but GET, HEAD and OPTIONS, in the
+/// audit log.
+///
+/// NOTE: Expects the request to be authenticated and authorized already.
+pub(crate) async fn audit_admin_request(
+  state: &AppState,
+  admin: &User,
+  request: Request,
+  next: Next,
+) -> Response {
+  if matches!(
+    *request.method(),
+    Method::GET | Method::HEAD | Method::OPTIONS
+  ) {
+    return next.run(request).await;
+  }
+
+  let ip = client_ip(request.headers(), request.extensions()).map(|ip| ip.to_string());
+  let fallback_action = format!("{} {}", request.method(), request.uri().path());
+
+  let (response, event) = AUDIT_EVENT
+    .scope(RefCell::new(None), async move {
+      let response = next.run(request).await;
+      return (response, AUDIT_EVENT.with(|event| event.take()));
+    })
+    .await;
+
+  if !response.status().is_success() {
+    return response;
+  }
+
+  let event = event.unwrap_or(AuditEvent {
+    action: fallback_action,
+    ..Default::default()
+  });
+  if let Err(err) = insert_audit_log_entry(state, admin, event, ip).await {
+    error!("Failed to write admin audit log: {err}");
+  }
+
+  return response;
+}
+
+async fn insert_audit_log_entry(
+  state: &AppState,
+  admin: &User,
+  event: AuditEvent,
+  ip: Option<String>,
+) -> Result<(), Error> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        INSERT INTO '{ADMIN_AUDIT_LOG_TABLE}' (admin_user_id, action, resource_type, resource_id, changes, ip)
+        VALUES (:admin_user_id, :action, :resource_type, :resource_id, :changes, :ip)
+      "#
+    );
+  }
+
+  let changes = event
+    .changes
+    .map(|changes| serde_json::to_string(&changes))
+    .transpose()?;
+
+  state
+    .conn()
+    .execute(
+      &QUERY,
+      named_params! {
+        ":admin_user_id": admin.uuid.into_bytes(),
+        ":action": event.action,
+        ":resource_type": event.resource_type,
+        ":resource_id": event.resource_id,
+        ":changes": changes,
+        ":ip": ip,
+      },
+    )
+    .await?;
+
+  return Ok(());
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct AuditLogEntryJson {
+  pub id: i64,
+  /// Url-safe Base64 encoded id of the admin who performed the operation.
+  pub admin_user_id: String,
+  pub action: String,
+  pub resource_type: Option<String>,
+  pub resource_id: Option<String>,
+  #[ts(type = "Object | undefined")]
+  pub changes: Option<serde_json::Value>,
+  pub ip: Option<String>,
+  /// Unix timestamp in seconds.
+  pub timestamp: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListAuditLogQuery {
+  limit: Option<usize>,
+  offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListAuditLogResponse {
+  /// Most recent entries first.
+  pub entries: Vec<AuditLogEntryJson>,
+}
+
+pub async fn list_audit_log_handler(
+  State(state): State<AppState>,
+  Query(query): Query<ListAuditLogQuery>,
+) -> Result<Json<ListAuditLogResponse>, Error> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT id, admin_user_id, action, resource_type, resource_id, changes, ip, timestamp
+        FROM '{ADMIN_AUDIT_LOG_TABLE}'
+        ORDER BY id DESC
+        LIMIT $1 OFFSET $2
+      "#
+    );
+  }
+
+  let rows = state
+    .conn()
+    .query(
+      &QUERY,
+      params!(
+        limit_or_default(query.limit) as i64,
+        query.offset.unwrap_or(0) as i64
+      ),
+    )
+    .await?;
+
+  let entries = rows
+    .iter()
+    .map(|row| -> Result<AuditLogEntryJson, Error> {
+      let admin_user_id: [u8; 16] = row.get(1)?;
+      let changes: Option<String> = row.get(5)?;
+      return Ok(AuditLogEntryJson {
+        id: row.get(0)?,
+        admin_user_id: id_to_b64(&admin_user_id),
+        action: row.get(2)?,
+        resource_type: row.get(3)?,
+        resource_id: row.get(4)?,
+        changes: changes
+          .map(|changes| serde_json::from_str(&changes))
+          .transpose()?,
+        ip: row.get(6)?,
+        timestamp: row.get(7)?,
+      });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  return Ok(Json(ListAuditLogResponse { entries }));
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::http::{header, StatusCode};
+  use axum::routing::post;
+  use axum::{middleware, Router};
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::admin::user::{create_user_for_test, create_user_handler, CreateUserRequest};
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::PermissionFlag;
+  use crate::records::{add_record_api, AccessRules, Acls};
+
+  #[tokio::test]
+  async fn test_admin_audit_log() {
+    let state = test_state(None).await.unwrap();
+
+    let password = "Secret!1!!";
+    let admin_id = create_user_for_test(&state, "admin@test.com", password)
+      .await
+      .unwrap();
+    let tokens = login_with_password(&state, "admin@test.com", password)
+      .await
+      .unwrap();
+    let admin = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+
+    let router = Router::new()
+      .route("/user", post(create_user_handler))
+      .layer(middleware::from_fn_with_state(
+        state.clone(),
+        move |State(state): State<AppState>, request: Request, next: Next| {
+          let admin = admin.clone();
+          async move { audit_admin_request(&state, &admin, request, next).await }
+        },
+      ))
+      .with_state(state.clone());
+
+    let response = router
+      .oneshot(
+        axum::http::Request::post("/user")
+          .header(header::CONTENT_TYPE, "application/json")
+          .body(Body::from(
+            serde_json::to_vec(&CreateUserRequest {
+              email: "new@test.com".to_string(),
+              password: password.to_string(),
+              verified: true,
+              ..Default::default()
+            })
+            .unwrap(),
+          ))
+          .unwrap(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    let user_id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["id"]
+      .as_str()
+      .unwrap()
+      .parse::<uuid::Uuid>()
+      .unwrap();
+
+    let Json(response) =
+      list_audit_log_handler(State(state.clone()), Query(ListAuditLogQuery::default()))
+        .await
+        .unwrap();
+    assert_eq!(response.entries.len(), 1);
+    let entry = &response.entries[0];
+    assert_eq!(entry.action, "create_user");
+    assert_eq!(entry.resource_type.as_deref(), Some("user"));
+    assert_eq!(entry.resource_id, Some(id_to_b64(&user_id.into_bytes())));
+    assert_eq!(entry.admin_user_id, id_to_b64(&admin_id.into_bytes()));
+    assert_eq!(
+      entry.changes.as_ref().unwrap()["after"]["email"],
+      "new@test.com"
+    );
+
+    // The log is immutable.
+    let conn = state.conn();
+    assert!(conn
+      .execute(&format!("DELETE FROM '{ADMIN_AUDIT_LOG_TABLE}'"), ())
+      .await
+      .is_err());
+    assert!(conn
+      .execute(
+        &format!("UPDATE '{ADMIN_AUDIT_LOG_TABLE}' SET action = 'forged'"),
+        ()
+      )
+      .await
+      .is_err());
+
+    // ...and can at most be exposed read-only through record APIs.
+    let acls = |flags: Vec<PermissionFlag>| Acls {
+      authenticated: flags,
+      ..Default::default()
+    };
+    assert!(add_record_api(
+      &state,
+      "audit_log",
+      ADMIN_AUDIT_LOG_TABLE,
+      acls(vec![PermissionFlag::Read, PermissionFlag::Delete]),
+      AccessRules::default(),
+    )
+    .await
+    .is_err());
+    add_record_api(
+      &state,
+      "audit_log",
+      ADMIN_AUDIT_LOG_TABLE,
+      acls(vec![PermissionFlag::Read]),
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+  }
+}
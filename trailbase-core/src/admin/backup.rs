@@ -0,0 +1,34 @@
+use axum::extract::State;
+use axum::Json;
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::backup::{backup, default_backup_filename};
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CreateBackupResponse {
+  /// Name of the compressed backup file within the "backups/" directory.
+  filename: String,
+}
+
+pub async fn create_backup_handler(
+  State(state): State<AppState>,
+) -> Result<Json<CreateBackupResponse>, Error> {
+  let filename = default_backup_filename(/* compress= */ true);
+  let backup_path = state.data_dir().backup_path();
+  tokio::fs::create_dir_all(&backup_path)
+    .await
+    .map_err(crate::backup::BackupError::from)?;
+
+  backup(
+    state.conn(),
+    backup_path.join(&filename),
+    /* compress= */ true,
+  )
+  .await?;
+
+  return Ok(Json(CreateBackupResponse { filename }));
+}
@@ -0,0 +1,62 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::WalCheckpointMode;
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+#[ts(export)]
+pub enum CheckpointMode {
+  #[default]
+  Passive,
+  Full,
+  Restart,
+  Truncate,
+}
+
+impl From<CheckpointMode> for WalCheckpointMode {
+  fn from(mode: CheckpointMode) -> Self {
+    return match mode {
+      CheckpointMode::Passive => Self::Passive,
+      CheckpointMode::Full => Self::Full,
+      CheckpointMode::Restart => Self::Restart,
+      CheckpointMode::Truncate => Self::Truncate,
+    };
+  }
+}
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[ts(export)]
+pub struct CheckpointRequest {
+  /// Defaults to "passive", which doesn't block concurrent readers or writers.
+  pub mode: Option<CheckpointMode>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CheckpointResponse {
+  /// Number of pages in the WAL.
+  pub wal_pages: u32,
+  /// Number of WAL pages moved into the main database file.
+  pub checkpointed_pages: u32,
+}
+
+/// Checkpoint the main database's WAL, e.g. to trigger checkpoints during low-traffic periods.
+pub async fn checkpoint_handler(
+  State(state): State<AppState>,
+  Json(request): Json<CheckpointRequest>,
+) -> Result<Json<CheckpointResponse>, Error> {
+  let (wal_pages, checkpointed_pages) = state
+    .conn()
+    .checkpoint(request.mode.unwrap_or_default().into())
+    .await?;
+
+  return Ok(Json(CheckpointResponse {
+    wal_pages,
+    checkpointed_pages,
+  }));
+}
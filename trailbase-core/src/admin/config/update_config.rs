@@ -4,10 +4,10 @@ use axum::response::IntoResponse;
 use axum_extra::protobuf::Protobuf;
 use base64::prelude::*;
 
-use crate::admin::AdminError as Error;
+use crate::admin::{annotate_audit_event, AdminError as Error, AuditEvent};
 use crate::app_state::AppState;
-use crate::config::proto::UpdateConfigRequest;
-use crate::config::ConfigError;
+use crate::config::proto::{Config, UpdateConfigRequest};
+use crate::config::{strip_secrets, ConfigError};
 
 pub async fn update_config_handler(
   State(state): State<AppState>,
@@ -20,14 +20,33 @@ pub async fn update_config_handler(
     return Err(Error::Precondition("Missing config".to_string()));
   };
 
-  let current_hash = state.get_config().hash();
+  let current_config = state.get_config();
+  let current_hash = current_config.hash();
   if current_hash.to_le_bytes() == *hash {
+    let changes = serde_json::json!({
+      "before": redacted_config_text(&current_config)?,
+      "after": redacted_config_text(&config)?,
+    });
+
     state
       .validate_and_update_config(config, Some(current_hash))
       .await?;
 
+    annotate_audit_event(AuditEvent {
+      action: "update_config".to_string(),
+      resource_type: Some("config".to_string()),
+      resource_id: None,
+      changes: Some(changes),
+    });
+
     return Ok((StatusCode::OK, "Config updated"));
   }
 
   return Err(ConfigError::Update("Concurrent edit. Stale admin-UI cache?".to_string()).into());
 }
+
+/// Text representation of the config with secrets removed, e.g. for the audit log.
+fn redacted_config_text(config: &Config) -> Result<String, ConfigError> {
+  let (stripped, _secrets) = strip_secrets(config)?;
+  return stripped.to_text();
+}
@@ -0,0 +1,212 @@
+use axum::{
+  extract::{Path, State},
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::email::{is_valid_template_name, CustomEmailTemplate};
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct EmailTemplateJson {
+  /// Name used to reference the template, e.g. in a record API's `send_email_on_create`.
+  pub name: String,
+  /// Subject template. Defaults to the application name.
+  pub subject: Option<String>,
+  /// HTML body template.
+  pub html: String,
+  /// Optional plain-text alternative body template.
+  pub text: Option<String>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListEmailTemplatesResponse {
+  pub templates: Vec<EmailTemplateJson>,
+}
+
+pub async fn list_email_templates_handler(
+  State(state): State<AppState>,
+) -> Result<Json<ListEmailTemplatesResponse>, Error> {
+  let mut templates: Vec<EmailTemplateJson> = vec![];
+  for name in CustomEmailTemplate::list(state.data_dir()).await? {
+    let template = CustomEmailTemplate::load(state.data_dir(), &name).await?;
+    templates.push(EmailTemplateJson {
+      name,
+      subject: template.subject,
+      html: template.html,
+      text: template.text,
+    });
+  }
+
+  return Ok(Json(ListEmailTemplatesResponse { templates }));
+}
+
+/// Creates or replaces the template with the given name.
+pub async fn update_email_template_handler(
+  State(state): State<AppState>,
+  Json(request): Json<EmailTemplateJson>,
+) -> Result<(), Error> {
+  if !is_valid_template_name(&request.name) {
+    return Err(Error::Precondition(format!(
+      "Invalid template name: {}",
+      request.name
+    )));
+  }
+
+  let template = CustomEmailTemplate {
+    subject: request.subject,
+    html: request.html,
+    text: request.text,
+  };
+  template.store(state.data_dir(), &request.name).await?;
+
+  return Ok(());
+}
+
+pub async fn delete_email_template_handler(
+  State(state): State<AppState>,
+  Path(name): Path<String>,
+) -> Result<(), Error> {
+  if !is_valid_template_name(&name) {
+    return Err(Error::Precondition(format!(
+      "Invalid template name: {name}"
+    )));
+  }
+
+  if !CustomEmailTemplate::delete(state.data_dir(), &name).await? {
+    return Err(Error::NotFound(format!("email template '{name}'")));
+  }
+
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::Query;
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::app_state::{test_state, TestStateOptions};
+  use crate::config::proto::{PermissionFlag, RecordApiEmailOnCreate};
+  use crate::email::{testing::TestAsyncSmtpTransport, Mailer};
+  use crate::extract::Either;
+  use crate::records::create_record::{create_record_handler, CreateRecordQuery};
+  use crate::records::test_utils::json_row_from_value;
+  use crate::records::{add_record_api, AccessRules, Acls};
+
+  #[tokio::test]
+  async fn test_send_email_on_create() {
+    let mailer = TestAsyncSmtpTransport::new();
+    let state = test_state(Some(TestStateOptions {
+      mailer: Some(Mailer::Smtp(Arc::new(mailer.clone()))),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE signup (
+            id      INTEGER PRIMARY KEY,
+            email   TEXT NOT NULL,
+            name    TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    update_email_template_handler(
+      State(state.clone()),
+      Json(EmailTemplateJson {
+        name: "welcome".to_string(),
+        subject: Some("Welcome {{ name }}".to_string()),
+        html: "<p>Hello {{ name }}</p>".to_string(),
+        text: None,
+      }),
+    )
+    .await
+    .unwrap();
+
+    let Json(response) = list_email_templates_handler(State(state.clone()))
+      .await
+      .unwrap();
+    assert_eq!(response.templates.len(), 1);
+    assert_eq!(response.templates[0].name, "welcome");
+
+    assert!(update_email_template_handler(
+      State(state.clone()),
+      Json(EmailTemplateJson {
+        name: "../escape".to_string(),
+        subject: None,
+        html: String::new(),
+        text: None,
+      }),
+    )
+    .await
+    .is_err());
+
+    add_record_api(
+      &state,
+      "signup_api",
+      "signup",
+      Acls {
+        world: vec![PermissionFlag::Create],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().send_email_on_create = Some(RecordApiEmailOnCreate {
+      to_field: Some("email".to_string()),
+      template: Some("welcome".to_string()),
+    });
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let email = "alice@test.com";
+    create_record_handler(
+      State(state.clone()),
+      Path("signup_api".to_string()),
+      Query(CreateRecordQuery::default()),
+      None,
+      Either::Json(
+        json_row_from_value(serde_json::json!({
+          "email": email,
+          "name": "Alice",
+        }))
+        .unwrap(),
+      ),
+    )
+    .await
+    .unwrap();
+
+    let logs = mailer.get_logs();
+    assert_eq!(logs.len(), 1);
+    let (envelope, body) = &logs[0];
+    assert_eq!(envelope.to()[0].to_string(), email);
+    assert!(body.contains("Subject: Welcome Alice"), "{body}");
+    assert!(body.contains("Hello Alice"), "{body}");
+
+    delete_email_template_handler(State(state.clone()), Path("welcome".to_string()))
+      .await
+      .unwrap();
+    assert!(
+      delete_email_template_handler(State(state.clone()), Path("welcome".to_string()))
+        .await
+        .is_err()
+    );
+  }
+}
@@ -20,6 +20,8 @@ pub enum AdminError {
   Base64Decode(#[from] base64::DecodeError),
   #[error("Already exists: {0}")]
   AlreadyExists(&'static str),
+  #[error("Not found: {0}")]
+  NotFound(String),
   #[error("precondition failed: {0}")]
   Precondition(String),
   #[error("Schema error: {0}")]
@@ -52,6 +54,10 @@ pub enum AdminError {
   File(#[from] crate::records::files::FileError),
   #[error("Sql parse error: {0}")]
   SqlParse(#[from] sqlite3_parser::lexer::sql::Error),
+  #[error("Backup error: {0}")]
+  Backup(#[from] crate::backup::BackupError),
+  #[error("Webhook error: {0}")]
+  Webhook(#[from] crate::webhooks::WebhookError),
 }
 
 impl IntoResponse for AdminError {
@@ -63,6 +69,7 @@ impl IntoResponse for AdminError {
       Self::Deserialization(_) => (StatusCode::BAD_REQUEST, self.to_string()),
       Self::Precondition(_) => (StatusCode::BAD_REQUEST, self.to_string()),
       Self::AlreadyExists(_) => (StatusCode::CONFLICT, self.to_string()),
+      Self::NotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
       // NOTE: We can almost always leak the internal error (except for permission errors) since
       // these are errors for the admin apis.
       ref _err => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
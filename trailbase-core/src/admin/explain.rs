@@ -0,0 +1,123 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::Value;
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::records::json_to_sql::simple_json_value_to_param;
+use crate::schema::ColumnDataType;
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct ExplainRequest {
+  pub sql: String,
+  /// Positional parameters bound to the statement.
+  #[ts(type = "Object[] | null")]
+  pub params: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct QueryPlanEntry {
+  pub id: i64,
+  /// Id of the parent entry or zero for top-level entries.
+  pub parent: i64,
+  pub detail: String,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ExplainResponse {
+  pub plan: Vec<QueryPlanEntry>,
+}
+
+/// Returns the query plan of the given statement without executing it, e.g. to check index usage.
+pub async fn explain_handler(
+  State(state): State<AppState>,
+  Json(request): Json<ExplainRequest>,
+) -> Result<Json<ExplainResponse>, Error> {
+  let params = request
+    .params
+    .unwrap_or_default()
+    .into_iter()
+    .map(|value| simple_json_value_to_param(ColumnDataType::Text, value))
+    .collect::<Result<Vec<Value>, _>>()?;
+
+  let plan = state
+    .conn()
+    .explain_query_plan(&request.sql, params)
+    .await?
+    .into_iter()
+    .map(|row| QueryPlanEntry {
+      id: row.id,
+      parent: row.parent,
+      detail: row.detail,
+    })
+    .collect();
+
+  return Ok(Json(ExplainResponse { plan }));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_explain_handler() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE item (
+            id           INTEGER PRIMARY KEY,
+            name         TEXT NOT NULL,
+            price        INTEGER NOT NULL
+          ) STRICT;
+          CREATE INDEX item__name_index ON item (name);
+        "#,
+      )
+      .await
+      .unwrap();
+
+    let explain = |sql: &str, params: Vec<serde_json::Value>| {
+      let state = state.clone();
+      let sql = sql.to_string();
+      async move {
+        return explain_handler(
+          State(state),
+          Json(ExplainRequest {
+            sql,
+            params: Some(params),
+          }),
+        )
+        .await;
+      }
+    };
+
+    let Json(response) = explain(
+      "SELECT * FROM item WHERE price > $1",
+      vec![serde_json::json!(5)],
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.plan.len(), 1);
+    assert!(response.plan[0].detail.starts_with("SCAN item"));
+    assert!(!response.plan[0].detail.contains("USING INDEX"));
+
+    let Json(response) = explain(
+      "SELECT * FROM item WHERE name = $1",
+      vec![serde_json::json!("foo")],
+    )
+    .await
+    .unwrap();
+    assert!(response.plan[0]
+      .detail
+      .contains("USING INDEX item__name_index"));
+
+    assert!(explain("SELECT * FROM missing", vec![]).await.is_err());
+  }
+}
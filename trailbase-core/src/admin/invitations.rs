@@ -0,0 +1,185 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::params;
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::auth::api::register::{
+  generate_invitation_token, hash_invitation_token, validate_and_normalize_email_address,
+};
+use crate::constants::INVITATIONS_TABLE;
+use crate::email::Email;
+
+const INVITATION_TTL_SEC: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateInvitationRequest {
+  pub email: String,
+  /// Ids of the groups the new user is added to upon registration.
+  pub group_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateInvitationResponse {
+  pub id: i64,
+  /// Unix timestamp in seconds after which the invitation can no longer be used.
+  pub expires: i64,
+}
+
+/// Creates a one-time invitation and sends the registration link to the invitee.
+pub async fn create_invitation_handler(
+  State(state): State<AppState>,
+  Json(request): Json<CreateInvitationRequest>,
+) -> Result<Json<CreateInvitationResponse>, Error> {
+  let email = validate_and_normalize_email_address(&request.email)?;
+  let group_ids = serde_json::to_string(&request.group_ids.unwrap_or_default())?;
+
+  let token = generate_invitation_token();
+  let Some(row) = state
+    .user_conn()
+    .query_row(
+      &format!(
+        r#"
+          INSERT INTO '{INVITATIONS_TABLE}' (email, token_hash, group_ids, expires)
+          VALUES ($1, $2, $3, UNIXEPOCH() + $4)
+          RETURNING id, expires
+        "#
+      ),
+      params!(
+        email.clone(),
+        hash_invitation_token(&token).to_vec(),
+        group_ids,
+        INVITATION_TTL_SEC
+      ),
+    )
+    .await?
+  else {
+    return Err(Error::Precondition(
+      "Failed to create invitation".to_string(),
+    ));
+  };
+
+  Email::invitation_email(&state, &email, &token)?
+    .send()
+    .await?;
+
+  return Ok(Json(CreateInvitationResponse {
+    id: row.get(0)?,
+    expires: row.get(1)?,
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::Form;
+  use axum::http::StatusCode;
+  use axum::response::IntoResponse;
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::app_state::{test_state, TestStateOptions};
+  use crate::auth::api::groups::user_groups;
+  use crate::auth::api::login::login_with_password;
+  use crate::auth::api::register::{register_user_handler, RegisterUserRequest};
+  use crate::auth::util::user_by_email;
+  use crate::auth::AuthError;
+  use crate::constants::GROUPS_TABLE;
+  use crate::email::{testing::TestAsyncSmtpTransport, Mailer};
+
+  #[tokio::test]
+  async fn test_invitation_registration() {
+    let mailer = TestAsyncSmtpTransport::new();
+    let state = test_state(Some(TestStateOptions {
+      mailer: Some(Mailer::Smtp(Arc::new(mailer.clone()))),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    let mut config = state.get_config();
+    config.auth.disable_public_registration = Some(true);
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    state
+      .user_conn()
+      .execute(
+        &format!("INSERT INTO '{GROUPS_TABLE}' (id, name) VALUES ('beta', 'Beta Testers')"),
+        (),
+      )
+      .await
+      .unwrap();
+
+    let password = "Secret!1!!".to_string();
+    let register = |email: &str, invitation_token: Option<String>| {
+      register_user_handler(
+        State(state.clone()),
+        Form(RegisterUserRequest {
+          email: email.to_string(),
+          password: password.clone(),
+          password_repeat: password.clone(),
+          invitation_token,
+        }),
+      )
+    };
+
+    // Public registration is disabled.
+    let email = "invitee@test.com";
+    let err = register(email, None).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+
+    create_invitation_handler(
+      State(state.clone()),
+      Json(CreateInvitationRequest {
+        email: email.to_string(),
+        group_ids: Some(vec!["beta".to_string()]),
+      }),
+    )
+    .await
+    .unwrap();
+
+    let logs = mailer.get_logs();
+    assert_eq!(logs.len(), 1);
+    let (envelope, body) = &logs[0];
+    assert_eq!(envelope.to()[0].to_string(), email);
+    let body = String::from_utf8_lossy(
+      &quoted_printable::decode(body.as_bytes(), quoted_printable::ParseMode::Robust).unwrap(),
+    )
+    .to_string();
+
+    // Extract the token from the e-mail's link.
+    const NEEDLE: &str = "invitation_token=";
+    let start = body.find(NEEDLE).unwrap() + NEEDLE.len();
+    let token: String = body[start..]
+      .chars()
+      .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+      .collect();
+
+    // Invitations are bound to the invitee's address.
+    assert!(matches!(
+      register("other@test.com", Some(token.clone())).await,
+      Err(AuthError::Forbidden)
+    ));
+
+    let response = register(email, Some(token.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Invited users don't need to verify their address and are added to the invitation's groups.
+    let user = user_by_email(&state, email).await.unwrap();
+    assert!(user.verified);
+    assert_eq!(
+      user_groups(state.user_conn(), user.uuid()).await.unwrap(),
+      vec!["beta".to_string()]
+    );
+    login_with_password(&state, email, &password).await.unwrap();
+
+    // Invitations are single-use.
+    let err = register(email, Some(token)).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+  }
+}
@@ -0,0 +1,147 @@
+use axum::{
+  extract::{Path, Query, State},
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::scheduler::{preview_next, Job};
+
+#[derive(Debug, Serialize, TS)]
+pub struct JobEntry {
+  name: String,
+  /// Cron expression or interval.
+  schedule: String,
+  /// Unix timestamps in seconds.
+  last_run_at: Option<i64>,
+  next_run_at: Option<i64>,
+  error_count: u64,
+  last_error: Option<String>,
+}
+
+impl From<&Job> for JobEntry {
+  fn from(job: &Job) -> Self {
+    let status = job.status();
+    return JobEntry {
+      name: job.name.clone(),
+      schedule: job.schedule.to_string(),
+      last_run_at: status.last_run_at.map(|t| t.timestamp()),
+      next_run_at: status.next_run_at.map(|t| t.timestamp()),
+      error_count: status.error_count,
+      last_error: status.last_error,
+    };
+  }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListJobsResponse {
+  jobs: Vec<JobEntry>,
+}
+
+pub async fn list_jobs_handler(
+  State(state): State<AppState>,
+) -> Result<Json<ListJobsResponse>, Error> {
+  return Ok(Json(ListJobsResponse {
+    jobs: state
+      .jobs()
+      .jobs()
+      .iter()
+      .map(|job| JobEntry::from(job.as_ref()))
+      .collect(),
+  }));
+}
+
+/// Runs the given job immediately, independent of its schedule.
+pub async fn run_job_handler(
+  State(state): State<AppState>,
+  Path(name): Path<String>,
+) -> Result<Json<JobEntry>, Error> {
+  let Some(job) = state.jobs().get(&name) else {
+    return Err(Error::NotFound(format!("job '{name}'")));
+  };
+
+  // NOTE: Failures are reflected in the returned entry's error count.
+  let _ = job.run().await;
+
+  return Ok(Json(JobEntry::from(job.as_ref())));
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PreviewScheduleQuery {
+  schedule: String,
+  count: Option<usize>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct PreviewScheduleResponse {
+  /// Unix timestamps in seconds of the next runs.
+  next_runs: Vec<i64>,
+}
+
+/// Validates a cron expression and lists its next run times.
+pub async fn preview_schedule_handler(
+  Query(query): Query<PreviewScheduleQuery>,
+) -> Result<Json<PreviewScheduleResponse>, Error> {
+  const DEFAULT_COUNT: usize = 5;
+  const MAX_COUNT: usize = 100;
+
+  let next_runs = preview_next(
+    &query.schedule,
+    query.count.unwrap_or(DEFAULT_COUNT).min(MAX_COUNT),
+  )
+  .map_err(|err| Error::Precondition(format!("Invalid schedule: {err}")))?;
+
+  return Ok(Json(PreviewScheduleResponse {
+    next_runs: next_runs.iter().map(|t| t.timestamp()).collect(),
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::scheduler::JobSchedule;
+
+  #[tokio::test]
+  async fn test_run_job() {
+    let state = test_state(None).await.unwrap();
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let counter_clone = counter.clone();
+    state.jobs().new_job(
+      "every_5_minutes",
+      JobSchedule::cron("0 */5 * * * *").unwrap(),
+      move || {
+        let counter = counter_clone.clone();
+        async move {
+          counter.fetch_add(1, Ordering::SeqCst);
+          Ok(())
+        }
+      },
+    );
+
+    let Json(entry) = run_job_handler(State(state.clone()), Path("every_5_minutes".to_string()))
+      .await
+      .unwrap();
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+    assert!(entry.last_run_at.is_some());
+    assert_eq!(entry.error_count, 0);
+
+    let Json(response) = list_jobs_handler(State(state.clone())).await.unwrap();
+    assert_eq!(response.jobs.len(), 1);
+    assert_eq!(response.jobs[0].schedule, "0 */5 * * * *");
+
+    assert!(
+      run_job_handler(State(state.clone()), Path("missing".to_string()))
+        .await
+        .is_err()
+    );
+  }
+}
@@ -37,6 +37,10 @@ pub struct LogJson {
   pub client_cc: Option<String>,
   pub referer: String,
   pub user_agent: String,
+  pub request_id: String,
+
+  pub request_body: Option<String>,
+  pub response_body: Option<String>,
 
   #[ts(type = "Object | undefined")]
   pub data: Option<serde_json::Value>,
@@ -60,6 +64,10 @@ struct LogQuery {
 
   referer: String,
   user_agent: String,
+  request_id: String,
+
+  request_body: Option<String>,
+  response_body: Option<String>,
 
   data: Option<serde_json::Value>,
 }
@@ -79,6 +87,9 @@ impl From<LogQuery> for LogJson {
       client_cc: value.client_cc,
       referer: value.referer,
       user_agent: value.user_agent,
+      request_id: value.request_id,
+      request_body: value.request_body,
+      response_body: value.response_body,
       data: value.data,
     };
   }
@@ -115,7 +126,7 @@ pub async fn list_logs_handler(
   // We could cache, however this is just the admin logs handler.
   let table = lookup_and_parse_table_schema(conn, LOGS_TABLE_NAME).await?;
   let table_metadata = TableMetadata::new(table.clone(), &[table]);
-  let filter_where_clause = build_filter_where_clause(&table_metadata, filter_params)?;
+  let filter_where_clause = build_filter_where_clause(&table_metadata, filter_params, None)?;
 
   let total_row_count = {
     let row = crate::util::query_one_row(
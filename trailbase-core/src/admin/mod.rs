@@ -1,16 +1,26 @@
+mod audit_log;
+mod backup;
+mod checkpoint;
 mod config;
+mod email_templates;
 mod error;
+mod explain;
 mod info;
+mod invitations;
+mod jobs;
 mod jwt;
 mod list_logs;
 mod oauth_providers;
 mod parse;
 mod query;
+mod rate_limits;
 pub(crate) mod rows;
 mod schema;
-mod table;
+pub(crate) mod table;
 pub(crate) mod user;
+mod webhooks;
 
+pub(crate) use audit_log::{annotate_audit_event, audit_admin_request, AuditEvent};
 pub use error::AdminError;
 
 use crate::app_state::AppState;
@@ -35,6 +45,7 @@ pub fn router() -> Router<AppState> {
     .route("/index", post(table::create_index_handler))
     .route("/index", patch(table::alter_index_handler))
     .route("/index", delete(table::drop_index_handler))
+    .route("/fts_index", post(table::create_fts_index_handler))
     // Table actions.
     .route(
       "/table/{table_name}/schema.json",
@@ -52,11 +63,25 @@ pub fn router() -> Router<AppState> {
     .route("/user", get(user::list_users_handler))
     .route("/user", post(user::create_user_handler))
     .route("/user", patch(user::update_user_handler))
+    .route(
+      "/user/{user_id}/sessions",
+      get(user::list_user_sessions_handler),
+    )
     // Schema actions
     .route("/schema", get(schema::list_schemas_handler))
     .route("/schema", post(schema::update_schema_handler))
+    .route("/schema/tables", get(schema::list_table_infos_handler))
+    .route(
+      "/schema/tables/{table_name}",
+      get(schema::get_table_info_handler),
+    )
+    .route(
+      "/schema/json_schema/{table_name}",
+      get(schema::get_table_json_schema_handler),
+    )
     // Logs
     .route("/logs", get(list_logs::list_logs_handler))
+    .route("/audit_log", get(audit_log::list_audit_log_handler))
     // Query execution handler for the UI editor
     .route("/query", post(query::query_handler))
     // Parse handler for UI validation.
@@ -68,4 +93,35 @@ pub fn router() -> Router<AppState> {
     )
     .route("/public_key", get(jwt::get_public_key))
     .route("/info", get(info::info_handler))
+    .route("/backup", post(backup::create_backup_handler))
+    .route("/database/checkpoint", post(checkpoint::checkpoint_handler))
+    .route("/database/explain", post(explain::explain_handler))
+    .route("/rate_limits", get(rate_limits::list_rate_limits_handler))
+    // Scheduled jobs.
+    .route("/jobs", get(jobs::list_jobs_handler))
+    .route("/jobs/preview", get(jobs::preview_schedule_handler))
+    .route("/jobs/{name}/run", post(jobs::run_job_handler))
+    // Custom email templates.
+    .route(
+      "/email_templates",
+      get(email_templates::list_email_templates_handler),
+    )
+    .route(
+      "/email_templates",
+      post(email_templates::update_email_template_handler),
+    )
+    .route(
+      "/email_templates/{name}",
+      delete(email_templates::delete_email_template_handler),
+    )
+    // Invitations.
+    .route("/invitations", post(invitations::create_invitation_handler))
+    // Webhooks.
+    .route("/webhooks", get(webhooks::list_webhooks_handler))
+    .route("/webhooks", post(webhooks::create_webhook_handler))
+    .route("/webhooks/{id}", delete(webhooks::delete_webhook_handler))
+    .route(
+      "/webhooks/{id}/deliveries",
+      get(webhooks::list_webhook_deliveries_handler),
+    )
 }
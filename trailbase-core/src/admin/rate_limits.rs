@@ -0,0 +1,51 @@
+use axum::{
+  extract::{Query, State},
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListRateLimitsQuery {
+  limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, TS)]
+pub struct RateLimitEntry {
+  /// Client IP address, user id or "global" depending on the rate limit configuration.
+  key: String,
+  /// Tokens left in the bucket as of the last request.
+  tokens: f64,
+  /// Seconds passed since the last request.
+  idle_seconds: f64,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListRateLimitsResponse {
+  /// Clients closest to or over their rate limit first.
+  entries: Vec<RateLimitEntry>,
+}
+
+pub async fn list_rate_limits_handler(
+  State(state): State<AppState>,
+  Query(query): Query<ListRateLimitsQuery>,
+) -> Result<Json<ListRateLimitsResponse>, Error> {
+  const DEFAULT_LIMIT: usize = 20;
+
+  let entries = state
+    .rate_limiter()
+    .top_consumers(query.limit.unwrap_or(DEFAULT_LIMIT))
+    .into_iter()
+    .map(|(key, tokens, idle)| RateLimitEntry {
+      key,
+      tokens,
+      idle_seconds: idle.as_secs_f64(),
+    })
+    .collect();
+
+  return Ok(Json(ListRateLimitsResponse { entries }));
+}
@@ -10,7 +10,7 @@ use ts_rs::TS;
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
 use crate::records::json_to_sql::simple_json_value_to_param;
-use crate::records::json_to_sql::DeleteQueryBuilder;
+use crate::records::json_to_sql::{DeleteQueryBuilder, WriteHooks};
 
 #[derive(Debug, Serialize, Deserialize, Default, TS)]
 #[ts(export)]
@@ -61,6 +61,7 @@ async fn delete_row(
     &table_metadata,
     pk_col,
     simple_json_value_to_param(column.data_type, value)?,
+    WriteHooks::default(),
   )
   .await?;
 
@@ -6,7 +6,7 @@ use ts_rs::TS;
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
 use crate::records::json_to_sql::{
-  simple_json_value_to_param, JsonRow, Params, UpdateQueryBuilder,
+  simple_json_value_to_param, JsonRow, Params, UpdateQueryBuilder, WriteHooks,
 };
 
 #[derive(Debug, Serialize, Deserialize, Default, TS)]
@@ -48,6 +48,8 @@ pub async fn update_row_handler(
     Params::from(&table_metadata, request.row, None)?,
     &column.name,
     simple_json_value_to_param(column.data_type, request.primary_key_value)?,
+    None,
+    WriteHooks::default(),
   )
   .await?;
 
@@ -7,6 +7,12 @@ use trailbase_sqlite::schema::{get_schemas, set_user_schema};
 use crate::admin::AdminError as Error;
 use crate::app_state::AppState;
 
+mod tables;
+
+pub(super) use tables::{
+  get_table_info_handler, get_table_json_schema_handler, list_table_infos_handler,
+};
+
 #[derive(Debug, Serialize, TS)]
 pub struct JsonSchema {
   pub name: String,
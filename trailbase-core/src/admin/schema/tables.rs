@@ -0,0 +1,231 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::schema::{
+  Column, ColumnDataType, ColumnOption, ColumnOrder, ForeignKey, ReferentialAction, TableIndex,
+};
+use crate::table_metadata::{
+  build_json_schema, lookup_and_parse_all_index_schemas, JsonSchemaMode, TableMetadata,
+};
+
+#[derive(Clone, Debug, Serialize, TS)]
+pub struct ColumnInfo {
+  pub name: String,
+  pub data_type: ColumnDataType,
+  pub primary_key: bool,
+  pub not_null: bool,
+  pub options: Vec<ColumnOption>,
+}
+
+impl From<&Column> for ColumnInfo {
+  fn from(column: &Column) -> Self {
+    return ColumnInfo {
+      name: column.name.clone(),
+      data_type: column.data_type,
+      primary_key: column.is_primary(),
+      not_null: column
+        .options
+        .iter()
+        .any(|opt| matches!(opt, ColumnOption::NotNull)),
+      options: column.options.clone(),
+    };
+  }
+}
+
+#[derive(Clone, Debug, Serialize, TS)]
+pub struct IndexInfo {
+  pub name: String,
+  pub columns: Vec<ColumnOrder>,
+  pub unique: bool,
+  pub predicate: Option<String>,
+}
+
+impl From<TableIndex> for IndexInfo {
+  fn from(index: TableIndex) -> Self {
+    return IndexInfo {
+      name: index.name,
+      columns: index.columns,
+      unique: index.unique,
+      predicate: index.predicate,
+    };
+  }
+}
+
+#[derive(Clone, Debug, Serialize, TS)]
+pub struct ForeignKeyInfo {
+  pub name: Option<String>,
+  /// Referencing columns of this table.
+  pub columns: Vec<String>,
+  pub foreign_table: String,
+  /// Referenced columns of the foreign table.
+  pub referred_columns: Vec<String>,
+  pub on_delete: Option<ReferentialAction>,
+  pub on_update: Option<ReferentialAction>,
+}
+
+impl From<ForeignKey> for ForeignKeyInfo {
+  fn from(fk: ForeignKey) -> Self {
+    return ForeignKeyInfo {
+      name: fk.name,
+      columns: fk.columns,
+      foreign_table: fk.foreign_table,
+      referred_columns: fk.referred_columns,
+      on_delete: fk.on_delete,
+      on_update: fk.on_update,
+    };
+  }
+}
+
+#[derive(Clone, Debug, Serialize, TS)]
+#[ts(export)]
+pub struct TableInfo {
+  pub name: String,
+  pub columns: Vec<ColumnInfo>,
+  pub indexes: Vec<IndexInfo>,
+  /// Both column-level and table-level foreign keys.
+  pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+fn build_table_info(table: &TableMetadata, indexes: &[TableIndex]) -> TableInfo {
+  return TableInfo {
+    name: table.name().to_string(),
+    columns: table.schema.columns.iter().map(ColumnInfo::from).collect(),
+    indexes: indexes
+      .iter()
+      .filter(|index| index.table_name == table.name())
+      .cloned()
+      .map(IndexInfo::from)
+      .collect(),
+    foreign_keys: table
+      .foreign_keys()
+      .into_iter()
+      .map(ForeignKeyInfo::from)
+      .collect(),
+  };
+}
+
+pub async fn list_table_infos_handler(
+  State(state): State<AppState>,
+) -> Result<Json<Vec<TableInfo>>, Error> {
+  // NOTE: Indexes are looked up fresh, since index changes don't invalidate the metadata cache.
+  let indexes = lookup_and_parse_all_index_schemas(state.conn()).await?;
+
+  return Ok(Json(
+    state
+      .table_metadata()
+      .tables()
+      .iter()
+      .map(|table| build_table_info(table, &indexes))
+      .collect(),
+  ));
+}
+
+pub async fn get_table_info_handler(
+  State(state): State<AppState>,
+  Path(table_name): Path<String>,
+) -> Result<Json<TableInfo>, Error> {
+  let Some(table) = state.table_metadata().get(&table_name) else {
+    return Err(Error::NotFound(format!("table '{table_name}'")));
+  };
+  let indexes = lookup_and_parse_all_index_schemas(state.conn()).await?;
+
+  return Ok(Json(build_table_info(&table, &indexes)));
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct JsonSchemaQuery {
+  /// Defaults to `Insert`.
+  mode: Option<JsonSchemaMode>,
+}
+
+pub async fn get_table_json_schema_handler(
+  State(state): State<AppState>,
+  Path(table_name): Path<String>,
+  Query(query): Query<JsonSchemaQuery>,
+) -> Result<Json<serde_json::Value>, Error> {
+  let Some(table) = state.table_metadata().get(&table_name) else {
+    return Err(Error::NotFound(format!("table '{table_name}'")));
+  };
+
+  let (_schema, json) = build_json_schema(
+    table.name(),
+    &*table,
+    query.mode.unwrap_or(JsonSchemaMode::Insert),
+  )?;
+
+  return Ok(Json(json));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_table_info_foreign_keys() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE parent (id INTEGER PRIMARY KEY) STRICT;
+          CREATE TABLE child (
+            id            INTEGER PRIMARY KEY,
+            parent        INTEGER REFERENCES parent(id) ON DELETE CASCADE,
+            other_parent  INTEGER NOT NULL,
+            FOREIGN KEY(other_parent) REFERENCES parent(id)
+          ) STRICT;
+          CREATE INDEX _child__parent_index ON child (parent);
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    let Json(tables) = list_table_infos_handler(State(state.clone()))
+      .await
+      .unwrap();
+    assert!(tables.iter().any(|t| t.name == "parent"));
+
+    let Json(child) = get_table_info_handler(State(state.clone()), Path("child".to_string()))
+      .await
+      .unwrap();
+    assert_eq!(child.columns.len(), 3);
+    assert!(child.columns[0].primary_key);
+    assert!(child.columns[2].not_null);
+
+    assert_eq!(child.indexes.len(), 1);
+    assert_eq!(child.indexes[0].name, "_child__parent_index");
+
+    assert_eq!(child.foreign_keys.len(), 2);
+    let fk = &child.foreign_keys[0];
+    assert_eq!(fk.columns, vec!["parent".to_string()]);
+    assert_eq!(fk.foreign_table, "parent");
+    assert_eq!(fk.referred_columns, vec!["id".to_string()]);
+    assert_eq!(fk.on_delete, Some(ReferentialAction::Cascade));
+    assert_eq!(
+      child.foreign_keys[1].columns,
+      vec!["other_parent".to_string()]
+    );
+
+    assert!(
+      get_table_info_handler(State(state.clone()), Path("missing".to_string()))
+        .await
+        .is_err()
+    );
+
+    let Json(schema) = get_table_json_schema_handler(
+      State(state.clone()),
+      Path("child".to_string()),
+      Query(JsonSchemaQuery::default()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(schema["required"], serde_json::json!(["other_parent"]));
+  }
+}
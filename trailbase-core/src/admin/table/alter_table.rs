@@ -10,6 +10,7 @@ use log::*;
 use serde::Deserialize;
 use ts_rs::TS;
 
+use crate::admin::{annotate_audit_event, AuditEvent};
 use crate::app_state::AppState;
 use crate::schema::Table;
 use crate::transaction::TransactionRecorder;
@@ -41,6 +42,17 @@ pub async fn alter_table_handler(
   let target_schema = request.target_schema;
   let target_table_name = target_schema.name.clone();
 
+  // Only recorded if the alteration succeeds.
+  annotate_audit_event(AuditEvent {
+    action: "alter_table".to_string(),
+    resource_type: Some("table".to_string()),
+    resource_id: Some(source_table_name.clone()),
+    changes: Some(serde_json::json!({
+      "before": source_schema,
+      "after": target_schema,
+    })),
+  });
+
   debug!("Alter table:\nsource: {source_schema:?}\ntarget: {target_schema:?}",);
 
   let temp_table_name: String = {
@@ -127,6 +139,9 @@ pub async fn alter_table_handler(
 
   state.table_metadata().invalidate_all().await?;
 
+  // Recreating the table dropped its webhook triggers.
+  crate::webhooks::install_all_webhook_triggers(conn, state.table_metadata()).await?;
+
   return Ok((StatusCode::OK, "altered table").into_response());
 }
 
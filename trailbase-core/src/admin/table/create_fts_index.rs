@@ -0,0 +1,135 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::transaction::TransactionRecorder;
+
+#[derive(Clone, Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateFtsIndexRequest {
+  pub table_name: String,
+  /// Columns to be indexed for full-text search.
+  pub columns: Vec<String>,
+  pub dry_run: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, TS)]
+#[ts(export)]
+pub struct CreateFtsIndexResponse {
+  pub sql: String,
+}
+
+/// Creates a "<table>_fts" FTS5 virtual table for the given table's columns, which is kept in sync
+/// by triggers and thus enables `?search=` queries against the table's record APIs.
+pub async fn create_fts_index_handler(
+  State(state): State<AppState>,
+  Json(request): Json<CreateFtsIndexRequest>,
+) -> Result<Json<CreateFtsIndexResponse>, Error> {
+  let dry_run = request.dry_run.unwrap_or(false);
+  let table_name = request.table_name.clone();
+
+  let Some(table_metadata) = state.table_metadata().get(&table_name) else {
+    return Err(Error::Precondition(format!("Table {table_name} not found")));
+  };
+  if request.columns.is_empty() {
+    return Err(Error::Precondition(
+      "FTS index needs at least one column".to_string(),
+    ));
+  }
+  for column in &request.columns {
+    if table_metadata.column_by_name(column).is_none() {
+      return Err(Error::Precondition(format!("Column {column} not found")));
+    }
+  }
+
+  let statements = create_fts_index_statements(&table_name, &request.columns);
+
+  if !dry_run {
+    let statements = statements.clone();
+    let migration_path = state.data_dir().migrations_path();
+    let conn = state.conn();
+    let writer = conn
+      .call(move |conn| {
+        let mut tx =
+          TransactionRecorder::new(conn, migration_path, format!("create_fts_{table_name}"))?;
+
+        for statement in &statements {
+          tx.execute(statement)?;
+        }
+
+        return tx
+          .rollback_and_create_migration()
+          .map_err(|err| trailbase_sqlite::Error::Other(err.into()));
+      })
+      .await?;
+
+    // Write to migration file.
+    if let Some(writer) = writer {
+      let _report = writer.write(conn).await?;
+    }
+
+    state.table_metadata().invalidate_all().await?;
+  }
+
+  return Ok(Json(CreateFtsIndexResponse {
+    sql: sqlformat::format(
+      &statements
+        .iter()
+        .map(|s| format!("{s};"))
+        .collect::<Vec<_>>()
+        .join("\n"),
+      &sqlformat::QueryParams::None,
+      &sqlformat::FormatOptions {
+        ignore_case_convert: None,
+        indent: sqlformat::Indent::Spaces(2),
+        uppercase: Some(true),
+        lines_between_queries: 1,
+      },
+    ),
+  }));
+}
+
+/// Builds the statements to create an external-content FTS5 table, back-fill it with the existing
+/// rows and keep it in sync using triggers.
+pub(crate) fn create_fts_index_statements(table_name: &str, columns: &[String]) -> Vec<String> {
+  let fts_table_name = format!("{table_name}_fts");
+
+  let column_list = columns
+    .iter()
+    .map(|c| format!(r#""{c}""#))
+    .collect::<Vec<_>>()
+    .join(", ");
+  let values = |prefix: &str| {
+    return columns
+      .iter()
+      .map(|c| format!(r#"{prefix}."{c}""#))
+      .collect::<Vec<_>>()
+      .join(", ");
+  };
+  let (new_values, old_values) = (values("new"), values("old"));
+
+  return vec![
+    format!(
+      r#"CREATE VIRTUAL TABLE "{fts_table_name}" USING fts5({column_list}, content='{table_name}', content_rowid='rowid')"#
+    ),
+    format!(r#"INSERT INTO "{fts_table_name}"("{fts_table_name}") VALUES('rebuild')"#),
+    format!(
+      r#"CREATE TRIGGER "__{fts_table_name}__insert_trigger" AFTER INSERT ON "{table_name}" BEGIN
+  INSERT INTO "{fts_table_name}"(rowid, {column_list}) VALUES (new.rowid, {new_values});
+END"#
+    ),
+    format!(
+      r#"CREATE TRIGGER "__{fts_table_name}__delete_trigger" AFTER DELETE ON "{table_name}" BEGIN
+  INSERT INTO "{fts_table_name}"("{fts_table_name}", rowid, {column_list}) VALUES ('delete', old.rowid, {old_values});
+END"#
+    ),
+    format!(
+      r#"CREATE TRIGGER "__{fts_table_name}__update_trigger" AFTER UPDATE ON "{table_name}" BEGIN
+  INSERT INTO "{fts_table_name}"("{fts_table_name}", rowid, {column_list}) VALUES ('delete', old.rowid, {old_values});
+  INSERT INTO "{fts_table_name}"(rowid, {column_list}) VALUES (new.rowid, {new_values});
+END"#
+    ),
+  ];
+}
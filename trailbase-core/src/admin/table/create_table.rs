@@ -2,7 +2,7 @@ use axum::{extract::State, Json};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::admin::AdminError as Error;
+use crate::admin::{annotate_audit_event, AdminError as Error, AuditEvent};
 use crate::app_state::AppState;
 use crate::schema::Table;
 use crate::transaction::TransactionRecorder;
@@ -58,6 +58,13 @@ pub async fn create_table_handler(
     }
 
     state.table_metadata().invalidate_all().await?;
+
+    annotate_audit_event(AuditEvent {
+      action: "create_table".to_string(),
+      resource_type: Some("table".to_string()),
+      resource_id: Some(request.schema.name.clone()),
+      changes: Some(serde_json::json!({ "after": request.schema })),
+    });
   }
 
   return Ok(Json(CreateTableResponse {
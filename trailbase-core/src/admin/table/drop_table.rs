@@ -8,7 +8,7 @@ use log::*;
 use serde::Deserialize;
 use ts_rs::TS;
 
-use crate::admin::AdminError as Error;
+use crate::admin::{annotate_audit_event, AdminError as Error, AuditEvent};
 use crate::app_state::AppState;
 use crate::transaction::TransactionRecorder;
 
@@ -79,5 +79,12 @@ pub async fn drop_table_handler(
 
   state.table_metadata().invalidate_all().await?;
 
+  annotate_audit_event(AuditEvent {
+    action: format!("drop_{}", entity_type.to_lowercase()),
+    resource_type: Some(entity_type.to_lowercase()),
+    resource_id: Some(table_name.clone()),
+    changes: None,
+  });
+
   return Ok((StatusCode::OK, "").into_response());
 }
@@ -1,10 +1,14 @@
 // Indexes
 mod alter_index;
+mod create_fts_index;
 mod create_index;
 mod drop_index;
 mod get_table_schema;
 
 pub(super) use alter_index::alter_index_handler;
+pub(super) use create_fts_index::create_fts_index_handler;
+#[allow(unused)]
+pub(crate) use create_fts_index::create_fts_index_statements;
 pub(super) use create_index::create_index_handler;
 pub(super) use drop_index::drop_index_handler;
 pub(super) use get_table_schema::get_table_schema_handler;
@@ -14,7 +18,7 @@ mod alter_table;
 mod create_table;
 mod drop_table;
 
-pub(crate) use alter_table::alter_table_handler;
+pub(crate) use alter_table::{alter_table_handler, AlterTableRequest};
 #[allow(unused)]
 pub(crate) use create_table::{create_table_handler, CreateTableRequest};
 pub(crate) use drop_table::drop_table_handler;
@@ -5,7 +5,7 @@ use trailbase_sqlite::named_params;
 use ts_rs::TS;
 use uuid::Uuid;
 
-use crate::admin::AdminError as Error;
+use crate::admin::{annotate_audit_event, AdminError as Error, AuditEvent};
 use crate::app_state::AppState;
 use crate::auth::api::register::validate_and_normalize_email_address;
 use crate::auth::password::hash_password;
@@ -15,6 +15,7 @@ use crate::auth::util::user_exists;
 use crate::constants::{PASSWORD_OPTIONS, USER_TABLE, VERIFICATION_CODE_LENGTH};
 use crate::email::Email;
 use crate::rand::generate_random_string;
+use crate::util::id_to_b64;
 
 #[derive(Debug, Serialize, Deserialize, Default, TS)]
 #[ts(export)]
@@ -24,6 +25,10 @@ pub struct CreateUserRequest {
   pub verified: bool,
 
   pub admin: bool,
+
+  /// Requires the user to change their (temporary) password before accessing any record APIs.
+  #[serde(default)]
+  pub force_password_reset: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -55,9 +60,9 @@ pub async fn create_user_handler(
     static ref INSERT_USER_QUERY: String = indoc::formatdoc!(
       r#"
         INSERT INTO '{USER_TABLE}'
-          (email, password_hash, verified, admin, email_verification_code)
+          (email, password_hash, verified, admin, email_verification_code, must_change_password)
         VALUES
-          (:email, :password_hash, :verified, :admin ,:email_verification_code)
+          (:email, :password_hash, :verified, :admin ,:email_verification_code, :must_change_password)
         RETURNING *
      "#,
     );
@@ -73,6 +78,7 @@ pub async fn create_user_handler(
         ":verified": request.verified,
         ":admin": request.admin,
         ":email_verification_code": email_verification_code.clone(),
+        ":must_change_password": request.force_password_reset,
       },
     )
     .await?
@@ -86,9 +92,21 @@ pub async fn create_user_handler(
       .await?;
   }
 
-  return Ok(Json(CreateUserResponse {
-    id: Uuid::from_bytes(user.id),
-  }));
+  let id = Uuid::from_bytes(user.id);
+  annotate_audit_event(AuditEvent {
+    action: "create_user".to_string(),
+    resource_type: Some("user".to_string()),
+    resource_id: Some(id_to_b64(&user.id)),
+    changes: Some(serde_json::json!({
+      "after": {
+        "email": user.email,
+        "verified": user.verified,
+        "admin": user.admin,
+      },
+    })),
+  });
+
+  return Ok(Json(CreateUserResponse { id }));
 }
 
 #[cfg(test)]
@@ -104,6 +122,7 @@ pub(crate) async fn create_user_for_test(
       password: password.to_string(),
       verified: true,
       admin: false,
+      force_password_reset: false,
     }),
   )
   .await?;
@@ -0,0 +1,18 @@
+use axum::{
+  extract::{Path, State},
+  Json,
+};
+use uuid::Uuid;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::auth::api::sessions::{list_active_sessions, ListSessionsResponse};
+
+pub async fn list_user_sessions_handler(
+  State(state): State<AppState>,
+  Path(user_id): Path<Uuid>,
+) -> Result<Json<ListSessionsResponse>, Error> {
+  return Ok(Json(ListSessionsResponse {
+    sessions: list_active_sessions(&state, user_id).await?,
+  }));
+}
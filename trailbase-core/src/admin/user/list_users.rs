@@ -77,7 +77,7 @@ pub async fn list_users_handler(
   };
   // Where clause contains column filters and cursor depending on what's present in the url query
   // string.
-  let filter_where_clause = build_filter_where_clause(&*table_metadata, filter_params)?;
+  let filter_where_clause = build_filter_where_clause(&*table_metadata, filter_params, None)?;
 
   let total_row_count = {
     let where_clause = &filter_where_clause.clause;
@@ -1,8 +1,10 @@
 mod create_user;
+mod list_sessions;
 mod list_users;
 mod update_user;
 
 pub use create_user::{create_user_handler, CreateUserRequest};
+pub(super) use list_sessions::list_user_sessions_handler;
 pub(super) use list_users::list_users_handler;
 pub(super) use update_user::update_user_handler;
 
@@ -44,6 +46,7 @@ mod tests {
         password: "Secret!1!!".to_string(),
         verified: true,
         admin: true,
+        force_password_reset: false,
       }),
     )
     .await
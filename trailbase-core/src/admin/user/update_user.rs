@@ -9,10 +9,11 @@ use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::admin::AdminError as Error;
+use crate::admin::{annotate_audit_event, AdminError as Error, AuditEvent};
 use crate::app_state::AppState;
 use crate::auth::password::hash_password;
 use crate::constants::USER_TABLE;
+use crate::util::id_to_b64;
 
 #[derive(Debug, Serialize, Deserialize, Default, TS)]
 #[ts(export)]
@@ -70,5 +71,19 @@ pub async fn update_user_handler(
     })
     .await?;
 
+  annotate_audit_event(AuditEvent {
+    action: "update_user".to_string(),
+    resource_type: Some("user".to_string()),
+    resource_id: Some(id_to_b64(&user_id_bytes)),
+    // NOTE: Never record the password itself.
+    changes: Some(serde_json::json!({
+      "after": {
+        "email": request.email,
+        "verified": request.verified,
+        "password_changed": request.password.is_some(),
+      },
+    })),
+  });
+
   return Ok((StatusCode::OK, format!("Updated user: {request:?}")).into_response());
 }
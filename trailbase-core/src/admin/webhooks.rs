@@ -0,0 +1,497 @@
+use axum::{
+  extract::{Path, Query, State},
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{named_params, params};
+use ts_rs::TS;
+
+use crate::admin::AdminError as Error;
+use crate::app_state::AppState;
+use crate::constants::{WEBHOOKS_TABLE, WEBHOOK_DELIVERIES_TABLE};
+use crate::listing::limit_or_default;
+use crate::rand::generate_random_string;
+use crate::webhooks::{install_webhook_triggers, uninstall_webhook_triggers, WEBHOOK_EVENTS};
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct WebhookJson {
+  pub id: i64,
+  pub name: String,
+  pub url: String,
+  /// Subscribed operations, i.e. "INSERT", "UPDATE" and/or "DELETE".
+  pub events: Vec<String>,
+  pub table_filter: String,
+  pub active: bool,
+  /// Unix timestamp in seconds.
+  pub created: i64,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListWebhooksResponse {
+  pub webhooks: Vec<WebhookJson>,
+}
+
+pub async fn list_webhooks_handler(
+  State(state): State<AppState>,
+) -> Result<Json<ListWebhooksResponse>, Error> {
+  let rows = state
+    .conn()
+    .query(
+      &format!(
+        "SELECT id, name, url, events, table_filter, active, created FROM '{WEBHOOKS_TABLE}' ORDER BY id"
+      ),
+      (),
+    )
+    .await?;
+
+  let webhooks = rows
+    .iter()
+    .map(|row| -> Result<WebhookJson, Error> {
+      let events: String = row.get(3)?;
+      return Ok(WebhookJson {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        events: serde_json::from_str(&events)?,
+        table_filter: row.get(4)?,
+        active: row.get(5)?,
+        created: row.get(6)?,
+      });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  return Ok(Json(ListWebhooksResponse { webhooks }));
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateWebhookRequest {
+  pub name: String,
+  pub url: String,
+  /// Secret for signing payloads. A random one is generated if absent.
+  pub secret: Option<String>,
+  /// Subscribed operations, i.e. "INSERT", "UPDATE" and/or "DELETE".
+  pub events: Vec<String>,
+  /// Name of the observed table.
+  pub table_filter: String,
+  pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct CreateWebhookResponse {
+  pub id: i64,
+  /// Secret for verifying the payload signatures.
+  pub secret: String,
+}
+
+pub async fn create_webhook_handler(
+  State(state): State<AppState>,
+  Json(request): Json<CreateWebhookRequest>,
+) -> Result<Json<CreateWebhookResponse>, Error> {
+  if request.name.is_empty() {
+    return Err(Error::Precondition("Missing name".into()));
+  }
+  match url::Url::parse(&request.url) {
+    Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+    _ => return Err(Error::Precondition(format!("Invalid url: {}", request.url))),
+  };
+  if request.events.is_empty() {
+    return Err(Error::Precondition("Missing events".into()));
+  }
+  if let Some(event) = request
+    .events
+    .iter()
+    .find(|e| !WEBHOOK_EVENTS.contains(&e.as_str()))
+  {
+    return Err(Error::Precondition(format!("Invalid event: {event}")));
+  }
+  let Some(metadata) = state.table_metadata().get(&request.table_filter) else {
+    return Err(Error::Precondition(format!(
+      "Table not found: {}",
+      request.table_filter
+    )));
+  };
+
+  let secret = request.secret.unwrap_or_else(|| generate_random_string(32));
+
+  let conn = state.conn();
+  let Some(row) = conn
+    .query_row(
+      &format!(
+        "INSERT INTO '{WEBHOOKS_TABLE}' (name, url, secret, events, table_filter, active) VALUES (:name, :url, :secret, :events, :table_filter, :active) RETURNING id"
+      ),
+      named_params! {
+        ":name": request.name,
+        ":url": request.url,
+        ":secret": secret.clone(),
+        ":events": serde_json::to_string(&request.events)?,
+        ":table_filter": request.table_filter,
+        ":active": request.active.unwrap_or(true),
+      },
+    )
+    .await?
+  else {
+    return Err(Error::Precondition("Failed to create webhook".into()));
+  };
+
+  install_webhook_triggers(conn, &metadata).await?;
+
+  return Ok(Json(CreateWebhookResponse {
+    id: row.get(0)?,
+    secret,
+  }));
+}
+
+pub async fn delete_webhook_handler(
+  State(state): State<AppState>,
+  Path(id): Path<i64>,
+) -> Result<(), Error> {
+  let conn = state.conn();
+  let Some(row) = conn
+    .query_row(
+      &format!("DELETE FROM '{WEBHOOKS_TABLE}' WHERE id = $1 RETURNING table_filter"),
+      params!(id),
+    )
+    .await?
+  else {
+    return Err(Error::NotFound(format!("webhook '{id}'")));
+  };
+
+  let table_name: String = row.get(0)?;
+  let remaining: i64 = conn
+    .query_row(
+      &format!("SELECT COUNT(*) FROM '{WEBHOOKS_TABLE}' WHERE table_filter = $1"),
+      params!(table_name.clone()),
+    )
+    .await?
+    .map_or(Ok(0), |row| row.get(0))?;
+  if remaining == 0 {
+    uninstall_webhook_triggers(conn, &table_name).await?;
+  }
+
+  return Ok(());
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export)]
+pub struct WebhookDeliveryJson {
+  pub id: i64,
+  pub event: String,
+  pub payload: String,
+  /// One of "pending", "delivered" or "failed".
+  pub status: String,
+  pub attempts: i64,
+  /// Unix timestamp in seconds of the next attempt for pending deliveries.
+  pub next_attempt: i64,
+  /// HTTP status code of the last attempt.
+  pub response_status: Option<i64>,
+  pub error: Option<String>,
+  /// Unix timestamp in seconds.
+  pub created: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListWebhookDeliveriesQuery {
+  limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
+pub struct ListWebhookDeliveriesResponse {
+  /// Most recent deliveries first.
+  pub deliveries: Vec<WebhookDeliveryJson>,
+}
+
+pub async fn list_webhook_deliveries_handler(
+  State(state): State<AppState>,
+  Path(id): Path<i64>,
+  Query(query): Query<ListWebhookDeliveriesQuery>,
+) -> Result<Json<ListWebhookDeliveriesResponse>, Error> {
+  let rows = state
+    .conn()
+    .query(
+      &format!(
+        r#"
+          SELECT id, event, payload, status, attempts, next_attempt, response_status, error, created
+          FROM '{WEBHOOK_DELIVERIES_TABLE}'
+          WHERE webhook_id = $1
+          ORDER BY id DESC
+          LIMIT $2
+        "#
+      ),
+      params!(id, limit_or_default(query.limit) as i64),
+    )
+    .await?;
+
+  let deliveries = rows
+    .iter()
+    .map(|row| -> Result<WebhookDeliveryJson, Error> {
+      return Ok(WebhookDeliveryJson {
+        id: row.get(0)?,
+        event: row.get(1)?,
+        payload: row.get(2)?,
+        status: row.get(3)?,
+        attempts: row.get(4)?,
+        next_attempt: row.get(5)?,
+        response_status: row.get(6)?,
+        error: row.get(7)?,
+        created: row.get(8)?,
+      });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  return Ok(Json(ListWebhookDeliveriesResponse { deliveries }));
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::HeaderMap;
+  use axum::routing::post;
+  use axum::Router;
+  use tokio::sync::mpsc;
+
+  use super::*;
+  use crate::admin::table::{alter_table_handler, AlterTableRequest};
+  use crate::app_state::test_state;
+  use crate::schema::{Column, ColumnDataType};
+  use crate::webhooks::{
+    deliver_pending_webhooks, delivery_client, sign_payload, HEADER_WEBHOOK_EVENT,
+    HEADER_WEBHOOK_SIGNATURE,
+  };
+
+  /// Starts an HTTP server on a random local port forwarding received requests.
+  async fn start_receiver() -> (String, mpsc::UnboundedReceiver<(HeaderMap, String)>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router = Router::new().route(
+      "/hook",
+      post(move |headers: HeaderMap, body: String| {
+        let sender = sender.clone();
+        async move {
+          sender.send((headers, body)).unwrap();
+        }
+      }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      axum::serve(listener, router).await.unwrap();
+    });
+
+    return (format!("http://{address}/hook"), receiver);
+  }
+
+  #[tokio::test]
+  async fn test_webhook_delivery() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE orders (
+            id           INTEGER PRIMARY KEY,
+            item         TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    let (url, mut receiver) = start_receiver().await;
+
+    assert!(create_webhook_handler(
+      State(state.clone()),
+      Json(CreateWebhookRequest {
+        name: "invalid".to_string(),
+        url: url.clone(),
+        secret: None,
+        events: vec!["TRUNCATE".to_string()],
+        table_filter: "orders".to_string(),
+        active: None,
+      }),
+    )
+    .await
+    .is_err());
+
+    let Json(CreateWebhookResponse { id, secret }) = create_webhook_handler(
+      State(state.clone()),
+      Json(CreateWebhookRequest {
+        name: "new_orders".to_string(),
+        url,
+        secret: Some("secret".to_string()),
+        events: vec!["INSERT".to_string()],
+        table_filter: "orders".to_string(),
+        active: None,
+      }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(secret, "secret");
+
+    let Json(ListWebhooksResponse { webhooks }) =
+      list_webhooks_handler(State(state.clone())).await.unwrap();
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].events, vec!["INSERT".to_string()]);
+
+    conn
+      .execute("INSERT INTO orders (item) VALUES ('book')", ())
+      .await
+      .unwrap();
+    // Not subscribed.
+    conn
+      .execute("UPDATE orders SET item = 'pen'", ())
+      .await
+      .unwrap();
+
+    let client = delivery_client();
+    assert_eq!(deliver_pending_webhooks(conn, &client).await.unwrap(), 1);
+    // Nothing left to deliver.
+    assert_eq!(deliver_pending_webhooks(conn, &client).await.unwrap(), 0);
+
+    let (headers, body) = receiver.recv().await.unwrap();
+    assert_eq!(headers.get(HEADER_WEBHOOK_EVENT).unwrap(), "INSERT");
+    assert_eq!(
+      headers
+        .get(HEADER_WEBHOOK_SIGNATURE)
+        .unwrap()
+        .to_str()
+        .unwrap(),
+      sign_payload("secret", body.as_bytes())
+    );
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["table"], "orders");
+    assert_eq!(payload["record"]["item"], "book");
+
+    let Json(ListWebhookDeliveriesResponse { deliveries }) = list_webhook_deliveries_handler(
+      State(state.clone()),
+      Path(id),
+      Query(ListWebhookDeliveriesQuery::default()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].status, "delivered");
+    assert_eq!(deliveries[0].attempts, 1);
+    assert_eq!(deliveries[0].response_status, Some(200));
+
+    delete_webhook_handler(State(state.clone()), Path(id))
+      .await
+      .unwrap();
+    conn
+      .execute("INSERT INTO orders (item) VALUES ('lamp')", ())
+      .await
+      .unwrap();
+    assert_eq!(deliver_pending_webhooks(conn, &client).await.unwrap(), 0);
+    assert!(delete_webhook_handler(State(state.clone()), Path(id))
+      .await
+      .is_err());
+  }
+
+  #[tokio::test]
+  async fn test_webhook_delivery_retries() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+    conn
+      .execute_batch("CREATE TABLE orders (id INTEGER PRIMARY KEY, item TEXT) STRICT")
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    // Nothing is listening on the discard port.
+    let Json(CreateWebhookResponse { id, .. }) = create_webhook_handler(
+      State(state.clone()),
+      Json(CreateWebhookRequest {
+        name: "unreachable".to_string(),
+        url: "http://127.0.0.1:9/hook".to_string(),
+        secret: None,
+        events: vec!["INSERT".to_string(), "DELETE".to_string()],
+        table_filter: "orders".to_string(),
+        active: None,
+      }),
+    )
+    .await
+    .unwrap();
+
+    conn
+      .execute("INSERT INTO orders (item) VALUES ('book')", ())
+      .await
+      .unwrap();
+
+    let client = delivery_client();
+    assert_eq!(deliver_pending_webhooks(conn, &client).await.unwrap(), 0);
+
+    let Json(ListWebhookDeliveriesResponse { deliveries }) = list_webhook_deliveries_handler(
+      State(state.clone()),
+      Path(id),
+      Query(ListWebhookDeliveriesQuery::default()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].status, "pending");
+    assert_eq!(deliveries[0].attempts, 1);
+    assert!(deliveries[0].error.is_some());
+    assert!(deliveries[0].next_attempt > deliveries[0].created);
+  }
+
+  #[tokio::test]
+  async fn test_webhook_triggers_survive_alter_table() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+    conn
+      .execute_batch("CREATE TABLE orders (id INTEGER PRIMARY KEY, item TEXT) STRICT")
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    let Json(CreateWebhookResponse { id, .. }) = create_webhook_handler(
+      State(state.clone()),
+      Json(CreateWebhookRequest {
+        name: "orders".to_string(),
+        url: "http://127.0.0.1:9/hook".to_string(),
+        secret: None,
+        events: vec!["INSERT".to_string()],
+        table_filter: "orders".to_string(),
+        active: None,
+      }),
+    )
+    .await
+    .unwrap();
+
+    // Altering the table recreates it.
+    let source_schema = state.table_metadata().get("orders").unwrap().schema.clone();
+    let mut target_schema = source_schema.clone();
+    target_schema.columns.push(Column {
+      name: "quantity".to_string(),
+      data_type: ColumnDataType::Integer,
+      options: vec![],
+    });
+    alter_table_handler(
+      State(state.clone()),
+      Json(AlterTableRequest {
+        source_schema,
+        target_schema,
+      }),
+    )
+    .await
+    .unwrap();
+
+    conn
+      .execute("INSERT INTO orders (item, quantity) VALUES ('book', 1)", ())
+      .await
+      .unwrap();
+
+    let Json(ListWebhookDeliveriesResponse { deliveries }) = list_webhook_deliveries_handler(
+      State(state.clone()),
+      Path(id),
+      Query(ListWebhookDeliveriesQuery::default()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(deliveries.len(), 1);
+  }
+}
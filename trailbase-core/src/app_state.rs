@@ -1,18 +1,25 @@
 use log::*;
 use object_store::ObjectStore;
+use parking_lot::RwLock;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::auth::jwt::JwtHelper;
 use crate::auth::oauth::providers::{ConfiguredOAuthProviders, OAuthProviderType};
+use crate::auth::tokens::CustomClaimsHook;
 use crate::config::proto::{Config, RecordApiConfig, S3StorageConfig};
 use crate::config::{validate_config, write_config_and_vault_textproto};
 use crate::constants::SITE_URL_DEFAULT;
 use crate::data_dir::DataDir;
 use crate::email::Mailer;
-use crate::js::RuntimeHandle;
+use crate::js::{RuntimeHandle, RuntimeOptions};
+use crate::rate_limit::{RateLimitConfig, RateLimiter};
+use crate::records::quota::QuotaCounts;
 use crate::records::subscribe::SubscriptionManager;
+use crate::records::upload::UploadSessions;
 use crate::records::RecordApi;
+use crate::scheduler::JobRegistry;
 use crate::table_metadata::TableMetadataCache;
 use crate::value_notifier::{Computed, ValueNotifier};
 
@@ -35,10 +42,18 @@ struct InternalState {
 
   table_metadata: TableMetadataCache,
   subscription_manager: SubscriptionManager,
+  upload_sessions: UploadSessions,
+  quota_counts: QuotaCounts,
   object_store: Box<dyn ObjectStore + Send + Sync>,
+  url_signer: Option<UrlSigner>,
 
   runtime: RuntimeHandle,
 
+  custom_claims_hook: RwLock<Option<CustomClaimsHook>>,
+
+  rate_limiter: RateLimiter,
+  jobs: JobRegistry,
+
   #[cfg(test)]
   #[allow(unused)]
   cleanup: Vec<Box<dyn std::any::Any + Send + Sync>>,
@@ -54,7 +69,12 @@ pub(crate) struct AppStateArgs {
   pub logs_conn: trailbase_sqlite::Connection,
   pub jwt: JwtHelper,
   pub object_store: Box<dyn ObjectStore + Send + Sync>,
-  pub js_runtime_threads: Option<usize>,
+  pub url_signer: Option<UrlSigner>,
+  pub js_runtime: RuntimeOptions,
+  pub rate_limit: Option<RateLimitConfig>,
+  pub rate_limit_routes: Vec<(String, RateLimitConfig)>,
+  pub sse_keepalive_secs: u64,
+  pub sse_replay_buffer_size: usize,
 }
 
 #[derive(Clone)]
@@ -85,9 +105,7 @@ impl AppState {
         .collect::<Vec<_>>();
     });
 
-    let runtime = args
-      .js_runtime_threads
-      .map_or_else(RuntimeHandle::new, RuntimeHandle::new_with_threads);
+    let runtime = RuntimeHandle::new_with_options(args.js_runtime);
     runtime.set_connection(args.conn.clone());
 
     AppState {
@@ -111,9 +129,21 @@ impl AppState {
         logs_conn: args.logs_conn,
         jwt: args.jwt,
         table_metadata: args.table_metadata.clone(),
-        subscription_manager: SubscriptionManager::new(args.conn, args.table_metadata, record_apis),
+        subscription_manager: SubscriptionManager::new(
+          args.conn,
+          args.table_metadata,
+          record_apis,
+          args.sse_keepalive_secs,
+          args.sse_replay_buffer_size,
+        ),
+        upload_sessions: UploadSessions::default(),
+        quota_counts: QuotaCounts::default(),
         object_store: args.object_store,
+        url_signer: args.url_signer,
         runtime,
+        custom_claims_hook: RwLock::new(None),
+        rate_limiter: RateLimiter::new(args.rate_limit, args.rate_limit_routes),
+        jobs: JobRegistry::default(),
         #[cfg(test)]
         cleanup: vec![],
       }),
@@ -162,10 +192,23 @@ impl AppState {
     self.table_metadata().invalidate_all().await
   }
 
+  pub(crate) fn upload_sessions(&self) -> &UploadSessions {
+    return &self.state.upload_sessions;
+  }
+
+  pub(crate) fn quota_counts(&self) -> &QuotaCounts {
+    return &self.state.quota_counts;
+  }
+
   pub(crate) fn objectstore(&self) -> &(dyn ObjectStore + Send + Sync) {
     return &*self.state.object_store;
   }
 
+  /// Signer for pre-signed download URLs if configured, see [UrlSigner].
+  pub(crate) fn url_signer(&self) -> Option<&UrlSigner> {
+    return self.state.url_signer.as_ref();
+  }
+
   pub(crate) fn get_oauth_provider(&self, name: &str) -> Option<Arc<OAuthProviderType>> {
     return self.state.oauth.load().lookup(name).cloned();
   }
@@ -195,6 +238,39 @@ impl AppState {
     return &self.state.jwt;
   }
 
+  pub(crate) fn rate_limiter(&self) -> &RateLimiter {
+    return &self.state.rate_limiter;
+  }
+
+  pub(crate) fn jobs(&self) -> &JobRegistry {
+    return &self.state.jobs;
+  }
+
+  /// Registers a hook deriving custom claims to be included in newly minted auth tokens.
+  pub fn set_custom_claims_hook(&self, hook: Option<CustomClaimsHook>) {
+    *self.state.custom_claims_hook.write() = hook;
+  }
+
+  pub(crate) fn custom_claims(
+    &self,
+    user_id: &uuid::Uuid,
+    email: &str,
+  ) -> Option<serde_json::Value> {
+    let hook = self.state.custom_claims_hook.read().clone()?;
+    return match hook(user_id, email) {
+      Some(claims @ serde_json::Value::Object(_)) => Some(claims),
+      Some(claims) => {
+        warn!("Custom claims must be a JSON object, got: {claims}");
+        None
+      }
+      None => None,
+    };
+  }
+
+  pub(crate) fn record_apis(&self) -> Arc<Vec<(String, RecordApi)>> {
+    return self.state.record_apis.load_full();
+  }
+
   pub(crate) fn lookup_record_api(&self, name: &str) -> Option<RecordApi> {
     for (record_api_name, record_api) in self.state.record_apis.load().iter() {
       if record_api_name == name {
@@ -221,6 +297,12 @@ impl AppState {
     hash: Option<u64>,
   ) -> Result<(), crate::config::ConfigError> {
     validate_config(self.table_metadata(), &config)?;
+    crate::records::validate_record_api_access_rules(self.conn(), self.table_metadata(), &config)
+      .await?;
+
+    crate::records::install_audit_trails(self.conn(), self.table_metadata(), &config)
+      .await
+      .map_err(|err| crate::config::ConfigError::Update(err.to_string()))?;
 
     match hash {
       Some(hash) => {
@@ -278,6 +360,7 @@ fn build_mailer(
 pub struct TestStateOptions {
   pub config: Option<Config>,
   pub(crate) mailer: Option<Mailer>,
+  pub(crate) rate_limiter: Option<RateLimiter>,
 }
 
 #[cfg(test)]
@@ -343,10 +426,10 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
     config
   };
 
-  let config = options
-    .as_ref()
-    .and_then(|o| o.config.clone())
-    .unwrap_or_else(build_default_config);
+  let (config, mailer, rate_limiter) = options.map_or((None, None, None), |o| {
+    return (o.config, o.mailer, o.rate_limiter);
+  });
+  let config = config.unwrap_or_else(build_default_config);
   validate_config(&table_metadata, &config).unwrap();
   let config = ValueNotifier::new(config);
 
@@ -355,23 +438,25 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
 
   let data_dir = DataDir(temp_dir.path().to_path_buf());
 
-  let object_store = if std::env::var("TEST_S3_OBJECT_STORE").map_or(false, |v| v == "TRUE") {
-    info!("Use S3 Storage for tests");
-
-    build_objectstore(
-      &data_dir,
-      Some(&S3StorageConfig {
-        endpoint: Some("http://127.0.0.1:9000".to_string()),
-        region: None,
-        bucket_name: Some("test".to_string()),
-        access_key: Some("minioadmin".to_string()),
-        secret_access_key: Some("minioadmin".to_string()),
-      }),
-    )
-    .unwrap()
-  } else {
-    build_objectstore(&data_dir, None).unwrap()
-  };
+  let (object_store, url_signer) =
+    if std::env::var("TEST_S3_OBJECT_STORE").map_or(false, |v| v == "TRUE") {
+      info!("Use S3 Storage for tests");
+
+      build_objectstore(
+        &data_dir,
+        Some(&S3StorageConfig {
+          endpoint: Some("http://127.0.0.1:9000".to_string()),
+          region: None,
+          bucket_name: Some("test".to_string()),
+          access_key: Some("minioadmin".to_string()),
+          secret_access_key: Some("minioadmin".to_string()),
+          presigned_url_ttl_sec: None,
+        }),
+      )
+      .unwrap()
+    } else {
+      build_objectstore(&data_dir, None).unwrap()
+    };
 
   let record_apis = Computed::new(&config, move |c| {
     return c
@@ -401,16 +486,22 @@ pub async fn test_state(options: Option<TestStateOptions>) -> anyhow::Result<App
       oauth: Computed::new(&config, |c| {
         ConfiguredOAuthProviders::from_config(c.auth.clone()).unwrap()
       }),
-      mailer: build_mailer(&config, options.and_then(|o| o.mailer)),
+      mailer: build_mailer(&config, mailer),
       record_apis: record_apis.clone(),
       config,
       conn: conn.clone(),
       logs_conn,
       jwt: jwt::test_jwt_helper(),
       table_metadata: table_metadata.clone(),
-      subscription_manager: SubscriptionManager::new(conn, table_metadata, record_apis),
+      subscription_manager: SubscriptionManager::new(conn, table_metadata, record_apis, 30, 128),
+      upload_sessions: UploadSessions::default(),
+      quota_counts: QuotaCounts::default(),
       object_store,
+      url_signer,
       runtime,
+      custom_claims_hook: RwLock::new(None),
+      rate_limiter: rate_limiter.unwrap_or_else(|| RateLimiter::new(None, vec![])),
+      jobs: JobRegistry::default(),
       cleanup: vec![Box::new(temp_dir)],
     }),
   });
@@ -436,10 +527,30 @@ fn build_record_api(
   return Err(format!("RecordApi references missing table: {config:?}"));
 }
 
+/// Issues pre-signed URLs for S3 objects, letting clients download files directly from the
+/// bucket.
+#[derive(Clone, Debug)]
+pub(crate) struct UrlSigner {
+  signer: Arc<dyn object_store::signer::Signer>,
+  ttl: Duration,
+}
+
+impl UrlSigner {
+  pub(crate) async fn signed_download_url(
+    &self,
+    path: &object_store::path::Path,
+  ) -> Result<url::Url, object_store::Error> {
+    return self
+      .signer
+      .signed_url(axum::http::Method::GET, path, self.ttl)
+      .await;
+  }
+}
+
 pub(crate) fn build_objectstore(
   data_dir: &DataDir,
   config: Option<&S3StorageConfig>,
-) -> Result<Box<dyn ObjectStore + Send + Sync>, object_store::Error> {
+) -> Result<(Box<dyn ObjectStore + Send + Sync>, Option<UrlSigner>), object_store::Error> {
   if let Some(config) = config {
     let mut builder = object_store::aws::AmazonS3Builder::from_env();
 
@@ -469,10 +580,65 @@ pub(crate) fn build_objectstore(
       builder = builder.with_secret_access_key(secret_access_key);
     }
 
-    return Ok(Box::new(builder.build()?));
+    let store = builder.build()?;
+    let url_signer = config.presigned_url_ttl_sec.map(|ttl| UrlSigner {
+      signer: Arc::new(store.clone()),
+      ttl: Duration::from_secs(ttl),
+    });
+
+    return Ok((Box::new(store), url_signer));
   }
 
-  return Ok(Box::new(
-    object_store::local::LocalFileSystem::new_with_prefix(data_dir.uploads_path())?,
+  return Ok((
+    Box::new(object_store::local::LocalFileSystem::new_with_prefix(
+      data_dir.uploads_path(),
+    )?),
+    None,
   ));
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_presigned_download_urls() {
+    let temp_dir = temp_dir::TempDir::new().unwrap();
+    let data_dir = DataDir(temp_dir.path().to_path_buf());
+    std::fs::create_dir_all(data_dir.uploads_path()).unwrap();
+
+    let (_store, signer) = build_objectstore(&data_dir, None).unwrap();
+    assert!(signer.is_none());
+
+    let config = S3StorageConfig {
+      endpoint: Some("http://127.0.0.1:9000".to_string()),
+      region: Some("us-east-1".to_string()),
+      bucket_name: Some("test".to_string()),
+      access_key: Some("access".to_string()),
+      secret_access_key: Some("secret".to_string()),
+      presigned_url_ttl_sec: None,
+    };
+    let (_store, signer) = build_objectstore(&data_dir, Some(&config)).unwrap();
+    assert!(signer.is_none());
+
+    let (_store, signer) = build_objectstore(
+      &data_dir,
+      Some(&S3StorageConfig {
+        presigned_url_ttl_sec: Some(300),
+        ..config
+      }),
+    )
+    .unwrap();
+
+    // Signing happens locally and doesn't require the bucket to be reachable.
+    let url = signer
+      .unwrap()
+      .signed_download_url(&object_store::path::Path::from("some_file"))
+      .await
+      .unwrap();
+    assert_eq!(url.path(), "/test/some_file");
+    let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    assert!(query.contains(&("X-Amz-Expires".to_string(), "300".to_string())));
+    assert!(query.iter().any(|(k, _v)| k == "X-Amz-Signature"));
+  }
+}
@@ -0,0 +1,341 @@
+use argon2::Argon2;
+use axum::extract::{Json, Path, State};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::auth::user::DbUser;
+use crate::auth::util::user_by_id;
+use crate::auth::{AuthError, User};
+use crate::constants::API_KEYS_TABLE;
+use crate::rand::generate_random_string;
+use crate::util::{b64_to_uuid, id_to_b64};
+
+/// API keys have the shape: "tb_<prefix>_<secret>", where the prefix is used for lookups and only
+/// an Argon2 hash of the secret is persisted.
+pub(crate) const API_KEY_PREFIX: &str = "tb_";
+const PREFIX_LENGTH: usize = 8;
+const SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CreateApiKeyRequest {
+  pub name: String,
+  /// Optional time-to-live in seconds. Keys without TTL never expire.
+  pub ttl_sec: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CreateApiKeyResponse {
+  pub id: String,
+  /// The full API key. It's only ever returned once and cannot be recovered.
+  pub key: String,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ApiKeyJson {
+  pub id: String,
+  pub name: String,
+  pub prefix: String,
+  pub created_at: i64,
+  pub last_used_at: Option<i64>,
+  pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ListApiKeysResponse {
+  pub keys: Vec<ApiKeyJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DbApiKey {
+  id: [u8; 16],
+  name: String,
+  prefix: String,
+  created_at: i64,
+  last_used_at: Option<i64>,
+  expires_at: Option<i64>,
+}
+
+pub struct NewApiKey {
+  pub id: Uuid,
+  pub key: String,
+}
+
+/// Creates a new API key for the current user.
+#[utoipa::path(
+  post,
+  path = "/api_keys",
+  request_body = CreateApiKeyRequest,
+  responses(
+    (status = 200, description = "Newly created API key.", body = CreateApiKeyResponse)
+  )
+)]
+pub(crate) async fn create_api_key_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreateApiKeyResponse>, AuthError> {
+  if request.name.is_empty() {
+    return Err(AuthError::BadRequest("missing name"));
+  }
+
+  let expires_at = match request.ttl_sec {
+    Some(ttl) if ttl <= 0 => return Err(AuthError::BadRequest("invalid ttl")),
+    Some(ttl) => Some(chrono::Utc::now().timestamp() + ttl),
+    None => None,
+  };
+
+  let NewApiKey { id, key } =
+    create_api_key(state.user_conn(), user.uuid, request.name, expires_at).await?;
+
+  return Ok(Json(CreateApiKeyResponse {
+    id: id_to_b64(&id.into_bytes()),
+    key,
+  }));
+}
+
+/// Lists the current user's API keys.
+#[utoipa::path(
+  get,
+  path = "/api_keys",
+  responses(
+    (status = 200, description = "The user's API keys.", body = ListApiKeysResponse)
+  )
+)]
+pub(crate) async fn list_api_keys_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<Json<ListApiKeysResponse>, AuthError> {
+  return Ok(Json(ListApiKeysResponse {
+    keys: list_api_keys(state.user_conn(), user.uuid).await?,
+  }));
+}
+
+/// Revokes one of the current user's API keys.
+#[utoipa::path(
+  delete,
+  path = "/api_keys/{id}",
+  responses(
+    (status = 200, description = "API key revoked.")
+  )
+)]
+pub(crate) async fn delete_api_key_handler(
+  State(state): State<AppState>,
+  Path(id): Path<String>,
+  user: User,
+) -> Result<(), AuthError> {
+  let id = b64_to_uuid(&id).map_err(|_err| AuthError::BadRequest("invalid id"))?;
+  return revoke_api_key(state.user_conn(), user.uuid, id).await;
+}
+
+pub async fn create_api_key(
+  user_conn: &trailbase_sqlite::Connection,
+  user_id: Uuid,
+  name: String,
+  expires_at: Option<i64>,
+) -> Result<NewApiKey, AuthError> {
+  let id = Uuid::now_v7();
+  let prefix = generate_random_string(PREFIX_LENGTH);
+  let secret = generate_random_string(SECRET_LENGTH);
+
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        INSERT INTO '{API_KEYS_TABLE}' (id, user_id, name, prefix, hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+      "#
+    );
+  }
+
+  user_conn
+    .execute(
+      &QUERY,
+      params!(
+        id.into_bytes(),
+        user_id.into_bytes(),
+        name,
+        prefix.clone(),
+        hash_secret(&id, &secret)?.to_vec(),
+        expires_at,
+      ),
+    )
+    .await?;
+
+  return Ok(NewApiKey {
+    id,
+    key: format!("{API_KEY_PREFIX}{prefix}_{secret}"),
+  });
+}
+
+pub async fn list_api_keys(
+  user_conn: &trailbase_sqlite::Connection,
+  user_id: Uuid,
+) -> Result<Vec<ApiKeyJson>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT id, name, prefix, created_at, last_used_at, expires_at
+        FROM '{API_KEYS_TABLE}' WHERE user_id = $1 ORDER BY created_at
+      "#
+    );
+  }
+
+  let keys = user_conn
+    .query_values::<DbApiKey>(&QUERY, params!(user_id.into_bytes()))
+    .await?;
+
+  return Ok(
+    keys
+      .into_iter()
+      .map(|key| ApiKeyJson {
+        id: id_to_b64(&key.id),
+        name: key.name,
+        prefix: key.prefix,
+        created_at: key.created_at,
+        last_used_at: key.last_used_at,
+        expires_at: key.expires_at,
+      })
+      .collect(),
+  );
+}
+
+pub async fn revoke_api_key(
+  user_conn: &trailbase_sqlite::Connection,
+  user_id: Uuid,
+  id: Uuid,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref QUERY: String =
+      format!("DELETE FROM '{API_KEYS_TABLE}' WHERE id = $1 AND user_id = $2");
+  }
+
+  let rows_affected = user_conn
+    .execute(&QUERY, params!(id.into_bytes(), user_id.into_bytes()))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+/// Looks up the user owning the given, unexpired API key.
+pub(crate) async fn user_by_api_key(state: &AppState, key: &str) -> Result<DbUser, AuthError> {
+  let Some((prefix, secret)) = key
+    .strip_prefix(API_KEY_PREFIX)
+    .and_then(|key| key.split_once('_'))
+  else {
+    return Err(AuthError::Unauthorized);
+  };
+
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT id, user_id, hash FROM '{API_KEYS_TABLE}'
+        WHERE prefix = $1 AND (expires_at IS NULL OR expires_at > UNIXEPOCH())
+      "#
+    );
+    static ref UPDATE_QUERY: String =
+      format!("UPDATE '{API_KEYS_TABLE}' SET last_used_at = UNIXEPOCH() WHERE id = $1");
+  }
+
+  let conn = state.user_conn();
+  let Some(row) = conn.query_row(&QUERY, params!(prefix.to_string())).await? else {
+    return Err(AuthError::Unauthorized);
+  };
+
+  let id: [u8; 16] = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+  let user_id: [u8; 16] = row.get(1).map_err(|err| AuthError::Internal(err.into()))?;
+  let hash: Vec<u8> = row.get(2).map_err(|err| AuthError::Internal(err.into()))?;
+
+  let expected = hash_secret(&Uuid::from_bytes(id), secret)?;
+  if !constant_time_eq(&expected, &hash) {
+    return Err(AuthError::Unauthorized);
+  }
+
+  conn.execute(&UPDATE_QUERY, params!(id)).await?;
+
+  let db_user = user_by_id(state, &Uuid::from_bytes(user_id)).await?;
+  if !db_user.verified {
+    return Err(AuthError::Unauthorized);
+  }
+  return Ok(db_user);
+}
+
+fn hash_secret(id: &Uuid, secret: &str) -> Result<[u8; 32], AuthError> {
+  // The key's random id doubles as salt.
+  let mut hash = [0u8; 32];
+  Argon2::default()
+    .hash_password_into(secret.as_bytes(), id.as_bytes(), &mut hash)
+    .map_err(|err| {
+      // NOTE: Wrapping needed since Argon's error doesn't implement the error trait.
+      AuthError::Internal(err.to_string().into())
+    })?;
+  return Ok(hash);
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  return a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_api_key_lifecycle() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.user_conn();
+
+    let user_id = create_user_for_test(&state, "name@bar.com", "secret123")
+      .await
+      .unwrap();
+
+    let NewApiKey { id, key } = create_api_key(conn, user_id, "ci".to_string(), None)
+      .await
+      .unwrap();
+    assert!(key.starts_with(API_KEY_PREFIX));
+
+    let db_user = user_by_api_key(&state, &key).await.unwrap();
+    assert_eq!(db_user.uuid(), user_id);
+
+    let keys = list_api_keys(conn, user_id).await.unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, id_to_b64(&id.into_bytes()));
+    assert!(keys[0].last_used_at.is_some());
+
+    // Tampered secret.
+    let mut tampered = key.clone();
+    tampered.pop();
+    tampered.push('_');
+    assert!(user_by_api_key(&state, &tampered).await.is_err());
+
+    // Expired key.
+    let NewApiKey { key: expired, .. } = create_api_key(
+      conn,
+      user_id,
+      "expired".to_string(),
+      Some(chrono::Utc::now().timestamp() - 10),
+    )
+    .await
+    .unwrap();
+    assert!(user_by_api_key(&state, &expired).await.is_err());
+
+    revoke_api_key(conn, user_id, id).await.unwrap();
+    assert!(user_by_api_key(&state, &key).await.is_err());
+    assert!(revoke_api_key(conn, user_id, id).await.is_err());
+  }
+}
@@ -5,14 +5,15 @@ use axum::{
 };
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use tower_cookies::Cookies;
 use trailbase_sqlite::named_params;
 use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::auth::password::{hash_password, validate_passwords};
-use crate::auth::util::validate_redirects;
+use crate::auth::util::{remove_cookie, validate_redirects};
 use crate::auth::{AuthError, User};
-use crate::constants::{PASSWORD_OPTIONS, USER_TABLE};
+use crate::constants::{COOKIE_AUTH_TOKEN, PASSWORD_OPTIONS, USER_TABLE};
 use crate::extract::Either;
 use crate::{app_state::AppState, auth::util::user_by_id};
 
@@ -30,6 +31,9 @@ pub struct ChangePasswordRequest {
 }
 
 /// Request a change of password.
+///
+/// Also lifts a forced password reset. Auth tokens carrying a `must_change_password` claim remain
+/// rejected by record APIs until they are refreshed.
 #[utoipa::path(
   post,
   path = "/change_password",
@@ -43,6 +47,7 @@ pub async fn change_password_handler(
   State(state): State<AppState>,
   Query(query): Query<ChangePasswordQuery>,
   user: User,
+  cookies: Cookies,
   either_request: Either<ChangePasswordRequest>,
 ) -> Result<Redirect, AuthError> {
   let redirect = validate_redirects(&state, &query.redirect_to, &None)?;
@@ -79,7 +84,8 @@ pub async fn change_password_handler(
         UPDATE
           '{USER_TABLE}'
         SET
-          password_hash = :new_password_hash
+          password_hash = :new_password_hash,
+          must_change_password = FALSE
         WHERE
           id = :user_id AND password_hash = :old_password_hash
       "#
@@ -98,6 +104,11 @@ pub async fn change_password_handler(
     )
     .await?;
 
+  if rows_affected == 1 && user.must_change_password {
+    // Drop the stale auth token to have cookie-based sessions auto-refresh on the next request.
+    remove_cookie(&cookies, COOKIE_AUTH_TOKEN);
+  }
+
   return match rows_affected {
     0 => Err(AuthError::BadRequest("Invalid old password")),
     1 => Ok(Redirect::to(
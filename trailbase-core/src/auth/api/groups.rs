@@ -0,0 +1,356 @@
+use axum::extract::{Json, Path, State};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::util::is_admin;
+use crate::auth::{AuthError, User};
+use crate::constants::{GROUPS_TABLE, GROUP_MEMBERS_TABLE, USER_TABLE};
+use crate::rand::generate_random_string;
+use crate::util::b64_to_uuid;
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CreateGroupRequest {
+  /// Well-known id, e.g. "admin", to reference the group in record API configs. Requires admin
+  /// privileges. A random id is generated otherwise.
+  pub id: Option<String>,
+  pub name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct CreateGroupResponse {
+  pub id: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct AddGroupMemberRequest {
+  /// Url-safe Base64 encoded id of the user to add.
+  pub user_id: String,
+}
+
+/// Creates a new group with the current user as its owner.
+#[utoipa::path(
+  post,
+  path = "/groups",
+  request_body = CreateGroupRequest,
+  responses(
+    (status = 200, description = "Group created.", body = CreateGroupResponse)
+  )
+)]
+pub(crate) async fn create_group_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<CreateGroupRequest>,
+) -> Result<Json<CreateGroupResponse>, AuthError> {
+  lazy_static! {
+    static ref INSERT_GROUP_QUERY: String =
+      format!("INSERT INTO '{GROUPS_TABLE}' (id, name) VALUES ($1, $2)");
+    static ref INSERT_OWNER_QUERY: String =
+      format!("INSERT INTO '{GROUP_MEMBERS_TABLE}' (group_id, user, owner) VALUES ($1, $2, TRUE)");
+  }
+
+  if request.name.is_empty() {
+    return Err(AuthError::BadRequest("missing name"));
+  }
+
+  let id = match request.id {
+    Some(id) => {
+      if !is_valid_group_id(&id) {
+        return Err(AuthError::BadRequest("invalid group id"));
+      }
+      if !is_admin(&state, &user).await {
+        return Err(AuthError::Forbidden);
+      }
+      id
+    }
+    None => generate_random_string(20),
+  };
+
+  let group_id = id.clone();
+  let user_id = user.uuid.into_bytes();
+  state
+    .user_conn()
+    .call(move |conn| {
+      let tx = conn.transaction()?;
+      tx.execute(
+        &INSERT_GROUP_QUERY,
+        rusqlite::params!(group_id, request.name),
+      )?;
+      tx.execute(&INSERT_OWNER_QUERY, rusqlite::params!(group_id, user_id))?;
+      tx.commit()?;
+
+      return Ok(());
+    })
+    .await
+    .map_err(|_err| {
+      #[cfg(debug_assertions)]
+      log::debug!("Failed to create group: {_err}");
+      // The insert will fail if the group id is already taken.
+      AuthError::Conflict
+    })?;
+
+  return Ok(Json(CreateGroupResponse { id }));
+}
+
+/// Adds a user to a group. Requires the current user to be an owner of the group or an admin.
+#[utoipa::path(
+  post,
+  path = "/groups/{id}/members",
+  request_body = AddGroupMemberRequest,
+  responses(
+    (status = 200, description = "Member added.")
+  )
+)]
+pub(crate) async fn add_group_member_handler(
+  State(state): State<AppState>,
+  Path(group_id): Path<String>,
+  user: User,
+  Json(request): Json<AddGroupMemberRequest>,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref OWNER_QUERY: String = format!(
+      "SELECT EXISTS(SELECT 1 FROM '{GROUP_MEMBERS_TABLE}' WHERE group_id = $1 AND user = $2 AND owner)"
+    );
+    static ref INSERT_MEMBER_QUERY: String = format!(
+      r#"
+        INSERT INTO '{GROUP_MEMBERS_TABLE}' (group_id, user)
+        SELECT g.id, u.id FROM '{GROUPS_TABLE}' AS g, '{USER_TABLE}' AS u
+        WHERE g.id = $1 AND u.id = $2
+        ON CONFLICT DO NOTHING
+      "#
+    );
+  }
+
+  let member_id = b64_to_uuid(&request.user_id).map_err(|_| AuthError::BadRequest("invalid id"))?;
+
+  let conn = state.user_conn();
+  let is_owner = conn
+    .query_row(
+      &OWNER_QUERY,
+      params!(group_id.clone(), user.uuid.into_bytes()),
+    )
+    .await?
+    .and_then(|row| row.get::<bool>(0).ok())
+    .unwrap_or(false);
+  if !is_owner && !is_admin(&state, &user).await {
+    return Err(AuthError::Forbidden);
+  }
+
+  let inserted = conn
+    .execute(
+      &INSERT_MEMBER_QUERY,
+      params!(group_id.clone(), member_id.into_bytes()),
+    )
+    .await?;
+  if inserted == 0 {
+    // Either the group or user doesn't exist or the user is already a member.
+    let is_member = user_groups(conn, member_id).await?.contains(&group_id);
+    if !is_member {
+      return Err(AuthError::NotFound);
+    }
+  }
+
+  return Ok(());
+}
+
+/// Ids of the groups the given user is a member of.
+pub(crate) async fn user_groups(
+  conn: &trailbase_sqlite::Connection,
+  user_id: uuid::Uuid,
+) -> Result<Vec<String>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String =
+      format!("SELECT group_id FROM '{GROUP_MEMBERS_TABLE}' WHERE user = $1 ORDER BY group_id");
+  }
+
+  let rows = conn.query(&QUERY, params!(user_id.into_bytes())).await?;
+  return rows
+    .iter()
+    .map(|row| {
+      row
+        .get::<String>(0)
+        .map_err(|err| AuthError::Internal(err.into()))
+    })
+    .collect();
+}
+
+fn is_valid_group_id(id: &str) -> bool {
+  return !id.is_empty()
+    && id.len() <= 64
+    && id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::{Query, State};
+  use axum::http::StatusCode;
+  use axum::response::IntoResponse;
+
+  use super::*;
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::auth::tokens::reauth_with_refresh_token;
+  use crate::config::proto::PermissionFlag;
+  use crate::records::read_record::{read_record_handler, ReadRecordQuery};
+  use crate::records::test_utils::*;
+  use crate::records::{add_record_api, AccessRules, Acls};
+  use crate::util::id_to_b64;
+
+  #[tokio::test]
+  async fn test_group_restricted_record_api() {
+    let state = test_state(None).await.unwrap();
+    create_chat_message_app_tables(&state).await.unwrap();
+    let room = add_room(state.conn(), "room0").await.unwrap();
+
+    let password = "Secret!1!!";
+    let member_id = create_user_for_test(&state, "member@test.com", password)
+      .await
+      .unwrap();
+    create_user_for_test(&state, "other@test.com", password)
+      .await
+      .unwrap();
+
+    let member = login_with_password(&state, "member@test.com", password)
+      .await
+      .unwrap();
+    let other = login_with_password(&state, "other@test.com", password)
+      .await
+      .unwrap();
+
+    // Non-admins cannot pick well-known group ids.
+    assert!(matches!(
+      create_group_handler(
+        State(state.clone()),
+        User::from_auth_token(&state, &other.auth_token).unwrap(),
+        Json(CreateGroupRequest {
+          id: Some("admin".to_string()),
+          name: "Admins".to_string(),
+        }),
+      )
+      .await,
+      Err(AuthError::Forbidden)
+    ));
+
+    let Json(CreateGroupResponse { id: group_id }) = create_group_handler(
+      State(state.clone()),
+      User::from_auth_token(&state, &other.auth_token).unwrap(),
+      Json(CreateGroupRequest {
+        id: None,
+        name: "Moderators".to_string(),
+      }),
+    )
+    .await
+    .unwrap();
+
+    add_group_member_handler(
+      State(state.clone()),
+      Path(group_id.clone()),
+      User::from_auth_token(&state, &other.auth_token).unwrap(),
+      Json(AddGroupMemberRequest {
+        user_id: id_to_b64(&member_id.into_bytes()),
+      }),
+    )
+    .await
+    .unwrap();
+
+    // Only owners can add members.
+    assert!(matches!(
+      add_group_member_handler(
+        State(state.clone()),
+        Path(group_id.clone()),
+        User::from_auth_token(&state, &member.auth_token).unwrap(),
+        Json(AddGroupMemberRequest {
+          user_id: id_to_b64(&member_id.into_bytes()),
+        }),
+      )
+      .await,
+      Err(AuthError::Forbidden)
+    ));
+
+    // Group membership is reflected in the claims once the token is refreshed.
+    let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+    let refresh = |refresh_token: String| {
+      let state = state.clone();
+      return async move {
+        let claims =
+          reauth_with_refresh_token(&state, refresh_token, refresh_token_ttl, auth_token_ttl)
+            .await
+            .unwrap();
+        return User::from_token_claims(claims).unwrap();
+      };
+    };
+    let member = refresh(member.refresh_token).await;
+    let other = refresh(other.refresh_token).await;
+    assert_eq!(member.groups, vec![group_id.clone()]);
+    assert_eq!(other.groups, vec![group_id.clone()]);
+
+    // Drop the owner from the group to have a member and a non-member.
+    state
+      .user_conn()
+      .execute(
+        &format!("DELETE FROM '{GROUP_MEMBERS_TABLE}' WHERE owner"),
+        (),
+      )
+      .await
+      .unwrap();
+    let other = refresh(
+      login_with_password(&state, "other@test.com", password)
+        .await
+        .unwrap()
+        .refresh_token,
+    )
+    .await;
+    assert!(other.groups.is_empty());
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().group_ids = vec![group_id.clone()];
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let message_id = send_message(state.conn(), member.uuid.into_bytes(), room, "hi")
+      .await
+      .unwrap();
+
+    let read = |user: User| {
+      let state = state.clone();
+      return async move {
+        read_record_handler(
+          State(state),
+          Path(("messages_api".to_string(), id_to_b64(&message_id))),
+          Query(ReadRecordQuery::default()),
+          Some(user),
+        )
+        .await
+      };
+    };
+
+    assert!(read(member).await.is_ok());
+
+    let err = read(other).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+  }
+}
@@ -1,28 +1,34 @@
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use axum::{
   extract::{Query, State},
+  http::{header, StatusCode},
   response::{IntoResponse, Redirect, Response},
   Json,
 };
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use tower_cookies::Cookies;
-use trailbase_sqlite::named_params;
+use tracing::Instrument;
+use trailbase_sqlite::{named_params, params};
 use ts_rs::TS;
 use utoipa::{IntoParams, ToSchema};
 
 use crate::app_state::AppState;
 use crate::auth::api::register::validate_and_normalize_email_address;
-use crate::auth::tokens::{mint_new_tokens, Tokens};
+use crate::auth::tokens::{mint_new_tokens, SessionMetadata, Tokens};
 use crate::auth::user::DbUser;
 use crate::auth::util::{new_cookie, user_by_email, validate_redirects};
 use crate::auth::AuthError;
 use crate::constants::{
-  COOKIE_AUTH_TOKEN, COOKIE_REFRESH_TOKEN, USER_TABLE, VERIFICATION_CODE_LENGTH,
+  COOKIE_AUTH_TOKEN, COOKIE_REFRESH_TOKEN, LOGIN_ATTEMPTS_TABLE, USER_TABLE,
+  VERIFICATION_CODE_LENGTH,
 };
 use crate::extract::Either;
 use crate::rand::generate_random_string;
 
+/// Where users are sent after logging in if they have to change their password first.
+const CHANGE_PASSWORD_REQUIRED_PATH: &str = "/_/auth/change_password?required=true";
+
 #[derive(Debug, Default, Deserialize, IntoParams)]
 pub(crate) struct LoginQuery {
   pub redirect_to: Option<String>,
@@ -48,19 +54,28 @@ pub struct LoginResponse {
 }
 
 /// Logs in user by email and password.
+///
+/// Repeated failed attempts for the same e-mail and client IP will result in a temporary lockout.
+/// Many more failed attempts for the same e-mail across all client IPs lock out the e-mail
+/// entirely.
+/// Users required to change their password are sent to the change-password page. For JSON
+/// requests this is signaled by a "202 Accepted" with a `Location` header.
 #[utoipa::path(
   post,
   path = "/login",
   params(LoginQuery),
   request_body = LoginRequest,
   responses(
-    (status = 200, description = "Auth & refresh tokens.", body = LoginResponse)
+    (status = 200, description = "Auth & refresh tokens.", body = LoginResponse),
+    (status = 202, description = "Auth & refresh tokens. Password change required.", body = LoginResponse),
+    (status = 429, description = "Locked out due to too many failed attempts.")
   )
 )]
 pub(crate) async fn login_handler(
   State(state): State<AppState>,
   Query(query): Query<LoginQuery>,
   cookies: Cookies,
+  metadata: SessionMetadata,
   either_request: Either<LoginRequest>,
 ) -> Result<Response, AuthError> {
   let (request, json) = match either_request {
@@ -74,16 +89,30 @@ pub(crate) async fn login_handler(
   let code_response = request.response_type.as_ref().is_some_and(|t| t == "code");
   let pkce_code_challenge = request.pkce_code_challenge.clone();
 
-  let response_or = login_handler_impl(&state, request).await;
+  let response_or = login_handler_impl(&state, request, &metadata)
+    .instrument(tracing::info_span!("auth.login", auth.method = "password"))
+    .await;
+  crate::metrics::record_login("password", response_or.is_ok());
 
   if json {
-    return Ok(Json(response_or?).into_response());
+    let (response, must_change_password) = response_or?;
+    if must_change_password {
+      return Ok(
+        (
+          StatusCode::ACCEPTED,
+          [(header::LOCATION, CHANGE_PASSWORD_REQUIRED_PATH)],
+          Json(response),
+        )
+          .into_response(),
+      );
+    }
+    return Ok(Json(response).into_response());
   }
 
   // Cookie and redirect handling for the non-json case. The assumption is that json login is used
   // by SPAs or mobile applications, which should handle credential passing explicitly. No cookies
   // also removes the risk for any CSRF.
-  let response = match response_or {
+  let (response, must_change_password) = match response_or {
     Ok(response) => response,
     Err(err) => {
       let err_str = err.to_string();
@@ -161,6 +190,10 @@ pub(crate) async fn login_handler(
     state.dev_mode(),
   ));
 
+  if must_change_password {
+    return Ok(Redirect::to(CHANGE_PASSWORD_REQUIRED_PATH).into_response());
+  }
+
   return Ok(
     Redirect::to(redirect.as_deref().unwrap_or_else(|| {
       if state.public_dir().is_some() {
@@ -176,25 +209,165 @@ pub(crate) async fn login_handler(
 async fn login_handler_impl(
   state: &AppState,
   request: LoginRequest,
-) -> Result<LoginResponse, AuthError> {
-  let email = if validate_and_normalize_email_address(&request.email).is_ok() {
-    request.email
-  } else {
+  metadata: &SessionMetadata,
+) -> Result<(LoginResponse, bool), AuthError> {
+  let Ok(normalized_email) = validate_and_normalize_email_address(&request.email) else {
     return Err(AuthError::BadRequest("invalid e-mail"));
   };
 
+  let client_ip = metadata.ip_address.as_deref().unwrap_or_default();
+  check_login_lockout(state, &normalized_email, client_ip).await?;
+
+  let result =
+    login_with_password_and_metadata(state, &request.email, &request.password, metadata).await;
+  match result {
+    Ok(_) => reset_failed_logins(state, &normalized_email, client_ip).await?,
+    Err(AuthError::Unauthorized | AuthError::NotFound) => {
+      record_failed_login(state, &normalized_email, client_ip).await?
+    }
+    Err(_) => {}
+  };
+
   let NewTokens {
     auth_token,
     refresh_token,
     csrf_token,
+    must_change_password,
     ..
-  } = login_with_password(state, &email, &request.password).await?;
+  } = result?;
+
+  return Ok((
+    LoginResponse {
+      auth_token,
+      refresh_token,
+      csrf_token,
+    },
+    must_change_password,
+  ));
+}
 
-  return Ok(LoginResponse {
-    auth_token,
-    refresh_token,
-    csrf_token,
-  });
+/// Pseudo client IP under which failed login attempts are additionally counted per e-mail across
+/// all client IPs, e.g. to curb distributed brute-force attacks.
+const ALL_CLIENT_IPS: &str = "*";
+
+/// Factor by which the per-e-mail limit exceeds the per-client limit of failed login attempts.
+/// Locking out an e-mail across all clients also locks out its owner, so we're more lenient.
+const EMAIL_LOCKOUT_FACTOR: u32 = 5;
+
+async fn check_login_lockout(
+  state: &AppState,
+  email: &str,
+  client_ip: &str,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT MAX(locked_until) - UNIXEPOCH() FROM '{LOGIN_ATTEMPTS_TABLE}'
+        WHERE email = $1 AND ip_address IN ($2, $3) AND locked_until > UNIXEPOCH()
+        HAVING COUNT(*) > 0
+      "#
+    );
+  }
+
+  if let Some(row) = state
+    .user_conn()
+    .query_row(
+      &QUERY,
+      params!(
+        email.to_string(),
+        client_ip.to_string(),
+        ALL_CLIENT_IPS.to_string()
+      ),
+    )
+    .await?
+  {
+    let retry_after: i64 = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+    return Err(AuthError::TooManyRequests(retry_after));
+  }
+
+  return Ok(());
+}
+
+async fn record_failed_login(
+  state: &AppState,
+  email: &str,
+  client_ip: &str,
+) -> Result<(), AuthError> {
+  let (max_failed_attempts, lockout_duration) = state.access_config(|c| c.auth.login_lockout());
+  if max_failed_attempts == 0 {
+    return Ok(());
+  }
+
+  lazy_static! {
+    static ref INSERT_QUERY: String = format!(
+      r#"
+        INSERT INTO '{LOGIN_ATTEMPTS_TABLE}' (email, ip_address, failed_count) VALUES ($1, $2, 1)
+          ON CONFLICT DO UPDATE SET failed_count = failed_count + 1
+      "#
+    );
+    // Start a lockout and reset the counter, such that the next lockout starts afresh.
+    static ref LOCKOUT_QUERY: String = format!(
+      r#"
+        UPDATE '{LOGIN_ATTEMPTS_TABLE}'
+        SET failed_count = 0, locked_until = UNIXEPOCH() + $3
+        WHERE email = $1 AND ip_address = $2 AND failed_count >= $4
+      "#
+    );
+  }
+
+  let conn = state.user_conn();
+  for (ip_address, max_failed_attempts) in [
+    (client_ip, max_failed_attempts),
+    (
+      ALL_CLIENT_IPS,
+      max_failed_attempts.saturating_mul(EMAIL_LOCKOUT_FACTOR),
+    ),
+  ] {
+    conn
+      .execute(
+        &INSERT_QUERY,
+        params!(email.to_string(), ip_address.to_string()),
+      )
+      .await?;
+    conn
+      .execute(
+        &LOCKOUT_QUERY,
+        params!(
+          email.to_string(),
+          ip_address.to_string(),
+          lockout_duration.num_seconds(),
+          max_failed_attempts as i64
+        ),
+      )
+      .await?;
+  }
+
+  return Ok(());
+}
+
+async fn reset_failed_logins(
+  state: &AppState,
+  email: &str,
+  client_ip: &str,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref QUERY: String =
+      format!("DELETE FROM '{LOGIN_ATTEMPTS_TABLE}' WHERE email = $1 AND ip_address IN ($2, $3)");
+  }
+
+  state
+    .user_conn()
+    .execute(
+      &QUERY,
+      params!(
+        email.to_string(),
+        client_ip.to_string(),
+        ALL_CLIENT_IPS.to_string()
+      ),
+    )
+    .await?;
+
+  return Ok(());
 }
 
 #[derive(Debug, Serialize, Deserialize, TS, ToSchema)]
@@ -247,12 +420,23 @@ pub struct NewTokens {
   pub auth_token: String,
   pub refresh_token: String,
   pub csrf_token: String,
+  pub must_change_password: bool,
 }
 
 pub async fn login_with_password(
   state: &AppState,
   email: &str,
   password: &str,
+) -> Result<NewTokens, AuthError> {
+  return login_with_password_and_metadata(state, email, password, &SessionMetadata::default())
+    .await;
+}
+
+pub(crate) async fn login_with_password_and_metadata(
+  state: &AppState,
+  email: &str,
+  password: &str,
+  metadata: &SessionMetadata,
 ) -> Result<NewTokens, AuthError> {
   let normalized_email = validate_and_normalize_email_address(email)?;
   let db_user: DbUser = user_by_email(state, &normalized_email).await?;
@@ -276,6 +460,9 @@ pub async fn login_with_password(
     db_user.verified,
     user_id,
     db_user.email,
+    db_user.require_totp,
+    db_user.must_change_password,
+    metadata,
     auth_token_ttl,
   )
   .await?;
@@ -289,5 +476,6 @@ pub async fn login_with_password(
     auth_token,
     refresh_token: tokens.refresh_token,
     csrf_token: tokens.auth_token_claims.csrf_token,
+    must_change_password: tokens.auth_token_claims.must_change_password,
   });
 }
@@ -10,7 +10,7 @@ use utoipa::{IntoParams, ToSchema};
 
 use crate::auth::user::User;
 use crate::auth::util::{
-  delete_all_sessions_for_user, delete_session, remove_all_cookies, validate_redirects,
+  remove_all_cookies, revoke_all_sessions_for_user, revoke_session, validate_redirects,
 };
 use crate::auth::AuthError;
 use crate::AppState;
@@ -20,9 +20,9 @@ pub struct LogoutQuery {
   redirect_to: Option<String>,
 }
 
-/// Logs out the current user and delete **all** pending sessions for that user.
+/// Logs out the current user and revokes **all** pending sessions for that user.
 ///
-/// Relies on the client to drop any auth tokens. We delete the session to avoid refresh tokens
+/// Relies on the client to drop any auth tokens. We revoke the sessions to avoid refresh tokens
 /// bringing a logged out session back to live.
 #[utoipa::path(
   get,
@@ -43,7 +43,7 @@ pub async fn logout_handler(
   remove_all_cookies(&cookies);
 
   if let Some(user) = user {
-    delete_all_sessions_for_user(&state, user.uuid).await?;
+    revoke_all_sessions_for_user(&state, user.uuid).await?;
   }
 
   return Ok(Redirect::to(redirect.as_deref().unwrap_or_else(|| {
@@ -61,7 +61,7 @@ pub struct LogoutRequest {
   pub refresh_token: String,
 }
 
-/// Logs out the current user and deletes the specific session for the given refresh token.
+/// Logs out the current user and revokes the specific session for the given refresh token.
 ///
 /// Relies on the client to drop any auth tokens.
 #[utoipa::path(
@@ -76,6 +76,6 @@ pub async fn post_logout_handler(
   State(state): State<AppState>,
   Json(request): Json<LogoutRequest>,
 ) -> Result<Response, AuthError> {
-  delete_session(&state, request.refresh_token).await?;
+  revoke_session(&state, request.refresh_token).await?;
   return Ok(StatusCode::OK.into_response());
 }
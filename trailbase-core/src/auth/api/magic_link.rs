@@ -0,0 +1,390 @@
+use axum::{
+  extract::{Query, State},
+  http::StatusCode,
+  response::{IntoResponse, Redirect, Response},
+  Json,
+};
+use base64::prelude::*;
+use lazy_static::lazy_static;
+use rand::{rngs::OsRng, RngCore};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tower_cookies::Cookies;
+use tracing::Instrument;
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::app_state::AppState;
+use crate::auth::api::login::LoginResponse;
+use crate::auth::api::register::validate_and_normalize_email_address;
+use crate::auth::tokens::{mint_new_tokens, SessionMetadata};
+use crate::auth::user::DbUser;
+use crate::auth::util::{new_cookie, user_by_email, user_by_id, validate_redirects};
+use crate::auth::AuthError;
+use crate::constants::{COOKIE_AUTH_TOKEN, COOKIE_REFRESH_TOKEN, MAGIC_LINK_TOKENS_TABLE};
+use crate::email::Email;
+use crate::extract::Either;
+
+const TOKEN_BYTES: usize = 32;
+const TTL_SEC: i64 = 15 * 60;
+const RATE_LIMIT_SEC: i64 = 60;
+
+#[derive(Debug, Default, Deserialize, TS, ToSchema)]
+#[ts(export)]
+pub struct MagicLinkRequest {
+  pub email: String,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub(crate) struct MagicLinkVerifyQuery {
+  pub token: String,
+  /// If present, tokens are returned as cookies followed by a redirect rather than as JSON.
+  pub redirect_to: Option<String>,
+}
+
+/// Request a one-time login link by e-mail.
+#[utoipa::path(
+  post,
+  path = "/magic_link/request",
+  request_body = MagicLinkRequest,
+  responses(
+    (status = 200, description = "Success.")
+  )
+)]
+pub(crate) async fn request_magic_link_handler(
+  State(state): State<AppState>,
+  either_request: Either<MagicLinkRequest>,
+) -> Result<Response, AuthError> {
+  if !state.access_config(|c| c.auth.enable_magic_link.unwrap_or(false)) {
+    return Err(AuthError::Forbidden);
+  }
+
+  let request = match either_request {
+    Either::Json(req) => req,
+    Either::Multipart(req, _) => req,
+    Either::Form(req) => req,
+  };
+
+  let normalized_email = validate_and_normalize_email_address(&request.email)?;
+
+  // Respond identically for unknown, unverified and verified users, to not reveal which e-mail
+  // addresses are registered.
+  if let Ok(user) = user_by_email(&state, &normalized_email).await {
+    if user.verified {
+      send_magic_link(&state, &user).await?;
+    }
+  }
+
+  return Ok((StatusCode::OK, "Magic link sent").into_response());
+}
+
+/// Sends a new magic link to the given user unless one was sent recently.
+async fn send_magic_link(state: &AppState, user: &DbUser) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref RECENT_QUERY: String = format!(
+      "SELECT EXISTS(SELECT 1 FROM '{MAGIC_LINK_TOKENS_TABLE}' WHERE user = $1 AND created > UNIXEPOCH() - $2)"
+    );
+    static ref INSERT_QUERY: String = format!(
+      "INSERT INTO '{MAGIC_LINK_TOKENS_TABLE}' (user, token_hash, expires) VALUES ($1, $2, UNIXEPOCH() + $3)"
+    );
+  }
+
+  let conn = state.user_conn();
+  let recently_sent = conn
+    .query_value::<bool>(&RECENT_QUERY, params!(user.id, RATE_LIMIT_SEC))
+    .await?
+    .unwrap_or(false);
+  if recently_sent {
+    return Ok(());
+  }
+
+  let token = generate_magic_token();
+  conn
+    .execute(
+      &INSERT_QUERY,
+      params!(user.id, hash_magic_token(&token).to_vec(), TTL_SEC),
+    )
+    .await?;
+
+  let email =
+    Email::magic_link_email(state, user, &token).map_err(|err| AuthError::Internal(err.into()))?;
+  email
+    .send()
+    .await
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  return Ok(());
+}
+
+/// Log in using a one-time token previously sent by e-mail.
+#[utoipa::path(
+  get,
+  path = "/magic_link/verify",
+  params(MagicLinkVerifyQuery),
+  responses(
+    (status = 200, description = "Auth & refresh tokens.", body = LoginResponse),
+    (status = 303, description = "Tokens set as cookies when 'redirect_to' is provided.")
+  )
+)]
+pub(crate) async fn verify_magic_link_handler(
+  State(state): State<AppState>,
+  Query(query): Query<MagicLinkVerifyQuery>,
+  cookies: Cookies,
+  metadata: SessionMetadata,
+) -> Result<Response, AuthError> {
+  if !state.access_config(|c| c.auth.enable_magic_link.unwrap_or(false)) {
+    return Err(AuthError::Forbidden);
+  }
+
+  let redirect = validate_redirects(&state, &query.redirect_to, &None)?;
+  let response_or = verify_magic_token(&state, &query.token, &metadata)
+    .instrument(tracing::info_span!(
+      "auth.login",
+      auth.method = "magic_link"
+    ))
+    .await;
+  crate::metrics::record_login("magic_link", response_or.is_ok());
+  let response = response_or?;
+
+  let Some(redirect) = redirect else {
+    return Ok(Json(response).into_response());
+  };
+
+  let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+  cookies.add(new_cookie(
+    COOKIE_AUTH_TOKEN,
+    response.auth_token,
+    auth_token_ttl,
+    state.dev_mode(),
+  ));
+  cookies.add(new_cookie(
+    COOKIE_REFRESH_TOKEN,
+    response.refresh_token,
+    refresh_token_ttl,
+    state.dev_mode(),
+  ));
+
+  return Ok(Redirect::to(&redirect).into_response());
+}
+
+/// Consumes the given one-time token and mints fresh tokens for its user.
+async fn verify_magic_token(
+  state: &AppState,
+  token: &str,
+  metadata: &SessionMetadata,
+) -> Result<LoginResponse, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        UPDATE '{MAGIC_LINK_TOKENS_TABLE}' SET used = TRUE
+        WHERE token_hash = $1 AND NOT used AND expires > UNIXEPOCH()
+        RETURNING user
+      "#
+    );
+  }
+
+  let Some(row) = state
+    .user_conn()
+    .query_row(&QUERY, params!(hash_magic_token(token).to_vec()))
+    .await?
+  else {
+    return Err(AuthError::Unauthorized);
+  };
+  let user_id: [u8; 16] = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+
+  let db_user = user_by_id(state, &uuid::Uuid::from_bytes(user_id)).await?;
+  if !db_user.verified {
+    return Err(AuthError::Unauthorized);
+  }
+
+  let (auth_token_ttl, _refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+  let tokens = mint_new_tokens(
+    state,
+    db_user.verified,
+    db_user.uuid(),
+    db_user.email,
+    db_user.require_totp,
+    db_user.must_change_password,
+    metadata,
+    auth_token_ttl,
+  )
+  .await?;
+  let auth_token = state
+    .jwt()
+    .encode(&tokens.auth_token_claims)
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  return Ok(LoginResponse {
+    auth_token,
+    refresh_token: tokens.refresh_token,
+    csrf_token: tokens.auth_token_claims.csrf_token,
+  });
+}
+
+fn generate_magic_token() -> String {
+  let mut bytes = [0u8; TOKEN_BYTES];
+  OsRng.fill_bytes(&mut bytes);
+  return BASE64_URL_SAFE_NO_PAD.encode(bytes);
+}
+
+fn hash_magic_token(token: &str) -> [u8; 32] {
+  // The token has enough entropy for an unsalted, fast hash to suffice.
+  return Sha256::digest(token.as_bytes()).into();
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::{test_state, TestStateOptions};
+  use crate::auth::User;
+  use crate::config::proto::Config;
+  use crate::constants::USER_TABLE;
+  use crate::email::{testing::TestAsyncSmtpTransport, Mailer};
+
+  #[tokio::test]
+  async fn test_magic_link_login() {
+    let mailer = TestAsyncSmtpTransport::new();
+    let state = test_state(Some(TestStateOptions {
+      mailer: Some(Mailer::Smtp(Arc::new(mailer.clone()))),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    let email = "name@bar.com".to_string();
+    let user_id = create_user_for_test(&state, &email, "secret123")
+      .await
+      .unwrap();
+
+    let request = || {
+      request_magic_link_handler(
+        State(state.clone()),
+        Either::Json(MagicLinkRequest {
+          email: email.clone(),
+        }),
+      )
+    };
+
+    // Disabled by default.
+    assert!(request().await.is_err());
+
+    let mut config: Config = state.get_config();
+    config.auth.enable_magic_link = Some(true);
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    request().await.unwrap();
+    // Rate limited, i.e. no further e-mail is sent.
+    request().await.unwrap();
+
+    let logs = mailer.get_logs();
+    assert_eq!(logs.len(), 1);
+    let (envelope, body) = &logs[0];
+    assert_eq!(envelope.to()[0].to_string(), email);
+    let body = String::from_utf8_lossy(
+      &quoted_printable::decode(body.as_bytes(), quoted_printable::ParseMode::Robust).unwrap(),
+    )
+    .to_string();
+
+    // Extract the token from the e-mail's link.
+    const NEEDLE: &str = "magic_token=";
+    let start = body.find(NEEDLE).unwrap() + NEEDLE.len();
+    let token: String = body[start..]
+      .chars()
+      .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+      .collect();
+
+    let verify = |token: String| {
+      verify_magic_link_handler(
+        State(state.clone()),
+        Query(MagicLinkVerifyQuery {
+          token,
+          redirect_to: None,
+        }),
+        Cookies::default(),
+        SessionMetadata::default(),
+      )
+    };
+
+    assert!(verify("invalid".to_string()).await.is_err());
+
+    let response = verify(token.clone()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    let login: LoginResponse = serde_json::from_slice(&body).unwrap();
+
+    let user = User::from_auth_token(&state, &login.auth_token).unwrap();
+    assert_eq!(user.uuid, user_id);
+
+    // Tokens are single-use.
+    assert!(verify(token).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_magic_link_request_does_not_reveal_users() {
+    let mailer = TestAsyncSmtpTransport::new();
+    let state = test_state(Some(TestStateOptions {
+      mailer: Some(Mailer::Smtp(Arc::new(mailer.clone()))),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    let mut config: Config = state.get_config();
+    config.auth.enable_magic_link = Some(true);
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    create_user_for_test(&state, "verified@bar.com", "secret123")
+      .await
+      .unwrap();
+    let unverified = create_user_for_test(&state, "unverified@bar.com", "secret123")
+      .await
+      .unwrap();
+    state
+      .user_conn()
+      .execute(
+        &format!("UPDATE '{USER_TABLE}' SET verified = FALSE WHERE id = $1"),
+        params!(unverified.into_bytes()),
+      )
+      .await
+      .unwrap();
+
+    let request = |email: &str| {
+      let state = state.clone();
+      let email = email.to_string();
+      async move {
+        let response =
+          request_magic_link_handler(State(state), Either::Json(MagicLinkRequest { email }))
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap();
+        return (status, body);
+      }
+    };
+
+    let verified = request("verified@bar.com").await;
+    assert_eq!(verified.0, StatusCode::OK);
+    assert_eq!(request("unverified@bar.com").await, verified);
+    assert_eq!(request("unknown@bar.com").await, verified);
+
+    // Only the verified user got an e-mail.
+    let logs = mailer.get_logs();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].0.to()[0].to_string(), "verified@bar.com");
+  }
+}
@@ -1,13 +1,19 @@
 pub mod login;
 
 pub(crate) mod register;
+pub(crate) mod sessions;
 
+pub(super) mod api_keys;
 pub(super) mod avatar;
 pub(super) mod change_email;
 pub(super) mod change_password;
 pub(super) mod delete;
+pub(crate) mod groups;
 pub(super) mod logout;
+pub(super) mod magic_link;
+pub(crate) mod push;
 pub(super) mod refresh;
 pub(super) mod reset_password;
 pub(super) mod token;
+pub(super) mod totp;
 pub(super) mod verify_email;
@@ -0,0 +1,109 @@
+use axum::extract::{Json, State};
+use serde::Deserialize;
+use trailbase_sqlite::{named_params, params};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::{AuthError, User};
+use crate::constants::PUSH_TOKENS_TABLE;
+use crate::push::PUSH_PROVIDERS;
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct RegisterPushTokenRequest {
+  /// Either "fcm" or "apns".
+  pub provider: String,
+  /// Device token issued by the provider.
+  pub token: String,
+  /// Optional stable device identifier. A device's previous token is replaced on re-registration.
+  pub device_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct UnregisterPushTokenRequest {
+  pub token: String,
+}
+
+/// Registers a device token for receiving push notifications for the current user.
+#[utoipa::path(
+  post,
+  path = "/push/register",
+  request_body = RegisterPushTokenRequest,
+  responses(
+    (status = 200, description = "Token registered.")
+  )
+)]
+pub(crate) async fn register_push_token_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<RegisterPushTokenRequest>,
+) -> Result<(), AuthError> {
+  if !PUSH_PROVIDERS.contains(&request.provider.as_str()) {
+    return Err(AuthError::BadRequest("invalid provider"));
+  }
+  if request.token.is_empty() {
+    return Err(AuthError::BadRequest("missing token"));
+  }
+
+  let conn = state.user_conn();
+  if let Some(ref device_id) = request.device_id {
+    conn
+      .execute(
+        &format!("DELETE FROM '{PUSH_TOKENS_TABLE}' WHERE user_id = $1 AND device_id = $2"),
+        params!(user.uuid.into_bytes(), device_id.clone()),
+      )
+      .await?;
+  }
+
+  // Tokens are unique per device, thus re-assign them if another user logs in on the same device.
+  conn
+    .execute(
+      &format!(
+        r#"
+          INSERT INTO '{PUSH_TOKENS_TABLE}' (user_id, provider, token, device_id)
+          VALUES (:user_id, :provider, :token, :device_id)
+          ON CONFLICT (provider, token) DO UPDATE SET
+            user_id = excluded.user_id, device_id = excluded.device_id
+        "#
+      ),
+      named_params! {
+        ":user_id": user.uuid.into_bytes(),
+        ":provider": request.provider,
+        ":token": request.token,
+        ":device_id": request.device_id,
+      },
+    )
+    .await?;
+
+  return Ok(());
+}
+
+/// Unregisters one of the current user's device tokens.
+#[utoipa::path(
+  delete,
+  path = "/push/unregister",
+  request_body = UnregisterPushTokenRequest,
+  responses(
+    (status = 200, description = "Token unregistered.")
+  )
+)]
+pub(crate) async fn unregister_push_token_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<UnregisterPushTokenRequest>,
+) -> Result<(), AuthError> {
+  let deleted = state
+    .user_conn()
+    .execute(
+      &format!("DELETE FROM '{PUSH_TOKENS_TABLE}' WHERE user_id = $1 AND token = $2"),
+      params!(user.uuid.into_bytes(), request.token),
+    )
+    .await?;
+
+  if deleted == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
@@ -3,9 +3,13 @@ use axum::{
   http::StatusCode,
   response::{IntoResponse, Redirect, Response},
 };
+use base64::prelude::*;
 use lazy_static::lazy_static;
+use rand::{rngs::OsRng, RngCore};
+use rusqlite::OptionalExtension;
 use serde::Deserialize;
-use trailbase_sqlite::named_params;
+use sha2::{Digest, Sha256};
+use trailbase_sqlite::{named_params, params};
 use utoipa::ToSchema;
 use validator::ValidateEmail;
 
@@ -14,7 +18,10 @@ use crate::auth::password::{hash_password, validate_passwords};
 use crate::auth::user::DbUser;
 use crate::auth::util::user_exists;
 use crate::auth::AuthError;
-use crate::constants::{PASSWORD_OPTIONS, USER_TABLE, VERIFICATION_CODE_LENGTH};
+use crate::constants::{
+  GROUPS_TABLE, GROUP_MEMBERS_TABLE, INVITATIONS_TABLE, PASSWORD_OPTIONS, USER_TABLE,
+  VERIFICATION_CODE_LENGTH,
+};
 use crate::email::Email;
 use crate::rand::generate_random_string;
 
@@ -39,6 +46,8 @@ pub struct RegisterUserRequest {
   pub email: String,
   pub password: String,
   pub password_repeat: String,
+  /// One-time token from an invitation e-mail. Required if public registration is disabled.
+  pub invitation_token: Option<String>,
 }
 
 /// Registers a new user with email and password.
@@ -56,6 +65,22 @@ pub async fn register_user_handler(
 ) -> Result<Response, AuthError> {
   let normalized_email = validate_and_normalize_email_address(&request.email)?;
 
+  let invitation_token = request.invitation_token.filter(|t| !t.is_empty());
+  match invitation_token {
+    Some(ref token) => {
+      // Invitations are bound to the invitee's address.
+      let invitation = lookup_invitation(&state, token).await?;
+      if invitation.is_none_or(|i| i.email != normalized_email) {
+        return Err(AuthError::Forbidden);
+      }
+    }
+    None => {
+      if state.access_config(|c| c.auth.disable_public_registration.unwrap_or(false)) {
+        return Err(AuthError::Forbidden);
+      }
+    }
+  };
+
   if let Err(_err) = validate_passwords(
     &request.password,
     &request.password_repeat,
@@ -71,9 +96,15 @@ pub async fn register_user_handler(
     return Ok(Redirect::to(&format!("/_/auth/register/?alert={msg}")).into_response());
   }
 
-  let email_verification_code = generate_random_string(VERIFICATION_CODE_LENGTH);
   let hashed_password = hash_password(&request.password)?;
 
+  if let Some(token) = invitation_token {
+    register_invited_user(&state, &token, normalized_email, hashed_password).await?;
+    return Ok((StatusCode::OK, "User registered").into_response());
+  }
+
+  let email_verification_code = generate_random_string(VERIFICATION_CODE_LENGTH);
+
   lazy_static! {
     static ref INSERT_USER_QUERY: String = indoc::formatdoc!(
       r#"
@@ -116,3 +147,116 @@ pub async fn register_user_handler(
 
   return Ok((StatusCode::OK, "User registered").into_response());
 }
+
+/// Pending invitation to register.
+#[derive(Debug)]
+pub(crate) struct Invitation {
+  pub email: String,
+}
+
+/// Looks up a pending, i.e. unused and unexpired, invitation by its token.
+pub(crate) async fn lookup_invitation(
+  state: &AppState,
+  token: &str,
+) -> Result<Option<Invitation>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      "SELECT email FROM '{INVITATIONS_TABLE}' WHERE token_hash = $1 AND used_at IS NULL AND expires > UNIXEPOCH()"
+    );
+  }
+
+  let Some(row) = state
+    .user_conn()
+    .query_row(&QUERY, params!(hash_invitation_token(token).to_vec()))
+    .await?
+  else {
+    return Ok(None);
+  };
+
+  return Ok(Some(Invitation {
+    email: row.get(0).map_err(|err| AuthError::Internal(err.into()))?,
+  }));
+}
+
+/// Consumes the invitation and creates an already verified user, since following the invitation
+/// link proves ownership of the address, who's added to the invitation's groups.
+async fn register_invited_user(
+  state: &AppState,
+  token: &str,
+  normalized_email: String,
+  hashed_password: String,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref CONSUME_INVITATION_QUERY: String = format!(
+      r#"
+        UPDATE '{INVITATIONS_TABLE}' SET used_at = UNIXEPOCH()
+        WHERE token_hash = $1 AND email = $2 AND used_at IS NULL AND expires > UNIXEPOCH()
+        RETURNING group_ids
+      "#
+    );
+    static ref INSERT_USER_QUERY: String = format!(
+      r#"
+        INSERT INTO "{USER_TABLE}" (email, password_hash, verified)
+        VALUES ($1, $2, TRUE)
+        RETURNING id
+      "#
+    );
+    static ref INSERT_MEMBERS_QUERY: String = format!(
+      r#"
+        INSERT OR IGNORE INTO '{GROUP_MEMBERS_TABLE}' (group_id, user)
+        SELECT value, $1 FROM json_each($2) WHERE value IN (SELECT id FROM '{GROUPS_TABLE}')
+      "#
+    );
+  }
+
+  let token_hash = hash_invitation_token(token).to_vec();
+  let registered = state
+    .user_conn()
+    .call(move |conn| {
+      let tx = conn.transaction()?;
+
+      let group_ids: Option<String> = tx
+        .query_row(
+          &CONSUME_INVITATION_QUERY,
+          rusqlite::params!(token_hash, normalized_email),
+          |row| row.get(0),
+        )
+        .optional()?;
+      let Some(group_ids) = group_ids else {
+        return Ok(false);
+      };
+
+      let user_id: [u8; 16] = tx.query_row(
+        &INSERT_USER_QUERY,
+        rusqlite::params!(normalized_email, hashed_password),
+        |row| row.get(0),
+      )?;
+      tx.execute(&INSERT_MEMBERS_QUERY, rusqlite::params!(user_id, group_ids))?;
+
+      tx.commit()?;
+      return Ok(true);
+    })
+    .await
+    .map_err(|_err| {
+      #[cfg(debug_assertions)]
+      log::debug!("Failed to register invited user: {_err}");
+      // The insert will fail if the user is already registered
+      AuthError::Conflict
+    })?;
+
+  if !registered {
+    return Err(AuthError::Forbidden);
+  }
+  return Ok(());
+}
+
+pub(crate) fn generate_invitation_token() -> String {
+  let mut bytes = [0u8; 32];
+  OsRng.fill_bytes(&mut bytes);
+  return BASE64_URL_SAFE_NO_PAD.encode(bytes);
+}
+
+pub(crate) fn hash_invitation_token(token: &str) -> [u8; 32] {
+  // The token has enough entropy for an unsalted, fast hash to suffice.
+  return Sha256::digest(token.as_bytes()).into();
+}
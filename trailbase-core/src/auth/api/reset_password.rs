@@ -144,7 +144,8 @@ pub async fn reset_password_update_handler(
         UPDATE '{USER_TABLE}'
         SET
           password_hash = $1,
-          password_reset_code = NULL
+          password_reset_code = NULL,
+          must_change_password = FALSE
         WHERE
           password_reset_code = $2 AND password_reset_code_sent_at > (UNIXEPOCH() - {TTL_SEC})
       "#
@@ -168,6 +169,8 @@ pub async fn reset_password_update_handler(
   };
 }
 
+/// Sets a temporary password for the user with the given e-mail address. The user will have to
+/// change it before being able to access record APIs again.
 pub async fn force_password_reset(
   user_conn: &trailbase_sqlite::Connection,
   email: String,
@@ -177,7 +180,7 @@ pub async fn force_password_reset(
 
   lazy_static! {
     static ref UPDATE_PASSWORD_QUERY: String =
-      format!("UPDATE '{USER_TABLE}' SET password_hash = $1 WHERE email = $2 RETURNING id");
+      format!("UPDATE '{USER_TABLE}' SET password_hash = $1, must_change_password = TRUE WHERE email = $2 RETURNING id");
   }
 
   let id: [u8; 16] = crate::util::query_one_row(
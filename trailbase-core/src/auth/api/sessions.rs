@@ -0,0 +1,190 @@
+use axum::extract::{Json, Path, State};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::util::revoke_all_sessions_for_user;
+use crate::auth::{AuthError, User};
+use crate::constants::SESSION_TABLE;
+
+/// An active session. The refresh token itself is never exposed.
+#[derive(Debug, Deserialize, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct SessionJson {
+  pub id: i64,
+  /// Unix timestamp of when the session was created. Absent for legacy sessions.
+  pub created: Option<i64>,
+  pub updated: i64,
+  /// Unix timestamp after which the session can no longer be refreshed.
+  pub expires: i64,
+  pub user_agent: Option<String>,
+  pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct ListSessionsResponse {
+  pub sessions: Vec<SessionJson>,
+}
+
+/// Lists the current user's active sessions.
+#[utoipa::path(
+  get,
+  path = "/sessions",
+  responses(
+    (status = 200, description = "Active sessions.", body = ListSessionsResponse)
+  )
+)]
+pub(crate) async fn list_sessions_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<Json<ListSessionsResponse>, AuthError> {
+  return Ok(Json(ListSessionsResponse {
+    sessions: list_active_sessions(&state, user.uuid).await?,
+  }));
+}
+
+/// Revokes one of the current user's sessions, e.g. to sign out a specific device.
+#[utoipa::path(
+  delete,
+  path = "/sessions/{id}",
+  responses(
+    (status = 200, description = "Session revoked.")
+  )
+)]
+pub(crate) async fn revoke_session_handler(
+  State(state): State<AppState>,
+  Path(id): Path<i64>,
+  user: User,
+) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      "UPDATE '{SESSION_TABLE}' SET revoked = TRUE WHERE id = $1 AND user = $2 AND NOT revoked"
+    );
+  }
+
+  let rows_affected = state
+    .user_conn()
+    .execute(&QUERY, params!(id, user.uuid.into_bytes()))
+    .await?;
+  if rows_affected == 0 {
+    return Err(AuthError::NotFound);
+  }
+  return Ok(());
+}
+
+/// Revokes all of the current user's sessions, i.e. global sign-out.
+#[utoipa::path(
+  delete,
+  path = "/sessions",
+  responses(
+    (status = 200, description = "All sessions revoked.")
+  )
+)]
+pub(crate) async fn revoke_all_sessions_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<(), AuthError> {
+  revoke_all_sessions_for_user(&state, user.uuid).await?;
+  return Ok(());
+}
+
+pub(crate) async fn list_active_sessions(
+  state: &AppState,
+  user_id: uuid::Uuid,
+) -> Result<Vec<SessionJson>, AuthError> {
+  let (_auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT id, created, updated, updated + $2 AS expires, user_agent, ip_address
+        FROM '{SESSION_TABLE}'
+        WHERE user = $1 AND NOT revoked AND updated + $2 > UNIXEPOCH()
+        ORDER BY updated DESC
+      "#
+    );
+  }
+
+  return Ok(
+    state
+      .user_conn()
+      .query_values::<SessionJson>(
+        &QUERY,
+        params!(user_id.into_bytes(), refresh_token_ttl.num_seconds()),
+      )
+      .await?,
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::auth::tokens::reauth_with_refresh_token;
+
+  #[tokio::test]
+  async fn test_session_listing_and_revocation() {
+    let state = test_state(None).await.unwrap();
+
+    let email = "name@bar.com".to_string();
+    let password = "secret123".to_string();
+    create_user_for_test(&state, &email, &password)
+      .await
+      .unwrap();
+
+    let tokens0 = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let tokens1 = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let user = User::from_auth_token(&state, &tokens0.auth_token).unwrap();
+
+    let Json(response) = list_sessions_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+    assert_eq!(response.sessions.len(), 2);
+
+    let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+    let refresh = |refresh_token: String| {
+      reauth_with_refresh_token(&state, refresh_token, refresh_token_ttl, auth_token_ttl)
+    };
+    refresh(tokens0.refresh_token.clone()).await.unwrap();
+
+    // Revoke a single session. The token hasn't expired yet but can no longer be used.
+    let id = response.sessions[0].id;
+    revoke_session_handler(State(state.clone()), Path(id), user.clone())
+      .await
+      .unwrap();
+    assert!(
+      revoke_session_handler(State(state.clone()), Path(id), user.clone())
+        .await
+        .is_err()
+    );
+
+    let Json(response) = list_sessions_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+    assert_eq!(response.sessions.len(), 1);
+    assert_ne!(response.sessions[0].id, id);
+
+    // Revoke all.
+    revoke_all_sessions_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+
+    let Json(response) = list_sessions_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+    assert!(response.sessions.is_empty());
+    assert!(refresh(tokens0.refresh_token).await.is_err());
+    assert!(refresh(tokens1.refresh_token).await.is_err());
+  }
+}
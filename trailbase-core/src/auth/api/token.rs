@@ -5,7 +5,7 @@ use trailbase_sqlite::params;
 use ts_rs::TS;
 use utoipa::ToSchema;
 
-use crate::auth::tokens::mint_new_tokens;
+use crate::auth::tokens::{mint_new_tokens, SessionMetadata};
 use crate::auth::util::derive_pkce_code_challenge;
 use crate::auth::AuthError;
 use crate::constants::{USER_TABLE, VERIFICATION_CODE_LENGTH};
@@ -41,6 +41,7 @@ pub struct TokenResponse {
 )]
 pub(crate) async fn auth_code_to_token_handler(
   State(state): State<AppState>,
+  metadata: SessionMetadata,
   Json(request): Json<AuthCodeToTokenRequest>,
 ) -> Result<Json<TokenResponse>, AuthError> {
   let authorization_code = match request.authorization_code {
@@ -92,6 +93,9 @@ pub(crate) async fn auth_code_to_token_handler(
     db_user.verified,
     user_id,
     db_user.email,
+    db_user.require_totp,
+    db_user.must_change_password,
+    &metadata,
     auth_token_ttl,
   )
   .await?;
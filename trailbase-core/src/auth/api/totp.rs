@@ -0,0 +1,515 @@
+use axum::extract::{Json, State};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, TOTP};
+use trailbase_sqlite::params;
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::tokens::Tokens;
+use crate::auth::util::{decrypt_secret, encrypt_secret, user_by_id};
+use crate::auth::{AuthError, User};
+use crate::constants::{SESSION_TABLE, TOTP_TABLE, USER_TABLE};
+
+const TOTP_KEY_PURPOSE: &str = "trailbase-totp-secret";
+// 160 bits, as recommended by RFC 4226.
+const SECRET_LENGTH: usize = 20;
+/// Number of time steps before and after the current one, for which codes are accepted to
+/// tolerate clock drift.
+const TOTP_SKEW: u64 = 1;
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct TotpEnrollResponse {
+  /// Provisioning URL, e.g. to be rendered as a QR code for authenticator apps.
+  pub otpauth_url: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct TotpRequest {
+  pub totp: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct TotpConfirmRequest {
+  pub totp: String,
+  /// Refresh token of the pending session. Defaults to the token provided via header or cookie.
+  pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema, TS)]
+#[ts(export)]
+pub struct TotpConfirmResponse {
+  pub auth_token: String,
+  pub csrf_token: String,
+}
+
+/// Generates a new TOTP secret for the current user.
+///
+/// The second factor will only be enforced once a code has been verified, see `/totp/verify`.
+#[utoipa::path(
+  post,
+  path = "/totp/enroll",
+  responses(
+    (status = 200, description = "TOTP provisioning URL.", body = TotpEnrollResponse)
+  )
+)]
+pub(crate) async fn totp_enroll_handler(
+  State(state): State<AppState>,
+  user: User,
+) -> Result<Json<TotpEnrollResponse>, AuthError> {
+  let db_user = user_by_id(&state, &user.uuid).await?;
+  if db_user.require_totp {
+    return Err(AuthError::Conflict);
+  }
+
+  let mut secret = vec![0u8; SECRET_LENGTH];
+  OsRng.fill_bytes(&mut secret);
+  let totp = new_totp(&state, secret, db_user.email)?;
+
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        INSERT INTO '{TOTP_TABLE}' (user, secret) VALUES ($1, $2)
+          ON CONFLICT(user) DO UPDATE SET secret = excluded.secret, created = UNIXEPOCH()
+      "#
+    );
+  }
+
+  state
+    .user_conn()
+    .execute(
+      &QUERY,
+      params!(
+        db_user.id,
+        encrypt_secret(&state, TOTP_KEY_PURPOSE, &totp.secret)?
+      ),
+    )
+    .await?;
+
+  return Ok(Json(TotpEnrollResponse {
+    otpauth_url: totp.get_url(),
+  }));
+}
+
+/// Verifies a TOTP code against the enrolled secret and starts requiring it for future logins.
+#[utoipa::path(
+  post,
+  path = "/totp/verify",
+  request_body = TotpRequest,
+  responses(
+    (status = 200, description = "TOTP verified and enabled.")
+  )
+)]
+pub(crate) async fn totp_verify_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<TotpRequest>,
+) -> Result<(), AuthError> {
+  check_totp(&state, &user.uuid, &request.totp).await?;
+
+  lazy_static! {
+    static ref QUERY: String =
+      format!("UPDATE '{USER_TABLE}' SET require_totp = TRUE WHERE id = $1");
+  }
+
+  state
+    .user_conn()
+    .execute(&QUERY, params!(user.uuid.into_bytes()))
+    .await?;
+
+  return Ok(());
+}
+
+/// Disables TOTP for the current user, which requires a valid code.
+#[utoipa::path(
+  post,
+  path = "/totp/disable",
+  request_body = TotpRequest,
+  responses(
+    (status = 200, description = "TOTP disabled.")
+  )
+)]
+pub(crate) async fn totp_disable_handler(
+  State(state): State<AppState>,
+  user: User,
+  Json(request): Json<TotpRequest>,
+) -> Result<(), AuthError> {
+  check_totp(&state, &user.uuid, &request.totp).await?;
+
+  lazy_static! {
+    static ref DELETE_QUERY: String = format!("DELETE FROM '{TOTP_TABLE}' WHERE user = $1");
+    static ref UPDATE_QUERY: String =
+      format!("UPDATE '{USER_TABLE}' SET require_totp = FALSE WHERE id = $1");
+  }
+
+  let conn = state.user_conn();
+  conn
+    .execute(&DELETE_QUERY, params!(user.uuid.into_bytes()))
+    .await?;
+  conn
+    .execute(&UPDATE_QUERY, params!(user.uuid.into_bytes()))
+    .await?;
+
+  return Ok(());
+}
+
+/// Confirms a pending login with a TOTP code, i.e. provides the second factor.
+///
+/// On success, the pending session can be refreshed and fresh auth tokens are returned.
+#[utoipa::path(
+  post,
+  path = "/totp/confirm",
+  request_body = TotpConfirmRequest,
+  responses(
+    (status = 200, description = "Confirmed auth tokens.", body = TotpConfirmResponse)
+  )
+)]
+pub(crate) async fn totp_confirm_handler(
+  State(state): State<AppState>,
+  tokens: Tokens,
+  Json(request): Json<TotpConfirmRequest>,
+) -> Result<Json<TotpConfirmResponse>, AuthError> {
+  let Tokens {
+    auth_token_claims: mut claims,
+    refresh_token,
+  } = tokens;
+
+  if claims.totp_verified != Some(false) {
+    return Err(AuthError::BadRequest("no pending TOTP confirmation"));
+  }
+  let Some(refresh_token) = request.refresh_token.or(refresh_token) else {
+    return Err(AuthError::BadRequest("missing refresh token"));
+  };
+
+  let user_id = crate::util::b64_to_uuid(&claims.sub)
+    .map_err(|_err| AuthError::BadRequest("invalid user id"))?;
+  check_totp(&state, &user_id, &request.totp).await?;
+
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        UPDATE '{SESSION_TABLE}' SET totp_pending = FALSE
+        WHERE user = $1 AND refresh_token = $2 AND totp_pending
+      "#
+    );
+  }
+
+  let rows_affected = state
+    .user_conn()
+    .execute(&QUERY, params!(user_id.into_bytes(), refresh_token))
+    .await?;
+  if rows_affected != 1 {
+    return Err(AuthError::Unauthorized);
+  }
+
+  claims.totp_verified = Some(true);
+  let auth_token = state
+    .jwt()
+    .encode(&claims)
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  return Ok(Json(TotpConfirmResponse {
+    auth_token,
+    csrf_token: claims.csrf_token,
+  }));
+}
+
+fn new_totp(state: &AppState, secret: Vec<u8>, email: String) -> Result<TOTP, AuthError> {
+  let issuer = state
+    .access_config(|c| c.server.application_name.clone())
+    .unwrap_or_else(|| "TrailBase".to_string());
+
+  // Parameters as expected by most authenticator apps. Clock drift is handled by
+  // [matching_time_step] rather than the skew, in order to learn the matched time step.
+  return TOTP::new(Algorithm::SHA1, 6, 0, 30, secret, Some(issuer), email)
+    .map_err(|err| AuthError::Internal(err.into()));
+}
+
+/// Returns the time step the given code is valid for, if any.
+fn matching_time_step(totp: &TOTP, code: &str) -> Result<Option<u64>, AuthError> {
+  let now = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_err(|err| AuthError::Internal(err.into()))?
+    .as_secs();
+  let current = now / totp.step;
+
+  return Ok(
+    (current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+      .find(|step| totp.check(code, step * totp.step)),
+  );
+}
+
+async fn check_totp(state: &AppState, user_id: &uuid::Uuid, code: &str) -> Result<(), AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      "SELECT secret, locked_until - UNIXEPOCH() FROM '{TOTP_TABLE}' WHERE user = $1"
+    );
+    // Only accept codes for time steps after the last accepted one, i.e. codes cannot be
+    // replayed. Conditional on the previous step to also reject concurrent uses of the same code.
+    static ref ACCEPT_QUERY: String = format!(
+      r#"
+        UPDATE '{TOTP_TABLE}' SET last_used_step = $2, failed_count = 0, locked_until = NULL
+        WHERE user = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+      "#
+    );
+  }
+
+  let Some(row) = state
+    .user_conn()
+    .query_row(&QUERY, params!(user_id.into_bytes()))
+    .await?
+  else {
+    return Err(AuthError::BadRequest("TOTP not enrolled"));
+  };
+  let encrypted: Vec<u8> = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+  let locked_for: Option<i64> = row.get(1).map_err(|err| AuthError::Internal(err.into()))?;
+  if let Some(retry_after) = locked_for.filter(|seconds| *seconds > 0) {
+    return Err(AuthError::TooManyRequests(retry_after));
+  }
+
+  let db_user = user_by_id(state, user_id).await?;
+  let totp = new_totp(
+    state,
+    decrypt_secret(state, TOTP_KEY_PURPOSE, &encrypted)?,
+    db_user.email,
+  )?;
+
+  if let Some(step) = matching_time_step(&totp, code)? {
+    let rows_affected = state
+      .user_conn()
+      .execute(&ACCEPT_QUERY, params!(user_id.into_bytes(), step as i64))
+      .await?;
+    if rows_affected > 0 {
+      return Ok(());
+    }
+  }
+
+  record_failed_totp(state, user_id).await?;
+  return Err(AuthError::Unauthorized);
+}
+
+/// Counts a failed TOTP verification and locks out further attempts once the configured
+/// maximum of failed login attempts is reached.
+async fn record_failed_totp(state: &AppState, user_id: &uuid::Uuid) -> Result<(), AuthError> {
+  let (max_failed_attempts, lockout_duration) = state.access_config(|c| c.auth.login_lockout());
+  if max_failed_attempts == 0 {
+    return Ok(());
+  }
+
+  lazy_static! {
+    static ref FAILED_QUERY: String =
+      format!("UPDATE '{TOTP_TABLE}' SET failed_count = failed_count + 1 WHERE user = $1");
+    // Start a lockout and reset the counter, such that the next lockout starts afresh.
+    static ref LOCKOUT_QUERY: String = format!(
+      r#"
+        UPDATE '{TOTP_TABLE}'
+        SET failed_count = 0, locked_until = UNIXEPOCH() + $2
+        WHERE user = $1 AND failed_count >= $3
+      "#
+    );
+  }
+
+  let conn = state.user_conn();
+  conn
+    .execute(&FAILED_QUERY, params!(user_id.into_bytes()))
+    .await?;
+  conn
+    .execute(
+      &LOCKOUT_QUERY,
+      params!(
+        user_id.into_bytes(),
+        lockout_duration.num_seconds(),
+        max_failed_attempts as i64
+      ),
+    )
+    .await?;
+
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use axum::extract::{Json, State};
+
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::auth::jwt::TokenClaims;
+  use crate::auth::tokens::reauth_with_refresh_token;
+
+  fn unix_now() -> u64 {
+    return std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap()
+      .as_secs();
+  }
+
+  #[tokio::test]
+  async fn test_totp_enroll_verify_and_confirm() {
+    let state = test_state(None).await.unwrap();
+
+    let email = "name@bar.com".to_string();
+    let password = "secret123".to_string();
+    create_user_for_test(&state, &email, &password)
+      .await
+      .unwrap();
+
+    let tokens = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+
+    let Json(response) = totp_enroll_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+    let totp = TOTP::from_url(&response.otpauth_url).unwrap();
+    // Every code can only be used once, so use consecutive time steps within the accepted skew.
+    let now = unix_now();
+
+    totp_verify_handler(
+      State(state.clone()),
+      user.clone(),
+      Json(TotpRequest {
+        totp: totp.generate(now - totp.step),
+      }),
+    )
+    .await
+    .unwrap();
+
+    // Re-enrolling an active TOTP is not allowed.
+    assert!(totp_enroll_handler(State(state.clone()), user.clone())
+      .await
+      .is_err());
+
+    // Login again, now with a pending second factor.
+    let tokens = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let claims: TokenClaims = state.jwt().decode(&tokens.auth_token).unwrap();
+    assert_eq!(claims.totp_verified, Some(false));
+    assert!(User::from_token_claims(claims.clone()).is_err());
+
+    let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+    assert!(reauth_with_refresh_token(
+      &state,
+      tokens.refresh_token.clone(),
+      refresh_token_ttl,
+      auth_token_ttl
+    )
+    .await
+    .is_err());
+
+    let Json(confirmed) = totp_confirm_handler(
+      State(state.clone()),
+      Tokens {
+        auth_token_claims: claims,
+        refresh_token: Some(tokens.refresh_token.clone()),
+      },
+      Json(TotpConfirmRequest {
+        totp: totp.generate(now),
+        refresh_token: None,
+      }),
+    )
+    .await
+    .unwrap();
+
+    let confirmed_user = User::from_auth_token(&state, &confirmed.auth_token).unwrap();
+    assert_eq!(confirmed_user, user);
+
+    let refreshed = reauth_with_refresh_token(
+      &state,
+      tokens.refresh_token,
+      refresh_token_ttl,
+      auth_token_ttl,
+    )
+    .await
+    .unwrap();
+    assert_eq!(refreshed.totp_verified, Some(true));
+
+    // Disable again.
+    totp_disable_handler(
+      State(state.clone()),
+      user.clone(),
+      Json(TotpRequest {
+        totp: totp.generate(now + totp.step),
+      }),
+    )
+    .await
+    .unwrap();
+
+    let tokens = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let claims: TokenClaims = state.jwt().decode(&tokens.auth_token).unwrap();
+    assert_eq!(claims.totp_verified, None);
+  }
+
+  #[tokio::test]
+  async fn test_totp_replay_and_lockout() {
+    let state = test_state(None).await.unwrap();
+
+    let email = "name@bar.com".to_string();
+    let password = "secret123".to_string();
+    create_user_for_test(&state, &email, &password)
+      .await
+      .unwrap();
+    let tokens = login_with_password(&state, &email, &password)
+      .await
+      .unwrap();
+    let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+
+    let Json(response) = totp_enroll_handler(State(state.clone()), user.clone())
+      .await
+      .unwrap();
+    let totp = TOTP::from_url(&response.otpauth_url).unwrap();
+    let now = unix_now();
+
+    let request = |code: String| Json(TotpRequest { totp: code });
+
+    totp_verify_handler(
+      State(state.clone()),
+      user.clone(),
+      request(totp.generate(now)),
+    )
+    .await
+    .unwrap();
+
+    // Neither the same nor an earlier code can be used again.
+    for code in [totp.generate(now), totp.generate(now - totp.step)] {
+      assert!(matches!(
+        totp_disable_handler(State(state.clone()), user.clone(), request(code)).await,
+        Err(AuthError::Unauthorized)
+      ));
+    }
+
+    // Failed attempts lock out further ones, even with valid codes.
+    let (max_failed_attempts, _) = state.access_config(|c| c.auth.login_lockout());
+    for _ in 2..max_failed_attempts {
+      assert!(matches!(
+        totp_disable_handler(
+          State(state.clone()),
+          user.clone(),
+          request("invalid".to_string())
+        )
+        .await,
+        Err(AuthError::Unauthorized)
+      ));
+    }
+    assert!(matches!(
+      totp_disable_handler(
+        State(state.clone()),
+        user.clone(),
+        request(totp.generate(now + totp.step))
+      )
+      .await,
+      Err(AuthError::TooManyRequests(_))
+    ));
+  }
+}
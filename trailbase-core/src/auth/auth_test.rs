@@ -1,8 +1,11 @@
 use axum::extract::{Form, Json, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
 use std::sync::Arc;
 use tower_cookies::Cookies;
 use trailbase_sqlite::params;
 
+use crate::admin::user::{create_user_for_test, create_user_handler, CreateUserRequest};
 use crate::api::TokenClaims;
 use crate::app_state::{test_state, TestStateOptions};
 use crate::auth::api::change_email;
@@ -11,7 +14,7 @@ use crate::auth::api::change_password::{
   change_password_handler, ChangePasswordQuery, ChangePasswordRequest,
 };
 use crate::auth::api::delete::delete_handler;
-use crate::auth::api::login::login_with_password;
+use crate::auth::api::login::{login_handler, login_with_password, LoginQuery, LoginRequest};
 use crate::auth::api::logout::{logout_handler, LogoutQuery};
 use crate::auth::api::refresh::{refresh_handler, RefreshRequest};
 use crate::auth::api::register::{register_user_handler, RegisterUserRequest};
@@ -20,11 +23,16 @@ use crate::auth::api::reset_password::{
   ResetPasswordUpdateRequest,
 };
 use crate::auth::api::verify_email::{verify_email_handler, VerifyEmailQuery};
+use crate::auth::tokens::SessionMetadata;
 use crate::auth::user::{DbUser, User};
+use crate::config::proto::PermissionFlag;
 use crate::constants::*;
 use crate::email::{testing::TestAsyncSmtpTransport, Mailer};
 use crate::extract::Either;
-use crate::util::query_one_row;
+use crate::records::read_record::{read_record_handler, ReadRecordQuery};
+use crate::records::test_utils::{add_room, create_chat_message_app_tables, send_message};
+use crate::records::{add_record_api, AccessRules, Acls};
+use crate::util::{id_to_b64, query_one_row};
 
 #[tokio::test]
 async fn test_auth_registration_reset_and_change_email() {
@@ -45,7 +53,7 @@ async fn test_auth_registration_reset_and_change_email() {
   let email = "user@test.org".to_string();
   let password = "secret123".to_string();
   let session_exists_query =
-    format!(r#"SELECT EXISTS(SELECT 1 FROM "{SESSION_TABLE}" WHERE user = $1)"#);
+    format!(r#"SELECT EXISTS(SELECT 1 FROM "{SESSION_TABLE}" WHERE user = $1 AND NOT revoked)"#);
 
   let user = {
     // Register new user and email verification flow.
@@ -388,6 +396,7 @@ async fn test_auth_registration_reset_and_change_email() {
       State(state.clone()),
       Query(ChangePasswordQuery::default()),
       user.clone(),
+      Cookies::default(),
       Either::Json(ChangePasswordRequest {
         old_password: old_password.clone(),
         new_password: new_password.clone(),
@@ -428,3 +437,329 @@ async fn test_auth_registration_reset_and_change_email() {
     assert!(!user_exists);
   }
 }
+
+#[tokio::test]
+async fn test_login_lockout() {
+  let state = test_state(None).await.unwrap();
+
+  let email = "user@test.org".to_string();
+  let password = "secret123".to_string();
+  create_user_for_test(&state, &email, &password)
+    .await
+    .unwrap();
+
+  let login = |password: &str, client_ip: &str| {
+    login_handler(
+      State(state.clone()),
+      Query(LoginQuery::default()),
+      Cookies::default(),
+      SessionMetadata {
+        user_agent: None,
+        ip_address: Some(client_ip.to_string()),
+      },
+      Either::Json(LoginRequest {
+        email: email.clone(),
+        password: password.to_string(),
+        redirect_to: None,
+        response_type: None,
+        pkce_code_challenge: None,
+      }),
+    )
+  };
+
+  let (max_failed_attempts, _) = state.access_config(|c| c.auth.login_lockout());
+  assert_eq!(max_failed_attempts, 10);
+
+  for _ in 0..max_failed_attempts {
+    let response = login("wrong", "22.11.22.11").await.into_response();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+  }
+
+  // The 11th attempt is locked out, even with the correct password.
+  let response = login(&password, "22.11.22.11").await.into_response();
+  assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+  let retry_after: i64 = response.headers()[header::RETRY_AFTER]
+    .to_str()
+    .unwrap()
+    .parse()
+    .unwrap();
+  assert!(retry_after > 0);
+
+  // Lockouts are scoped to the client's IP.
+  let response = login(&password, "33.22.33.22").await.into_response();
+  assert_eq!(response.status(), StatusCode::OK);
+
+  // ...however, failed attempts from many clients eventually lock out the e-mail entirely.
+  for i in 0..5 {
+    for _ in 0..max_failed_attempts {
+      let response = login("wrong", &format!("44.0.0.{i}")).await.into_response();
+      assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+  }
+  let response = login(&password, "55.0.0.1").await.into_response();
+  assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_custom_claims() {
+  let state = test_state(None).await.unwrap();
+
+  let email = "user@test.org".to_string();
+  let password = "secret123".to_string();
+  let user_id = create_user_for_test(&state, &email, &password)
+    .await
+    .unwrap();
+
+  state.set_custom_claims_hook(Some(Arc::new(|_user_id: &uuid::Uuid, email: &str| {
+    if email == "user@test.org" {
+      return Some(serde_json::json!({"role": "admin"}));
+    }
+    return None;
+  })));
+
+  let tokens = login_with_password(&state, &email, &password)
+    .await
+    .unwrap();
+
+  let claims: TokenClaims = state.jwt().decode(&tokens.auth_token).unwrap();
+  assert_eq!(
+    claims.custom_claims,
+    Some(serde_json::json!({"role": "admin"}))
+  );
+
+  let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+  assert_eq!(user.uuid, user_id);
+  assert_eq!(user.custom_claims.unwrap()["role"], "admin");
+
+  // Non-object claims are dropped.
+  state.set_custom_claims_hook(Some(Arc::new(|_user_id: &uuid::Uuid, _email: &str| {
+    return Some(serde_json::json!("admin"));
+  })));
+  let tokens = login_with_password(&state, &email, &password)
+    .await
+    .unwrap();
+  let claims: TokenClaims = state.jwt().decode(&tokens.auth_token).unwrap();
+  assert_eq!(claims.custom_claims, None);
+}
+
+#[tokio::test]
+async fn test_forced_password_reset() {
+  let state = test_state(None).await.unwrap();
+  create_chat_message_app_tables(&state).await.unwrap();
+  let room = add_room(state.conn(), "room0").await.unwrap();
+  add_record_api(
+    &state,
+    "messages_api",
+    "message",
+    Acls {
+      authenticated: vec![PermissionFlag::Read],
+      ..Default::default()
+    },
+    AccessRules::default(),
+  )
+  .await
+  .unwrap();
+
+  let email = "temporary@test.org".to_string();
+  let password = "Temp0rary!!".to_string();
+  let Json(response) = create_user_handler(
+    State(state.clone()),
+    Json(CreateUserRequest {
+      email: email.clone(),
+      password: password.clone(),
+      verified: true,
+      admin: false,
+      force_password_reset: true,
+    }),
+  )
+  .await
+  .unwrap();
+  let message_id = send_message(state.conn(), response.id.into_bytes(), room, "hi")
+    .await
+    .unwrap();
+
+  let login = |password: String| {
+    login_handler(
+      State(state.clone()),
+      Query(LoginQuery::default()),
+      Cookies::default(),
+      SessionMetadata::default(),
+      Either::Json(LoginRequest {
+        email: email.clone(),
+        password,
+        redirect_to: None,
+        response_type: None,
+        pkce_code_challenge: None,
+      }),
+    )
+  };
+
+  let response = login(password.clone()).await.unwrap();
+  assert_eq!(response.status(), StatusCode::ACCEPTED);
+  assert_eq!(
+    response.headers().get(header::LOCATION).unwrap(),
+    "/_/auth/change_password?required=true"
+  );
+
+  let tokens = login_with_password(&state, &email, &password)
+    .await
+    .unwrap();
+  assert!(tokens.must_change_password);
+  let claims: TokenClaims = state.jwt().decode(&tokens.auth_token).unwrap();
+  assert!(claims.must_change_password);
+
+  let read = |user: User| {
+    read_record_handler(
+      State(state.clone()),
+      Path(("messages_api".to_string(), id_to_b64(&message_id))),
+      Query(ReadRecordQuery::default()),
+      Some(user),
+    )
+  };
+
+  let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+  let err = read(user.clone()).await.unwrap_err();
+  assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+
+  let new_password = "NewSecret!1!".to_string();
+  change_password_handler(
+    State(state.clone()),
+    Query(ChangePasswordQuery::default()),
+    user,
+    Cookies::default(),
+    Either::Json(ChangePasswordRequest {
+      old_password: password.clone(),
+      new_password: new_password.clone(),
+      new_password_repeat: new_password.clone(),
+    }),
+  )
+  .await
+  .unwrap();
+
+  // Refreshed tokens no longer carry the claim.
+  let Json(refreshed) = refresh_handler(
+    State(state.clone()),
+    Json(RefreshRequest {
+      refresh_token: tokens.refresh_token,
+    }),
+  )
+  .await
+  .unwrap();
+  let user = User::from_auth_token(&state, &refreshed.auth_token).unwrap();
+  assert!(!user.must_change_password);
+  assert!(read(user).await.is_ok());
+
+  let response = login(new_password).await.unwrap();
+  assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_forced_password_reset_lifted_by_password_reset() {
+  let state = test_state(None).await.unwrap();
+
+  let email = "temporary@test.org".to_string();
+  let password = "Temp0rary!!".to_string();
+  let Json(response) = create_user_handler(
+    State(state.clone()),
+    Json(CreateUserRequest {
+      email: email.clone(),
+      password: password.clone(),
+      verified: true,
+      admin: false,
+      force_password_reset: true,
+    }),
+  )
+  .await
+  .unwrap();
+
+  // Forgotten the temporary password.
+  let reset_code = "reset_code";
+  state
+    .user_conn()
+    .execute(
+      &format!(
+        "UPDATE '{USER_TABLE}' SET password_reset_code = $1, password_reset_code_sent_at = UNIXEPOCH() WHERE id = $2"
+      ),
+      params!(reset_code.to_string(), response.id.into_bytes()),
+    )
+    .await
+    .unwrap();
+
+  let new_password = "NewSecret!1!".to_string();
+  reset_password_update_handler(
+    State(state.clone()),
+    Path(reset_code.to_string()),
+    Either::Json(ResetPasswordUpdateRequest {
+      password: new_password.clone(),
+      password_repeat: new_password.clone(),
+    }),
+  )
+  .await
+  .unwrap();
+
+  let tokens = login_with_password(&state, &email, &new_password)
+    .await
+    .unwrap();
+  assert!(!tokens.must_change_password);
+  let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+  assert!(!user.must_change_password);
+}
+
+#[tokio::test]
+async fn test_custom_claims_access_rule() {
+  let state = test_state(None).await.unwrap();
+  create_chat_message_app_tables(&state).await.unwrap();
+  let room = add_room(state.conn(), "room0").await.unwrap();
+  add_record_api(
+    &state,
+    "messages_api",
+    "message",
+    Acls {
+      authenticated: vec![PermissionFlag::Read],
+      ..Default::default()
+    },
+    AccessRules {
+      read: Some("_USER_.claims ->> '$.role' = 'admin'".to_string()),
+      ..Default::default()
+    },
+  )
+  .await
+  .unwrap();
+
+  let email = "user@test.org".to_string();
+  let password = "secret123".to_string();
+  let user_id = create_user_for_test(&state, &email, &password)
+    .await
+    .unwrap();
+  let message_id = send_message(state.conn(), user_id.into_bytes(), room, "hi")
+    .await
+    .unwrap();
+
+  let read = |role: &'static str| {
+    let state = state.clone();
+    let email = email.clone();
+    let password = password.clone();
+    async move {
+      state.set_custom_claims_hook(Some(Arc::new(
+        move |_user_id: &uuid::Uuid, _email: &str| {
+          return Some(serde_json::json!({"role": role}));
+        },
+      )));
+      let tokens = login_with_password(&state, &email, &password)
+        .await
+        .unwrap();
+
+      return read_record_handler(
+        State(state.clone()),
+        Path(("messages_api".to_string(), id_to_b64(&message_id))),
+        Query(ReadRecordQuery::default()),
+        User::from_auth_token(&state, &tokens.auth_token),
+      )
+      .await;
+    }
+  };
+
+  assert!(read("admin").await.is_ok());
+  let err = read("user").await.unwrap_err();
+  assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+}
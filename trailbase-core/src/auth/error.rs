@@ -1,9 +1,10 @@
-use axum::body::Body;
-use axum::http::{header::CONTENT_TYPE, StatusCode};
+use axum::http::{header::RETRY_AFTER, StatusCode};
 use axum::response::{IntoResponse, Response};
 use log::*;
 use thiserror::Error;
 
+use crate::request_id::error_response;
+
 #[derive(Debug, Error)]
 pub enum AuthError {
   #[error("Unauthorized")]
@@ -18,6 +19,9 @@ pub enum AuthError {
   NotFound,
   #[error("OAuth provider not found")]
   OAuthProviderNotFound,
+  /// Too many requests, e.g. login lockout, with the number of seconds to retry after.
+  #[error("Too many requests")]
+  TooManyRequests(i64),
   #[error("Bad request: {0}")]
   BadRequest(&'static str),
   #[error("Failed dependency: {0}")]
@@ -64,6 +68,14 @@ impl From<trailbase_sqlite::Error> for AuthError {
 
 impl IntoResponse for AuthError {
   fn into_response(self) -> Response {
+    if let Self::TooManyRequests(retry_after) = self {
+      let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, None);
+      response
+        .headers_mut()
+        .insert(RETRY_AFTER, retry_after.max(1).into());
+      return response;
+    }
+
     let (status, body) = match self {
       Self::Unauthorized => (StatusCode::UNAUTHORIZED, None),
       Self::UnauthorizedExt(msg) if cfg!(debug_assertions) => {
@@ -74,6 +86,7 @@ impl IntoResponse for AuthError {
       Self::Conflict => (StatusCode::CONFLICT, None),
       Self::NotFound => (StatusCode::NOT_FOUND, None),
       Self::OAuthProviderNotFound => (StatusCode::METHOD_NOT_ALLOWED, None),
+      Self::TooManyRequests(_) => (StatusCode::TOO_MANY_REQUESTS, None),
       Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, Some(msg.to_string())),
       Self::FailedDependency(msg) => (StatusCode::FAILED_DEPENDENCY, Some(msg.to_string())),
       Self::Internal(err) if cfg!(debug_assertions) => {
@@ -82,18 +95,7 @@ impl IntoResponse for AuthError {
       Self::Internal(_err) => (StatusCode::INTERNAL_SERVER_ERROR, None),
     };
 
-    if let Some(body) = body {
-      return Response::builder()
-        .status(status)
-        .header(CONTENT_TYPE, "text/plain")
-        .body(Body::new(body))
-        .unwrap();
-    }
-
-    return Response::builder()
-      .status(status)
-      .body(Body::empty())
-      .unwrap();
+    return error_response(status, body);
   }
 }
 
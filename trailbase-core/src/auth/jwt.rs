@@ -6,6 +6,7 @@ use ed25519_dalek::{SigningKey, VerifyingKey};
 use jsonwebtoken::{errors::Error as JwtError, DecodingKey, EncodingKey, Header, Validation};
 use rand::rngs::OsRng;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use tokio::{
@@ -42,6 +43,26 @@ pub struct TokenClaims {
   /// CSRF random token. Requiring that the client echos this random token back on a non-cookie,
   /// non-auto-attach channel can be used to protect from CSRF.
   pub csrf_token: String,
+
+  /// Only present for users who have TOTP two-factor auth enabled. `Some(false)` means that the
+  /// first factor was provided but the TOTP code is yet to be confirmed.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub totp_verified: Option<bool>,
+
+  /// Application-specific claims, e.g. `{"role": "admin"}`, as derived by the
+  /// [crate::auth::CustomClaimsHook] when the token was minted. Nested under "custom_claims"
+  /// rather than merged into the top-level claims, such that they cannot shadow reserved ones.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub custom_claims: Option<serde_json::Value>,
+
+  /// Ids of the groups the user was a member of when the token was minted.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub groups: Vec<String>,
+
+  /// Set if the user has to change their password, e.g. after an admin assigned a temporary one.
+  /// Such tokens are rejected by record APIs.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub must_change_password: bool,
 }
 
 impl TokenClaims {
@@ -60,6 +81,10 @@ impl TokenClaims {
       iat: now.timestamp(),
       email,
       csrf_token: generate_random_string(20),
+      totp_verified: None,
+      custom_claims: None,
+      groups: vec![],
+      must_change_password: false,
     };
   }
 }
@@ -74,6 +99,9 @@ pub struct JwtHelper {
   // The public key used for validating provided JWTs.
   decoding_key: DecodingKey,
   public_key: Vec<u8>,
+
+  // Seed for deriving symmetric keys, e.g. for encrypting secrets at rest.
+  key_seed: [u8; 32],
 }
 
 impl JwtHelper {
@@ -84,6 +112,7 @@ impl JwtHelper {
       encoding_key: EncodingKey::from_ed_pem(&private_key)?,
       decoding_key: DecodingKey::from_ed_pem(&public_key)?,
       public_key,
+      key_seed: Sha256::digest(&private_key).into(),
     });
   }
 
@@ -126,6 +155,16 @@ impl JwtHelper {
   pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
     return jsonwebtoken::encode::<T>(&self.header, claims, &self.encoding_key);
   }
+
+  /// Derives a purpose-specific 256-bit symmetric key from the private key.
+  ///
+  /// NOTE: Rotating the private key will render anything encrypted with a derived key unreadable.
+  pub(crate) fn derive_symmetric_key(&self, purpose: &str) -> [u8; 32] {
+    let mut sha = Sha256::new();
+    sha.update(self.key_seed);
+    sha.update(purpose.as_bytes());
+    return sha.finalize().into();
+  }
 }
 
 fn generate_new_key_pair() -> (SigningKey, VerifyingKey) {
@@ -16,9 +16,11 @@ pub(crate) mod util;
 mod error;
 mod ui;
 
+pub use api::api_keys::{create_api_key, list_api_keys, revoke_api_key, ApiKeyJson, NewApiKey};
 pub use api::reset_password::force_password_reset;
 pub use error::AuthError;
 pub use jwt::{JwtHelper, TokenClaims};
+pub use tokens::CustomClaimsHook;
 pub(crate) use ui::auth_ui_router;
 pub use user::User;
 
@@ -42,6 +44,23 @@ use crate::constants::AUTH_API_PATH;
     api::change_password::change_password_handler,
     api::reset_password::reset_password_request_handler,
     api::reset_password::reset_password_update_handler,
+    api::magic_link::request_magic_link_handler,
+    api::magic_link::verify_magic_link_handler,
+    api::totp::totp_enroll_handler,
+    api::totp::totp_verify_handler,
+    api::totp::totp_disable_handler,
+    api::totp::totp_confirm_handler,
+    api::api_keys::create_api_key_handler,
+    api::api_keys::list_api_keys_handler,
+    api::api_keys::delete_api_key_handler,
+    api::sessions::list_sessions_handler,
+    api::sessions::revoke_session_handler,
+    api::sessions::revoke_all_sessions_handler,
+    api::push::register_push_token_handler,
+    api::push::unregister_push_token_handler,
+    api::groups::create_group_handler,
+    api::groups::add_group_member_handler,
+    oauth::provider_tokens::get_oauth_token_handler,
   ),
   components(schemas(
     api::login::LoginRequest,
@@ -55,8 +74,25 @@ use crate::constants::AUTH_API_PATH;
     api::verify_email::EmailVerificationRequest,
     api::reset_password::ResetPasswordRequest,
     api::reset_password::ResetPasswordUpdateRequest,
+    api::magic_link::MagicLinkRequest,
     api::change_email::ChangeEmailRequest,
     api::change_password::ChangePasswordRequest,
+    api::totp::TotpEnrollResponse,
+    api::totp::TotpRequest,
+    api::totp::TotpConfirmRequest,
+    api::totp::TotpConfirmResponse,
+    api::api_keys::CreateApiKeyRequest,
+    api::api_keys::CreateApiKeyResponse,
+    api::api_keys::ApiKeyJson,
+    api::api_keys::ListApiKeysResponse,
+    api::sessions::SessionJson,
+    api::sessions::ListSessionsResponse,
+    api::push::RegisterPushTokenRequest,
+    api::push::UnregisterPushTokenRequest,
+    api::groups::CreateGroupRequest,
+    api::groups::CreateGroupResponse,
+    api::groups::AddGroupMemberRequest,
+    oauth::provider_tokens::OAuthTokenResponse,
   ))
 )]
 pub(super) struct AuthAPI;
@@ -68,6 +104,7 @@ pub(super) fn router() -> Router<crate::AppState> {
   //  * unauthed: register, login, get-avatar-url
   //  * unauthed + rate limited:
   //    * reset-password
+  //    * magic-link login (if enabled)
   //    * verify-email (+retrigger)
   //  * authed:
   //    * get-login-status (no CSRF, no side-effect)
@@ -76,6 +113,13 @@ pub(super) fn router() -> Router<crate::AppState> {
   //    * change-password (no CSRF: requires old pass),
   //    * change-email (TODO: CSRF: requires old email so only targeted),
   //    * delete-user (technically CSRF: however, currently DELETE method)
+  //    * totp enroll/verify/disable (requires fully authenticated user)
+  //    * api-keys create/list/revoke
+  //    * sessions list/revoke
+  //    * push notification tokens register/unregister
+  //    * groups create/add-member
+  //    * external OAuth provider access tokens
+  //  * pending second factor: totp confirm
   //
   //  Avatar life-cycle: read+update are handled as record APIs.
   //
@@ -114,11 +158,78 @@ pub(super) fn router() -> Router<crate::AppState> {
       &format!("/{AUTH_API_PATH}/reset_password/update/{{password_reset_code}}"),
       post(api::reset_password::reset_password_update_handler),
     )
+    // Passwordless magic-link login flow.
+    .route(
+      &format!("/{AUTH_API_PATH}/magic_link/request"),
+      post(api::magic_link::request_magic_link_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/magic_link/verify"),
+      get(api::magic_link::verify_magic_link_handler),
+    )
     // Change password flow.
     .route(
       &format!("/{AUTH_API_PATH}/change_password"),
       post(api::change_password::change_password_handler),
     )
+    // TOTP two-factor flows.
+    .route(
+      &format!("/{AUTH_API_PATH}/totp/enroll"),
+      post(api::totp::totp_enroll_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/totp/verify"),
+      post(api::totp::totp_verify_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/totp/disable"),
+      post(api::totp::totp_disable_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/totp/confirm"),
+      post(api::totp::totp_confirm_handler),
+    )
+    // API keys: static bearer tokens for non-interactive clients.
+    .route(
+      &format!("/{AUTH_API_PATH}/api_keys"),
+      get(api::api_keys::list_api_keys_handler).post(api::api_keys::create_api_key_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/api_keys/{{id}}"),
+      delete(api::api_keys::delete_api_key_handler),
+    )
+    // Session management: list and revoke sessions.
+    .route(
+      &format!("/{AUTH_API_PATH}/sessions"),
+      get(api::sessions::list_sessions_handler).delete(api::sessions::revoke_all_sessions_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/sessions/{{id}}"),
+      delete(api::sessions::revoke_session_handler),
+    )
+    // Device tokens for push notifications.
+    .route(
+      &format!("/{AUTH_API_PATH}/push/register"),
+      post(api::push::register_push_token_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/push/unregister"),
+      delete(api::push::unregister_push_token_handler),
+    )
+    // Groups for group-restricted record APIs.
+    .route(
+      &format!("/{AUTH_API_PATH}/groups"),
+      post(api::groups::create_group_handler),
+    )
+    .route(
+      &format!("/{AUTH_API_PATH}/groups/{{id}}/members"),
+      post(api::groups::add_group_member_handler),
+    )
+    // Access tokens of external OAuth providers.
+    .route(
+      &format!("/{AUTH_API_PATH}/oauth_token"),
+      get(oauth::provider_tokens::get_oauth_token_handler),
+    )
     // Token refresh flow.
     .route(
       &format!("/{AUTH_API_PATH}/refresh"),
@@ -12,9 +12,10 @@ use thiserror::Error;
 use tower_cookies::Cookies;
 use trailbase_sqlite::{named_params, params};
 
+use crate::auth::oauth::provider_tokens::store_oauth_tokens;
 use crate::auth::oauth::state::{OAuthState, ResponseType};
 use crate::auth::oauth::OAuthUser;
-use crate::auth::tokens::{mint_new_tokens, FreshTokens};
+use crate::auth::tokens::{mint_new_tokens, FreshTokens, SessionMetadata};
 use crate::auth::user::DbUser;
 use crate::auth::util::{new_cookie, remove_cookie, user_by_id, validate_redirects};
 use crate::auth::AuthError;
@@ -85,6 +86,7 @@ pub(crate) async fn callback_from_external_auth_provider(
   Path(provider): Path<String>,
   Query(query): Query<AuthRequest>,
   cookies: Cookies,
+  metadata: SessionMetadata,
 ) -> Result<Redirect, AuthError> {
   let Some(provider) = state.get_oauth_provider(&provider) else {
     return Err(AuthError::OAuthProviderNotFound);
@@ -158,6 +160,9 @@ pub(crate) async fn callback_from_external_auth_provider(
     }
   };
 
+  // Persist the provider's tokens, e.g. for calling provider APIs on the user's behalf later.
+  store_oauth_tokens(&state, db_user.uuid(), provider.name(), &token_response).await?;
+
   // Mint user token.
   let (auth_token_ttl, refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
   let expires_in = token_response.expires_in().map_or(auth_token_ttl, |exp| {
@@ -173,6 +178,9 @@ pub(crate) async fn callback_from_external_auth_provider(
     db_user.verified,
     db_user.uuid(),
     db_user.email,
+    db_user.require_totp,
+    db_user.must_change_password,
+    &metadata,
     expires_in,
   )
   .await?;
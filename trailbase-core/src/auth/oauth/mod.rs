@@ -1,4 +1,5 @@
 pub(crate) mod provider;
+pub(crate) mod provider_tokens;
 pub(crate) mod providers;
 
 mod callback;
@@ -9,10 +9,12 @@ use tower_cookies::Cookies;
 use crate::app_state::{test_state, TestStateOptions};
 use crate::auth::oauth::providers::test::{TestOAuthProvider, TestUser};
 use crate::auth::oauth::state::OAuthState;
-use crate::auth::oauth::{callback, list_providers, login};
+use crate::auth::oauth::{callback, list_providers, login, provider_tokens};
+use crate::auth::tokens::SessionMetadata;
 use crate::auth::util::derive_pkce_code_challenge;
+use crate::auth::{TokenClaims, User};
 use crate::config::proto::{Config, OAuthProviderConfig, OAuthProviderId};
-use crate::constants::{AUTH_API_PATH, COOKIE_OAUTH_STATE, USER_TABLE};
+use crate::constants::{AUTH_API_PATH, COOKIE_OAUTH_STATE, USER_OAUTH_TOKENS_TABLE, USER_TABLE};
 
 fn unpack_redirect(redirect: Redirect) -> String {
   let response = redirect.into_response();
@@ -39,15 +41,20 @@ struct AuthQuery {
 #[derive(Debug, Deserialize, Serialize)]
 struct TokenRequest {
   grant_type: String,
-  code: String,
-  code_verifier: String,
-  redirect_uri: String,
+  // Authorization code grant.
+  code: Option<String>,
+  code_verifier: Option<String>,
+  redirect_uri: Option<String>,
+  // Refresh token grant.
+  refresh_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct TokenResponse {
   pub access_token: String,
   pub token_type: String,
+  pub refresh_token: Option<String>,
+  pub expires_in: Option<u64>,
   pub request: TokenRequest,
 }
 
@@ -68,9 +75,23 @@ async fn test_oauth() {
     .route(
       token_path,
       post(|Form(req): Form<TokenRequest>| async move {
+        if req.grant_type == "refresh_token" {
+          assert_eq!(req.refresh_token.as_deref(), Some("opaque_refresh_token"));
+          return Json(TokenResponse {
+            access_token: "refreshed_token".to_string(),
+            token_type: "Bearer".to_string(),
+            refresh_token: None,
+            expires_in: Some(3600),
+            request: req,
+          });
+        }
+
         Json(TokenResponse {
           access_token: "opaque_token".to_string(),
           token_type: "Bearer".to_string(),
+          refresh_token: Some("opaque_refresh_token".to_string()),
+          // About to expire and thus due for a refresh.
+          expires_in: Some(60),
           request: req,
         })
       }),
@@ -175,6 +196,7 @@ async fn test_oauth() {
       code: auth_query.code_challenge.clone(),
     }),
     cookies.clone(),
+    SessionMetadata::default(),
   )
   .await
   .unwrap();
@@ -185,7 +207,7 @@ async fn test_oauth() {
   let row = state
     .user_conn()
     .query_row(
-      &format!(r#"SELECT email FROM "{USER_TABLE}" WHERE provider_user_id = $1"#),
+      &format!(r#"SELECT email, id FROM "{USER_TABLE}" WHERE provider_user_id = $1"#),
       (external_user_id,),
     )
     .await
@@ -193,4 +215,48 @@ async fn test_oauth() {
     .unwrap();
 
   assert_eq!(row.get::<String>(0).unwrap(), external_user_email);
+  let user_id = uuid::Uuid::from_bytes(row.get::<[u8; 16]>(1).unwrap());
+
+  // The provider's tokens are stored encrypted.
+  let row = state
+    .user_conn()
+    .query_row(
+      &format!(r#"SELECT access_token FROM "{USER_OAUTH_TOKENS_TABLE}" WHERE user = $1"#),
+      (user_id.into_bytes(),),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+  assert_ne!(row.get::<Vec<u8>>(0).unwrap(), b"opaque_token".to_vec());
+
+  assert_eq!(
+    provider_tokens::refresh_expiring_oauth_tokens(&state)
+      .await
+      .unwrap(),
+    1
+  );
+
+  let user = User::from_token_claims(TokenClaims::new(
+    true,
+    user_id,
+    external_user_email.to_string(),
+    chrono::Duration::minutes(5),
+  ))
+  .unwrap();
+  let Json(token) = provider_tokens::get_oauth_token_handler(
+    State(state.clone()),
+    Query(provider_tokens::OAuthTokenQuery { provider: name }),
+    user,
+  )
+  .await
+  .unwrap();
+  assert_eq!(token.access_token, "refreshed_token");
+
+  // No longer due for a refresh.
+  assert_eq!(
+    provider_tokens::refresh_expiring_oauth_tokens(&state)
+      .await
+      .unwrap(),
+    0
+  );
 }
@@ -0,0 +1,219 @@
+use axum::extract::{Json, Query, State};
+use chrono::Utc;
+use lazy_static::lazy_static;
+use log::*;
+use oauth2::basic::BasicTokenResponse;
+use oauth2::{RefreshToken, TokenResponse};
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::{named_params, params};
+use ts_rs::TS;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::app_state::AppState;
+use crate::auth::util::{decrypt_secret, encrypt_secret};
+use crate::auth::{AuthError, User};
+use crate::constants::USER_OAUTH_TOKENS_TABLE;
+
+const OAUTH_TOKEN_KEY_PURPOSE: &str = "trailbase-oauth-token";
+
+/// Access tokens expiring within this margin are refreshed. Needs to exceed the interval of the
+/// background refresh job.
+pub(crate) const REFRESH_MARGIN_SEC: i64 = 10 * 60;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct OAuthTokenQuery {
+  /// Name of the OAuth provider the user logged in with, e.g. "google".
+  pub provider: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, TS)]
+#[ts(export)]
+pub struct OAuthTokenResponse {
+  /// The provider's access token to call provider APIs on the user's behalf.
+  pub access_token: String,
+  /// Unix timestamp in seconds when the access token expires, if known.
+  pub expires: Option<i64>,
+}
+
+/// Returns the current user's access token for the given OAuth provider, refreshing it if needed.
+#[utoipa::path(
+  get,
+  path = "/oauth_token",
+  params(OAuthTokenQuery),
+  responses(
+    (status = 200, description = "Provider access token.", body = OAuthTokenResponse)
+  )
+)]
+pub(crate) async fn get_oauth_token_handler(
+  State(state): State<AppState>,
+  Query(query): Query<OAuthTokenQuery>,
+  user: User,
+) -> Result<Json<OAuthTokenResponse>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"SELECT access_token, refresh_token IS NOT NULL, expires FROM '{USER_OAUTH_TOKENS_TABLE}' WHERE user = $1 AND provider = $2"#
+    );
+  }
+
+  let Some(row) = state
+    .user_conn()
+    .query_row(
+      &QUERY,
+      params!(user.uuid.into_bytes(), query.provider.clone()),
+    )
+    .await?
+  else {
+    return Err(AuthError::NotFound);
+  };
+
+  let access_token: Vec<u8> = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+  let refreshable: bool = row.get(1).map_err(|err| AuthError::Internal(err.into()))?;
+  let expires: Option<i64> = row.get(2).map_err(|err| AuthError::Internal(err.into()))?;
+
+  let expiring = expires.is_some_and(|exp| exp < Utc::now().timestamp() + REFRESH_MARGIN_SEC);
+  if expiring && refreshable {
+    return Ok(Json(
+      refresh_oauth_token(&state, user.uuid, &query.provider).await?,
+    ));
+  }
+
+  return Ok(Json(OAuthTokenResponse {
+    access_token: decrypt_token(&state, &access_token)?,
+    expires,
+  }));
+}
+
+/// Persists the tokens from a provider's token response. A previous refresh token is retained if
+/// the provider doesn't issue a new one.
+pub(crate) async fn store_oauth_tokens(
+  state: &AppState,
+  user_id: uuid::Uuid,
+  provider: &str,
+  response: &BasicTokenResponse,
+) -> Result<Option<i64>, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        INSERT INTO '{USER_OAUTH_TOKENS_TABLE}' (user, provider, access_token, refresh_token, expires)
+        VALUES (:user, :provider, :access_token, :refresh_token, :expires)
+        ON CONFLICT (user, provider) DO UPDATE SET
+          access_token = excluded.access_token,
+          refresh_token = COALESCE(excluded.refresh_token, refresh_token),
+          expires = excluded.expires,
+          updated = UNIXEPOCH()
+      "#
+    );
+  }
+
+  let expires = response
+    .expires_in()
+    .map(|exp| Utc::now().timestamp() + exp.as_secs() as i64);
+  let refresh_token = response
+    .refresh_token()
+    .map(|token| encrypt_token(state, token.secret()))
+    .transpose()?;
+
+  state
+    .user_conn()
+    .execute(
+      &QUERY,
+      named_params! {
+        ":user": user_id.into_bytes(),
+        ":provider": provider.to_string(),
+        ":access_token": encrypt_token(state, response.access_token().secret())?,
+        ":refresh_token": refresh_token,
+        ":expires": expires,
+      },
+    )
+    .await?;
+
+  return Ok(expires);
+}
+
+/// Exchanges the stored refresh token for a new access token.
+pub(crate) async fn refresh_oauth_token(
+  state: &AppState,
+  user_id: uuid::Uuid,
+  provider_name: &str,
+) -> Result<OAuthTokenResponse, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"SELECT refresh_token FROM '{USER_OAUTH_TOKENS_TABLE}' WHERE user = $1 AND provider = $2 AND refresh_token IS NOT NULL"#
+    );
+  }
+
+  let Some(provider) = state.get_oauth_provider(provider_name) else {
+    return Err(AuthError::OAuthProviderNotFound);
+  };
+
+  let Some(row) = state
+    .user_conn()
+    .query_row(
+      &QUERY,
+      params!(user_id.into_bytes(), provider_name.to_string()),
+    )
+    .await?
+  else {
+    return Err(AuthError::NotFound);
+  };
+  let refresh_token: Vec<u8> = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+
+  let http_client = reqwest::ClientBuilder::new()
+    // Following redirects opens the client up to SSRF vulnerabilities.
+    .redirect(reqwest::redirect::Policy::none())
+    .build()
+    .map_err(|err| AuthError::Internal(err.into()))?;
+
+  let response = provider
+    .oauth_client(state)?
+    .exchange_refresh_token(&RefreshToken::new(decrypt_token(state, &refresh_token)?))
+    .request_async(&http_client)
+    .await
+    .map_err(|err| AuthError::FailedDependency(err.into()))?;
+
+  let expires = store_oauth_tokens(state, user_id, provider_name, &response).await?;
+
+  return Ok(OAuthTokenResponse {
+    access_token: response.access_token().secret().clone(),
+    expires,
+  });
+}
+
+/// Refreshes all access tokens about to expire. Returns the number of refreshed tokens.
+pub(crate) async fn refresh_expiring_oauth_tokens(state: &AppState) -> Result<usize, AuthError> {
+  lazy_static! {
+    static ref QUERY: String = format!(
+      r#"
+        SELECT user, provider FROM '{USER_OAUTH_TOKENS_TABLE}'
+        WHERE refresh_token IS NOT NULL AND expires < UNIXEPOCH() + $1
+      "#
+    );
+  }
+
+  let rows = state
+    .user_conn()
+    .query(&QUERY, params!(REFRESH_MARGIN_SEC))
+    .await?;
+
+  let mut count = 0;
+  for row in rows.iter() {
+    let user_id: [u8; 16] = row.get(0).map_err(|err| AuthError::Internal(err.into()))?;
+    let provider: String = row.get(1).map_err(|err| AuthError::Internal(err.into()))?;
+
+    match refresh_oauth_token(state, uuid::Uuid::from_bytes(user_id), &provider).await {
+      Ok(_) => count += 1,
+      Err(err) => warn!("Failed to refresh {provider} OAuth token: {err}"),
+    }
+  }
+
+  return Ok(count);
+}
+
+fn encrypt_token(state: &AppState, token: &str) -> Result<Vec<u8>, AuthError> {
+  return encrypt_secret(state, OAUTH_TOKEN_KEY_PURPOSE, token.as_bytes());
+}
+
+fn decrypt_token(state: &AppState, encrypted: &[u8]) -> Result<String, AuthError> {
+  let token = decrypt_secret(state, OAUTH_TOKEN_KEY_PURPOSE, encrypted)?;
+  return String::from_utf8(token).map_err(|err| AuthError::Internal(err.into()));
+}
@@ -4,10 +4,14 @@ use axum::{
 };
 use chrono::Duration;
 use lazy_static::lazy_static;
+use std::convert::Infallible;
+use std::sync::Arc;
 use tower_cookies::Cookies;
 use trailbase_sqlite::params;
 
 use crate::app_state::AppState;
+use crate::auth::api::api_keys::{user_by_api_key, API_KEY_PREFIX};
+use crate::auth::api::groups::user_groups;
 use crate::auth::jwt::TokenClaims;
 use crate::auth::user::DbUser;
 use crate::auth::util::{extract_cookies_from_parts, new_cookie};
@@ -16,6 +20,7 @@ use crate::constants::{
   COOKIE_AUTH_TOKEN, COOKIE_REFRESH_TOKEN, HEADER_REFRESH_TOKEN, REFRESH_TOKEN_LENGTH,
   SESSION_TABLE, USER_TABLE,
 };
+use crate::extract::client_ip;
 use crate::rand::generate_random_string;
 
 #[derive(Clone)]
@@ -60,6 +65,12 @@ where
       return Ok(Some(tokens));
     }
 
+    // Unlike expired JWTs, which may be auto-refreshed via cookies, an invalid or revoked API key
+    // should never silently downgrade the request to anonymous.
+    if bearer_token(&parts.headers).is_some_and(|token| token.starts_with(API_KEY_PREFIX)) {
+      return Err(AuthError::Unauthorized);
+    }
+
     let cookies = extract_cookies_from_parts(parts)?;
     return Ok(extract_tokens_from_cookies(&state, &cookies).await.ok());
   }
@@ -69,15 +80,26 @@ async fn extract_tokens_from_headers(
   state: &AppState,
   headers: &header::HeaderMap,
 ) -> Result<Tokens, AuthError> {
-  let Some(auth_token) = headers.get(header::AUTHORIZATION).and_then(|value| {
-    if let Ok(value) = value.to_str() {
-      return value.strip_prefix("Bearer ");
-    }
-    None
-  }) else {
+  let Some(auth_token) = bearer_token(headers) else {
     return Err(AuthError::Unauthorized);
   };
 
+  if auth_token.starts_with(API_KEY_PREFIX) {
+    let db_user = user_by_api_key(state, auth_token).await?;
+    let (auth_token_ttl, _refresh_token_ttl) = state.access_config(|c| c.auth.token_ttls());
+
+    let user_id = db_user.uuid();
+    let mut claims = TokenClaims::new(db_user.verified, user_id, db_user.email, auth_token_ttl);
+    claims.custom_claims = state.custom_claims(&user_id, &claims.email);
+    claims.groups = user_groups(state.user_conn(), user_id).await?;
+    claims.must_change_password = db_user.must_change_password;
+
+    return Ok(Tokens {
+      auth_token_claims: claims,
+      refresh_token: None,
+    });
+  }
+
   let refresh_token = headers
     .get(HEADER_REFRESH_TOKEN)
     .and_then(|value| value.to_str().ok().map(|s| s.to_string()));
@@ -92,6 +114,15 @@ async fn extract_tokens_from_headers(
   return Err(AuthError::Unauthorized);
 }
 
+fn bearer_token(headers: &header::HeaderMap) -> Option<&str> {
+  return headers.get(header::AUTHORIZATION).and_then(|value| {
+    if let Ok(value) = value.to_str() {
+      return value.strip_prefix("Bearer ");
+    }
+    None
+  });
+}
+
 async fn extract_tokens_from_cookies(
   state: &AppState,
   cookies: &Cookies,
@@ -154,17 +185,58 @@ async fn extract_tokens_from_cookies(
   return Err(AuthError::Unauthorized);
 }
 
+/// Client information recorded alongside new sessions, e.g. to help users identify their devices.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SessionMetadata {
+  pub user_agent: Option<String>,
+  pub ip_address: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for SessionMetadata
+where
+  S: Send + Sync,
+{
+  type Rejection = Infallible;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    return Ok(SessionMetadata {
+      user_agent: parts
+        .headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok().map(|s| s.to_string())),
+      ip_address: client_ip(&parts.headers, &parts.extensions).map(|ip| ip.to_string()),
+    });
+  }
+}
+
 /// Only difference to Tokens above, refresh token presence is guaranteed.
 pub struct FreshTokens {
   pub auth_token_claims: TokenClaims,
   pub refresh_token: String,
 }
 
+/// Hook deriving application-specific claims for a user given their id and e-mail address, e.g.
+/// `{"role": "admin"}`. The claims are embedded in newly minted auth tokens and are available to
+/// record API access rules as `_USER_.claims`. Returning anything but a JSON object omits them.
+pub type CustomClaimsHook =
+  Arc<dyn Fn(&uuid::Uuid, &str) -> Option<serde_json::Value> + Send + Sync>;
+
+/// Mints new auth and refresh tokens for the given user.
+///
+/// If `require_totp` is set, the minted tokens are merely pending: the auth token carries a
+/// `totp_verified: false` claim and the session cannot be refreshed until the user confirms a TOTP
+/// code.
+///
+/// If `must_change_password` is set, the auth token carries a `must_change_password: true` claim
+/// and is rejected by record APIs.
 pub(crate) async fn mint_new_tokens(
   state: &AppState,
   verified: bool,
   user_id: uuid::Uuid,
   user_email: String,
+  require_totp: bool,
+  must_change_password: bool,
+  metadata: &SessionMetadata,
   expires_in: Duration,
 ) -> Result<FreshTokens, AuthError> {
   assert!(verified);
@@ -174,20 +246,37 @@ pub(crate) async fn mint_new_tokens(
     ));
   }
 
-  let claims = TokenClaims::new(verified, user_id, user_email, expires_in);
+  let mut claims = TokenClaims::new(verified, user_id, user_email, expires_in);
+  claims.custom_claims = state.custom_claims(&user_id, &claims.email);
+  claims.groups = user_groups(state.user_conn(), user_id).await?;
+  claims.must_change_password = must_change_password;
+  if require_totp {
+    claims.totp_verified = Some(false);
+  }
 
   // Unlike JWT auth tokens, refresh tokens are opaque.
   let refresh_token = generate_random_string(REFRESH_TOKEN_LENGTH);
   lazy_static! {
-    static ref QUERY: String =
-      format!("INSERT INTO '{SESSION_TABLE}' (user, refresh_token) VALUES ($1, $2)");
+    static ref QUERY: String = format!(
+      r#"
+        INSERT INTO '{SESSION_TABLE}'
+          (user, refresh_token, totp_pending, created, user_agent, ip_address)
+        VALUES ($1, $2, $3, UNIXEPOCH(), $4, $5)
+      "#
+    );
   }
 
   state
     .user_conn()
     .execute(
       &QUERY,
-      params!(user_id.into_bytes().to_vec(), refresh_token.clone(),),
+      params!(
+        user_id.into_bytes().to_vec(),
+        refresh_token.clone(),
+        require_totp,
+        metadata.user_agent.clone(),
+        metadata.ip_address.clone(),
+      ),
     )
     .await?;
 
@@ -212,6 +301,7 @@ pub(crate) async fn reauth_with_refresh_token(
           INNER JOIN {USER_TABLE} AS user ON s.user = user.id
         WHERE
           s.refresh_token = $1 AND s.updated > (UNIXEPOCH() - $2) AND user.verified
+            AND NOT s.totp_pending AND NOT s.revoked
       "#
     );
   }
@@ -226,9 +316,10 @@ pub(crate) async fn reauth_with_refresh_token(
   else {
     // Row not found case, typically expected in one of 4 cases:
     //  1. Above where clause doesn't match, e.g. refresh token expired.
-    //  2. Token was actively deleted and thus revoked.
-    //  3. User explicitly logged out, which will delete **all** sessions for that user.
-    //  4. Database was overwritten, e.g. by tests or periodic reset for the demo.
+    //  2. Session was explicitly revoked or the user logged out, which will revoke **all**
+    //     sessions for that user.
+    //  3. Database was overwritten, e.g. by tests or periodic reset for the demo.
+    //  4. The session is pending a TOTP confirmation.
     #[cfg(debug_assertions)]
     log::debug!("Refresh token not found");
 
@@ -240,10 +331,15 @@ pub(crate) async fn reauth_with_refresh_token(
     "unverified user, should have been caught by above query"
   );
 
-  return Ok(TokenClaims::new(
-    db_user.verified,
-    db_user.uuid(),
-    db_user.email,
-    auth_token_ttl,
-  ));
+  let user_id = db_user.uuid();
+  let mut claims = TokenClaims::new(db_user.verified, user_id, db_user.email, auth_token_ttl);
+  claims.custom_claims = state.custom_claims(&user_id, &claims.email);
+  claims.groups = user_groups(state.user_conn(), user_id).await?;
+  claims.must_change_password = db_user.must_change_password;
+  if db_user.require_totp {
+    // Only sessions that have been confirmed can be refreshed, see query above.
+    claims.totp_verified = Some(true);
+  }
+
+  return Ok(claims);
 }
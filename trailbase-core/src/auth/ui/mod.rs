@@ -1,4 +1,5 @@
-use axum::extract::Query;
+use axum::extract::{Query, State};
+use axum::http::header;
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::get;
 use axum::Router;
@@ -8,13 +9,21 @@ use reqwest::StatusCode;
 use rust_embed::RustEmbed;
 use serde::Deserialize;
 
+use crate::app_state::AppState;
 use crate::assets::{cow_to_string, AssetService};
+use crate::auth::api::register::lookup_invitation;
 use crate::auth::User;
+use crate::constants::AUTH_API_PATH;
+use crate::security_headers::{CspNonce, AUTH_UI_CSP_POLICY};
+use crate::util::urlencode;
 
 fn build_env() -> Environment<'static> {
   fn get(fname: &str) -> String {
     let file = AuthAssets::get(fname).unwrap();
-    cow_to_string(file.data)
+    // Allow-list the template's own scripts, including the ones generated by the JS bundler, via
+    // the per-request CSP nonce. This happens on the trusted template source before any rendering,
+    // i.e. scripts injected through template values don't receive the nonce.
+    cow_to_string(file.data).replace("<script", "<script nonce=\"{{ csp_nonce }}\"")
   }
 
   lazy_static! {
@@ -54,15 +63,68 @@ fn templates() -> &'static Environment<'static> {
   return &env;
 }
 
+/// Renders the given page with a per-request nonce allow-listing its scripts under the default CSP.
+fn render_page(name: &str, ctx: minijinja::Value) -> Response {
+  let nonce = CspNonce::generate();
+  let ctx = context! {
+    csp_nonce => nonce.0.clone(),
+    ..ctx
+  };
+
+  let html = match templates().get_template(name).unwrap().render(ctx) {
+    Ok(html) => html,
+    Err(err) => {
+      return (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("failed to render template: {err}"),
+      )
+        .into_response();
+    }
+  };
+
+  let mut response = Html(html).into_response();
+  if let Some(policy) = nonce.apply(AUTH_UI_CSP_POLICY) {
+    response
+      .headers_mut()
+      .insert(header::CONTENT_SECURITY_POLICY, policy);
+  }
+  // Allows the server's CSP policy, if configured, to reference the same nonce.
+  response.extensions_mut().insert(nonce);
+  return response;
+}
+
 #[derive(Debug, Default, Deserialize)]
 pub struct LoginQuery {
   redirect_to: Option<String>,
   response_type: Option<String>,
   pkce_code_challenge: Option<String>,
   alert: Option<String>,
+  magic_token: Option<String>,
 }
 
-async fn ui_login_handler(Query(query): Query<LoginQuery>) -> Response {
+async fn ui_login_handler(
+  State(state): State<AppState>,
+  Query(query): Query<LoginQuery>,
+) -> Response {
+  let magic_link = state.access_config(|c| c.auth.enable_magic_link.unwrap_or(false));
+
+  // Following a magic link: exchange the one-time token for cookies.
+  if let (true, Some(magic_token)) = (magic_link, query.magic_token.as_ref()) {
+    let redirect_to = query.redirect_to.as_deref().unwrap_or_else(|| {
+      if state.public_dir().is_some() {
+        "/"
+      } else {
+        "/_/auth/profile"
+      }
+    });
+    return Redirect::to(&format!(
+      "/{AUTH_API_PATH}/magic_link/verify?token={}&redirect_to={}",
+      urlencode(magic_token),
+      urlencode(redirect_to)
+    ))
+    .into_response();
+  }
+
   let form_state = indoc::formatdoc!(
     r#"
     {redirect_to}
@@ -75,38 +137,62 @@ async fn ui_login_handler(Query(query): Query<LoginQuery>) -> Response {
   );
 
   let ctx = context! {
-    alert => query.alert.as_deref().unwrap_or(""),
+    alert => escape_html(query.alert.as_deref().unwrap_or("")),
     state => form_state,
+    magic_link => magic_link,
   };
 
-  return match templates().get_template("login").unwrap().render(ctx) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
-  };
+  return render_page("login", ctx);
 }
 
 #[derive(Debug, Default, Deserialize)]
 pub struct RegisterQuery {
   redirect_to: Option<String>,
   alert: Option<String>,
+  invitation_token: Option<String>,
 }
 
-async fn ui_register_handler(Query(query): Query<RegisterQuery>) -> Response {
-  return match templates().get_template("register").unwrap().render(context! {
-    alert => query.alert.as_deref().unwrap_or(""),
-    state => query.redirect_to.map(|r| format!("<input name=\"redirect_to\" type=\"hidden\" value=\"{r}\" />")),
-  }) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
+async fn ui_register_handler(
+  State(state): State<AppState>,
+  Query(query): Query<RegisterQuery>,
+) -> Response {
+  // Only pass on valid invitations and pre-fill the invitee's address.
+  let invitation = match query.invitation_token {
+    Some(ref token) => lookup_invitation(&state, token)
+      .await
+      .ok()
+      .flatten()
+      .map(|invitation| (token, invitation.email)),
+    None => None,
   };
+
+  let alert = match (&invitation, &query.invitation_token) {
+    (None, Some(_)) => "Invalid or expired invitation",
+    (None, None)
+      if state.access_config(|c| c.auth.disable_public_registration.unwrap_or(false)) =>
+    {
+      "Registration requires an invitation"
+    }
+    _ => query.alert.as_deref().unwrap_or(""),
+  };
+
+  let form_state = indoc::formatdoc!(
+    r#"
+    {redirect_to}
+    {invitation_token}
+  "#,
+    redirect_to = hidden_input("redirect_to", query.redirect_to.as_ref()),
+    invitation_token = hidden_input("invitation_token", invitation.as_ref().map(|(t, _)| *t)),
+  );
+
+  return render_page(
+    "register",
+    context! {
+      alert => escape_html(alert),
+      state => form_state,
+      email => invitation.as_ref().map(|(_, email)| email.as_str()).unwrap_or(""),
+    },
+  );
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -118,20 +204,13 @@ pub struct ResetPasswordRequestQuery {
 async fn ui_reset_password_request_handler(
   Query(query): Query<ResetPasswordRequestQuery>,
 ) -> Response {
-  return match templates()
-    .get_template("reset_password_request")
-    .unwrap()
-    .render(context! {
-      alert => query.alert.as_deref().unwrap_or(""),
-      state => query.redirect_to.map(|r| format!("<input name=\"redirect_to\" type=\"hidden\" value=\"{r}\" />")),
-    }) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
-  };
+  return render_page(
+    "reset_password_request",
+    context! {
+      alert => escape_html(query.alert.as_deref().unwrap_or("")),
+      state => hidden_input("redirect_to", query.redirect_to.as_ref()),
+    },
+  );
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -143,40 +222,39 @@ pub struct ResetPasswordUpdateQuery {
 async fn ui_reset_password_update_handler(
   Query(query): Query<ResetPasswordUpdateQuery>,
 ) -> Response {
-  return match templates()
-    .get_template("reset_password_update")
-    .unwrap()
-    .render(context! {
-      alert => query.alert.as_deref().unwrap_or(""),
-      state => query.redirect_to.map(|r| format!("<input name=\"redirect_to\" type=\"hidden\" value=\"{r}\" />")),
-    }) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
-  };
+  return render_page(
+    "reset_password_update",
+    context! {
+      alert => escape_html(query.alert.as_deref().unwrap_or("")),
+      state => hidden_input("redirect_to", query.redirect_to.as_ref()),
+    },
+  );
 }
 
 #[derive(Debug, Default, Deserialize)]
 pub struct ChangePasswordQuery {
   redirect_to: Option<String>,
   alert: Option<String>,
+  /// Set when users are sent here after logging in with a password they're required to change.
+  required: Option<bool>,
 }
 
 async fn ui_change_password_handler(Query(query): Query<ChangePasswordQuery>) -> Response {
-  return match templates().get_template("change_password").unwrap().render(context! {
-    alert => query.alert.as_deref().unwrap_or(""),
-    state => query.redirect_to.map(|r| format!("<input name=\"redirect_to\" type=\"hidden\" value=\"{r}\" />")),
-  }) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
-  };
+  let alert = query.alert.as_deref().unwrap_or_else(|| {
+    if query.required == Some(true) {
+      "Please choose a new password to continue."
+    } else {
+      ""
+    }
+  });
+
+  return render_page(
+    "change_password",
+    context! {
+      alert => escape_html(alert),
+      state => hidden_input("redirect_to", query.redirect_to.as_ref()),
+    },
+  );
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -195,20 +273,13 @@ async fn ui_change_email_handler(Query(query): Query<ChangeEmailQuery>, user: Us
     csrf_token = hidden_input("csrf_token", Some(&user.csrf_token)),
   );
 
-  return match templates()
-    .get_template("change_email")
-    .unwrap()
-    .render(context! {
-      alert => query.alert.as_deref().unwrap_or(""),
+  return render_page(
+    "change_email",
+    context! {
+      alert => escape_html(query.alert.as_deref().unwrap_or("")),
       state => form_state,
-    }) {
-    Ok(output) => Html(output).into_response(),
-    Err(err) => (
-      StatusCode::INTERNAL_SERVER_ERROR,
-      format!("failed to render template: {err}"),
-    )
-      .into_response(),
-  };
+    },
+  );
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -251,11 +322,28 @@ pub(crate) fn auth_ui_router() -> Router<crate::AppState> {
 
 fn hidden_input(name: &str, value: Option<&String>) -> String {
   if let Some(value) = value {
+    let value = escape_html(value);
     return format!("<input name=\"{name}\" type=\"hidden\" value=\"{value}\" />");
   }
   return "".to_string();
 }
 
+/// Escapes untrusted values, e.g. from query parameters, before splicing them into templates.
+fn escape_html(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#x27;"),
+      c => escaped.push(c),
+    }
+  }
+  return escaped;
+}
+
 #[derive(RustEmbed, Clone)]
 #[folder = "js/auth/dist/"]
 struct AuthAssets;
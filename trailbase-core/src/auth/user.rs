@@ -39,6 +39,12 @@ pub(crate) struct DbUser {
   pub provider_id: i64,
   pub provider_user_id: Option<String>,
   pub provider_avatar_url: Option<String>,
+
+  // Whether logins require a second TOTP factor.
+  pub require_totp: bool,
+
+  // Whether the user has to change their password before accessing record APIs.
+  pub must_change_password: bool,
 }
 
 impl DbUser {
@@ -68,6 +74,16 @@ pub struct User {
 
   /// The "expected" CSRF token as included in the auth token claims [User] was constructed from.
   pub csrf_token: String,
+
+  /// Application-specific claims included in the auth token, see [crate::auth::CustomClaimsHook].
+  pub custom_claims: Option<serde_json::Value>,
+
+  /// Ids of the groups the user is a member of as included in the auth token claims. Membership
+  /// changes only take effect once the token is refreshed.
+  pub groups: Vec<String>,
+
+  /// Set for users who have to change their password before accessing record APIs.
+  pub must_change_password: bool,
 }
 
 impl PartialEq for User {
@@ -85,11 +101,19 @@ impl User {
     if uuid.get_version_num() != 7 {
       return Err(AuthError::UnauthorizedExt("Invalid UUID version".into()));
     }
+    if claims.totp_verified == Some(false) {
+      return Err(AuthError::UnauthorizedExt(
+        "TOTP confirmation pending".into(),
+      ));
+    }
     return Ok(Self {
       id: claims.sub,
       email: claims.email,
       uuid,
       csrf_token: claims.csrf_token,
+      custom_claims: claims.custom_claims,
+      groups: claims.groups,
+      must_change_password: claims.must_change_password,
     });
   }
 
@@ -105,6 +129,9 @@ impl User {
       email: email.to_string(),
       uuid: user_id,
       csrf_token: crate::rand::generate_random_string(20),
+      custom_claims: None,
+      groups: vec![],
+      must_change_password: false,
     };
   }
 }
@@ -118,7 +145,9 @@ where
 
   async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
     let tokens = <Tokens as FromRequestParts<S>>::from_request_parts(parts, state).await?;
-    return User::from_token_claims(tokens.auth_token_claims);
+    let user = User::from_token_claims(tokens.auth_token_claims)?;
+    tracing::Span::current().record("user.id", user.id.as_str());
+    return Ok(user);
   }
 }
 
@@ -135,7 +164,9 @@ where
   ) -> Result<Option<Self>, Self::Rejection> {
     let tokens = <Tokens as OptionalFromRequestParts<S>>::from_request_parts(parts, state).await?;
     if let Some(tokens) = tokens {
-      return Ok(Some(User::from_token_claims(tokens.auth_token_claims)?));
+      let user = User::from_token_claims(tokens.auth_token_claims)?;
+      tracing::Span::current().record("user.id", user.id.as_str());
+      return Ok(Some(user));
     }
     return Ok(None);
   }
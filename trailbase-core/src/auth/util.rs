@@ -1,5 +1,7 @@
 use axum::http::request::Parts;
 use base64::prelude::*;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
 use chrono::Duration;
 use lazy_static::lazy_static;
 use sha2::{Digest, Sha256};
@@ -182,6 +184,42 @@ pub async fn user_exists(state: &AppState, email: &str) -> Result<bool, AuthErro
     .map_err(|err| AuthError::Internal(err.into()));
 }
 
+const NONCE_LENGTH: usize = 12;
+
+/// Encrypts secrets at rest, e.g. TOTP secrets, with a key derived for the given purpose. Returns
+/// nonce || ciphertext.
+pub(crate) fn encrypt_secret(
+  state: &AppState,
+  purpose: &str,
+  secret: &[u8],
+) -> Result<Vec<u8>, AuthError> {
+  let cipher = ChaCha20Poly1305::new(&state.jwt().derive_symmetric_key(purpose).into());
+  let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+  let ciphertext = cipher
+    .encrypt(&nonce, secret)
+    .map_err(|err| AuthError::Internal(err.to_string().into()))?;
+
+  let mut encrypted = nonce.to_vec();
+  encrypted.extend(ciphertext);
+  return Ok(encrypted);
+}
+
+pub(crate) fn decrypt_secret(
+  state: &AppState,
+  purpose: &str,
+  encrypted: &[u8],
+) -> Result<Vec<u8>, AuthError> {
+  if encrypted.len() < NONCE_LENGTH {
+    return Err(AuthError::Internal("invalid encrypted secret".into()));
+  }
+  let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+
+  let cipher = ChaCha20Poly1305::new(&state.jwt().derive_symmetric_key(purpose).into());
+  return cipher
+    .decrypt(Nonce::from_slice(nonce), ciphertext)
+    .map_err(|err| AuthError::Internal(err.to_string().into()));
+}
+
 pub(crate) async fn is_admin(state: &AppState, user: &User) -> bool {
   lazy_static! {
     static ref QUERY: String = format!(r#"SELECT admin FROM "{USER_TABLE}" WHERE id = $1"#);
@@ -217,12 +255,32 @@ pub(crate) async fn delete_all_sessions_for_user(
   );
 }
 
-pub(crate) async fn delete_session(
+/// Revokes all sessions for the given user. Unlike deletion, revoked sessions remain visible
+/// until they expire.
+pub(crate) async fn revoke_all_sessions_for_user(
+  state: &AppState,
+  user_id: uuid::Uuid,
+) -> Result<usize, AuthError> {
+  lazy_static! {
+    static ref QUERY: String =
+      format!(r#"UPDATE "{SESSION_TABLE}" SET revoked = TRUE WHERE user = $1 AND NOT revoked"#);
+  };
+
+  return Ok(
+    state
+      .user_conn()
+      .execute(&QUERY, params!(user_id.into_bytes()))
+      .await?,
+  );
+}
+
+pub(crate) async fn revoke_session(
   state: &AppState,
   refresh_token: String,
 ) -> Result<usize, AuthError> {
   lazy_static! {
-    static ref QUERY: String = format!(r#"DELETE FROM "{SESSION_TABLE}" WHERE refresh_token = $1"#);
+    static ref QUERY: String =
+      format!(r#"UPDATE "{SESSION_TABLE}" SET revoked = TRUE WHERE refresh_token = $1"#);
   };
 
   return Ok(
@@ -0,0 +1,168 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+  #[error("SQLite error: {0}")]
+  Sql(#[from] trailbase_sqlite::Error),
+  #[error("Rusqlite error: {0}")]
+  Rusqlite(#[from] rusqlite::Error),
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Join error: {0}")]
+  Join(#[from] tokio::task::JoinError),
+  #[error("Corrupt backup: {0}")]
+  Corrupt(String),
+}
+
+/// Returns the default backup filename, e.g. "trailbase_backup_1700000000.sqlite.gz".
+pub fn default_backup_filename(compress: bool) -> String {
+  let timestamp = chrono::Utc::now().timestamp();
+  return if compress {
+    format!("trailbase_backup_{timestamp}.sqlite.gz")
+  } else {
+    format!("trailbase_backup_{timestamp}.sqlite")
+  };
+}
+
+/// Creates a consistent copy of the main database at `output` using SQLite's online backup API,
+/// i.e. it's safe to call while the database is being written to.
+pub async fn backup(
+  conn: &trailbase_sqlite::Connection,
+  output: PathBuf,
+  compress: bool,
+) -> Result<(), BackupError> {
+  if !compress {
+    conn.backup_to_path(&output, /* progress= */ None).await?;
+    return Ok(());
+  }
+
+  let tmp = tmp_path(&output);
+  conn.backup_to_path(&tmp, /* progress= */ None).await?;
+
+  tokio::task::spawn_blocking(move || {
+    let result = compress_file(&tmp, &output);
+    let _ = std::fs::remove_file(&tmp);
+    return result;
+  })
+  .await??;
+
+  return Ok(());
+}
+
+/// Replaces the main database at `main_db_path` with the backup at `from`.
+///
+/// NOTE: The server must not be running, since the database file is swapped out underneath any
+/// open connection.
+pub fn restore(from: &Path, main_db_path: &Path, compress: bool) -> Result<(), BackupError> {
+  let tmp = tmp_path(main_db_path);
+  if compress {
+    decompress_file(from, &tmp)?;
+  } else {
+    std::fs::copy(from, &tmp)?;
+  }
+
+  // Make sure we don't replace a working database with garbage.
+  if let Err(err) = check_integrity(&tmp) {
+    let _ = std::fs::remove_file(&tmp);
+    return Err(err);
+  }
+
+  // Renaming is atomic, i.e. the database is either entirely replaced or not at all.
+  std::fs::rename(&tmp, main_db_path)?;
+
+  // Stale WAL files of the replaced database must not be applied to the restored one.
+  for suffix in ["-wal", "-shm"] {
+    let mut path = main_db_path.as_os_str().to_owned();
+    path.push(suffix);
+    match std::fs::remove_file(path) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+      _ => {}
+    };
+  }
+
+  return Ok(());
+}
+
+fn check_integrity(path: &Path) -> Result<(), BackupError> {
+  let check: String =
+    rusqlite::Connection::open(path)?.query_row("PRAGMA integrity_check", (), |row| row.get(0))?;
+  if check != "ok" {
+    return Err(BackupError::Corrupt(check));
+  }
+  return Ok(());
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+  return tmp.into();
+}
+
+fn compress_file(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+  let mut encoder = GzEncoder::new(File::create(dst)?, Compression::default());
+  std::io::copy(&mut File::open(src)?, &mut encoder)?;
+  encoder.finish()?.sync_all()?;
+  return Ok(());
+}
+
+fn decompress_file(src: &Path, dst: &Path) -> Result<(), std::io::Error> {
+  let mut decoder = GzDecoder::new(File::open(src)?);
+  let mut file = File::create(dst)?;
+  std::io::copy(&mut decoder, &mut file)?;
+  file.sync_all()?;
+  return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn test_backup_and_restore() {
+    let dir = temp_dir::TempDir::new().unwrap();
+    let main_db_path = dir.child("main.db");
+
+    let conn = trailbase_sqlite::Connection::from_conn(
+      trailbase_sqlite::connect_sqlite(Some(main_db_path.clone()), None).unwrap(),
+    )
+    .unwrap();
+    conn
+      .execute(
+        "CREATE TABLE records (id INTEGER PRIMARY KEY, value TEXT) STRICT",
+        (),
+      )
+      .await
+      .unwrap();
+    for i in 0..100 {
+      conn
+        .execute(
+          "INSERT INTO records (value) VALUES ($1)",
+          trailbase_sqlite::params!(format!("value{i}")),
+        )
+        .await
+        .unwrap();
+    }
+
+    let backup_path = dir.child(default_backup_filename(true));
+    backup(&conn, backup_path.clone(), true).await.unwrap();
+
+    // Compressed backups aren't valid databases.
+    assert!(restore(&backup_path, &main_db_path, false).is_err());
+
+    conn.close().await.unwrap();
+    std::fs::remove_file(&main_db_path).unwrap();
+
+    restore(&backup_path, &main_db_path, true).unwrap();
+
+    let conn = rusqlite::Connection::open(&main_db_path).unwrap();
+    let count: i64 = conn
+      .query_row("SELECT COUNT(*) FROM records", (), |row| row.get(0))
+      .unwrap();
+    assert_eq!(count, 100);
+  }
+}
@@ -93,7 +93,8 @@ pub mod proto {
 
   use crate::config::ConfigError;
   use crate::constants::{
-    AVATAR_TABLE, DEFAULT_AUTH_TOKEN_TTL, DEFAULT_REFRESH_TOKEN_TTL, LOGS_RETENTION_DEFAULT,
+    AVATAR_TABLE, DEFAULT_AUTH_TOKEN_TTL, DEFAULT_LOGIN_LOCKOUT_DURATION,
+    DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS, DEFAULT_REFRESH_TOKEN_TTL, LOGS_RETENTION_DEFAULT,
     SITE_URL_DEFAULT,
   };
   use crate::email;
@@ -148,6 +149,8 @@ pub mod proto {
         auth: AuthConfig {
           auth_token_ttl_sec: Some(DEFAULT_AUTH_TOKEN_TTL.num_seconds()),
           refresh_token_ttl_sec: Some(DEFAULT_REFRESH_TOKEN_TTL.num_seconds()),
+          max_failed_attempts: Some(DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS),
+          lockout_duration_sec: Some(DEFAULT_LOGIN_LOCKOUT_DURATION.num_seconds()),
           ..Default::default()
         },
         ..Default::default()
@@ -170,6 +173,15 @@ pub mod proto {
         update_access_rule: Some("_ROW_.user = _USER_.id".to_string()),
         delete_access_rule: Some("_ROW_.user = _USER_.id".to_string()),
         schema_access_rule: None,
+        write_access_rule: None,
+        soft_delete: None,
+        audit_trail: None,
+        cors_allowed_origins: vec![],
+        quota: None,
+        send_email_on_create: None,
+        notify_on_change: None,
+        group_ids: vec![],
+        max_body_size_bytes: None,
       }];
 
       return config;
@@ -209,6 +221,18 @@ pub mod proto {
           .map_or(DEFAULT_REFRESH_TOKEN_TTL, Duration::seconds),
       );
     }
+
+    /// Returns the max number of failed login attempts and the subsequent lockout duration.
+    pub fn login_lockout(&self) -> (u32, Duration) {
+      return (
+        self
+          .max_failed_attempts
+          .unwrap_or(DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS),
+        self
+          .lockout_duration_sec
+          .map_or(DEFAULT_LOGIN_LOCKOUT_DURATION, Duration::seconds),
+      );
+    }
   }
 }
 
@@ -7,6 +7,18 @@ pub(crate) const USER_TABLE_ID_COLUMN: &str = "id";
 
 pub(crate) const SESSION_TABLE: &str = "_session";
 pub(crate) const AVATAR_TABLE: &str = "_user_avatar";
+pub(crate) const TOTP_TABLE: &str = "_user_totp";
+pub(crate) const API_KEYS_TABLE: &str = "_api_keys";
+pub(crate) const LOGIN_ATTEMPTS_TABLE: &str = "_login_attempts";
+pub(crate) const MAGIC_LINK_TOKENS_TABLE: &str = "_magic_link_tokens";
+pub(crate) const INVITATIONS_TABLE: &str = "_invitations";
+pub(crate) const USER_OAUTH_TOKENS_TABLE: &str = "_user_oauth_tokens";
+pub(crate) const WEBHOOKS_TABLE: &str = "_webhooks";
+pub(crate) const WEBHOOK_DELIVERIES_TABLE: &str = "_webhook_deliveries";
+pub(crate) const PUSH_TOKENS_TABLE: &str = "_push_tokens";
+pub(crate) const GROUPS_TABLE: &str = "_groups";
+pub(crate) const GROUP_MEMBERS_TABLE: &str = "_group_members";
+pub(crate) const ADMIN_AUDIT_LOG_TABLE: &str = "_admin_audit_log";
 
 pub(crate) const LOGS_TABLE_ID_COLUMN: &str = "id";
 pub const LOGS_RETENTION_DEFAULT: Duration = Duration::days(7);
@@ -19,6 +31,7 @@ pub const COOKIE_OAUTH_STATE: &str = "oauth_state";
 // naming: https://datatracker.ietf.org/doc/html/draft-saintandre-xdash-00
 pub const HEADER_REFRESH_TOKEN: &str = "Refresh-Token";
 pub const HEADER_CSRF_TOKEN: &str = "CSRF-Token";
+pub const HEADER_REQUEST_ID: &str = "X-Request-ID";
 
 #[cfg(debug_assertions)]
 pub const DEFAULT_AUTH_TOKEN_TTL: Duration = Duration::minutes(2);
@@ -26,6 +39,8 @@ pub const DEFAULT_AUTH_TOKEN_TTL: Duration = Duration::minutes(2);
 pub const DEFAULT_AUTH_TOKEN_TTL: Duration = Duration::minutes(60);
 
 pub const DEFAULT_REFRESH_TOKEN_TTL: Duration = Duration::days(30);
+pub const DEFAULT_MAX_FAILED_LOGIN_ATTEMPTS: u32 = 10;
+pub const DEFAULT_LOGIN_LOCKOUT_DURATION: Duration = Duration::minutes(15);
 
 pub const SITE_URL_DEFAULT: &str = "http://localhost:4000";
 
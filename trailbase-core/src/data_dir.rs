@@ -51,6 +51,10 @@ impl DataDir {
     return self.0.join("uploads/");
   }
 
+  pub fn email_templates_path(&self) -> PathBuf {
+    return self.0.join("email_templates/");
+  }
+
   pub fn key_path(&self) -> PathBuf {
     return self.secrets_path().join("keys/");
   }
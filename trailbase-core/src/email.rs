@@ -1,12 +1,15 @@
-use lettre::message::{header::ContentType, Body, Mailbox, Message};
+use lettre::message::{header::ContentType, Body, Mailbox, Message, MultiPart};
 use lettre::transport::smtp;
 use lettre::{AsyncSendmailTransport, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
 use minijinja::{context, Environment};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
 
 use crate::auth::user::DbUser;
 use crate::config::proto::{Config, EmailTemplate};
+use crate::data_dir::DataDir;
 use crate::AppState;
 
 #[derive(Debug, Error)]
@@ -23,6 +26,10 @@ pub enum EmailError {
   Sendmail(#[from] lettre::transport::sendmail::Error),
   #[error("Template error: {0}")]
   Template(#[from] minijinja::Error),
+  #[error("Invalid template name: {0}")]
+  InvalidTemplateName(String),
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
 }
 
 pub struct Email {
@@ -33,6 +40,8 @@ pub struct Email {
 
   subject: String,
   body: String,
+  /// Optional plain-text alternative to the HTML body.
+  text: Option<String>,
 }
 
 impl Email {
@@ -48,16 +57,78 @@ impl Email {
       to: to.parse()?,
       subject,
       body,
+      text: None,
     });
   }
 
+  /// Renders the custom template `template_name` from the data dir's "email_templates/", see
+  /// [CustomEmailTemplate], with the given variables and sends it to `to`.
+  ///
+  /// In addition to `variables`, templates can reference `APP_NAME` and `SITE_URL`.
+  pub async fn send_template(
+    state: &AppState,
+    to: &str,
+    template_name: &str,
+    variables: HashMap<&str, &str>,
+  ) -> Result<(), EmailError> {
+    let template = CustomEmailTemplate::load(state.data_dir(), template_name).await?;
+
+    let server_config = state.access_config(|c| c.server.clone());
+    let mut vars: HashMap<&str, &str> = HashMap::from([
+      (
+        "APP_NAME",
+        server_config
+          .application_name
+          .as_deref()
+          .unwrap_or_default(),
+      ),
+      (
+        "SITE_URL",
+        server_config.site_url.as_deref().unwrap_or_default(),
+      ),
+    ]);
+    vars.extend(variables);
+
+    let env = Environment::new();
+    let subject = match template.subject {
+      Some(ref subject) => env
+        .template_from_named_str("subject.txt", subject)?
+        .render(&vars)?,
+      None => vars["APP_NAME"].to_string(),
+    };
+    // NOTE: The ".html" suffix enables auto-escaping of variables.
+    let body = env
+      .template_from_named_str("body.html", &template.html)?
+      .render(&vars)?;
+    let text = match template.text {
+      Some(ref text) => Some(
+        env
+          .template_from_named_str("body.txt", text)?
+          .render(&vars)?,
+      ),
+      None => None,
+    };
+
+    let mut email = Email::new(state, to.to_string(), subject, body)?;
+    email.text = text;
+    return email.send().await;
+  }
+
   pub async fn send(&self) -> Result<(), EmailError> {
-    let email = Message::builder()
+    let builder = Message::builder()
       .to(self.to.clone())
       .from(self.from.clone())
-      .subject(self.subject.clone())
-      .header(ContentType::TEXT_HTML)
-      .body(Body::new(self.body.clone()))?;
+      .subject(self.subject.clone());
+
+    let email = match self.text {
+      Some(ref text) => builder.multipart(MultiPart::alternative_plain_html(
+        text.clone(),
+        self.body.clone(),
+      ))?,
+      None => builder
+        .header(ContentType::TEXT_HTML)
+        .body(Body::new(self.body.clone()))?,
+    };
 
     match &*self.mailer {
       Mailer::Smtp(mailer) => {
@@ -208,6 +279,206 @@ impl Email {
 
     return Email::new(state, user.email.clone(), subject, body);
   }
+
+  pub(crate) fn magic_link_email(
+    state: &AppState,
+    user: &DbUser,
+    magic_token: &str,
+  ) -> Result<Self, EmailError> {
+    let (server_config, template) =
+      state.access_config(|c| (c.server.clone(), c.email.magic_link_template.clone()));
+
+    let Some(ref site_url) = server_config.site_url else {
+      return Err(EmailError::Missing("config.site_url"));
+    };
+
+    let (subject_template, body_template) = match template {
+      Some(EmailTemplate {
+        subject: Some(subject),
+        body: Some(body),
+      }) => (subject, body),
+      _ => {
+        log::debug!("Falling back to default magic link email");
+        let d = defaults::magic_link_email();
+        (d.subject.unwrap(), d.body.unwrap())
+      }
+    };
+
+    let verification_url = format!("{site_url}/_/auth/login?magic_token={magic_token}");
+
+    let env = Environment::new();
+    let subject = env
+      .template_from_named_str("subject", &subject_template)?
+      .render(context! {
+        APP_NAME => server_config.application_name,
+        EMAIL => user.email,
+      })?;
+    let body = env
+      .template_from_named_str("body", &body_template)?
+      .render(context! {
+        APP_NAME => server_config.application_name,
+        VERIFICATION_URL => verification_url,
+        SITE_URL => server_config.site_url,
+        CODE => magic_token,
+        EMAIL => user.email,
+      })?;
+
+    return Email::new(state, user.email.clone(), subject, body);
+  }
+
+  pub(crate) fn invitation_email(
+    state: &AppState,
+    email: &str,
+    invitation_token: &str,
+  ) -> Result<Self, EmailError> {
+    let (server_config, template) =
+      state.access_config(|c| (c.server.clone(), c.email.invitation_template.clone()));
+
+    let Some(ref site_url) = server_config.site_url else {
+      return Err(EmailError::Missing("config.site_url"));
+    };
+
+    let (subject_template, body_template) = match template {
+      Some(EmailTemplate {
+        subject: Some(subject),
+        body: Some(body),
+      }) => (subject, body),
+      _ => {
+        log::debug!("Falling back to default invitation email");
+        let d = defaults::invitation_email();
+        (d.subject.unwrap(), d.body.unwrap())
+      }
+    };
+
+    let verification_url =
+      format!("{site_url}/_/auth/register?invitation_token={invitation_token}");
+
+    let env = Environment::new();
+    let subject = env
+      .template_from_named_str("subject", &subject_template)?
+      .render(context! {
+        APP_NAME => server_config.application_name,
+        EMAIL => email,
+      })?;
+    let body = env
+      .template_from_named_str("body", &body_template)?
+      .render(context! {
+        APP_NAME => server_config.application_name,
+        VERIFICATION_URL => verification_url,
+        SITE_URL => server_config.site_url,
+        CODE => invitation_token,
+        EMAIL => email,
+      })?;
+
+    return Email::new(state, email.to_string(), subject, body);
+  }
+}
+
+/// Application-defined email template stored as "<name>.html", and optionally "<name>.txt" and
+/// "<name>.subject", in the data dir's "email_templates/".
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CustomEmailTemplate {
+  pub subject: Option<String>,
+  pub html: String,
+  pub text: Option<String>,
+}
+
+impl CustomEmailTemplate {
+  pub(crate) async fn load(data_dir: &DataDir, name: &str) -> Result<Self, EmailError> {
+    let dir = data_dir.email_templates_path();
+    let path = |ext: &str| template_path(&dir, name, ext);
+
+    return Ok(Self {
+      subject: read_optional(&path("subject")?).await?,
+      html: tokio::fs::read_to_string(path("html")?).await?,
+      text: read_optional(&path("txt")?).await?,
+    });
+  }
+
+  pub(crate) async fn store(&self, data_dir: &DataDir, name: &str) -> Result<(), EmailError> {
+    let dir = data_dir.email_templates_path();
+    tokio::fs::create_dir_all(&dir).await?;
+
+    tokio::fs::write(template_path(&dir, name, "html")?, &self.html).await?;
+    for (ext, content) in [("subject", &self.subject), ("txt", &self.text)] {
+      let path = template_path(&dir, name, ext)?;
+      match content {
+        Some(content) => tokio::fs::write(path, content).await?,
+        None => remove_optional(&path).await?,
+      };
+    }
+    return Ok(());
+  }
+
+  /// Returns false if no such template exists.
+  pub(crate) async fn delete(data_dir: &DataDir, name: &str) -> Result<bool, EmailError> {
+    let dir = data_dir.email_templates_path();
+    let html = template_path(&dir, name, "html")?;
+    if !tokio::fs::try_exists(&html).await? {
+      return Ok(false);
+    }
+
+    tokio::fs::remove_file(html).await?;
+    for ext in ["subject", "txt"] {
+      remove_optional(&template_path(&dir, name, ext)?).await?;
+    }
+    return Ok(true);
+  }
+
+  /// Lists the names of all custom templates in alphabetical order.
+  pub(crate) async fn list(data_dir: &DataDir) -> Result<Vec<String>, EmailError> {
+    let mut names: Vec<String> = vec![];
+
+    let mut entries = match tokio::fs::read_dir(data_dir.email_templates_path()).await {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+      Err(err) => return Err(err.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+      let path = entry.path();
+      if path.extension().is_some_and(|ext| ext == "html") {
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+          if is_valid_template_name(name) {
+            names.push(name.to_string());
+          }
+        }
+      }
+    }
+
+    names.sort();
+    return Ok(names);
+  }
+}
+
+/// Template names are restricted to ASCII alphanumerics, '_' and '-' to prevent path traversal.
+pub(crate) fn is_valid_template_name(name: &str) -> bool {
+  return !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+}
+
+fn template_path(dir: &Path, name: &str, ext: &str) -> Result<std::path::PathBuf, EmailError> {
+  if !is_valid_template_name(name) {
+    return Err(EmailError::InvalidTemplateName(name.to_string()));
+  }
+  return Ok(dir.join(format!("{name}.{ext}")));
+}
+
+async fn read_optional(path: &Path) -> Result<Option<String>, std::io::Error> {
+  return match tokio::fs::read_to_string(path).await {
+    Ok(content) => Ok(Some(content)),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(err),
+  };
+}
+
+async fn remove_optional(path: &Path) -> Result<(), std::io::Error> {
+  return match tokio::fs::remove_file(path).await {
+    Ok(_) => Ok(()),
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+    Err(err) => Err(err),
+  };
 }
 
 fn get_sender(state: &AppState) -> Result<Mailbox, EmailError> {
@@ -348,6 +619,52 @@ pub(crate) mod defaults {
       body: Some(BODY.to_string()),
     };
   }
+
+  pub fn magic_link_email() -> EmailTemplate {
+    const SUBJECT: &str = "Log in to {{ APP_NAME }}";
+    const BODY: &str = indoc! {r#"
+        <html>
+          <body>
+            <h1>Log in to {{ APP_NAME }}</h1>
+
+            <p>
+              Click the link below to log in. The link can only be used once and expires in 15 minutes.
+            </p>
+
+            <a class="btn" href="{{ VERIFICATION_URL }}">
+              {{ VERIFICATION_URL }}
+            </a>
+          </body>
+        </html>"#};
+
+    return EmailTemplate {
+      subject: Some(SUBJECT.to_string()),
+      body: Some(BODY.to_string()),
+    };
+  }
+
+  pub fn invitation_email() -> EmailTemplate {
+    const SUBJECT: &str = "You have been invited to {{ APP_NAME }}";
+    const BODY: &str = indoc! {r#"
+        <html>
+          <body>
+            <h1>Join {{ APP_NAME }}</h1>
+
+            <p>
+              You have been invited to join {{ APP_NAME }}. Click the link below to register with {{ EMAIL }}.
+            </p>
+
+            <a class="btn" href="{{ VERIFICATION_URL }}">
+              {{ VERIFICATION_URL }}
+            </a>
+          </body>
+        </html>"#};
+
+    return EmailTemplate {
+      subject: Some(SUBJECT.to_string()),
+      body: Some(BODY.to_string()),
+    };
+  }
 }
 
 #[cfg(test)]
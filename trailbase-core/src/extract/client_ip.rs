@@ -0,0 +1,60 @@
+use axum::http::{Extensions, HeaderMap};
+use axum_client_ip::{SecureClientIp, SecureClientIpSource};
+use std::net::IpAddr;
+
+/// Parses [crate::ServerOptions::client_ip_source], defaulting to the connection's peer address.
+pub(crate) fn parse_client_ip_source(source: Option<&str>) -> Result<SecureClientIpSource, String> {
+  return match source {
+    Some(source) => source
+      .parse()
+      .map_err(|err| format!("invalid client IP source '{source}': {err}")),
+    None => Ok(SecureClientIpSource::ConnectInfo),
+  };
+}
+
+/// Returns the client's IP address from the configured source, i.e. the connection's peer address
+/// unless a header set by a trusted reverse proxy was configured.
+///
+/// NOTE: Unlike `InsecureClientIp`, this doesn't fall back to client-controlled headers like
+/// "X-Forwarded-For", which makes it suitable for security decisions such as lockouts.
+pub(crate) fn client_ip(headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+  let source = extensions
+    .get::<SecureClientIpSource>()
+    .unwrap_or(&SecureClientIpSource::ConnectInfo);
+  return SecureClientIp::from(source, headers, extensions)
+    .ok()
+    .map(|ip| ip.0);
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::ConnectInfo;
+  use std::net::SocketAddr;
+
+  use super::*;
+
+  #[test]
+  fn test_client_ip() {
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Forwarded-For", "1.1.1.1".parse().unwrap());
+
+    let mut extensions = Extensions::new();
+    assert_eq!(client_ip(&headers, &extensions), None);
+
+    // By default, the peer address is used and forwarding headers are ignored.
+    extensions.insert(ConnectInfo("2.2.2.2:1234".parse::<SocketAddr>().unwrap()));
+    assert_eq!(
+      client_ip(&headers, &extensions),
+      Some("2.2.2.2".parse().unwrap())
+    );
+
+    // ...unless configured, e.g. when running behind a reverse proxy.
+    extensions.insert(parse_client_ip_source(Some("RightmostXForwardedFor")).unwrap());
+    assert_eq!(
+      client_ip(&headers, &extensions),
+      Some("1.1.1.1".parse().unwrap())
+    );
+
+    assert!(parse_client_ip_source(Some("Bogus")).is_err());
+  }
+}
@@ -1,4 +1,6 @@
+mod client_ip;
 mod either;
 mod multipart;
 
+pub(crate) use client_ip::{client_ip, parse_client_ip_source};
 pub use either::Either;
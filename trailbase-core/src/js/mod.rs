@@ -4,6 +4,23 @@ mod import_provider;
 #[cfg(feature = "v8")]
 mod runtime;
 
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RuntimeOptions {
+  /// Number of V8 isolates/worker threads. Defaults to the number of available cores.
+  pub n_threads: Option<usize>,
+  /// Maximum heap size per isolate in MB.
+  pub heap_limit_mb: Option<u32>,
+  /// V8 stack size in KB.
+  pub stack_size_kb: Option<u32>,
+  /// Time after which JS HTTP handlers are interrupted.
+  ///
+  /// NOTE: Unlike the other options, which configure the process-wide V8 runtime on first use,
+  /// the timeout applies per runtime handle.
+  pub timeout: Option<Duration>,
+}
+
 #[cfg(not(feature = "v8"))]
 mod fallback {
   #[derive(Clone)]
@@ -16,7 +33,7 @@ mod fallback {
       return Self {};
     }
 
-    pub(crate) fn new_with_threads(n_threads: usize) -> Self {
+    pub(crate) fn new_with_options(_options: super::RuntimeOptions) -> Self {
       return Self {};
     }
   }
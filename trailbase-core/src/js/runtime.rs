@@ -5,20 +5,26 @@ use axum::response::{IntoResponse, Response};
 use axum::Router;
 use parking_lot::Mutex;
 use rustyscript::{
-  deno_core::PollEventLoopOptions, init_platform, js_value::Promise, json_args, Module, Runtime,
+  deno_core::{v8, v8_set_flags, PollEventLoopOptions},
+  init_platform,
+  js_value::Promise,
+  json_args, Module, Runtime,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::from_value;
 use std::collections::HashSet;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{Arc, OnceLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::oneshot;
 
 use crate::assets::cow_to_string;
 use crate::auth::user::User;
 use crate::js::import_provider::JsRuntimeAssets;
+use crate::js::RuntimeOptions;
 use crate::records::sql_to_json::rows_to_json_arrays;
 use crate::{AppState, DataDir};
 
@@ -35,6 +41,8 @@ struct JsResponse {
 pub enum JsResponseError {
   #[error("Precondition: {0}")]
   Precondition(String),
+  #[error("Timeout")]
+  Timeout,
   #[error("Internal: {0}")]
   Internal(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -55,6 +63,7 @@ struct DispatchArgs {
   headers: Vec<(String, String)>,
   user: Option<JsUser>,
   body: bytes::Bytes,
+  timeout: Option<Duration>,
 
   reply: tokio::sync::oneshot::Sender<Result<JsResponse, JsResponseError>>,
 }
@@ -100,9 +109,74 @@ impl Drop for RuntimeSingleton {
   }
 }
 
+/// Terminates JS execution on an isolate once the earliest deadline of its in-flight requests
+/// has passed. This is needed to break out of, e.g., infinite loops, which would otherwise block
+/// the isolate's thread forever.
+struct Watchdog {
+  sender: std::sync::mpsc::Sender<Option<Instant>>,
+  deadline: Option<Instant>,
+  terminated: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+  fn spawn(isolate: v8::IsolateHandle) -> Self {
+    let (sender, receiver) = std::sync::mpsc::channel::<Option<Instant>>();
+    let terminated = Arc::new(AtomicBool::new(false));
+
+    let terminated_clone = terminated.clone();
+    std::thread::spawn(move || {
+      let mut deadline: Option<Instant> = None;
+      loop {
+        let next = match deadline {
+          Some(deadline) => {
+            receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+          }
+          None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match next {
+          Ok(next) => deadline = next,
+          Err(RecvTimeoutError::Timeout) => {
+            terminated_clone.store(true, Ordering::Release);
+            isolate.terminate_execution();
+            deadline = None;
+          }
+          Err(RecvTimeoutError::Disconnected) => return,
+        }
+      }
+    });
+
+    return Self {
+      sender,
+      deadline: None,
+      terminated,
+    };
+  }
+
+  fn set_deadline(&mut self, deadline: Option<Instant>) {
+    if self.deadline != deadline {
+      self.deadline = deadline;
+      let _ = self.sender.send(deadline);
+    }
+  }
+
+  /// Re-enables JS execution if it was terminated. Returns whether it was.
+  fn reset(&self, runtime: &mut Runtime) -> bool {
+    if self.terminated.swap(false, Ordering::AcqRel) {
+      runtime
+        .deno_runtime()
+        .v8_isolate()
+        .cancel_terminate_execution();
+      return true;
+    }
+    return false;
+  }
+}
+
 struct Completer {
   name: String,
   promise: Promise<JsResponse>,
+  deadline: Option<Instant>,
   reply: tokio::sync::oneshot::Sender<Result<JsResponse, JsResponseError>>,
 }
 
@@ -111,6 +185,10 @@ impl Completer {
     return !self.promise.is_pending(runtime);
   }
 
+  fn is_expired(&self, now: Instant) -> bool {
+    return self.deadline.is_some_and(|deadline| deadline <= now);
+  }
+
   async fn resolve(self, runtime: &mut Runtime) {
     let value = self
       .promise
@@ -129,6 +207,7 @@ impl RuntimeSingleton {
     runtime: &mut Runtime,
     msg: Message,
     completers: &mut Vec<Completer>,
+    watchdog: &mut Watchdog,
   ) -> Result<(), AnyError> {
     match msg {
       Message::Run(f) => {
@@ -137,6 +216,16 @@ impl RuntimeSingleton {
       Message::Dispatch(args) => {
         let channel = args.reply;
         let uri = args.uri.clone();
+
+        // NOTE: The deadline needs to be armed before calling into JS, since the synchronous part
+        // of the handler may already block.
+        let deadline = args.timeout.map(|timeout| Instant::now() + timeout);
+        if let Some(deadline) = deadline {
+          watchdog.set_deadline(Some(
+            watchdog.deadline.map_or(deadline, |d| d.min(deadline)),
+          ));
+        }
+
         let promise = match runtime.call_function_immediate::<Promise<JsResponse>>(
           None,
           "__dispatch",
@@ -152,10 +241,13 @@ impl RuntimeSingleton {
         ) {
           Ok(promise) => promise,
           Err(err) => {
-            if channel
-              .send(Err(JsResponseError::Internal(err.into())))
-              .is_err()
-            {
+            let err = if watchdog.reset(runtime) && deadline.is_some_and(|d| d <= Instant::now()) {
+              JsResponseError::Timeout
+            } else {
+              JsResponseError::Internal(err.into())
+            };
+
+            if channel.send(Err(err)).is_err() {
               log::error!("dispatch sending error failed");
             }
             return Ok(());
@@ -165,6 +257,7 @@ impl RuntimeSingleton {
         completers.push(Completer {
           name: uri,
           promise,
+          deadline,
           reply: channel,
         });
       }
@@ -200,10 +293,25 @@ impl RuntimeSingleton {
     private_recv: async_channel::Receiver<Message>,
     shared_recv: async_channel::Receiver<Message>,
   ) {
+    let mut watchdog = Watchdog::spawn(runtime.deno_runtime().v8_isolate().thread_safe_handle());
+
     runtime.tokio_runtime().block_on(async {
       let mut completers: Vec<Completer> = vec![];
 
       loop {
+        watchdog.reset(runtime);
+
+        let now = Instant::now();
+        for index in (0..completers.len()).rev() {
+          if completers[index].is_expired(now) && !completers[index].is_ready(runtime) {
+            let completer = completers.swap_remove(index);
+            if completer.reply.send(Err(JsResponseError::Timeout)).is_err() {
+              log::error!("Completer send failed for : {}", completer.name);
+            }
+          }
+        }
+        watchdog.set_deadline(completers.iter().filter_map(|c| c.deadline).min());
+
         let completed = completers
           .iter()
           .enumerate()
@@ -238,7 +346,8 @@ impl RuntimeSingleton {
             let Ok(msg) = msg else {
               panic!("private channel closed");
             };
-            if let Err(err) = Self::handle_message(runtime, msg, &mut completers).await {
+            let result = Self::handle_message(runtime, msg, &mut completers, &mut watchdog).await;
+            if let Err(err) = result {
               log::error!("Handle private message: {err}");
             }
           },
@@ -246,7 +355,8 @@ impl RuntimeSingleton {
             let Ok(msg) = msg else {
               panic!("private channel closed");
             };
-            if let Err(err) = Self::handle_message(runtime, msg, &mut completers).await {
+            let result = Self::handle_message(runtime, msg, &mut completers, &mut watchdog).await;
+            if let Err(err) = result {
               log::error!("Handle shared message: {err}");
             }
           },
@@ -255,8 +365,8 @@ impl RuntimeSingleton {
     });
   }
 
-  fn new_with_threads(threads: Option<usize>) -> Self {
-    let n_threads = match threads {
+  fn new(options: RuntimeOptions) -> Self {
+    let n_threads = match options.n_threads {
       Some(n) => n,
       None => std::thread::available_parallelism().map_or_else(
         |err| {
@@ -285,8 +395,21 @@ impl RuntimeSingleton {
       })
       .unzip();
 
+    let heap_limit_mb = options.heap_limit_mb;
+    let stack_size_kb = options.stack_size_kb;
     let handle = if n_threads > 0 {
       Some(std::thread::spawn(move || {
+        // NOTE: V8 flags have to be set before the platform is initialized.
+        if let Some(stack_size_kb) = stack_size_kb {
+          let unrecognized = v8_set_flags(vec![
+            "trailbase".to_string(),
+            format!("--stack-size={stack_size_kb}"),
+          ]);
+          if unrecognized.len() > 1 {
+            log::error!("Unrecognized V8 flags: {unrecognized:?}");
+          }
+        }
+
         init_platform(n_threads as u32, true);
 
         let threads: Vec<_> = receivers
@@ -305,12 +428,13 @@ impl RuntimeSingleton {
                   .unwrap(),
               );
 
-              let mut js_runtime = match Self::init_runtime(index, tokio_runtime.clone()) {
-                Ok(js_runtime) => js_runtime,
-                Err(err) => {
-                  panic!("Failed to init v8 runtime on thread {index}: {err}");
-                }
-              };
+              let mut js_runtime =
+                match Self::init_runtime(index, heap_limit_mb, tokio_runtime.clone()) {
+                  Ok(js_runtime) => js_runtime,
+                  Err(err) => {
+                    panic!("Failed to init v8 runtime on thread {index}: {err}");
+                  }
+                };
 
               Self::event_loop(&mut js_runtime, receiver, shared_receiver);
             });
@@ -337,12 +461,14 @@ impl RuntimeSingleton {
 
   fn init_runtime(
     index: usize,
+    heap_limit_mb: Option<u32>,
     tokio_runtime: std::rc::Rc<tokio::runtime::Runtime>,
   ) -> Result<Runtime, AnyError> {
     let mut runtime = rustyscript::Runtime::with_tokio_runtime(
       rustyscript::RuntimeOptions {
         import_provider: Some(Box::new(crate::js::import_provider::ImportProviderImpl)),
         schema_whlist: HashSet::from(["trailbase".to_string()]),
+        max_heap_size: heap_limit_mb.map(|mb| mb as usize * 1024 * 1024),
         ..Default::default()
       },
       tokio_runtime,
@@ -417,14 +543,15 @@ impl RuntimeSingleton {
 // NOTE: Repeated runtime initialization, e.g. in a multi-threaded context, leads to segfaults.
 // rustyscript::init_platform is supposed to help with this but we haven't found a way to
 // make it work. Thus, we're making the V8 VM a singleton (like Dart's).
-fn get_runtime(n_threads: Option<usize>) -> &'static RuntimeSingleton {
+fn get_runtime(options: Option<RuntimeOptions>) -> &'static RuntimeSingleton {
   static RUNTIME: OnceLock<RuntimeSingleton> = OnceLock::new();
-  return RUNTIME.get_or_init(move || RuntimeSingleton::new_with_threads(n_threads));
+  return RUNTIME.get_or_init(move || RuntimeSingleton::new(options.unwrap_or_default()));
 }
 
 #[derive(Clone)]
 pub(crate) struct RuntimeHandle {
   runtime: &'static RuntimeSingleton,
+  timeout: Option<Duration>,
 }
 
 impl RuntimeHandle {
@@ -465,12 +592,15 @@ impl RuntimeHandle {
   pub(crate) fn new() -> Self {
     return Self {
       runtime: get_runtime(None),
+      timeout: None,
     };
   }
 
-  pub(crate) fn new_with_threads(n_threads: usize) -> Self {
+  pub(crate) fn new_with_options(options: RuntimeOptions) -> Self {
+    let timeout = options.timeout;
     return Self {
-      runtime: get_runtime(Some(n_threads)),
+      runtime: get_runtime(Some(options)),
+      timeout,
     };
   }
 
@@ -531,6 +661,7 @@ impl IntoResponse for JsResponseError {
   fn into_response(self) -> Response {
     let (status, body): (StatusCode, Option<String>) = match self {
       Self::Precondition(err) => (StatusCode::PRECONDITION_FAILED, Some(err.to_string())),
+      Self::Timeout => (StatusCode::GATEWAY_TIMEOUT, None),
       Self::Internal(err) => (StatusCode::INTERNAL_SERVER_ERROR, Some(err.to_string())),
     };
 
@@ -607,12 +738,21 @@ fn add_route_to_router(
         headers,
         user: js_user,
         body: body_bytes,
+        timeout: runtime_handle.timeout,
         reply: sender,
       }))
       .await
       .unwrap();
 
-    let js_response = receiver.await.unwrap()?;
+    // NOTE: The isolate only starts the clock once it picks up the request, thus also time out
+    // here in case all isolates are busy.
+    let js_response = match runtime_handle.timeout {
+      Some(timeout) => tokio::time::timeout(timeout, receiver)
+        .await
+        .map_err(|_| JsResponseError::Timeout)?,
+      None => receiver.await,
+    }
+    .unwrap()?;
 
     let mut http_response = Response::builder()
       .status(js_response.status.unwrap_or(200))
@@ -811,6 +951,7 @@ pub(crate) async fn write_js_runtime_files(data_dir: &DataDir) {
 mod tests {
   use super::*;
   use rustyscript::Module;
+  use tower::ServiceExt;
 
   #[tokio::test]
   async fn test_serial_tests() {
@@ -820,6 +961,7 @@ mod tests {
     test_runtime_javascript().await;
     test_javascript_query().await;
     test_javascript_execute().await;
+    test_javascript_timeout().await;
   }
 
   async fn test_runtime_apply() {
@@ -944,4 +1086,62 @@ mod tests {
     let count: i64 = row.get(0).unwrap();
     assert_eq!(0, count);
   }
+
+  async fn test_javascript_timeout() {
+    let state = crate::app_state::test_state(None).await.unwrap();
+
+    let timeout_handle = RuntimeHandle::new_with_options(RuntimeOptions {
+      timeout: Some(Duration::from_millis(1)),
+      ..Default::default()
+    });
+    let loop_router = install_routes(
+      timeout_handle,
+      Module::new(
+        "loop.ts",
+        r#"
+          import { addRoute, stringHandler } from "trailbase:main";
+
+          addRoute("GET", "/loop", stringHandler(async (_req) => {
+            while (true) {}
+          }));
+        "#,
+      ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let ok_router = install_routes(
+      RuntimeHandle::new(),
+      Module::new(
+        "ok.ts",
+        r#"
+          import { addRoute, stringHandler } from "trailbase:main";
+
+          addRoute("GET", "/ok", stringHandler(async (_req) => "ok"));
+        "#,
+      ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let router = loop_router.merge(ok_router).with_state(state);
+    let get = |path: &str| {
+      return Request::builder().uri(path).body(Body::empty()).unwrap();
+    };
+
+    // Send more requests than there are isolates to make sure that the isolates get unblocked.
+    for _ in 0..=RuntimeHandle::new().runtime.n_threads {
+      let response = router.clone().oneshot(get("/loop")).await.unwrap();
+      assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    let response =
+      tokio::time::timeout(Duration::from_secs(10), router.clone().oneshot(get("/ok")))
+        .await
+        .expect("isolates blocked")
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+  }
 }
@@ -7,23 +7,33 @@ pub mod assets;
 pub mod config;
 pub mod constants;
 pub mod logging;
+pub mod openapi;
 pub mod records;
 pub mod util;
 
 mod admin;
 mod auth;
+mod backup;
 mod data_dir;
 mod email;
 mod extract;
 mod js;
 mod listing;
+mod metrics;
 mod migrations;
+mod otel;
+mod push;
+mod rate_limit;
+mod request_id;
 mod scheduler;
 mod schema;
+mod security_headers;
 mod server;
+mod sql_template;
 mod table_metadata;
 mod transaction;
 mod value_notifier;
+mod webhooks;
 
 #[cfg(test)]
 mod test;
@@ -31,7 +41,8 @@ mod test;
 pub use app_state::AppState;
 pub use auth::User;
 pub use data_dir::DataDir;
-pub use server::{InitError, Server, ServerOptions};
+pub use rate_limit::{RateLimitConfig, RateLimitKeyBy};
+pub use server::{InitError, Server, ServerOptions, VirtualHostConfig};
 
 use prost_reflect::DescriptorPool;
 use std::sync::LazyLock;
@@ -43,30 +54,23 @@ static DESCRIPTOR_POOL: LazyLock<DescriptorPool> = LazyLock::new(|| {
   DescriptorPool::decode(FILE_DESCRIPTOR_SET).expect("Failed to load file descriptor set")
 });
 
-pub mod openapi {
-  use utoipa::OpenApi;
-
-  #[derive(OpenApi)]
-  #[openapi(
-        modifiers(),
-        nest(
-            (path = "/api/auth/v1", api = crate::auth::AuthAPI),
-            (path = "/api/records/v1", api = crate::records::RecordOpenApi),
-        ),
-        tags()
-    )]
-  pub struct Doc;
-}
-
 pub mod api {
   pub use trailbase_sqlite::connect_sqlite;
 
   pub use crate::admin::user::{create_user_handler, CreateUserRequest};
   pub use crate::auth::api::login::login_with_password;
-  pub use crate::auth::{force_password_reset, JwtHelper, TokenClaims};
+  pub use crate::auth::{
+    create_api_key, force_password_reset, list_api_keys, revoke_api_key, ApiKeyJson,
+    CustomClaimsHook, JwtHelper, NewApiKey, TokenClaims,
+  };
+  pub use crate::backup::{backup, default_backup_filename, restore, BackupError};
   pub use crate::email::{Email, EmailError};
-  pub use crate::migrations::new_unique_migration_filename;
+  pub use crate::migrations::{
+    apply_pending_migrations, migration_status, new_unique_migration_filename, MigrationStatus,
+  };
+  pub use crate::schema::SchemaError;
   pub use crate::server::{init_app_state, InitArgs};
+  pub use crate::sql_template::{Order, SqlTemplate};
   pub use crate::table_metadata::{build_json_schema, JsonSchemaMode, TableMetadataCache};
 }
 
@@ -18,6 +18,8 @@ pub enum WhereClauseError {
   NotImplemented(String),
   #[error("Unrecognized param error: {0}")]
   UnrecognizedParam(String),
+  #[error("Full-text search not supported for: {0}")]
+  FullTextNotSupported(String),
 }
 
 // Syntax: ?key[gte]=value&key[lte]=value
@@ -38,7 +40,23 @@ pub enum Qualifier {
   LessThanEqual,
   LessThan,
   Like,
+  NotLike,
+  /// Explicitly case-insensitive LIKE comparing lower-cased values.
+  ///
+  /// NOTE: Filtering on `lower(col)` cannot use plain indexes on `col`. Add an index on the
+  /// expression, i.e. `CREATE INDEX ... ON table (lower(col))`, for large tables.
+  ILike,
+  NotILike,
   Regexp,
+  /// Inclusive range, e.g. "col[between]=low,high".
+  Between,
+  /// Set membership, e.g. "col[in]=a,b,c".
+  In,
+  /// Takes no value, e.g. "col[isnull]".
+  IsNull,
+  IsNotNull,
+  /// Full-text match using the table's FTS5 index, e.g. "col[fulltext]=hello world".
+  FullText,
 }
 
 impl Qualifier {
@@ -51,7 +69,15 @@ impl Qualifier {
       Some("not") => Some(Self::Not),
       Some("ne") => Some(Self::NotEqual),
       Some("like") => Some(Self::Like),
+      Some("nlike") => Some(Self::NotLike),
+      Some("ilike") => Some(Self::ILike),
+      Some("nilike") => Some(Self::NotILike),
       Some("re") => Some(Self::Regexp),
+      Some("between") => Some(Self::Between),
+      Some("in") => Some(Self::In),
+      Some("isnull") => Some(Self::IsNull),
+      Some("notnull") => Some(Self::IsNotNull),
+      Some("fulltext") => Some(Self::FullText),
       None => Some(Self::Equal),
       _ => None,
     };
@@ -66,10 +92,22 @@ impl Qualifier {
       Self::Not => "<>",
       Self::NotEqual => "<>",
       Self::Like => "LIKE",
+      Self::NotLike => "NOT LIKE",
+      Self::ILike => "LIKE",
+      Self::NotILike => "NOT LIKE",
       Self::Regexp => "REGEXP",
       Self::Equal => "=",
+      Self::Between => "BETWEEN",
+      Self::In => "IN",
+      Self::IsNull => "IS NULL",
+      Self::IsNotNull => "IS NOT NULL",
+      Self::FullText => "MATCH",
     };
   }
+
+  fn takes_value(self) -> bool {
+    return !matches!(self, Self::IsNull | Self::IsNotNull);
+  }
 }
 
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
@@ -89,6 +127,13 @@ pub struct QueryParseResult {
   // Ordering. It's a vector for &order=-col0,+col1,col2
   pub order: Option<Vec<(String, Order)>>,
 
+  // Full-text search query and whether to include the bm25 relevance score.
+  pub search: Option<String>,
+  pub include_score: Option<bool>,
+
+  // Whether to include soft-deleted records.
+  pub include_deleted: Option<bool>,
+
   // Map from filter params to filter value. It's a vector in cases like
   // "col0[gte]=2&col0[lte]=10".
   pub params: Option<HashMap<String, Vec<QueryParam>>>,
@@ -104,7 +149,7 @@ pub fn limit_or_default(limit: Option<usize>) -> usize {
 fn parse_bool(s: &str) -> Option<bool> {
   return match s {
     "TRUE" | "true" | "1" => Some(true),
-    "FALSE" | "false" | "0" => Some(true),
+    "FALSE" | "false" | "0" => Some(false),
     _ => None,
   };
 }
@@ -129,6 +174,9 @@ pub fn parse_query(query: Option<&str>) -> Result<QueryParseResult, String> {
       "cursor" => result.cursor = b64_to_id(value.as_ref()).ok(),
       "offset" => result.offset = value.parse::<usize>().ok(),
       "count" => result.count = parse_bool(&value),
+      "search" => result.search = Some(value.to_string()),
+      "include_score" => result.include_score = parse_bool(&value),
+      "include_deleted" => result.include_deleted = parse_bool(&value),
       "order" => {
         let order: Vec<(String, Order)> = value
           .split(",")
@@ -158,13 +206,14 @@ pub fn parse_query(query: Option<&str>) -> Result<QueryParseResult, String> {
           return Err(key.to_string());
         }
 
-        if value.is_empty() {
+        let qualifier = Qualifier::from(maybe_op);
+        if value.is_empty() && qualifier.is_none_or(|q| q.takes_value()) {
           return Err(key.to_string());
         }
 
         let query_param = QueryParam {
           value: value.to_string(),
-          qualifier: Qualifier::from(maybe_op),
+          qualifier,
         };
 
         let params = result.params.get_or_insert_default();
@@ -186,9 +235,54 @@ pub struct WhereClause {
   pub params: Vec<(Cow<'static, str>, trailbase_sqlite::Value)>,
 }
 
+/// A "<table>_fts" FTS5 virtual table and the columns it indexes.
+#[derive(Debug, Clone)]
+pub struct FullTextIndex {
+  pub table_name: String,
+  pub columns: Vec<String>,
+}
+
+/// Looks up the "<table>_fts" FTS5 index of the given table, if any.
+pub async fn lookup_full_text_index(
+  conn: &trailbase_sqlite::Connection,
+  table_name: &str,
+) -> Result<Option<FullTextIndex>, trailbase_sqlite::Error> {
+  let fts_table_name = format!("{table_name}_fts");
+  let rows = conn
+    .query(
+      "SELECT name FROM pragma_table_info($1)",
+      trailbase_sqlite::params!(fts_table_name.clone()),
+    )
+    .await?;
+  if rows.is_empty() {
+    return Ok(None);
+  }
+
+  return Ok(Some(FullTextIndex {
+    table_name: fts_table_name,
+    columns: rows
+      .iter()
+      .map(|row| row.get::<String>(0))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|err| trailbase_sqlite::Error::Other(err.into()))?,
+  }));
+}
+
+pub fn has_full_text_filter(filter_params: Option<&HashMap<String, Vec<QueryParam>>>) -> bool {
+  return filter_params.is_some_and(|params| {
+    params
+      .values()
+      .flatten()
+      .any(|p| p.qualifier == Some(Qualifier::FullText))
+  });
+}
+
+/// Builds the WHERE clause for the given column filters. "fulltext" filters require the
+/// `full_text_index` to cover the filtered column and the table to be aliased as `_ROW_`.
 pub fn build_filter_where_clause(
   table_metadata: &dyn TableOrViewMetadata,
   filter_params: Option<HashMap<String, Vec<QueryParam>>>,
+  full_text_index: Option<&FullTextIndex>,
 ) -> Result<WhereClause, WhereClauseError> {
   let mut where_clauses = Vec::<String>::with_capacity(16);
   let mut params = Vec::<(Cow<'static, str>, trailbase_sqlite::Value)>::with_capacity(16);
@@ -210,17 +304,92 @@ pub fn build_filter_where_clause(
       };
 
       for query_param in query_params {
-        let Some(op) = query_param.qualifier.map(|q| q.to_sql()) else {
+        let Some(qualifier) = query_param.qualifier else {
           info!("No op for: {column_name}={query_param:?}");
           continue;
         };
+        let op = qualifier.to_sql();
+
+        match qualifier {
+          Qualifier::IsNull | Qualifier::IsNotNull => {
+            where_clauses.push(format!("{column_name} {op}"));
+          }
+          Qualifier::FullText => {
+            let Some(fts) = full_text_index.filter(|fts| fts.columns.contains(&column_name)) else {
+              return Err(WhereClauseError::FullTextNotSupported(column_name));
+            };
 
-        match json_string_to_value(col.data_type, query_param.value) {
-          Ok(value) => {
-            where_clauses.push(format!("{column_name} {op} :{column_name}"));
-            params.push((format!(":{column_name}").into(), value));
+            where_clauses.push(format!(
+              r#"_ROW_.rowid IN (SELECT rowid FROM "{fts_table_name}" WHERE "{column_name}" {op} :{column_name}_fts)"#,
+              fts_table_name = fts.table_name,
+            ));
+            params.push((
+              format!(":{column_name}_fts").into(),
+              trailbase_sqlite::Value::Text(query_param.value),
+            ));
           }
-          Err(err) => debug!("Parameter conversion for {column_name} failed: {err}"),
+          Qualifier::Between => {
+            let Some((low, high)) = query_param.value.split_once(',') else {
+              debug!("Expected 'low,high' range for {column_name}: {query_param:?}");
+              continue;
+            };
+
+            match (
+              json_string_to_value(col.data_type, low.to_string()),
+              json_string_to_value(col.data_type, high.to_string()),
+            ) {
+              (Ok(low), Ok(high)) => {
+                where_clauses.push(format!(
+                  "{column_name} {op} :{column_name}_low AND :{column_name}_high"
+                ));
+                params.push((format!(":{column_name}_low").into(), low));
+                params.push((format!(":{column_name}_high").into(), high));
+              }
+              (Err(err), _) | (_, Err(err)) => {
+                debug!("Parameter conversion for {column_name} failed: {err}")
+              }
+            };
+          }
+          Qualifier::In => {
+            let values = query_param
+              .value
+              .split(',')
+              .map(|v| json_string_to_value(col.data_type, v.to_string()))
+              .collect::<Result<Vec<_>, _>>();
+
+            match values {
+              Ok(values) => {
+                let placeholders: Vec<String> = (0..values.len())
+                  .map(|i| format!(":{column_name}_{i}"))
+                  .collect();
+                where_clauses.push(format!(
+                  "{column_name} {op} ({placeholders})",
+                  placeholders = placeholders.join(", ")
+                ));
+                params.extend(std::iter::zip(
+                  placeholders.into_iter().map(Cow::Owned),
+                  values,
+                ));
+              }
+              Err(err) => debug!("Parameter conversion for {column_name} failed: {err}"),
+            };
+          }
+          Qualifier::ILike | Qualifier::NotILike => {
+            match json_string_to_value(col.data_type, query_param.value) {
+              Ok(value) => {
+                where_clauses.push(format!("lower({column_name}) {op} lower(:{column_name})"));
+                params.push((format!(":{column_name}").into(), value));
+              }
+              Err(err) => debug!("Parameter conversion for {column_name} failed: {err}"),
+            };
+          }
+          _ => match json_string_to_value(col.data_type, query_param.value) {
+            Ok(value) => {
+              where_clauses.push(format!("{column_name} {op} :{column_name}"));
+              params.push((format!(":{column_name}").into(), value));
+            }
+            Err(err) => debug!("Parameter conversion for {column_name} failed: {err}"),
+          },
         };
       }
     }
@@ -256,7 +425,10 @@ lazy_static! {
 
 #[cfg(test)]
 mod tests {
+  use trailbase_sqlite::Value;
+
   use super::*;
+  use crate::app_state::test_state;
   use crate::util::id_to_b64;
 
   #[test]
@@ -304,6 +476,16 @@ mod tests {
       );
     }
 
+    {
+      let query = Some("search=hello%20world&include_score=true&include_deleted=false");
+      let result = parse_query(query).unwrap();
+
+      assert_eq!(result.search.as_deref(), Some("hello world"));
+      assert_eq!(result.include_score, Some(true));
+      assert_eq!(result.include_deleted, Some(false));
+      assert!(result.params.is_none());
+    }
+
     {
       let query = Some("baz=23&bar[like]=foo");
       let result = parse_query(query).unwrap();
@@ -393,5 +575,141 @@ mod tests {
         result.params
       );
     }
+
+    {
+      let query = Some("a[between]=1,10&b[in]=x,y,z&c[isnull]&d[notnull]=");
+      let result = parse_query(query).unwrap();
+      let params = result.params.unwrap();
+
+      assert_eq!(params["a"][0].qualifier, Some(Qualifier::Between));
+      assert_eq!(params["a"][0].value, "1,10");
+      assert_eq!(params["b"][0].qualifier, Some(Qualifier::In));
+      assert_eq!(params["c"][0].qualifier, Some(Qualifier::IsNull));
+      assert_eq!(params["d"][0].qualifier, Some(Qualifier::IsNotNull));
+
+      // Only null checks may omit the value.
+      assert!(parse_query(Some("a[in]=")).is_err());
+    }
+  }
+
+  #[tokio::test]
+  async fn test_filter_where_clause() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute(
+        "CREATE TABLE t (id INTEGER PRIMARY KEY, price INTEGER, name TEXT) STRICT",
+        (),
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+    let metadata = state.table_metadata().get("t").unwrap();
+
+    let build = |query: &str| {
+      let params = parse_query(Some(query)).unwrap().params;
+      return build_filter_where_clause(&*metadata, params, None).unwrap();
+    };
+
+    let clause = build("price[between]=5,10");
+    assert_eq!(clause.clause, "price BETWEEN :price_low AND :price_high");
+    assert_eq!(
+      clause.params,
+      vec![
+        (Cow::from(":price_low"), Value::Integer(5)),
+        (Cow::from(":price_high"), Value::Integer(10)),
+      ]
+    );
+
+    let clause = build("name[in]=a,b,c");
+    assert_eq!(clause.clause, "name IN (:name_0, :name_1, :name_2)");
+    assert_eq!(
+      clause.params,
+      vec![
+        (Cow::from(":name_0"), Value::Text("a".to_string())),
+        (Cow::from(":name_1"), Value::Text("b".to_string())),
+        (Cow::from(":name_2"), Value::Text("c".to_string())),
+      ]
+    );
+
+    let clause = build("name[ilike]=foo%");
+    assert_eq!(clause.clause, "lower(name) LIKE lower(:name)");
+    assert_eq!(
+      clause.params,
+      vec![(Cow::from(":name"), Value::Text("foo%".to_string()))]
+    );
+
+    let clause = build("name[nilike]=foo%");
+    assert_eq!(clause.clause, "lower(name) NOT LIKE lower(:name)");
+
+    let clause = build("name[nlike]=foo%");
+    assert_eq!(clause.clause, "name NOT LIKE :name");
+
+    let clause = build("name[isnull]");
+    assert_eq!(clause.clause, "name IS NULL");
+    assert!(clause.params.is_empty());
+
+    let clause = build("name[notnull]");
+    assert_eq!(clause.clause, "name IS NOT NULL");
+    assert!(clause.params.is_empty());
+
+    let fts = FullTextIndex {
+      table_name: "t_fts".to_string(),
+      columns: vec!["name".to_string()],
+    };
+    let params = parse_query(Some("name[fulltext]=hello world"))
+      .unwrap()
+      .params;
+    let clause = build_filter_where_clause(&*metadata, params, Some(&fts)).unwrap();
+    assert_eq!(
+      clause.clause,
+      r#"_ROW_.rowid IN (SELECT rowid FROM "t_fts" WHERE "name" MATCH :name_fts)"#
+    );
+    assert_eq!(
+      clause.params,
+      vec![(
+        Cow::from(":name_fts"),
+        Value::Text("hello world".to_string())
+      )]
+    );
+
+    // Full-text filters require an index covering the column.
+    let params = parse_query(Some("price[fulltext]=5")).unwrap().params;
+    assert!(matches!(
+      build_filter_where_clause(&*metadata, params, Some(&fts)),
+      Err(WhereClauseError::FullTextNotSupported(_))
+    ));
+    let params = parse_query(Some("name[fulltext]=hello")).unwrap().params;
+    assert!(build_filter_where_clause(&*metadata, params, None).is_err());
+
+    // Case-insensitive matching regardless of the stored value's case.
+    state
+      .conn()
+      .execute("INSERT INTO t (name) VALUES ('FooBar'), ('other')", ())
+      .await
+      .unwrap();
+    for (query, expected) in [
+      ("name[ilike]=foob%", 1),
+      ("name[ilike]=FOOB%", 1),
+      ("name[nilike]=FOOB%", 1),
+    ] {
+      let WhereClause { clause, params } = build(query);
+      let rows = state
+        .conn()
+        .query(&format!("SELECT * FROM t WHERE {clause}"), params)
+        .await
+        .unwrap();
+      assert_eq!(rows.len(), expected, "{query}");
+    }
+
+    // The generated clauses are valid SQL.
+    for query in ["price[between]=5,10", "name[in]=a,b", "name[notnull]"] {
+      let WhereClause { clause, params } = build(query);
+      state
+        .conn()
+        .query(&format!("SELECT * FROM t WHERE {clause}"), params)
+        .await
+        .unwrap();
+    }
   }
 }
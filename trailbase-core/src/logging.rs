@@ -1,5 +1,7 @@
 use axum::body::Body;
-use axum::http::{header::HeaderMap, Request};
+use axum::extract::State;
+use axum::http::{header::HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
 use axum::response::Response;
 use axum_client_ip::InsecureClientIp;
 use log::*;
@@ -12,6 +14,7 @@ use tracing::Level;
 use tracing_subscriber::layer::{Context, Layer};
 
 use crate::constants::{ADMIN_API_PATH, RECORD_API_PATH};
+use crate::request_id::{error_response, RequestId};
 use crate::AppState;
 
 // Memo to my future self.
@@ -55,8 +58,13 @@ pub(crate) struct Log {
   pub client_ip: String,
   pub referer: String,
   pub user_agent: String,
+  pub request_id: String,
 
   pub data: Option<serde_json::Value>,
+
+  // Only captured in dev mode if enabled, see [BodyLogging].
+  pub request_body: Option<String>,
+  pub response_body: Option<String>,
 }
 
 const LEVEL: Level = Level::INFO;
@@ -69,6 +77,10 @@ pub(super) fn sqlite_logger_make_span(request: &Request<Body>) -> Span {
   let referer = get_header(headers, "referer").unwrap_or("");
   let client_ip = InsecureClientIp::from(headers, request.extensions())
     .map_or_else(|_err| String::new(), |ip| ip.0.to_string());
+  let request_id = request
+    .extensions()
+    .get::<RequestId>()
+    .map_or("", |id| id.0.as_str());
 
   let uri = request.uri();
   let request_type = {
@@ -99,9 +111,13 @@ pub(super) fn sqlite_logger_make_span(request: &Request<Body>) -> Span {
       client_ip,
       user_agent,
       referer,
+      request_id,
+      user.id = tracing::field::Empty,
       latency_ms = tracing::field::Empty,
       status = tracing::field::Empty,
       length = tracing::field::Empty,
+      request_body = tracing::field::Empty,
+      response_body = tracing::field::Empty,
   );
 }
 
@@ -111,6 +127,8 @@ pub(super) fn sqlite_logger_on_request(_req: &Request<Body>, _span: &Span) {
 }
 
 pub(super) fn sqlite_logger_on_response(response: &Response<Body>, latency: Duration, span: &Span) {
+  crate::metrics::record_http_response(response, latency);
+
   let length = get_header(response.headers(), "content-length");
   span.record("latency_ms", as_millis_f64(&latency));
   span.record("status", response.status().as_u16());
@@ -120,6 +138,66 @@ pub(super) fn sqlite_logger_on_response(response: &Response<Body>, latency: Dura
   tracing::event!(LEVEL, "response sent");
 }
 
+/// Logged bodies are truncated to this many bytes.
+const MAX_LOGGED_BODY_BYTES: usize = 4096;
+
+/// Which bodies to capture into the logs. Bodies may well contain PII and are thus only ever
+/// captured in dev mode.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct BodyLogging {
+  pub request: bool,
+  pub response: bool,
+}
+
+/// Captures request and/or response bodies into the current request span. Needs to be installed
+/// inside the TraceLayer, i.e. within the span created by [sqlite_logger_make_span].
+pub(super) async fn body_logging_middleware(
+  State(opts): State<BodyLogging>,
+  request: Request<Body>,
+  next: Next,
+) -> Response {
+  let span = Span::current();
+
+  let request = if opts.request {
+    // The entire body needs to be buffered to be passed on. Its size is limited by the outer
+    // RequestBodyLimitLayer.
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+      Ok(bytes) => bytes,
+      Err(err) => {
+        return error_response(StatusCode::BAD_REQUEST, Some(err.to_string()));
+      }
+    };
+    span.record("request_body", truncate_body(&bytes).as_str());
+    Request::from_parts(parts, Body::from(bytes))
+  } else {
+    request
+  };
+
+  let response = next.run(request).await;
+
+  // Don't buffer never-ending streams, e.g. realtime subscriptions.
+  let is_event_stream = get_header(response.headers(), "content-type")
+    .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+  if !opts.response || is_event_stream {
+    return response;
+  }
+
+  let (parts, body) = response.into_parts();
+  return match axum::body::to_bytes(body, usize::MAX).await {
+    Ok(bytes) => {
+      span.record("response_body", truncate_body(&bytes).as_str());
+      Response::from_parts(parts, Body::from(bytes))
+    }
+    Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, Some(err.to_string())),
+  };
+}
+
+#[inline]
+fn truncate_body(bytes: &[u8]) -> String {
+  return String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_LOGGED_BODY_BYTES)]).into_owned();
+}
+
 pub struct SqliteLogLayer {
   sender: tokio::sync::mpsc::UnboundedSender<Box<LogFieldStorage>>,
 }
@@ -185,9 +263,9 @@ impl SqliteLogLayer {
     lazy_static::lazy_static! {
       static ref QUERY: String = indoc::formatdoc! {"
         INSERT INTO
-          _logs (type, level, status, method, url, latency, client_ip, referer, user_agent)
+          _logs (type, level, status, method, url, latency, client_ip, referer, user_agent, request_id, request_body, response_body)
         VALUES
-          ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+          ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
       "};
     }
 
@@ -203,6 +281,9 @@ impl SqliteLogLayer {
       log.client_ip,
       log.referer,
       log.user_agent,
+      log.request_id,
+      log.request_body,
+      log.response_body,
     ))?;
 
     return Ok(());
@@ -285,6 +366,8 @@ struct LogFieldStorage {
   referer: String,
   user_agent: String,
   version: String,
+  request_id: String,
+  request_body: Option<String>,
 
   // Log level.
   level: i64,
@@ -293,6 +376,7 @@ struct LogFieldStorage {
   status: u64,
   latency_ms: f64,
   length: i64,
+  response_body: Option<String>,
 
   // All other fields.
   fields: serde_json::Map<String, serde_json::Value>,
@@ -339,6 +423,9 @@ impl tracing::field::Visit for LogJsonVisitor<'_> {
       "host" => self.0.host = s.to_string(),
       "referer" => self.0.referer = s.to_string(),
       "user_agent" => self.0.user_agent = s.to_string(),
+      "request_id" => self.0.request_id = s.to_string(),
+      "request_body" => self.0.request_body = Some(s.to_string()),
+      "response_body" => self.0.response_body = Some(s.to_string()),
       name => {
         self.0.fields.insert(name.into(), s.into());
       }
@@ -390,3 +477,82 @@ fn level_to_int(level: &tracing::Level) -> i64 {
     tracing::Level::ERROR => 0,
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use axum::routing::post;
+  use axum::{middleware, Router};
+  use tower::ServiceExt;
+  use tower_http::trace::TraceLayer;
+  use tracing_subscriber::layer::SubscriberExt;
+
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_body_logging() {
+    let state = test_state(None).await.unwrap();
+    let _guard = tracing::subscriber::set_default(
+      tracing_subscriber::registry().with(SqliteLogLayer::new(&state)),
+    );
+
+    let router = Router::new()
+      .route("/echo", post(|body: String| async move { body }))
+      .layer(middleware::from_fn_with_state(
+        BodyLogging {
+          request: true,
+          response: true,
+        },
+        body_logging_middleware,
+      ))
+      .layer(
+        TraceLayer::new_for_http()
+          .make_span_with(sqlite_logger_make_span)
+          .on_request(sqlite_logger_on_request)
+          .on_response(sqlite_logger_on_response),
+      );
+
+    let body = serde_json::to_string(&json!({
+      "data": "x".repeat(2 * MAX_LOGGED_BODY_BYTES),
+    }))
+    .unwrap();
+
+    let response = router
+      .oneshot(
+        Request::post("/echo")
+          .header("content-type", "application/json")
+          .body(Body::from(body.clone()))
+          .unwrap(),
+      )
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let echoed = axum::body::to_bytes(response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    assert_eq!(echoed, body.as_bytes());
+
+    // Logs are written asynchronously.
+    let mut logged: Option<(String, String)> = None;
+    for _ in 0..50 {
+      logged = state
+        .logs_conn()
+        .query_row(
+          "SELECT request_body, response_body FROM _logs WHERE request_body IS NOT NULL",
+          (),
+        )
+        .await
+        .unwrap()
+        .map(|row| (row.get(0).unwrap(), row.get(1).unwrap()));
+      if logged.is_some() {
+        break;
+      }
+      tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let (request_body, response_body) = logged.unwrap();
+    assert_eq!(request_body.len(), MAX_LOGGED_BODY_BYTES);
+    assert!(body.starts_with(&request_body));
+    assert_eq!(response_body, request_body);
+  }
+}
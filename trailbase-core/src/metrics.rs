@@ -0,0 +1,113 @@
+use axum::extract::{MatchedPath, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use prometheus::{
+  register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+  TextEncoder,
+};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+lazy_static! {
+  static ref HTTP_REQUESTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+    "trailbase_http_requests_total",
+    "Number of HTTP requests handled.",
+    &["method", "path", "status"]
+  )
+  .unwrap();
+  static ref HTTP_REQUEST_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+    "trailbase_http_request_duration_seconds",
+    "HTTP request latencies in seconds.",
+    &["method", "path"]
+  )
+  .unwrap();
+  static ref AUTH_LOGINS_TOTAL: IntCounterVec = register_int_counter_vec!(
+    "trailbase_auth_logins_total",
+    "Number of login attempts.",
+    &["method", "result"]
+  )
+  .unwrap();
+  static ref SQLITE_QUERY_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+    "trailbase_sqlite_query_duration_seconds",
+    "Record API query latencies in seconds.",
+    &["type"]
+  )
+  .unwrap();
+}
+
+/// Route-level labels of a request, attached to its response for the tracing layer to pick up.
+///
+/// Using the matched route rather than the actual path keeps the cardinality bounded, e.g.
+/// "/api/records/v1/{name}/{record}" rather than one time series per record.
+#[derive(Clone, Debug)]
+pub(crate) struct RouteLabels {
+  method: String,
+  path: String,
+}
+
+/// Route layer attaching [RouteLabels] to responses. Only installed if metrics are enabled.
+pub(crate) async fn route_labels_middleware(req: Request, next: Next) -> Response {
+  let labels = RouteLabels {
+    method: req.method().to_string(),
+    path: req
+      .extensions()
+      .get::<MatchedPath>()
+      .map_or("", |p| p.as_str())
+      .to_string(),
+  };
+
+  let mut response = next.run(req).await;
+  response.extensions_mut().insert(labels);
+  return response;
+}
+
+/// Records HTTP metrics for responses carrying [RouteLabels]. Called from the tracing layer's
+/// `on_response` callback.
+pub(crate) fn record_http_response(response: &Response, latency: Duration) {
+  let Some(labels) = response.extensions().get::<RouteLabels>() else {
+    return;
+  };
+
+  HTTP_REQUESTS_TOTAL
+    .with_label_values(&[&labels.method, &labels.path, response.status().as_str()])
+    .inc();
+  HTTP_REQUEST_DURATION_SECONDS
+    .with_label_values(&[&labels.method, &labels.path])
+    .observe(latency.as_secs_f64());
+}
+
+pub(crate) fn record_login(method: &str, success: bool) {
+  AUTH_LOGINS_TOTAL
+    .with_label_values(&[method, if success { "success" } else { "failure" }])
+    .inc();
+}
+
+/// Awaits the given query future within a "db.query" span and records its latency.
+pub(crate) async fn time_query<F: Future>(r#type: &str, query: F) -> F::Output {
+  let span = tracing::info_span!("db.query", db.system = "sqlite", db.operation = r#type);
+
+  let start = Instant::now();
+  let result = query.instrument(span).await;
+  SQLITE_QUERY_DURATION_SECONDS
+    .with_label_values(&[r#type])
+    .observe(start.elapsed().as_secs_f64());
+  return result;
+}
+
+/// Exports all metrics in the Prometheus text format.
+pub(crate) async fn metrics_handler() -> Response {
+  let mut buffer = vec![];
+  let encoder = TextEncoder::new();
+  if let Err(err) = encoder.encode(&prometheus::gather(), &mut buffer) {
+    return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
+  }
+
+  return (
+    [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+    buffer,
+  )
+    .into_response();
+}
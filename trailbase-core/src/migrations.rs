@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 use log::*;
 use parking_lot::Mutex;
 use std::path::PathBuf;
-use trailbase_refinery_core::Migration;
+use trailbase_refinery_core::{Migration, Target};
 
 mod main {
   trailbase_refinery_macros::embed_migrations!("migrations/main");
@@ -45,27 +45,31 @@ pub(crate) fn new_migration_runner(migrations: &[Migration]) -> trailbase_refine
   return runner;
 }
 
-pub(crate) fn apply_main_migrations(
-  conn: &mut rusqlite::Connection,
+fn load_main_migrations(
   user_migrations_path: Option<PathBuf>,
-) -> Result<bool, trailbase_refinery_core::Error> {
-  let all_migrations = {
-    let mut migrations: Vec<Migration> = vec![];
+) -> Result<Vec<Migration>, trailbase_refinery_core::Error> {
+  let mut migrations: Vec<Migration> = vec![];
 
-    let system_migrations_runner = main::migrations::runner();
-    migrations.extend(system_migrations_runner.get_migrations().iter().cloned());
+  let system_migrations_runner = main::migrations::runner();
+  migrations.extend(system_migrations_runner.get_migrations().iter().cloned());
 
-    if let Some(path) = user_migrations_path {
-      // NOTE: refinery has a bug where it will name-check the directory and write a warning... :/.
-      let user_migrations = trailbase_refinery_core::load_sql_migrations(path)?;
-      migrations.extend(user_migrations);
-    }
+  if let Some(path) = user_migrations_path {
+    // NOTE: refinery has a bug where it will name-check the directory and write a warning... :/.
+    let user_migrations = trailbase_refinery_core::load_sql_migrations(path)?;
+    migrations.extend(user_migrations);
+  }
 
-    // Interleave the system and user migrations based on their version prefixes.
-    migrations.sort();
+  // Interleave the system and user migrations based on their version prefixes.
+  migrations.sort();
 
-    migrations
-  };
+  return Ok(migrations);
+}
+
+pub(crate) fn apply_main_migrations(
+  conn: &mut rusqlite::Connection,
+  user_migrations_path: Option<PathBuf>,
+) -> Result<bool, trailbase_refinery_core::Error> {
+  let all_migrations = load_main_migrations(user_migrations_path)?;
 
   let runner = new_migration_runner(&all_migrations);
   let report = match runner.run(conn) {
@@ -90,6 +94,95 @@ pub(crate) fn apply_main_migrations(
   return Ok(new_db);
 }
 
+/// State of a system or user migration of the main database.
+#[derive(Clone, Debug)]
+pub struct MigrationStatus {
+  pub version: i64,
+  pub name: String,
+  /// Time of application or `None` if the migration is still pending.
+  pub applied_on: Option<String>,
+  pub checksum: u64,
+  /// SQL of pending migrations.
+  pub sql: Option<String>,
+}
+
+impl MigrationStatus {
+  fn new(migration: &Migration, applied: Option<&Migration>) -> Self {
+    return MigrationStatus {
+      version: i64::from(migration.version()),
+      name: migration.name().to_string(),
+      applied_on: applied.and_then(|m| m.applied_on().map(|t| t.to_string())),
+      checksum: applied.map_or_else(|| migration.checksum(), |m| m.checksum()),
+      sql: match applied {
+        Some(_) => None,
+        None => migration.sql().map(|sql| sql.to_string()),
+      },
+    };
+  }
+
+  pub fn is_pending(&self) -> bool {
+    return self.applied_on.is_none();
+  }
+}
+
+/// Lists all known migrations, i.e. applied ones and pending ones from `user_migrations_path`,
+/// ordered by version.
+pub fn migration_status(
+  conn: &mut rusqlite::Connection,
+  user_migrations_path: Option<PathBuf>,
+) -> Result<Vec<MigrationStatus>, trailbase_refinery_core::Error> {
+  let migrations = load_main_migrations(user_migrations_path)?;
+  let applied = new_migration_runner(&migrations).get_applied_migrations(conn)?;
+
+  let mut status: Vec<MigrationStatus> = migrations
+    .iter()
+    .map(|m| MigrationStatus::new(m, applied.iter().find(|a| a.version() == m.version())))
+    .collect();
+
+  // Applied migrations, whose files have since been removed.
+  for m in &applied {
+    if !migrations.iter().any(|a| a.version() == m.version()) {
+      status.push(MigrationStatus::new(m, Some(m)));
+    }
+  }
+
+  status.sort_by_key(|s| s.version);
+  return Ok(status);
+}
+
+/// Applies pending migrations, only the first `steps` ones if given, and returns the applied
+/// migrations.
+pub fn apply_pending_migrations(
+  conn: &mut rusqlite::Connection,
+  user_migrations_path: Option<PathBuf>,
+  steps: Option<usize>,
+) -> Result<Vec<MigrationStatus>, trailbase_refinery_core::Error> {
+  let migrations = load_main_migrations(user_migrations_path)?;
+
+  let mut runner = new_migration_runner(&migrations);
+  if let Some(steps) = steps {
+    let applied = runner.get_applied_migrations(conn)?;
+    let Some(last) = migrations
+      .iter()
+      .filter(|m| !applied.iter().any(|a| a.version() == m.version()))
+      .take(steps)
+      .last()
+    else {
+      return Ok(vec![]);
+    };
+    runner = runner.set_target(Target::Version(last.version() as u32));
+  }
+
+  let report = runner.run(conn)?;
+  return Ok(
+    report
+      .applied_migrations()
+      .iter()
+      .map(|m| MigrationStatus::new(m, Some(m)))
+      .collect(),
+  );
+}
+
 #[cfg(test)]
 pub(crate) fn apply_user_migrations(
   user_conn: &mut rusqlite::Connection,
@@ -130,3 +223,47 @@ pub(crate) fn apply_logs_migrations(
 
   return Ok(());
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_migration_status() {
+    let dir = temp_dir::TempDir::new().unwrap();
+    let write_migration = |filename: &str, table: &str| {
+      std::fs::write(
+        dir.child(filename),
+        format!("CREATE TABLE {table} (id INTEGER PRIMARY KEY) STRICT;"),
+      )
+      .unwrap();
+    };
+
+    write_migration("U1700000001__create_foo.sql", "foo");
+    write_migration("U1700000002__create_bar.sql", "bar");
+
+    let mut conn = trailbase_sqlite::connect_sqlite(None, None).unwrap();
+    apply_main_migrations(&mut conn, Some(dir.path().to_path_buf())).unwrap();
+
+    write_migration("U1700000003__create_baz.sql", "baz");
+
+    let status = migration_status(&mut conn, Some(dir.path().to_path_buf())).unwrap();
+    let foo = status.iter().find(|s| s.name == "create_foo").unwrap();
+    assert!(!foo.is_pending());
+    assert!(foo.sql.is_none());
+
+    let pending: Vec<_> = status.iter().filter(|s| s.is_pending()).collect();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].version, 1700000003);
+    assert_eq!(pending[0].name, "create_baz");
+    assert!(pending[0].sql.as_ref().unwrap().contains("baz"));
+
+    let applied =
+      apply_pending_migrations(&mut conn, Some(dir.path().to_path_buf()), Some(1)).unwrap();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(applied[0].name, "create_baz");
+
+    let status = migration_status(&mut conn, Some(dir.path().to_path_buf())).unwrap();
+    assert!(status.iter().all(|s| !s.is_pending()));
+  }
+}
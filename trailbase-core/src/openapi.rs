@@ -0,0 +1,221 @@
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use log::*;
+use utoipa::openapi::path::{OperationBuilder, ParameterBuilder, ParameterIn};
+use utoipa::openapi::request_body::RequestBodyBuilder;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{
+  ArrayBuilder, ContentBuilder, HttpMethod, Object, ObjectBuilder, Ref, RefOr, Required,
+  ResponseBuilder, Schema, SecurityRequirement, Type,
+};
+use utoipa::{Modify, OpenApi};
+
+use crate::app_state::AppState;
+use crate::constants::RECORD_API_PATH;
+use crate::records::RecordApi;
+use crate::table_metadata::{build_json_schema, JsonSchemaMode};
+
+const BEARER_AUTH: &str = "BearerAuth";
+
+#[derive(OpenApi)]
+#[openapi(
+      modifiers(&SecurityAddon),
+      nest(
+          (path = "/api/auth/v1", api = crate::auth::AuthAPI),
+          (path = "/api/records/v1", api = crate::records::RecordOpenApi),
+      ),
+      tags()
+  )]
+pub struct Doc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+  fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+    openapi
+      .components
+      .get_or_insert_with(Default::default)
+      .add_security_scheme(
+        BEARER_AUTH,
+        SecurityScheme::Http(
+          HttpBuilder::new()
+            .scheme(HttpAuthScheme::Bearer)
+            .bearer_format("JWT")
+            .build(),
+        ),
+      );
+  }
+}
+
+/// Builds the full spec: the static auth and record API endpoints plus typed endpoints for every
+/// configured record API using its table's JSON schemas as request and response bodies.
+pub fn build(state: &AppState) -> utoipa::openapi::OpenApi {
+  let mut doc = Doc::openapi();
+
+  for (_name, api) in state.record_apis().iter() {
+    if let Err(err) = add_record_api(&mut doc, api) {
+      warn!(
+        "Skipping record API '{}' in OpenAPI spec: {err}",
+        api.api_name()
+      );
+    }
+  }
+
+  return doc;
+}
+
+fn add_record_api(
+  doc: &mut utoipa::openapi::OpenApi,
+  api: &RecordApi,
+) -> Result<(), crate::table_metadata::JsonSchemaError> {
+  let api_name = api.api_name();
+  let insert_schema = format!("{api_name}Insert");
+  let select_schema = format!("{api_name}Select");
+  let update_schema = format!("{api_name}Update");
+
+  let components = doc.components.get_or_insert_with(Default::default);
+  for (schema_name, mode) in [
+    (&insert_schema, JsonSchemaMode::Insert),
+    (&select_schema, JsonSchemaMode::Select),
+    (&update_schema, JsonSchemaMode::Update),
+  ] {
+    let (_validator, json) = build_json_schema(api.table_name(), api.metadata(), mode)?;
+    components
+      .schemas
+      .insert(schema_name.clone(), json_to_openapi_schema(json));
+  }
+
+  let body = |schema_name: &str| {
+    return ContentBuilder::new()
+      .schema(Some(Ref::from_schema_name(schema_name)))
+      .build();
+  };
+  let record_param = ParameterBuilder::new()
+    .name("record")
+    .parameter_in(ParameterIn::Path)
+    .required(Required::True)
+    .schema(Some(Object::with_type(Type::String)))
+    .build();
+  let operation = |summary: String| {
+    return OperationBuilder::new()
+      .tag(api_name)
+      .summary(Some(summary))
+      .security(SecurityRequirement::new::<_, _, String>(BEARER_AUTH, []));
+  };
+
+  let base_path = format!("/{RECORD_API_PATH}/{api_name}");
+  let record_path = format!("{base_path}/{{record}}");
+
+  doc.paths.add_path_operation(
+    &base_path,
+    vec![HttpMethod::Post],
+    operation(format!("Create '{api_name}' record"))
+      .request_body(Some(
+        RequestBodyBuilder::new()
+          .content("application/json", body(&insert_schema))
+          .required(Some(Required::True))
+          .build(),
+      ))
+      .response(
+        "200",
+        ResponseBuilder::new().description("Id of the created record"),
+      ),
+  );
+  doc.paths.add_path_operation(
+    &base_path,
+    vec![HttpMethod::Get],
+    operation(format!("List '{api_name}' records")).response(
+      "200",
+      ResponseBuilder::new()
+        .description("Records matching the query")
+        .content(
+          "application/json",
+          ContentBuilder::new()
+            .schema(Some(
+              ObjectBuilder::new()
+                .property("cursor", Object::with_type(Type::String))
+                .property(
+                  "records",
+                  ArrayBuilder::new().items(Ref::from_schema_name(&select_schema)),
+                )
+                .property("total_count", Object::with_type(Type::Integer))
+                .required("records"),
+            ))
+            .build(),
+        ),
+    ),
+  );
+  doc.paths.add_path_operation(
+    &record_path,
+    vec![HttpMethod::Get],
+    operation(format!("Read '{api_name}' record"))
+      .parameter(record_param.clone())
+      .response(
+        "200",
+        ResponseBuilder::new()
+          .description("Record contents")
+          .content("application/json", body(&select_schema)),
+      ),
+  );
+  doc.paths.add_path_operation(
+    &record_path,
+    vec![HttpMethod::Patch],
+    operation(format!("Update '{api_name}' record"))
+      .parameter(record_param.clone())
+      .request_body(Some(
+        RequestBodyBuilder::new()
+          .content("application/json", body(&update_schema))
+          .required(Some(Required::True))
+          .build(),
+      ))
+      .response("200", ResponseBuilder::new().description("Record updated")),
+  );
+  doc.paths.add_path_operation(
+    &record_path,
+    vec![HttpMethod::Delete],
+    operation(format!("Delete '{api_name}' record"))
+      .parameter(record_param)
+      .response("200", ResponseBuilder::new().description("Record deleted")),
+  );
+
+  return Ok(());
+}
+
+/// Converts a table's JSON schema into an OpenAPI schema. Column schemas referenced via local
+/// "$defs" are inlined, since OpenAPI only resolves references to components.
+fn json_to_openapi_schema(mut json: serde_json::Value) -> RefOr<Schema> {
+  if let Some(serde_json::Value::Object(defs)) =
+    json.as_object_mut().and_then(|o| o.remove("$defs"))
+  {
+    if let Some(serde_json::Value::Object(properties)) = json.get_mut("properties") {
+      for (name, property) in properties.iter_mut() {
+        if let Some(def) = defs.get(name) {
+          *property = def.clone();
+        }
+      }
+    }
+  }
+
+  return match serde_json::from_value::<Schema>(json) {
+    Ok(schema) => RefOr::T(schema),
+    Err(err) => {
+      debug!("Falling back to generic object schema: {err}");
+      RefOr::T(Schema::Object(Object::with_type(Type::Object)))
+    }
+  };
+}
+
+pub(crate) async fn openapi_json_handler(State(state): State<AppState>) -> Response {
+  return match build(&state).to_pretty_json() {
+    Ok(json) => ([(header::CONTENT_TYPE, "application/json")], json).into_response(),
+    Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+  };
+}
+
+pub(crate) async fn openapi_yaml_handler(State(state): State<AppState>) -> Response {
+  return match build(&state).to_yaml() {
+    Ok(yaml) => ([(header::CONTENT_TYPE, "application/yaml")], yaml).into_response(),
+    Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+  };
+}
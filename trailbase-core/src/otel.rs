@@ -0,0 +1,74 @@
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Config, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const SERVICE_NAME: &str = "trailbase";
+
+/// Sets up a batching OTLP/HTTP span exporter, e.g. for Jaeger or Tempo, and installs it as the
+/// global tracer provider.
+pub(crate) fn init_tracer_provider(endpoint: &str) -> Result<TracerProvider, TraceError> {
+  let provider = opentelemetry_otlp::new_pipeline()
+    .tracing()
+    .with_exporter(
+      opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint),
+    )
+    .with_trace_config(
+      Config::default().with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+    )
+    .install_batch(runtime::Tokio)?;
+
+  opentelemetry::global::set_tracer_provider(provider.clone());
+  return Ok(provider);
+}
+
+/// Tracing layer forwarding spans to the given provider.
+pub(crate) fn layer<S>(provider: &TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+where
+  S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+  return tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME));
+}
+
+#[cfg(test)]
+mod tests {
+  use opentelemetry::Value;
+  use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+  use tracing::Instrument;
+  use tracing_subscriber::prelude::*;
+
+  use super::*;
+  use crate::metrics::time_query;
+
+  #[tokio::test]
+  async fn test_query_spans_are_exported() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = TracerProvider::builder()
+      .with_simple_exporter(exporter.clone())
+      .build();
+    let _guard =
+      tracing::subscriber::set_default(tracing_subscriber::registry().with(layer(&provider)));
+
+    time_query("select", async {})
+      .instrument(tracing::info_span!("request"))
+      .await;
+
+    let _ = provider.force_flush();
+    let spans = exporter.get_finished_spans().unwrap();
+    assert_eq!(spans.len(), 2);
+
+    let request = spans.iter().find(|s| s.name == "request").unwrap();
+    let query = spans.iter().find(|s| s.name == "db.query").unwrap();
+    assert_eq!(query.parent_span_id, request.span_context.span_id());
+    assert!(query
+      .attributes
+      .iter()
+      .any(|kv| kv.key.as_str() == "db.system" && kv.value == Value::from("sqlite")));
+  }
+}
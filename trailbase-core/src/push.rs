@@ -0,0 +1,456 @@
+use axum::http::header::AUTHORIZATION;
+use base64::prelude::*;
+use log::*;
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+use trailbase_sqlite::{params, Row, Value};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::config::proto::PushNotificationConfig;
+use crate::constants::PUSH_TOKENS_TABLE;
+use crate::records::json_to_sql::SelectQueryBuilder;
+use crate::records::RecordApi;
+
+pub(crate) const PUSH_PROVIDERS: [&str; 2] = ["fcm", "apns"];
+
+const FCM_URL: &str = "https://fcm.googleapis.com/fcm/send";
+const APNS_URL: &str = "https://api.push.apple.com";
+const APNS_SANDBOX_URL: &str = "https://api.sandbox.push.apple.com";
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum PushError {
+  #[error("TokioRusqlite error: {0}")]
+  TokioRusqlite(#[from] trailbase_sqlite::Error),
+  #[error("Rusqlite FromSql error: {0}")]
+  FromSql(#[from] rusqlite::types::FromSqlError),
+  #[error("HTTP error: {0}")]
+  Http(#[from] reqwest::Error),
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Missing: {0}")]
+  Missing(&'static str),
+  /// The device token is no longer valid, e.g. the app was uninstalled.
+  #[error("Invalid token: {0}")]
+  InvalidToken(String),
+  #[error("Rejected: {0}")]
+  Rejected(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PushNotification {
+  pub title: String,
+  pub body: String,
+}
+
+/// Sends the notification to all devices registered by the given user. Tokens reported as invalid
+/// by the provider are removed.
+///
+/// Returns the number of successful deliveries.
+pub(crate) async fn send_push_notification(
+  state: &AppState,
+  user_id: Uuid,
+  notification: &PushNotification,
+) -> Result<usize, PushError> {
+  let Some(config) = state.access_config(|c| c.server.push_notifications.clone()) else {
+    return Err(PushError::Missing("push notification config"));
+  };
+
+  let conn = state.user_conn();
+  let rows = conn
+    .query(
+      &format!("SELECT id, provider, token FROM '{PUSH_TOKENS_TABLE}' WHERE user_id = $1"),
+      params!(user_id.into_bytes()),
+    )
+    .await?;
+
+  let client = reqwest::Client::new();
+  let mut delivered: usize = 0;
+  for row in rows.iter() {
+    let id: i64 = row.get(0)?;
+    let provider: String = row.get(1)?;
+    let token: String = row.get(2)?;
+
+    let result = match provider.as_str() {
+      "fcm" => send_fcm(&client, &config, &token, notification).await,
+      "apns" => send_apns(&config, &token, notification).await,
+      _ => Err(PushError::Rejected(format!("Unknown provider: {provider}"))),
+    };
+
+    match result {
+      Ok(()) => delivered += 1,
+      Err(PushError::InvalidToken(reason)) => {
+        debug!("Removing invalid {provider} push token: {reason}");
+        conn
+          .execute(
+            &format!("DELETE FROM '{PUSH_TOKENS_TABLE}' WHERE id = $1"),
+            params!(id),
+          )
+          .await?;
+      }
+      Err(err) => warn!("Failed to send {provider} push notification: {err}"),
+    };
+  }
+
+  return Ok(delivered);
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FcmResponse {
+  #[serde(default)]
+  results: Vec<FcmResult>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FcmResult {
+  error: Option<String>,
+}
+
+/// Sends a notification using FCM's legacy HTTP API.
+async fn send_fcm(
+  client: &reqwest::Client,
+  config: &PushNotificationConfig,
+  token: &str,
+  notification: &PushNotification,
+) -> Result<(), PushError> {
+  let Some(ref server_key) = config.fcm_server_key else {
+    return Err(PushError::Missing("FCM server key"));
+  };
+
+  let response = client
+    .post(config.fcm_url.as_deref().unwrap_or(FCM_URL))
+    .timeout(PUSH_TIMEOUT)
+    .header(AUTHORIZATION, format!("key={server_key}"))
+    .json(&serde_json::json!({
+      "to": token,
+      "notification": {
+        "title": notification.title,
+        "body": notification.body,
+      },
+    }))
+    .send()
+    .await?;
+
+  let status = response.status();
+  if !status.is_success() {
+    return Err(PushError::Rejected(format!("Unexpected status: {status}")));
+  }
+
+  // Delivery errors are reported per message with status 200.
+  let response: FcmResponse = response.json().await.unwrap_or_default();
+  return match response.results.into_iter().find_map(|r| r.error) {
+    Some(err) if err == "NotRegistered" || err == "InvalidRegistration" => {
+      Err(PushError::InvalidToken(err))
+    }
+    Some(err) => Err(PushError::Rejected(err)),
+    None => Ok(()),
+  };
+}
+
+/// Sends a notification using APNs' HTTP/2 API with certificate-based authentication.
+async fn send_apns(
+  config: &PushNotificationConfig,
+  token: &str,
+  notification: &PushNotification,
+) -> Result<(), PushError> {
+  let Some(ref cert_path) = config.apns_cert_path else {
+    return Err(PushError::Missing("APNs certificate"));
+  };
+  let Some(ref topic) = config.apns_topic else {
+    return Err(PushError::Missing("APNs topic"));
+  };
+
+  let pem = tokio::fs::read(cert_path).await?;
+  let client = reqwest::Client::builder()
+    .identity(reqwest::Identity::from_pem(&pem)?)
+    .build()?;
+
+  let base_url = if config.apns_sandbox.unwrap_or(false) {
+    APNS_SANDBOX_URL
+  } else {
+    APNS_URL
+  };
+  let response = client
+    .post(format!("{base_url}/3/device/{token}"))
+    .timeout(PUSH_TIMEOUT)
+    .header("apns-topic", topic)
+    .header("apns-push-type", "alert")
+    .json(&serde_json::json!({
+      "aps": {
+        "alert": {
+          "title": notification.title,
+          "body": notification.body,
+        },
+      },
+    }))
+    .send()
+    .await?;
+
+  let status = response.status();
+  if status.is_success() {
+    return Ok(());
+  }
+
+  let reason = response
+    .json::<ApnsErrorResponse>()
+    .await
+    .map_or_else(|_| format!("Unexpected status: {status}"), |r| r.reason);
+  return match reason.as_str() {
+    "BadDeviceToken" | "Unregistered" | "DeviceTokenNotForTopic" => {
+      Err(PushError::InvalidToken(reason))
+    }
+    _ => Err(PushError::Rejected(reason)),
+  };
+}
+
+#[derive(Debug, Deserialize)]
+struct ApnsErrorResponse {
+  reason: String,
+}
+
+/// Sends the API's configured push notification, if any, to the user referenced by the created
+/// or updated record. Notifications are sent in the background.
+pub(crate) async fn notify_record_change(
+  state: &AppState,
+  api: &RecordApi,
+  record_id: Value,
+) -> Result<(), trailbase_sqlite::Error> {
+  let Some(config) = api.notify_on_change() else {
+    return Ok(());
+  };
+
+  let Some(row) = SelectQueryBuilder::run(
+    state,
+    api.table_name(),
+    &api.record_pk_column().name,
+    record_id,
+    vec![],
+  )
+  .await?
+  else {
+    return Ok(());
+  };
+
+  let to_user_field = config.to_user_field.as_deref().unwrap_or_default();
+  let user_id = column_index(&row, to_user_field).and_then(|index| match row.get_value(index) {
+    Some(Value::Blob(blob)) => Uuid::from_slice(blob).ok(),
+    _ => None,
+  });
+  let Some(user_id) = user_id else {
+    return Ok(());
+  };
+
+  let notification = PushNotification {
+    title: config.title.clone().unwrap_or_default(),
+    body: render_body(config.body_template.as_deref().unwrap_or_default(), &row),
+  };
+
+  let state = state.clone();
+  tokio::spawn(async move {
+    if let Err(err) = send_push_notification(&state, user_id, &notification).await {
+      warn!("Failed to send push notification: {err}");
+    }
+  });
+
+  return Ok(());
+}
+
+fn column_index(row: &Row, name: &str) -> Option<usize> {
+  return row.column_names().iter().position(|c| *c == name);
+}
+
+/// Replaces "{column}" placeholders with the record's values. Other braces are kept verbatim.
+fn render_body(template: &str, row: &Row) -> String {
+  let mut body = String::with_capacity(template.len());
+  let mut rest = template;
+
+  while let Some(start) = rest.find('{') {
+    body.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let value = rest.find('}').and_then(|end| {
+      let index = column_index(row, &rest[1..end])?;
+      return Some((end, row.get_value(index)?));
+    });
+
+    match value {
+      Some((end, value)) => {
+        match value {
+          Value::Null => {}
+          Value::Integer(i) => body.push_str(&i.to_string()),
+          Value::Real(r) => body.push_str(&r.to_string()),
+          Value::Text(text) => body.push_str(text),
+          Value::Blob(blob) => body.push_str(&BASE64_URL_SAFE.encode(blob)),
+        };
+        rest = &rest[end + 1..];
+      }
+      None => {
+        body.push('{');
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  body.push_str(rest);
+  return body;
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::{Json, Path, Query, State};
+  use axum::http::HeaderMap;
+  use axum::routing::post;
+  use axum::Router;
+  use tokio::sync::mpsc;
+
+  use super::*;
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::auth::api::push::{register_push_token_handler, RegisterPushTokenRequest};
+  use crate::auth::User;
+  use crate::config::proto::{PermissionFlag, RecordApiNotifyOnChange};
+  use crate::extract::Either;
+  use crate::records::create_record::{create_record_handler, CreateRecordQuery};
+  use crate::records::test_utils::json_row_from_value;
+  use crate::records::{add_record_api, AccessRules, Acls};
+  use crate::util::id_to_b64;
+
+  /// Starts a mock FCM endpoint on a random local port forwarding received requests.
+  async fn start_fcm_mock() -> (
+    String,
+    mpsc::UnboundedReceiver<(HeaderMap, serde_json::Value)>,
+  ) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router = Router::new().route(
+      "/fcm/send",
+      post(
+        move |headers: HeaderMap, Json(body): Json<serde_json::Value>| {
+          let sender = sender.clone();
+          async move {
+            sender.send((headers, body)).unwrap();
+            Json(serde_json::json!({"success": 1, "failure": 0, "results": [{}]}))
+          }
+        },
+      ),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+      axum::serve(listener, router).await.unwrap();
+    });
+
+    return (format!("http://{address}/fcm/send"), receiver);
+  }
+
+  #[tokio::test]
+  async fn test_notify_on_change() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE task (
+            id              INTEGER PRIMARY KEY,
+            owner_id        BLOB NOT NULL REFERENCES _user(id),
+            text_not_null   TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    let (fcm_url, mut receiver) = start_fcm_mock().await;
+
+    add_record_api(
+      &state,
+      "task_api",
+      "task",
+      Acls {
+        authenticated: vec![PermissionFlag::Create],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut config = state.get_config();
+    config.server.push_notifications = Some(PushNotificationConfig {
+      fcm_server_key: Some("server_key".to_string()),
+      fcm_url: Some(fcm_url),
+      ..Default::default()
+    });
+    config.record_apis.last_mut().unwrap().notify_on_change = Some(RecordApiNotifyOnChange {
+      to_user_field: Some("owner_id".to_string()),
+      title: Some("Record updated".to_string()),
+      body_template: Some("{text_not_null} was updated {unknown}".to_string()),
+    });
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let email = "owner@test.com";
+    let password = "Secret!1!!";
+    let user_id = create_user_for_test(&state, email, password).await.unwrap();
+    let tokens = login_with_password(&state, email, password).await.unwrap();
+    let user = User::from_auth_token(&state, &tokens.auth_token).unwrap();
+
+    assert!(register_push_token_handler(
+      State(state.clone()),
+      user.clone(),
+      Json(RegisterPushTokenRequest {
+        provider: "carrier_pigeon".to_string(),
+        token: "device_token".to_string(),
+        device_id: None,
+      }),
+    )
+    .await
+    .is_err());
+
+    register_push_token_handler(
+      State(state.clone()),
+      user.clone(),
+      Json(RegisterPushTokenRequest {
+        provider: "fcm".to_string(),
+        token: "device_token".to_string(),
+        device_id: Some("phone".to_string()),
+      }),
+    )
+    .await
+    .unwrap();
+
+    create_record_handler(
+      State(state.clone()),
+      Path("task_api".to_string()),
+      Query(CreateRecordQuery::default()),
+      Some(user),
+      Either::Json(
+        json_row_from_value(serde_json::json!({
+          "owner_id": id_to_b64(&user_id.into_bytes()),
+          "text_not_null": "Laundry",
+        }))
+        .unwrap(),
+      ),
+    )
+    .await
+    .unwrap();
+
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(headers.get(AUTHORIZATION).unwrap(), "key=server_key");
+    assert_eq!(body["to"], "device_token");
+    assert_eq!(body["notification"]["title"], "Record updated");
+    assert_eq!(
+      body["notification"]["body"],
+      "Laundry was updated {unknown}"
+    );
+  }
+}
@@ -0,0 +1,284 @@
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::RequestExt;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+use crate::auth::User;
+use crate::extract::client_ip;
+use crate::request_id::error_response;
+
+/// Number of tracked buckets above which idle ones get evicted.
+const MAX_BUCKETS: usize = 100_000;
+/// Buckets idle for this long are refilled entirely and can thus safely be evicted.
+const IDLE_EVICTION: Duration = Duration::from_secs(300);
+
+/// Determines which requests share a token bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitKeyBy {
+  /// One bucket per client IP address.
+  #[default]
+  Ip,
+  /// One bucket per authenticated user. Anonymous requests are keyed by their IP address.
+  UserId,
+  /// A single bucket shared by all requests.
+  Global,
+}
+
+/// Token-bucket rate limit. Applies to all routes when set in
+/// [crate::ServerOptions::rate_limit] and can be overridden for individual routes using
+/// [crate::ServerOptions::rate_limit_routes].
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+  /// Rate at which tokens are replenished.
+  pub requests_per_second: f64,
+  /// Capacity of the bucket, i.e. the number of requests that can be made in a short burst.
+  pub burst: u32,
+  pub key_by: RateLimitKeyBy,
+}
+
+pub(crate) struct RateLimiter {
+  config: Option<RateLimitConfig>,
+  /// Overrides for routes starting with the given path prefixes.
+  routes: Vec<(String, RateLimitConfig)>,
+  /// Maps keys to the time of the last request and the tokens left thereafter.
+  buckets: DashMap<String, (Instant, f64)>,
+}
+
+impl RateLimiter {
+  pub(crate) fn new(
+    config: Option<RateLimitConfig>,
+    routes: Vec<(String, RateLimitConfig)>,
+  ) -> Self {
+    return Self {
+      config,
+      routes,
+      buckets: DashMap::new(),
+    };
+  }
+
+  /// Returns the config applying to the given path alongside the matching route prefix, if the
+  /// default is overridden. The longest matching prefix wins.
+  fn config_for_path(&self, path: &str) -> Option<(&str, &RateLimitConfig)> {
+    let matches = |prefix: &str| {
+      let Some(rest) = path.strip_prefix(prefix) else {
+        return false;
+      };
+      return rest.is_empty() || prefix.ends_with('/') || rest.starts_with('/');
+    };
+
+    return match self
+      .routes
+      .iter()
+      .filter(|(prefix, _)| matches(prefix))
+      .max_by_key(|(prefix, _)| prefix.len())
+    {
+      Some((prefix, config)) => Some((prefix.as_str(), config)),
+      None => self.config.as_ref().map(|config| ("", config)),
+    };
+  }
+
+  /// Takes a token from the key's bucket or returns the time until one becomes available.
+  fn acquire(&self, key: String, config: &RateLimitConfig) -> Result<(), Duration> {
+    let now = Instant::now();
+    if self.buckets.len() > MAX_BUCKETS {
+      self
+        .buckets
+        .retain(|_, (last, _)| now.duration_since(*last) < IDLE_EVICTION);
+    }
+
+    let burst = config.burst as f64;
+    let mut bucket = self.buckets.entry(key).or_insert((now, burst));
+    let (last, tokens) = *bucket;
+    let tokens =
+      (tokens + now.duration_since(last).as_secs_f64() * config.requests_per_second).min(burst);
+
+    if tokens >= 1.0 {
+      *bucket = (now, tokens - 1.0);
+      return Ok(());
+    }
+
+    *bucket = (now, tokens);
+    return Err(
+      Duration::try_from_secs_f64((1.0 - tokens) / config.requests_per_second)
+        .unwrap_or(IDLE_EVICTION),
+    );
+  }
+
+  /// Returns up to `limit` keys with the fewest tokens left as of their last request, alongside
+  /// the time passed since.
+  pub(crate) fn top_consumers(&self, limit: usize) -> Vec<(String, f64, Duration)> {
+    let now = Instant::now();
+    let mut consumers: Vec<_> = self
+      .buckets
+      .iter()
+      .map(|entry| {
+        let (last, tokens) = *entry.value();
+        return (entry.key().clone(), tokens, now.duration_since(last));
+      })
+      .collect();
+
+    consumers.sort_by(|a, b| a.1.total_cmp(&b.1));
+    consumers.truncate(limit);
+    return consumers;
+  }
+}
+
+/// Middleware rejecting requests exceeding the configured rate limit with "429 Too Many Requests".
+pub(crate) async fn rate_limit_middleware(
+  State(state): State<AppState>,
+  mut req: Request,
+  next: Next,
+) -> Response {
+  let limiter = state.rate_limiter();
+  let Some((route, config)) = limiter.config_for_path(req.uri().path()) else {
+    return next.run(req).await;
+  };
+  let (route, config) = (route.to_string(), config.clone());
+
+  let ip_key =
+    || client_ip(req.headers(), req.extensions()).map_or_else(String::new, |ip| ip.to_string());
+  let key = match config.key_by {
+    RateLimitKeyBy::Ip => ip_key(),
+    RateLimitKeyBy::Global => "global".to_string(),
+    RateLimitKeyBy::UserId => {
+      let ip = ip_key();
+      match req.extract_parts_with_state::<User, _>(&state).await {
+        Ok(user) => user.id,
+        Err(_) => ip,
+      }
+    }
+  };
+
+  // Overridden routes get their own buckets.
+  let key = if route.is_empty() {
+    key
+  } else {
+    format!("{route}:{key}")
+  };
+
+  if let Err(retry_after) = limiter.acquire(key, &config) {
+    let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, None);
+    response.headers_mut().insert(
+      header::RETRY_AFTER,
+      (retry_after.as_secs_f64().ceil() as u64).into(),
+    );
+    return response;
+  }
+
+  return next.run(req).await;
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::middleware;
+  use axum::routing::get;
+  use axum::Router;
+  use axum_client_ip::SecureClientIpSource;
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::app_state::{test_state, TestStateOptions};
+
+  #[tokio::test]
+  async fn test_rate_limit_by_ip() {
+    let limited = RateLimitConfig {
+      requests_per_second: 10.0,
+      burst: 10,
+      key_by: RateLimitKeyBy::Ip,
+    };
+    let state = test_state(Some(TestStateOptions {
+      rate_limiter: Some(RateLimiter::new(
+        None,
+        vec![("/limited".to_string(), limited)],
+      )),
+      ..Default::default()
+    }))
+    .await
+    .unwrap();
+
+    // Same layer order as in production, with the server configured to run behind a proxy.
+    let router = Router::new()
+      .route("/limited", get(|| async { "Ok" }))
+      .route("/unlimited", get(|| async { "Ok" }))
+      .layer(middleware::from_fn_with_state(
+        state.clone(),
+        rate_limit_middleware,
+      ))
+      .layer(SecureClientIpSource::RightmostXForwardedFor.into_extension())
+      .with_state(state.clone());
+
+    let request = |path: &str, ip: &str| {
+      return Request::builder()
+        .uri(path)
+        .header("X-Forwarded-For", ip)
+        .body(Body::empty())
+        .unwrap();
+    };
+
+    let mut rejected = 0;
+    for _ in 0..200 {
+      let response = router
+        .clone()
+        .oneshot(request("/limited", "10.0.0.1"))
+        .await
+        .unwrap();
+      if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        rejected += 1;
+      }
+    }
+    assert!(rejected >= 180, "{rejected}");
+
+    // Other clients and routes are unaffected.
+    let response = router
+      .clone()
+      .oneshot(request("/limited", "10.0.0.2"))
+      .await
+      .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    for _ in 0..20 {
+      let response = router
+        .clone()
+        .oneshot(request("/unlimited", "10.0.0.1"))
+        .await
+        .unwrap();
+      assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let consumers = state.rate_limiter().top_consumers(1);
+    assert_eq!(consumers[0].0, "/limited:10.0.0.1");
+  }
+
+  #[test]
+  fn test_config_for_path() {
+    let config = |burst: u32| RateLimitConfig {
+      requests_per_second: 1.0,
+      burst,
+      key_by: RateLimitKeyBy::Ip,
+    };
+    let limiter = RateLimiter::new(
+      Some(config(1)),
+      vec![
+        ("/api/auth".to_string(), config(2)),
+        ("/api/auth/v1/login".to_string(), config(3)),
+      ],
+    );
+
+    let burst = |path: &str| {
+      let (route, config) = limiter.config_for_path(path).unwrap();
+      return (route.to_string(), config.burst);
+    };
+    assert_eq!(burst("/"), ("".to_string(), 1));
+    assert_eq!(burst("/api/authx"), ("".to_string(), 1));
+    assert_eq!(burst("/api/auth/v1/register"), ("/api/auth".to_string(), 2));
+    assert_eq!(
+      burst("/api/auth/v1/login"),
+      ("/api/auth/v1/login".to_string(), 3)
+    );
+  }
+}
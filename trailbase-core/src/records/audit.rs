@@ -0,0 +1,448 @@
+use axum::{
+  extract::{Path, Query, State},
+  Json,
+};
+use serde::{Deserialize, Serialize};
+use trailbase_sqlite::named_params;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::config::proto::Config;
+use crate::listing::limit_or_default;
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::schema::ColumnDataType;
+use crate::table_metadata::{TableMetadata, TableMetadataCache, TableOrViewMetadata};
+use crate::util::id_to_b64;
+
+/// Single change to a record as recorded by the audit trail triggers.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEntry {
+  /// Monotonically increasing id of the entry.
+  pub id: i64,
+  /// One of "INSERT", "UPDATE" or "DELETE".
+  pub operation: String,
+  /// Record contents prior to the change, absent for insertions.
+  pub old_data: Option<serde_json::Value>,
+  /// Record contents after the change, absent for deletions.
+  pub new_data: Option<serde_json::Value>,
+  /// Url-safe Base64 encoded id of the user making the change, if made through a record API by
+  /// an authenticated user.
+  pub changed_by: Option<String>,
+  /// Unix timestamp in seconds.
+  pub changed_at: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistoryResponse {
+  /// Pagination cursor. Round-trip to get the next batch.
+  pub cursor: Option<String>,
+  /// Audit trail entries in chronological order.
+  pub records: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct HistoryQuery {
+  pub limit: Option<usize>,
+  /// Id of the last entry of the previous batch.
+  pub cursor: Option<i64>,
+}
+
+/// Read the change history of a record.
+#[utoipa::path(
+  get,
+  path = "/:name/:record/history",
+  params(HistoryQuery),
+  responses(
+    (status = 200, description = "Audit trail entries.", body = HistoryResponse)
+  )
+)]
+pub async fn history_handler(
+  State(state): State<AppState>,
+  Path((api_name, record)): Path<(String, String)>,
+  Query(query): Query<HistoryQuery>,
+  user: Option<User>,
+) -> Result<Json<HistoryResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  if !api.audit_trail() {
+    return Err(RecordError::BadRequest("Audit trail not enabled"));
+  }
+
+  let record_id = api.id_to_sql(&record)?;
+
+  api
+    .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  let limit = limit_or_default(query.limit);
+  let rows = state
+    .conn()
+    .query(
+      &format!(
+        r#"
+          SELECT id, operation, old_data, new_data, changed_by, changed_at
+          FROM "{audit_table}"
+          WHERE record_id = :record_id AND id > :cursor
+          ORDER BY id ASC
+          LIMIT :limit
+        "#,
+        audit_table = audit_table_name(api.table_name()),
+      ),
+      named_params! {
+        ":record_id": record_id,
+        ":cursor": query.cursor.unwrap_or(0),
+        ":limit": limit as i64,
+      },
+    )
+    .await?;
+
+  let parse_json = |data: Option<String>| -> Result<Option<serde_json::Value>, RecordError> {
+    return data
+      .map(|data| serde_json::from_str(&data))
+      .transpose()
+      .map_err(|err| RecordError::Internal(err.into()));
+  };
+
+  let records = rows
+    .iter()
+    .map(|row| -> Result<AuditEntry, RecordError> {
+      let get_err = |err: rusqlite::types::FromSqlError| RecordError::Internal(err.into());
+
+      let changed_by: Option<[u8; 16]> = row.get(4).map_err(get_err)?;
+      return Ok(AuditEntry {
+        id: row.get(0).map_err(get_err)?,
+        operation: row.get(1).map_err(get_err)?,
+        old_data: parse_json(row.get(2).map_err(get_err)?)?,
+        new_data: parse_json(row.get(3).map_err(get_err)?)?,
+        changed_by: changed_by.map(|id| id_to_b64(&id)),
+        changed_at: row.get(5).map_err(get_err)?,
+      });
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let cursor = if records.len() == limit {
+    records.last().map(|entry| entry.id.to_string())
+  } else {
+    None
+  };
+
+  return Ok(Json(HistoryResponse { cursor, records }));
+}
+
+/// Attributes the audit trail entries written by the triggers during a record API write to the
+/// user making the change. Triggers have no notion of users, thus the entries are attributed
+/// within the write's transaction: all entries past the watermark taken right before the write
+/// were written by it.
+pub(crate) struct AuditAttribution {
+  audit_table: String,
+  user_id: [u8; 16],
+}
+
+impl AuditAttribution {
+  /// Returns the attribution for writes to the given API by the given user, if any.
+  pub(crate) fn new(api: &RecordApi, user: Option<&User>) -> Option<Self> {
+    if !api.audit_trail() {
+      return None;
+    }
+    return user.map(|user| AuditAttribution {
+      audit_table: audit_table_name(api.table_name()),
+      user_id: user.uuid.into_bytes(),
+    });
+  }
+
+  pub(crate) fn watermark(&self, tx: &rusqlite::Transaction<'_>) -> Result<i64, rusqlite::Error> {
+    return tx.query_row(
+      &format!(
+        r#"SELECT COALESCE(MAX(id), 0) FROM "{audit_table}""#,
+        audit_table = self.audit_table
+      ),
+      (),
+      |row| row.get(0),
+    );
+  }
+
+  pub(crate) fn attribute(
+    &self,
+    tx: &rusqlite::Transaction<'_>,
+    watermark: i64,
+  ) -> Result<(), rusqlite::Error> {
+    tx.execute(
+      &format!(
+        r#"UPDATE "{audit_table}" SET changed_by = ?1 WHERE id > ?2"#,
+        audit_table = self.audit_table
+      ),
+      rusqlite::params![self.user_id, watermark],
+    )?;
+    return Ok(());
+  }
+}
+
+/// Creates the audit tables and (re-)installs the triggers for all record APIs configured with
+/// `audit_trail`. Triggers are re-created to pick up schema changes to the audited tables.
+pub(crate) async fn install_audit_trails(
+  conn: &trailbase_sqlite::Connection,
+  tables: &TableMetadataCache,
+  config: &Config,
+) -> Result<(), trailbase_sqlite::Error> {
+  for api_config in &config.record_apis {
+    if !api_config.audit_trail.unwrap_or(false) {
+      continue;
+    }
+    let Some(ref table_name) = api_config.table_name else {
+      continue;
+    };
+    let Some(metadata) = tables.get(table_name) else {
+      continue;
+    };
+    let Some((_index, pk_column)) = metadata.record_pk_column() else {
+      continue;
+    };
+
+    conn
+      .execute_batch(&audit_trail_statements(&metadata, &pk_column.name).join(";\n"))
+      .await?;
+  }
+
+  return Ok(());
+}
+
+fn audit_table_name(table_name: &str) -> String {
+  return format!("_audit_{table_name}");
+}
+
+/// SQL expression building a JSON object from the NEW or OLD row within a trigger, given as
+/// `prefix`. Blobs are hex-encoded, since JSON cannot hold binary data.
+pub(crate) fn record_json_object(metadata: &TableMetadata, prefix: &str) -> String {
+  let pairs = metadata
+    .schema
+    .columns
+    .iter()
+    .map(|c| match c.data_type {
+      ColumnDataType::Blob => format!(r#"'{name}', hex({prefix}."{name}")"#, name = c.name),
+      _ => format!(r#"'{name}', {prefix}."{name}""#, name = c.name),
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  return format!("json_object({pairs})");
+}
+
+fn audit_trail_statements(metadata: &TableMetadata, pk_column: &str) -> Vec<String> {
+  let table_name = &metadata.schema.name;
+  let audit_table = audit_table_name(table_name);
+
+  let (new_data, old_data) = (
+    record_json_object(metadata, "NEW"),
+    record_json_object(metadata, "OLD"),
+  );
+
+  return vec![
+    format!(
+      r#"CREATE TABLE IF NOT EXISTS "{audit_table}" (
+  id             INTEGER PRIMARY KEY,
+  record_id      ANY NOT NULL,
+  operation      TEXT NOT NULL,
+  old_data       TEXT,
+  new_data       TEXT,
+  changed_by     BLOB,
+  changed_at     INTEGER NOT NULL DEFAULT (UNIXEPOCH())
+) STRICT"#
+    ),
+    format!(
+      r#"CREATE INDEX IF NOT EXISTS "__{audit_table}__record_id_index" ON "{audit_table}" (record_id)"#
+    ),
+    format!(r#"DROP TRIGGER IF EXISTS "__{audit_table}__insert_trigger""#),
+    format!(
+      r#"CREATE TRIGGER "__{audit_table}__insert_trigger" AFTER INSERT ON "{table_name}" BEGIN
+  INSERT INTO "{audit_table}" (record_id, operation, new_data) VALUES (NEW."{pk_column}", 'INSERT', {new_data});
+END"#
+    ),
+    format!(r#"DROP TRIGGER IF EXISTS "__{audit_table}__update_trigger""#),
+    format!(
+      r#"CREATE TRIGGER "__{audit_table}__update_trigger" AFTER UPDATE ON "{table_name}" BEGIN
+  INSERT INTO "{audit_table}" (record_id, operation, old_data, new_data) VALUES (NEW."{pk_column}", 'UPDATE', {old_data}, {new_data});
+END"#
+    ),
+    format!(r#"DROP TRIGGER IF EXISTS "__{audit_table}__delete_trigger""#),
+    format!(
+      r#"CREATE TRIGGER "__{audit_table}__delete_trigger" AFTER DELETE ON "{table_name}" BEGIN
+  INSERT INTO "{audit_table}" (record_id, operation, old_data) VALUES (OLD."{pk_column}", 'DELETE', {old_data});
+END"#
+    ),
+  ];
+}
+
+#[cfg(test)]
+mod test {
+  use axum::http::HeaderMap;
+
+  use super::*;
+  use crate::admin::user::*;
+  use crate::app_state::*;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::PermissionFlag;
+  use crate::extract::Either;
+  use crate::records::create_record::{
+    create_record_handler, CreateRecordQuery, CreateRecordResponse,
+  };
+  use crate::records::delete_record::delete_record_handler;
+  use crate::records::test_utils::*;
+  use crate::records::update_record::{update_record_handler, UpdateRecordQuery};
+  use crate::records::*;
+  use crate::test::unpack_json_response;
+
+  #[tokio::test]
+  async fn test_record_api_history() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE note (
+            id           INTEGER PRIMARY KEY,
+            text         TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "notes_api",
+      "note",
+      Acls {
+        authenticated: vec![
+          PermissionFlag::Create,
+          PermissionFlag::Read,
+          PermissionFlag::Update,
+          PermissionFlag::Delete,
+        ],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().audit_trail = Some(true);
+    state.validate_and_update_config(config, None).await?;
+
+    let email = "user@test.com";
+    let password = "Secret!1!!";
+    let user_id = create_user_for_test(&state, email, password).await?;
+    let token = login_with_password(&state, email, password).await?;
+    let user = || User::from_auth_token(&state, &token.auth_token);
+
+    let response: CreateRecordResponse = unpack_json_response(
+      create_record_handler(
+        State(state.clone()),
+        Path("notes_api".to_string()),
+        Query(CreateRecordQuery::default()),
+        user(),
+        Either::Json(json_row_from_value(serde_json::json!({"text": "first"}))?),
+      )
+      .await?,
+    )
+    .await?;
+    let path = || Path(("notes_api".to_string(), response.id.clone()));
+
+    for text in ["second", "third"] {
+      update_record_handler(
+        State(state.clone()),
+        path(),
+        Query(UpdateRecordQuery::default()),
+        user(),
+        HeaderMap::new(),
+        Either::Json(json_row_from_value(serde_json::json!({"text": text}))?),
+      )
+      .await?;
+    }
+
+    // Changes made outside the record APIs remain unattributed.
+    state
+      .conn()
+      .execute(
+        "UPDATE note SET text = 'fourth' WHERE id = $1",
+        trailbase_sqlite::params!(response.id.parse::<i64>()?),
+      )
+      .await?;
+
+    delete_record_handler(State(state.clone()), path(), user(), HeaderMap::new()).await?;
+
+    let Json(history) = history_handler(
+      State(state.clone()),
+      path(),
+      Query(HistoryQuery::default()),
+      user(),
+    )
+    .await?;
+
+    assert_eq!(
+      history
+        .records
+        .iter()
+        .map(|e| e.operation.as_str())
+        .collect::<Vec<_>>(),
+      ["INSERT", "UPDATE", "UPDATE", "UPDATE", "DELETE"]
+    );
+    assert_eq!(
+      history.records[0].new_data.as_ref().unwrap()["text"],
+      "first"
+    );
+    assert_eq!(
+      history.records[2].old_data.as_ref().unwrap()["text"],
+      "second"
+    );
+    assert_eq!(history.records[4].new_data, None);
+    let user_id = Some(id_to_b64(&user_id.into_bytes()));
+    assert_eq!(
+      history
+        .records
+        .iter()
+        .map(|e| e.changed_by == user_id)
+        .collect::<Vec<_>>(),
+      [true, true, true, false, true]
+    );
+
+    // Paginate.
+    let Json(page) = history_handler(
+      State(state.clone()),
+      path(),
+      Query(HistoryQuery {
+        limit: Some(3),
+        cursor: None,
+      }),
+      user(),
+    )
+    .await?;
+    assert_eq!(page.records.len(), 3);
+    let Json(page) = history_handler(
+      State(state.clone()),
+      path(),
+      Query(HistoryQuery {
+        limit: Some(3),
+        cursor: Some(page.cursor.unwrap().parse()?),
+      }),
+      user(),
+    )
+    .await?;
+    assert_eq!(page.records.len(), 2);
+    assert_eq!(page.records[1].operation, "DELETE");
+
+    // Anonymous users cannot read the history.
+    assert!(history_handler(
+      State(state.clone()),
+      path(),
+      Query(HistoryQuery::default()),
+      None
+    )
+    .await
+    .is_err());
+
+    return Ok(());
+  }
+}
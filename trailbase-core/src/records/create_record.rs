@@ -1,25 +1,41 @@
 use axum::extract::{Json, Path, Query, State};
 use axum::response::{IntoResponse, Redirect, Response};
 use base64::prelude::*;
+use log::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use trailbase_sqlite::{Row, Value};
 use utoipa::{IntoParams, ToSchema};
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
+use crate::config::proto::RecordApiEmailOnCreate;
+use crate::email::Email;
 use crate::extract::Either;
-use crate::records::json_to_sql::{InsertQueryBuilder, JsonRow, LazyParams};
+use crate::push::notify_record_change;
+use crate::records::audit::AuditAttribution;
+use crate::records::json_to_sql::{InsertQueryBuilder, JsonRow, LazyParams, Params};
+use crate::records::quota;
+use crate::records::sql_to_json::row_to_json;
 use crate::records::{Permission, RecordError};
 use crate::schema::ColumnDataType;
+use crate::table_metadata::TableMetadata;
 
 #[derive(Clone, Debug, Default, Deserialize, IntoParams)]
 pub struct CreateRecordQuery {
   pub redirect_to: Option<String>,
+  /// If set to "representation", the response will include the newly created record.
+  #[serde(rename = "return")]
+  pub return_: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct CreateRecordResponse {
   /// Safe-url base64 encoded id of the newly created record.
   pub id: String,
+  /// The newly created record if requested using `?return=representation`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub record: Option<serde_json::Value>,
 }
 
 /// Create new record.
@@ -52,6 +68,12 @@ pub async fn create_record_handler(
     Either::Form(value) => (value, None),
   };
 
+  let return_record = wants_representation(create_record_query.return_.as_deref())?;
+  if return_record {
+    // Fail early rather than after the insertion if the user cannot read the table at all.
+    api.check_table_level_access(Permission::Read, user.as_ref())?;
+  }
+
   let mut lazy_params = LazyParams::new(table_metadata, request, multipart_files);
 
   api
@@ -68,52 +90,75 @@ pub async fn create_record_handler(
   };
 
   if api.insert_autofill_missing_user_id_columns() {
-    let column_names = params.column_names();
-    let missing_columns = table_metadata
-      .user_id_columns
-      .iter()
-      .filter_map(|index| {
-        let col = &table_metadata.schema.columns[*index];
-        if column_names.iter().any(|c| c == &col.name) {
-          return None;
-        }
-        return Some(col.name.clone());
-      })
-      .collect::<Vec<_>>();
-
-    if !missing_columns.is_empty() {
-      if let Some(user) = user {
-        for col in missing_columns {
-          params.push_param(col, trailbase_sqlite::Value::Blob(user.uuid.into()));
-        }
-      }
-    }
+    autofill_missing_user_id_columns(table_metadata, &mut params, user.as_ref());
   }
 
+  quota::check_create_quota(&state, &api, user.as_ref()).await?;
+
+  // The full record is also needed to address and render emails.
+  let return_all = return_record || api.send_email_on_create().is_some();
+
   let pk_column = api.record_pk_column();
   let row = InsertQueryBuilder::run(
     &state,
     params,
     api.insert_conflict_resolution_strategy(),
-    Some(&pk_column.name),
+    Some(if return_all { "*" } else { &pk_column.name }),
+    AuditAttribution::new(&api, user.as_ref()),
   )
   .await
   .map_err(|err| RecordError::Internal(err.into()))?;
+  quota::record_created(&state, &api, user.as_ref());
+
+  let pk_index = if return_all {
+    row
+      .column_names()
+      .iter()
+      .position(|name| *name == pk_column.name)
+      .ok_or_else(|| RecordError::Internal("Missing primary key".into()))?
+  } else {
+    0
+  };
+
+  if let Some(record_id) = row.get_value(pk_index) {
+    notify_record_change(&state, &api, record_id.clone()).await?;
+  }
+
+  if let Some(email) = api.send_email_on_create() {
+    send_email_on_create(&state, email, &row).await;
+  }
 
   if let Some(redirect_to) = create_record_query.redirect_to {
     return Ok(Redirect::to(&redirect_to).into_response());
   }
 
+  let record = if return_record {
+    let record_id = row
+      .get_value(pk_index)
+      .ok_or_else(|| RecordError::Internal("Missing primary key".into()))?
+      .clone();
+    api
+      .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
+      .await?;
+
+    Some(
+      row_to_json(api.metadata(), &row, |col_name| !col_name.starts_with("_"))
+        .map_err(|err| RecordError::Internal(err.into()))?,
+    )
+  } else {
+    None
+  };
+
   return Ok(
     Json(CreateRecordResponse {
       id: match pk_column.data_type {
         ColumnDataType::Blob => BASE64_URL_SAFE.encode(
           row
-            .get::<[u8; 16]>(0)
+            .get::<[u8; 16]>(pk_index)
             .map_err(|err| RecordError::Internal(err.into()))?,
         ),
         ColumnDataType::Integer => row
-          .get::<i64>(0)
+          .get::<i64>(pk_index)
           .map_err(|err| RecordError::Internal(err.into()))?
           .to_string(),
         _ => {
@@ -122,11 +167,90 @@ pub async fn create_record_handler(
           ));
         }
       },
+      record,
     })
     .into_response(),
   );
 }
 
+/// Sends the configured template to the address in the newly created record. Failures are only
+/// logged, since the record has already been created.
+async fn send_email_on_create(state: &AppState, config: &RecordApiEmailOnCreate, row: &Row) {
+  let variables: Vec<(&str, String)> = row
+    .column_names()
+    .into_iter()
+    .enumerate()
+    .filter_map(|(index, name)| {
+      let value = match row.get_value(index)? {
+        Value::Text(text) => text.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(r) => r.to_string(),
+        Value::Null | Value::Blob(_) => return None,
+      };
+      return Some((name, value));
+    })
+    .collect();
+
+  let to_field = config.to_field.as_deref().unwrap_or_default();
+  let Some((_, to)) = variables.iter().find(|(name, _)| *name == to_field) else {
+    debug!("Skipping email on create, missing '{to_field}'");
+    return;
+  };
+
+  let template = config.template.as_deref().unwrap_or_default();
+  let result = Email::send_template(
+    state,
+    to,
+    template,
+    variables
+      .iter()
+      .map(|(name, value)| (*name, value.as_str()))
+      .collect::<HashMap<_, _>>(),
+  )
+  .await;
+
+  if let Err(err) = result {
+    warn!("Failed to send '{template}' email on create: {err}");
+  }
+}
+
+/// Sets the user-id columns missing from `params` to the id of the given user.
+pub(crate) fn autofill_missing_user_id_columns(
+  table_metadata: &TableMetadata,
+  params: &mut Params,
+  user: Option<&User>,
+) {
+  let Some(user) = user else {
+    return;
+  };
+
+  let column_names = params.column_names();
+  let missing_columns = table_metadata
+    .user_id_columns
+    .iter()
+    .filter_map(|index| {
+      let col = &table_metadata.schema.columns[*index];
+      if column_names.iter().any(|c| c == &col.name) {
+        return None;
+      }
+      return Some(col.name.clone());
+    })
+    .collect::<Vec<_>>();
+
+  for col in missing_columns {
+    params.push_param(col, trailbase_sqlite::Value::Blob(user.uuid.into()));
+  }
+}
+
+/// Parses the `?return=` query parameter, where "representation" requests the full record.
+pub(crate) fn wants_representation(value: Option<&str>) -> Result<bool, RecordError> {
+  return match value {
+    None | Some("minimal") => Ok(false),
+    Some("representation") => Ok(true),
+    Some(_) => Err(RecordError::BadRequest("Invalid 'return' parameter")),
+  };
+}
+
 #[cfg(test)]
 mod test {
   use super::*;
@@ -134,8 +258,10 @@ mod test {
   use crate::app_state::*;
   use crate::auth::api::login::login_with_password;
   use crate::config::proto::PermissionFlag;
+  use crate::records::read_record::{read_record_handler, ReadRecordQuery};
   use crate::records::test_utils::*;
   use crate::records::*;
+  use crate::test::unpack_json_response;
   use crate::util::id_to_b64;
 
   #[tokio::test]
@@ -289,6 +415,39 @@ mod test {
       assert!(response.is_ok(), "{response:?}");
     }
 
+    {
+      // Request the created record to be returned, which should match a subsequent read.
+      let json = serde_json::json!({
+        "_owner": id_to_b64(&user_x),
+        "room": id_to_b64(&room),
+        "data": "user_x message with representation",
+      });
+      let response: CreateRecordResponse = unpack_json_response(
+        create_record_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          Query(CreateRecordQuery {
+            return_: Some("representation".to_string()),
+            ..Default::default()
+          }),
+          User::from_auth_token(&state, &user_x_token.auth_token),
+          Either::Json(json_row_from_value(json).unwrap()),
+        )
+        .await?,
+      )
+      .await?;
+
+      let (_, Json(read)) = read_record_handler(
+        State(state.clone()),
+        Path(("messages_api".to_string(), response.id.clone())),
+        Query(ReadRecordQuery::default()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+      )
+      .await?;
+
+      assert_eq!(response.record, Some(read));
+    }
+
     return Ok(());
   }
 }
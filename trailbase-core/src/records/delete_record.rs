@@ -1,12 +1,17 @@
 use axum::{
   extract::{Path, State},
-  http::StatusCode,
+  http::{HeaderMap, StatusCode},
   response::{IntoResponse, Response},
 };
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
-use crate::records::json_to_sql::DeleteQueryBuilder;
+use crate::records::audit::AuditAttribution;
+use crate::records::etag::if_match_precondition;
+use crate::records::json_to_sql::{
+  DeleteQueryBuilder, QueryError, SoftDeleteQueryBuilder, WriteHooks,
+};
+use crate::records::record_api::SOFT_DELETE_COLUMN;
 use crate::records::{Permission, RecordError};
 
 /// Delete record.
@@ -14,13 +19,15 @@ use crate::records::{Permission, RecordError};
   delete,
   path = "/:name/:record",
   responses(
-    (status = 200, description = "Successful deletion.")
+    (status = 200, description = "Successful deletion."),
+    (status = 412, description = "Record was modified concurrently, i.e. If-Match doesn't match its ETag.")
   )
 )]
 pub async fn delete_record_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
   user: Option<User>,
+  headers: HeaderMap,
 ) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
@@ -31,26 +38,112 @@ pub async fn delete_record_handler(
     .ok_or_else(|| RecordError::ApiRequiresTable)?;
 
   let record_id = api.id_to_sql(&record)?;
+  let precondition = if_match_precondition(&api, &headers)?;
 
   api
     .check_record_level_access(Permission::Delete, Some(&record_id), None, user.as_ref())
     .await?;
 
+  if api.soft_delete() {
+    SoftDeleteQueryBuilder::run(
+      &state,
+      table_metadata,
+      SOFT_DELETE_COLUMN,
+      &api.record_pk_column().name,
+      record_id,
+      true,
+      WriteHooks {
+        precondition,
+        audit: AuditAttribution::new(&api, user.as_ref()),
+      },
+    )
+    .await
+    .map_err(map_soft_delete_error)?;
+
+    return Ok((StatusCode::OK, "deleted").into_response());
+  }
+
   DeleteQueryBuilder::run(
     &state,
     table_metadata,
     &api.record_pk_column().name,
     record_id,
+    WriteHooks {
+      precondition,
+      audit: AuditAttribution::new(&api, user.as_ref()),
+    },
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::PreconditionFailed => RecordError::PreconditionFailed,
+    err => RecordError::Internal(err.into()),
+  })?;
 
   return Ok((StatusCode::OK, "deleted").into_response());
 }
 
+/// Restore soft-deleted record.
+#[utoipa::path(
+  post,
+  path = "/:name/:record/restore",
+  responses(
+    (status = 200, description = "Successful restoration.")
+  )
+)]
+pub async fn restore_record_handler(
+  State(state): State<AppState>,
+  Path((api_name, record)): Path<(String, String)>,
+  user: Option<User>,
+) -> Result<Response, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  let table_metadata = api
+    .table_metadata()
+    .ok_or_else(|| RecordError::ApiRequiresTable)?;
+
+  if !api.soft_delete() {
+    return Err(RecordError::BadRequest("Soft deletion not enabled"));
+  }
+
+  let record_id = api.id_to_sql(&record)?;
+
+  // Whoever can delete a record can also restore it.
+  api
+    .check_record_level_access(Permission::Delete, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  SoftDeleteQueryBuilder::run(
+    &state,
+    table_metadata,
+    SOFT_DELETE_COLUMN,
+    &api.record_pk_column().name,
+    record_id.clone(),
+    false,
+    WriteHooks {
+      precondition: None,
+      audit: AuditAttribution::new(&api, user.as_ref()),
+    },
+  )
+  .await
+  .map_err(map_soft_delete_error)?;
+
+  return Ok((StatusCode::OK, "restored").into_response());
+}
+
+fn map_soft_delete_error(err: QueryError) -> RecordError {
+  return match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::PreconditionFailed => RecordError::PreconditionFailed,
+    err => RecordError::Internal(err.into()),
+  };
+}
+
 #[cfg(test)]
 mod test {
-  use axum::extract::Query;
+  use axum::extract::{Query, RawQuery};
+  use axum::Json;
   use trailbase_sqlite::params;
 
   use super::*;
@@ -63,6 +156,8 @@ mod test {
   use crate::records::create_record::{
     create_record_handler, CreateRecordQuery, CreateRecordResponse,
   };
+  use crate::records::list_records::list_records_handler;
+  use crate::records::read_record::{read_record_handler, ReadRecordQuery};
   use crate::records::test_utils::*;
   use crate::records::*;
   use crate::test::unpack_json_response;
@@ -189,8 +284,102 @@ mod test {
       State(state.clone()),
       Path(("messages_api".to_string(), id_to_b64(&id))),
       User::from_auth_token(state, auth_token),
+      HeaderMap::new(),
+    )
+    .await?;
+    return Ok(());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_soft_delete() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE note (
+            id           INTEGER PRIMARY KEY,
+            text         TEXT NOT NULL,
+            deleted_at   INTEGER
+          ) STRICT;
+          INSERT INTO note (text) VALUES ('first'), ('second');
+        "#,
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "notes_api",
+      "note",
+      Acls {
+        world: vec![PermissionFlag::Read, PermissionFlag::Delete],
+        ..Default::default()
+      },
+      AccessRules::default(),
     )
     .await?;
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().soft_delete = Some(true);
+    state.validate_and_update_config(config, None).await?;
+
+    let list = |query: Option<&str>| {
+      let response = list_records_handler(
+        State(state.clone()),
+        Path("notes_api".to_string()),
+        RawQuery(query.map(|q| q.to_string())),
+        None,
+      );
+      async move {
+        let Json(response) = response.await?;
+        let value = serde_json::to_value(response)?;
+        return Ok::<_, anyhow::Error>(value["records"].as_array().unwrap().len());
+      }
+    };
+    let read = |include_deleted: Option<bool>| {
+      read_record_handler(
+        State(state.clone()),
+        Path(("notes_api".to_string(), "1".to_string())),
+        Query(ReadRecordQuery { include_deleted }),
+        None,
+      )
+    };
+    let path = || Path(("notes_api".to_string(), "1".to_string()));
+
+    assert_eq!(list(None).await?, 2);
+
+    delete_record_handler(State(state.clone()), path(), None, HeaderMap::new()).await?;
+
+    // The record is retained but hidden.
+    let count: i64 = conn
+      .query_row("SELECT COUNT(*) FROM note", ())
+      .await?
+      .unwrap()
+      .get(0)?;
+    assert_eq!(count, 2);
+    assert_eq!(list(None).await?, 1);
+    assert!(read(None).await.is_err());
+    assert_eq!(list(Some("include_deleted=true")).await?, 2);
+    assert!(read(Some(true)).await.is_ok());
+
+    // Deleting again fails.
+    assert!(
+      delete_record_handler(State(state.clone()), path(), None, HeaderMap::new())
+        .await
+        .is_err()
+    );
+
+    restore_record_handler(State(state.clone()), path(), None).await?;
+    assert_eq!(list(None).await?, 2);
+    assert!(read(None).await.is_ok());
+
+    // Restoring a record that isn't deleted fails.
+    assert!(restore_record_handler(State(state.clone()), path(), None)
+      .await
+      .is_err());
+
     return Ok(());
   }
 }
@@ -0,0 +1,302 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use log::*;
+
+use crate::records::import_export::json_to_csv_cell;
+use crate::records::RecordError;
+
+pub(crate) const MIME_JSON: &str = "application/json";
+pub(crate) const MIME_MSGPACK: &str = "application/msgpack";
+pub(crate) const MIME_CSV: &str = "text/csv";
+
+/// Encoding of record API responses negotiated based on the request's "Accept" header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ResponseEncoding {
+  Json,
+  MessagePack,
+  Csv,
+}
+
+impl ResponseEncoding {
+  fn from_mime(mime: &str) -> Option<Self> {
+    return match mime {
+      "application/json" | "application/*" | "*/*" => Some(Self::Json),
+      "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+        Some(Self::MessagePack)
+      }
+      "text/csv" => Some(Self::Csv),
+      _ => None,
+    };
+  }
+
+  /// Picks the supported encoding with the highest quality, falling back to JSON.
+  pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+    let mut best: Option<(Self, f32)> = None;
+
+    for accept in headers.get_all(header::ACCEPT) {
+      let Ok(accept) = accept.to_str() else {
+        continue;
+      };
+
+      for entry in accept.split(',') {
+        let mut parts = entry.split(';').map(|p| p.trim());
+        let Some(encoding) = parts.next().and_then(Self::from_mime) else {
+          continue;
+        };
+
+        let quality = parts
+          .find_map(|p| p.strip_prefix("q="))
+          .and_then(|q| q.parse::<f32>().ok())
+          .unwrap_or(1.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+          best = Some((encoding, quality));
+        }
+      }
+    }
+
+    return best.map_or(Self::Json, |(encoding, _)| encoding);
+  }
+
+  fn content_type(&self) -> &'static str {
+    return match self {
+      Self::Json => MIME_JSON,
+      Self::MessagePack => MIME_MSGPACK,
+      Self::Csv => "text/csv; charset=utf-8",
+    };
+  }
+}
+
+/// Re-encodes successful JSON responses, i.e. records or listings, as MessagePack or CSV if the
+/// client asked for it.
+pub(super) async fn response_encoding_middleware(req: Request, next: Next) -> Response {
+  let encoding = ResponseEncoding::from_headers(req.headers());
+
+  let mut response = next.run(req).await;
+  response
+    .headers_mut()
+    .append(header::VARY, HeaderValue::from_static("accept"));
+
+  if encoding == ResponseEncoding::Json || !response.status().is_success() {
+    return response;
+  }
+
+  let (mut parts, body) = response.into_parts();
+  let result = match axum::body::to_bytes(body, usize::MAX).await {
+    Ok(bytes) => encode(encoding, &bytes),
+    Err(err) => Err(RecordError::Internal(err.into())),
+  };
+
+  return match result {
+    Ok(body) => {
+      parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+      );
+      parts.headers.remove(header::CONTENT_LENGTH);
+      Response::from_parts(parts, Body::from(body))
+    }
+    Err(err) => {
+      debug!("Failed to encode response as {encoding:?}: {err}");
+      err.into_response()
+    }
+  };
+}
+
+fn encode(encoding: ResponseEncoding, json: &[u8]) -> Result<Vec<u8>, RecordError> {
+  let value: serde_json::Value =
+    serde_json::from_slice(json).map_err(|err| RecordError::Internal(err.into()))?;
+
+  return match encoding {
+    ResponseEncoding::Json => Ok(json.to_vec()),
+    ResponseEncoding::MessagePack => {
+      rmp_serde::to_vec_named(&value).map_err(|err| RecordError::Internal(err.into()))
+    }
+    ResponseEncoding::Csv => to_csv(&value).map_err(|err| RecordError::Internal(err.into())),
+  };
+}
+
+/// Converts a listing's records or a single record to CSV with a header row. Nested JSON is
+/// flattened to strings.
+///
+/// NOTE: Pagination cursor and total count of listings are omitted.
+fn to_csv(value: &serde_json::Value) -> Result<Vec<u8>, csv::Error> {
+  let records: Vec<&serde_json::Map<String, serde_json::Value>> = match value {
+    serde_json::Value::Object(obj) => match obj.get("records") {
+      Some(serde_json::Value::Array(records)) => {
+        records.iter().filter_map(|r| r.as_object()).collect()
+      }
+      _ => vec![obj],
+    },
+    _ => vec![],
+  };
+
+  // Union of all columns in order of appearance, since records may be sparse.
+  let mut columns: Vec<&str> = vec![];
+  for record in &records {
+    for key in record.keys() {
+      if !columns.contains(&key.as_str()) {
+        columns.push(key);
+      }
+    }
+  }
+
+  let mut writer = csv::Writer::from_writer(vec![]);
+  writer.write_record(&columns)?;
+  for record in records {
+    writer.write_record(columns.iter().map(|c| json_to_csv_cell(record.get(*c))))?;
+  }
+
+  return writer.into_inner().map_err(|err| err.into_error().into());
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::http::StatusCode;
+  use serde::Deserialize;
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::config::proto::PermissionFlag;
+  use crate::constants::RECORD_API_PATH;
+  use crate::records::{add_record_api, AccessRules, Acls};
+
+  #[test]
+  fn test_response_encoding_from_headers() {
+    let encoding = |accept: &str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::ACCEPT, accept.parse().unwrap());
+      return ResponseEncoding::from_headers(&headers);
+    };
+
+    assert_eq!(
+      ResponseEncoding::from_headers(&HeaderMap::new()),
+      ResponseEncoding::Json
+    );
+    assert_eq!(encoding("*/*"), ResponseEncoding::Json);
+    assert_eq!(
+      encoding("application/msgpack"),
+      ResponseEncoding::MessagePack
+    );
+    assert_eq!(
+      encoding("text/csv, application/json"),
+      ResponseEncoding::Csv
+    );
+    assert_eq!(
+      encoding("application/json;q=0.5, text/csv;q=0.9"),
+      ResponseEncoding::Csv
+    );
+    assert_eq!(encoding("text/html"), ResponseEncoding::Json);
+  }
+
+  #[derive(Debug, PartialEq, Deserialize)]
+  struct Entry {
+    id: i64,
+    name: String,
+    value: f64,
+  }
+
+  #[derive(Debug, PartialEq, Deserialize)]
+  struct Listing {
+    cursor: Option<String>,
+    records: Vec<Entry>,
+  }
+
+  #[tokio::test]
+  async fn test_list_encodings() {
+    let state = test_state(None).await.unwrap();
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE entries (
+            id      INTEGER PRIMARY KEY,
+            name    TEXT NOT NULL,
+            value   REAL NOT NULL
+          ) STRICT;
+
+          WITH RECURSIVE seq(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM seq WHERE x < 100)
+          INSERT INTO entries (name, value) SELECT 'name' || x, x / 2.0 FROM seq;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    add_record_api(
+      &state,
+      "entries_api",
+      "entries",
+      Acls {
+        world: vec![PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let router = crate::records::router().with_state(state.clone());
+    let fetch = |path: String, accept: &'static str| {
+      let router = router.clone();
+      return async move {
+        let response = router
+          .oneshot(
+            Request::builder()
+              .uri(path)
+              .header(header::ACCEPT, accept)
+              .body(Body::empty())
+              .unwrap(),
+          )
+          .await
+          .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let content_type = response.headers()[header::CONTENT_TYPE]
+          .to_str()
+          .unwrap()
+          .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+          .await
+          .unwrap();
+        return (content_type, body);
+      };
+    };
+
+    let list_path = format!("/{RECORD_API_PATH}/entries_api?limit=100");
+    let (content_type, json) = fetch(list_path.clone(), MIME_JSON).await;
+    assert_eq!(content_type, MIME_JSON);
+    let (content_type, msgpack) = fetch(list_path.clone(), MIME_MSGPACK).await;
+    assert_eq!(content_type, MIME_MSGPACK);
+
+    assert!(
+      msgpack.len() < json.len(),
+      "{} {}",
+      msgpack.len(),
+      json.len()
+    );
+
+    let from_json: Listing = serde_json::from_slice(&json).unwrap();
+    let from_msgpack: Listing = rmp_serde::from_slice(&msgpack).unwrap();
+    assert_eq!(from_json.records.len(), 100);
+    assert_eq!(from_json, from_msgpack);
+
+    let (content_type, csv) = fetch(list_path, MIME_CSV).await;
+    assert!(content_type.starts_with(MIME_CSV));
+    let from_csv: Vec<Entry> = csv::Reader::from_reader(csv.as_ref())
+      .deserialize()
+      .collect::<Result<_, _>>()
+      .unwrap();
+    assert_eq!(from_json.records, from_csv);
+
+    let (content_type, csv) = fetch(format!("/{RECORD_API_PATH}/entries_api/1"), MIME_CSV).await;
+    assert!(content_type.starts_with(MIME_CSV));
+    assert_eq!(
+      String::from_utf8(csv.to_vec()).unwrap(),
+      "id,name,value\n1,name1,0.5\n"
+    );
+  }
+}
@@ -1,9 +1,10 @@
-use axum::body::Body;
-use axum::http::{header::CONTENT_TYPE, StatusCode};
+use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use log::*;
 use thiserror::Error;
 
+use crate::request_id::error_response;
+
 /// Publicly visible errors of record APIs.
 ///
 /// This error is deliberately opaque and kept very close to HTTP error codes to avoid the leaking
@@ -19,6 +20,10 @@ pub enum RecordError {
   RecordNotFound,
   #[error("Forbidden")]
   Forbidden,
+  #[error("Precondition Failed")]
+  PreconditionFailed,
+  #[error("Too Many Requests")]
+  TooManyRequests,
   #[error("Bad request: {0}")]
   BadRequest(&'static str),
   #[error("Internal: {0}")]
@@ -68,6 +73,8 @@ impl IntoResponse for RecordError {
       Self::ApiRequiresTable => (StatusCode::METHOD_NOT_ALLOWED, None),
       Self::RecordNotFound => (StatusCode::NOT_FOUND, None),
       Self::Forbidden => (StatusCode::FORBIDDEN, None),
+      Self::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, None),
+      Self::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, None),
       Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, Some(msg.to_string())),
       Self::Internal(err) if cfg!(debug_assertions) => {
         (StatusCode::INTERNAL_SERVER_ERROR, Some(err.to_string()))
@@ -75,17 +82,6 @@ impl IntoResponse for RecordError {
       Self::Internal(_err) => (StatusCode::INTERNAL_SERVER_ERROR, None),
     };
 
-    if let Some(body) = body {
-      return Response::builder()
-        .status(status)
-        .header(CONTENT_TYPE, "text/plain")
-        .body(Body::new(body))
-        .unwrap();
-    }
-
-    return Response::builder()
-      .status(status)
-      .body(Body::empty())
-      .unwrap();
+    return error_response(status, body);
   }
 }
@@ -0,0 +1,41 @@
+use axum::http::{header, HeaderMap};
+
+use crate::records::json_to_sql::Precondition;
+use crate::records::sql_to_json::row_to_json;
+use crate::records::{RecordApi, RecordError};
+
+/// Computes a strong ETag, i.e. a quoted CRC32 of the serialized record.
+pub(crate) fn record_etag(record: &serde_json::Value) -> String {
+  let bytes = serde_json::to_vec(record).unwrap_or_default();
+  return format!("\"{:08x}\"", crc32fast::hash(&bytes));
+}
+
+/// Turns the optional `If-Match` request header into a precondition on the record's current
+/// contents, which holds if any of the given tags match. The precondition is checked within the
+/// write's transaction, i.e. concurrent writes cannot sneak in between check and write.
+pub(crate) fn if_match_precondition(
+  api: &RecordApi,
+  headers: &HeaderMap,
+) -> Result<Option<Precondition>, RecordError> {
+  let Some(if_match) = headers.get(header::IF_MATCH) else {
+    return Ok(None);
+  };
+  let if_match = if_match
+    .to_str()
+    .map_err(|_| RecordError::BadRequest("Invalid If-Match header"))?
+    .to_string();
+
+  let api = api.clone();
+  return Ok(Some(Box::new(move |row: &trailbase_sqlite::Row| {
+    if if_match.trim() == "*" {
+      return true;
+    }
+
+    let Ok(record) = row_to_json(api.metadata(), row, |col_name| !col_name.starts_with("_")) else {
+      return false;
+    };
+    let etag = record_etag(&record);
+
+    return if_match.split(',').any(|tag| tag.trim() == etag);
+  })));
+}
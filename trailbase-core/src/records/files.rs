@@ -1,6 +1,6 @@
 use axum::body::Body;
 use axum::http::header;
-use axum::response::{IntoResponse, Response};
+use axum::response::{IntoResponse, Redirect, Response};
 use log::*;
 use object_store::ObjectStore;
 use thiserror::Error;
@@ -23,8 +23,16 @@ pub(crate) async fn read_file_into_response(
   state: &AppState,
   file_upload: FileUpload,
 ) -> Result<Response, FileError> {
-  let store = state.objectstore();
   let path = object_store::path::Path::from(file_upload.path());
+
+  // Let clients fetch the file directly from S3 if configured. Note that the pre-signed URL is
+  // served with the content type stored in the bucket.
+  if let Some(signer) = state.url_signer() {
+    let url = signer.signed_download_url(&path).await?;
+    return Ok(Redirect::temporary(url.as_str()).into_response());
+  }
+
+  let store = state.objectstore();
   let result = store.get(&path).await?;
 
   let headers = || {
@@ -0,0 +1,283 @@
+use std::io::{Read, Write};
+use thiserror::Error;
+use trailbase_sqlite::params;
+
+use crate::records::json_to_sql::{InsertQueryBuilder, JsonRow, Params, ParamsError, QueryError};
+use crate::records::sql_to_json::{row_to_json, JsonError};
+use crate::table_metadata::TableMetadata;
+
+/// Number of rows read per page when exporting.
+const EXPORT_PAGE_SIZE: usize = 1000;
+/// Alias of the rowid used for paging, which is excluded from the output.
+const EXPORT_ROWID: &str = "_export_rowid_";
+
+#[derive(Debug, Error)]
+pub enum TransferError {
+  #[error("SQLite error: {0}")]
+  Sql(#[from] trailbase_sqlite::Error),
+  #[error("FromSql error: {0}")]
+  FromSql(#[from] rusqlite::types::FromSqlError),
+  #[error("Query error: {0}")]
+  Query(#[from] QueryError),
+  #[error("Params error: {0}")]
+  Params(#[from] ParamsError),
+  #[error("Json error: {0}")]
+  Json(#[from] JsonError),
+  #[error("SerdeJson error: {0}")]
+  SerdeJson(#[from] serde_json::Error),
+  #[error("CSV error: {0}")]
+  Csv(#[from] csv::Error),
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Invalid record: {0}")]
+  InvalidRecord(String),
+}
+
+/// Serialization format for exported and imported records. Blobs, e.g. UUIDs, are represented as
+/// url-safe base64 in all formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataFormat {
+  /// A single JSON array of objects.
+  Json,
+  /// One JSON object per line.
+  NdJson,
+  /// Comma-separated values with a header row. Empty cells are treated as NULL.
+  Csv,
+}
+
+/// Writes all rows of the given table, optionally restricted by an SQL `filter` expression, to
+/// `writer`. Rows are read page by page to bound memory usage. Returns the number of exported
+/// rows.
+///
+/// NOTE: Paging relies on the rowid and thus doesn't work for "WITHOUT ROWID" tables.
+pub async fn export_table(
+  conn: &trailbase_sqlite::Connection,
+  metadata: &TableMetadata,
+  format: DataFormat,
+  filter: Option<&str>,
+  writer: &mut (impl Write + Send),
+) -> Result<usize, TransferError> {
+  let table_name = metadata.name();
+  let query = format!(
+    r#"SELECT _rowid_ AS {EXPORT_ROWID}, * FROM "{table_name}" WHERE _rowid_ > $1 AND ({filter}) ORDER BY _rowid_ LIMIT {EXPORT_PAGE_SIZE}"#,
+    filter = filter.unwrap_or("TRUE"),
+  );
+
+  let column_names: Vec<String> = metadata
+    .schema
+    .columns
+    .iter()
+    .map(|c| c.name.clone())
+    .collect();
+
+  match format {
+    DataFormat::Csv => {
+      let mut csv_writer = csv::Writer::from_writer(vec![]);
+      csv_writer.write_record(&column_names)?;
+      writer.write_all(&into_inner(csv_writer)?)?;
+    }
+    DataFormat::Json => writer.write_all(b"[")?,
+    DataFormat::NdJson => {}
+  };
+
+  let mut count: usize = 0;
+  let mut last_rowid: i64 = i64::MIN;
+  loop {
+    let rows = conn.query(&query, params!(last_rowid)).await?;
+    if rows.is_empty() {
+      break;
+    }
+
+    let mut page: Vec<u8> = vec![];
+    let mut csv_writer = csv::Writer::from_writer(vec![]);
+    for row in rows.iter() {
+      last_rowid = row.get(0)?;
+      let serde_json::Value::Object(record) =
+        row_to_json(metadata, row, |name| name != EXPORT_ROWID)?
+      else {
+        return Err(TransferError::InvalidRecord("Not an object".to_string()));
+      };
+
+      match format {
+        DataFormat::Csv => {
+          csv_writer.write_record(
+            column_names
+              .iter()
+              .map(|name| json_to_csv_cell(record.get(name))),
+          )?;
+        }
+        DataFormat::Json => {
+          if count > 0 {
+            page.push(b',');
+          }
+          page.push(b'\n');
+          serde_json::to_writer(&mut page, &record)?;
+        }
+        DataFormat::NdJson => {
+          serde_json::to_writer(&mut page, &record)?;
+          page.push(b'\n');
+        }
+      }
+      count += 1;
+    }
+
+    if format == DataFormat::Csv {
+      page = into_inner(csv_writer)?;
+    }
+    writer.write_all(&page)?;
+  }
+
+  if format == DataFormat::Json {
+    writer.write_all(b"\n]\n")?;
+  }
+  writer.flush()?;
+
+  return Ok(count);
+}
+
+/// Reads records from `reader` and inserts them into the given table in transactions of
+/// `batch_size` records. Returns the number of imported records.
+pub async fn import_table(
+  conn: &trailbase_sqlite::Connection,
+  metadata: &TableMetadata,
+  format: DataFormat,
+  reader: impl Read + Send,
+  batch_size: usize,
+) -> Result<usize, TransferError> {
+  let batch_size = batch_size.max(1);
+
+  let records: Box<dyn Iterator<Item = Result<JsonRow, TransferError>> + Send + '_> = match format {
+    DataFormat::Json => {
+      let records: Vec<JsonRow> = serde_json::from_reader(reader)?;
+      Box::new(records.into_iter().map(Ok))
+    }
+    DataFormat::NdJson => Box::new(
+      serde_json::Deserializer::from_reader(reader)
+        .into_iter::<JsonRow>()
+        .map(|record| record.map_err(TransferError::from)),
+    ),
+    DataFormat::Csv => {
+      let mut csv_reader = csv::Reader::from_reader(reader);
+      let headers = csv_reader.headers()?.clone();
+      Box::new(
+        csv_reader
+          .into_records()
+          .map(move |record| -> Result<JsonRow, TransferError> {
+            return Ok(
+              std::iter::zip(headers.iter(), record?.iter())
+                .map(|(name, cell)| (name.to_string(), csv_cell_to_json(cell)))
+                .collect(),
+            );
+          }),
+      )
+    }
+  };
+
+  let mut count: usize = 0;
+  let mut batch: Vec<Params> = Vec::with_capacity(batch_size);
+  for record in records {
+    batch.push(Params::from(metadata, record?, None)?);
+
+    if batch.len() >= batch_size {
+      count += InsertQueryBuilder::run_bulk(conn, std::mem::take(&mut batch)).await?;
+    }
+  }
+
+  if !batch.is_empty() {
+    count += InsertQueryBuilder::run_bulk(conn, batch).await?;
+  }
+
+  return Ok(count);
+}
+
+fn into_inner(csv_writer: csv::Writer<Vec<u8>>) -> Result<Vec<u8>, TransferError> {
+  return csv_writer
+    .into_inner()
+    .map_err(|err| TransferError::Io(err.into_error()));
+}
+
+pub(super) fn json_to_csv_cell(value: Option<&serde_json::Value>) -> String {
+  return match value {
+    None | Some(serde_json::Value::Null) => String::new(),
+    Some(serde_json::Value::String(s)) => s.clone(),
+    // Numbers, booleans and nested JSON.
+    Some(value) => value.to_string(),
+  };
+}
+
+fn csv_cell_to_json(cell: &str) -> serde_json::Value {
+  if cell.is_empty() {
+    return serde_json::Value::Null;
+  }
+  // NOTE: Strings get converted to the column's type when building the insert params.
+  return serde_json::Value::String(cell.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::app_state::test_state;
+
+  #[tokio::test]
+  async fn test_export_import_roundtrip() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE data (
+            id      BLOB PRIMARY KEY NOT NULL CHECK(is_uuid_v7(id)) DEFAULT (uuid_v7()),
+            name    TEXT,
+            value   REAL
+          ) STRICT;
+
+          WITH RECURSIVE seq(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM seq WHERE x < 10000)
+          INSERT INTO data (name, value) SELECT 'name' || x, x / 2.0 FROM seq;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.table_metadata().invalidate_all().await.unwrap();
+    let metadata = state.table_metadata().get("data").unwrap();
+
+    let count_rows = || async {
+      return conn
+        .query_row("SELECT COUNT(*) FROM data", ())
+        .await
+        .unwrap()
+        .unwrap()
+        .get::<i64>(0)
+        .unwrap();
+    };
+
+    for format in [DataFormat::NdJson, DataFormat::Json, DataFormat::Csv] {
+      let mut buffer: Vec<u8> = vec![];
+      let exported = export_table(conn, &metadata, format, None, &mut buffer)
+        .await
+        .unwrap();
+      assert_eq!(exported, 10000);
+
+      conn.execute("DELETE FROM data", ()).await.unwrap();
+      assert_eq!(count_rows().await, 0);
+
+      let imported = import_table(conn, &metadata, format, buffer.as_slice(), 1000)
+        .await
+        .unwrap();
+      assert_eq!(imported, 10000, "{format:?}");
+      assert_eq!(count_rows().await, 10000);
+    }
+
+    let mut buffer: Vec<u8> = vec![];
+    let exported = export_table(
+      conn,
+      &metadata,
+      DataFormat::NdJson,
+      Some("value < 10"),
+      &mut buffer,
+    )
+    .await
+    .unwrap();
+    assert_eq!(exported, 19);
+  }
+}
@@ -0,0 +1,396 @@
+use axum::extract::{Json, Multipart, Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::records::create_record::autofill_missing_user_id_columns;
+use crate::records::json_to_sql::{InsertQueryBuilder, JsonRow, LazyParams, Params, ParamsError};
+use crate::records::quota;
+use crate::records::{Permission, RecordApi, RecordError};
+use crate::table_metadata::TableMetadata;
+
+/// Number of rows inserted per transaction.
+const IMPORT_BATCH_SIZE: usize = 1000;
+/// Maximum number of errors reported back. Further failures are only counted.
+const MAX_IMPORT_ERRORS: usize = 100;
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ImportRowError {
+  /// 1-based index of the failed data row not counting the header. Zero refers to the header.
+  pub row: usize,
+  pub message: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct ImportResponse {
+  /// Number of successfully inserted records.
+  pub inserted: usize,
+  /// Number of rows that could not be inserted.
+  pub failed: usize,
+  pub errors: Vec<ImportRowError>,
+}
+
+impl ImportResponse {
+  fn fail(&mut self, row: usize, message: String) {
+    self.failed += 1;
+    if self.errors.len() < MAX_IMPORT_ERRORS {
+      self.errors.push(ImportRowError { row, message });
+    }
+  }
+}
+
+/// Bulk-create records from a CSV file, whose first row contains the column names.
+///
+/// Empty cells are omitted, i.e. the respective columns fall back to their default values.
+#[utoipa::path(
+  post,
+  path = "/:name/import",
+  request_body(content = String, description = "Multipart form with a CSV 'file' field", content_type = "multipart/form-data"),
+  responses(
+    (status = 200, description = "All rows were inserted.", body = ImportResponse),
+    (status = 207, description = "Some rows failed to insert.", body = ImportResponse),
+  )
+)]
+pub async fn import_csv_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  user: Option<User>,
+  mut multipart: Multipart,
+) -> Result<Response, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  let table_metadata = api
+    .table_metadata()
+    .ok_or_else(|| RecordError::ApiRequiresTable)?;
+
+  // Fail early rather than for every row if the user cannot create records at all.
+  api.check_table_level_access(Permission::Create, user.as_ref())?;
+
+  let mut data: Option<Vec<u8>> = None;
+  while let Some(mut field) = multipart
+    .next_field()
+    .await
+    .map_err(|_| RecordError::BadRequest("Invalid multipart"))?
+  {
+    if field.name() != Some("file") {
+      continue;
+    }
+
+    let mut buffer: Vec<u8> = vec![];
+    while let Some(chunk) = field
+      .chunk()
+      .await
+      .map_err(|_| RecordError::BadRequest("Invalid multipart"))?
+    {
+      buffer.extend_from_slice(&chunk);
+    }
+    data = Some(buffer);
+  }
+  let Some(data) = data else {
+    return Err(RecordError::BadRequest("Missing 'file' field"));
+  };
+
+  let mut reader = csv::Reader::from_reader(data.as_slice());
+  let headers = reader
+    .headers()
+    .map_err(|_| RecordError::BadRequest("Invalid CSV header"))?
+    .clone();
+
+  let mut response = ImportResponse::default();
+  for name in headers.iter() {
+    if table_metadata.column_by_name(name).is_none() {
+      response.errors.push(ImportRowError {
+        row: 0,
+        message: format!("Unknown column '{name}'"),
+      });
+    }
+  }
+  if !response.errors.is_empty() {
+    return Ok((StatusCode::BAD_REQUEST, Json(response)).into_response());
+  }
+
+  // Reject imports exceeding the API's quota as a whole rather than partially importing them.
+  let num_rows = csv::Reader::from_reader(data.as_slice()).records().count();
+  quota::check_bulk_create_quota(&state, &api, user.as_ref(), num_rows as u64).await?;
+
+  let mut batch: Vec<(usize, JsonRow, Params)> = Vec::with_capacity(IMPORT_BATCH_SIZE);
+  for (index, record) in reader.records().enumerate() {
+    let row = index + 1;
+    let record = match record {
+      Ok(record) => record,
+      Err(err) => {
+        response.fail(row, err.to_string());
+        continue;
+      }
+    };
+
+    // NOTE: Strings get converted to the column's type when building the insert params.
+    let json_row: JsonRow = std::iter::zip(headers.iter(), record.iter())
+      .filter(|(_name, cell)| !cell.is_empty())
+      .map(|(name, cell)| {
+        (
+          name.to_string(),
+          serde_json::Value::String(cell.to_string()),
+        )
+      })
+      .collect();
+
+    let mut lazy_params = LazyParams::new(table_metadata, json_row.clone(), None);
+    if let Err(err) = api
+      .check_record_level_access(
+        Permission::Create,
+        None,
+        Some(&mut lazy_params),
+        user.as_ref(),
+      )
+      .await
+    {
+      response.fail(row, err.to_string());
+      continue;
+    }
+
+    let mut params = match lazy_params.consume() {
+      Ok(params) => params,
+      Err(err) => {
+        response.fail(row, err.to_string());
+        continue;
+      }
+    };
+    if api.insert_autofill_missing_user_id_columns() {
+      autofill_missing_user_id_columns(table_metadata, &mut params, user.as_ref());
+    }
+
+    batch.push((row, json_row, params));
+    if batch.len() >= IMPORT_BATCH_SIZE {
+      insert_batch(
+        &state,
+        &api,
+        table_metadata,
+        user.as_ref(),
+        std::mem::take(&mut batch),
+        &mut response,
+      )
+      .await;
+    }
+  }
+
+  if !batch.is_empty() {
+    insert_batch(
+      &state,
+      &api,
+      table_metadata,
+      user.as_ref(),
+      batch,
+      &mut response,
+    )
+    .await;
+  }
+
+  quota::records_created(&state, &api, user.as_ref(), response.inserted as u64);
+
+  // NOTE: Imported records aren't attributed to the user in the audit trail, since bulk
+  // insertions don't return the ids of the created records.
+  let status = if response.failed == 0 {
+    StatusCode::OK
+  } else {
+    StatusCode::MULTI_STATUS
+  };
+
+  return Ok((status, Json(response)).into_response());
+}
+
+/// Inserts the batch in a single transaction. If that fails, rows are retried individually to
+/// pinpoint the failing ones.
+async fn insert_batch(
+  state: &AppState,
+  api: &RecordApi,
+  table_metadata: &TableMetadata,
+  user: Option<&User>,
+  batch: Vec<(usize, JsonRow, Params)>,
+  response: &mut ImportResponse,
+) {
+  let (rows, params): (Vec<_>, Vec<_>) = batch
+    .into_iter()
+    .map(|(row, json_row, params)| ((row, json_row), params))
+    .unzip();
+
+  if let Ok(count) = InsertQueryBuilder::run_bulk(state.conn(), params).await {
+    response.inserted += count;
+    return;
+  }
+
+  let build_params = |json_row: JsonRow| -> Result<Params, ParamsError> {
+    let mut params = Params::from(table_metadata, json_row, None)?;
+    if api.insert_autofill_missing_user_id_columns() {
+      autofill_missing_user_id_columns(table_metadata, &mut params, user);
+    }
+    return Ok(params);
+  };
+
+  for (row, json_row) in rows {
+    let result = match build_params(json_row) {
+      Ok(params) => InsertQueryBuilder::run_bulk(state.conn(), vec![params])
+        .await
+        .map_err(|err| err.to_string()),
+      Err(err) => Err(err.to_string()),
+    };
+
+    match result {
+      Ok(count) => response.inserted += count,
+      Err(message) => response.fail(row, message),
+    };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::extract::{FromRequest, Request};
+  use axum::http::header;
+
+  use super::*;
+  use crate::app_state::*;
+  use crate::config::proto::{PermissionFlag, RecordApiQuota};
+  use crate::records::test_utils::*;
+  use crate::records::*;
+  use crate::test::unpack_json_response;
+
+  async fn csv_multipart(csv: String) -> Multipart {
+    const BOUNDARY: &str = "import_boundary";
+    let body = format!(
+      "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"data.csv\"\r\nContent-Type: text/csv\r\n\r\n{csv}\r\n--{BOUNDARY}--\r\n"
+    );
+    let request = Request::builder()
+      .header(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={BOUNDARY}"),
+      )
+      .body(Body::from(body))
+      .unwrap();
+    return Multipart::from_request(request, &()).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_record_api_import_csv() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE product (
+            id           INTEGER PRIMARY KEY,
+            name         TEXT NOT NULL,
+            price        INTEGER NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "products_api",
+      "product",
+      Acls {
+        world: vec![PermissionFlag::Create],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let import = |csv: String| {
+      let state = state.clone();
+      async move {
+        return import_csv_handler(
+          State(state),
+          Path("products_api".to_string()),
+          None,
+          csv_multipart(csv).await,
+        )
+        .await;
+      }
+    };
+    let count = || async {
+      return conn
+        .query_row("SELECT COUNT(*) FROM product", ())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no rows"))?
+        .get::<i64>(0)
+        .map_err(anyhow::Error::from);
+    };
+
+    let csv = std::iter::once("name,price".to_string())
+      .chain((0..500).map(|i| format!("product{i},{i}")))
+      .collect::<Vec<_>>()
+      .join("\n");
+    let response = import(csv).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response: ImportResponse = unpack_json_response(response).await?;
+    assert_eq!(response.inserted, 500);
+    assert_eq!(response.failed, 0);
+    assert_eq!(count().await?, 500);
+
+    // Rows violating constraints are reported, all others get inserted.
+    let response = import("name,price\nvalid,1\nmissing_price,\nalso_valid,2".to_string()).await?;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let response: ImportResponse = unpack_json_response(response).await?;
+    assert_eq!(response.inserted, 2);
+    assert_eq!(response.failed, 1);
+    assert_eq!(response.errors[0].row, 2);
+    assert_eq!(count().await?, 502);
+
+    // Unknown columns are rejected up-front.
+    let response = import("name,cost\nproduct,1".to_string()).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response: ImportResponse = unpack_json_response(response).await?;
+    assert_eq!(response.errors[0].row, 0);
+    assert_eq!(count().await?, 502);
+
+    // Imports exceeding the quota are rejected as a whole.
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().quota = Some(RecordApiQuota {
+      max_records_per_user: None,
+      max_total_records: Some(504),
+    });
+    state.validate_and_update_config(config, None).await?;
+
+    let err = import(
+      "name,price
+a,1
+b,2
+c,3"
+        .to_string(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(count().await?, 502);
+
+    let response = import(
+      "name,price
+a,1
+b,2"
+        .to_string(),
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(count().await?, 504);
+
+    let err = import(
+      "name,price
+c,3"
+        .to_string(),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::TOO_MANY_REQUESTS);
+
+    return Ok(());
+  }
+}
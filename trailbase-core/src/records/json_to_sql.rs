@@ -9,6 +9,8 @@ use trailbase_sqlite::schema::{FileUpload, FileUploadInput, FileUploads};
 use trailbase_sqlite::{NamedParams, Value};
 
 use crate::config::proto::ConflictResolutionStrategy;
+use crate::metrics::time_query;
+use crate::records::audit::AuditAttribution;
 use crate::records::files::delete_files_in_row;
 use crate::schema::{Column, ColumnDataType};
 use crate::table_metadata::{self, ColumnMetadata, JsonColumnMetadata, TableMetadata};
@@ -76,6 +78,8 @@ pub enum QueryError {
   File(Arc<crate::records::files::FileError>),
   #[error("Not found")]
   NotFound,
+  #[error("Precondition failed")]
+  PreconditionFailed,
 }
 
 impl From<serde_json::Error> for QueryError {
@@ -86,6 +90,14 @@ impl From<serde_json::Error> for QueryError {
 
 impl From<trailbase_sqlite::Error> for QueryError {
   fn from(err: trailbase_sqlite::Error) -> Self {
+    if let trailbase_sqlite::Error::Other(ref err) = err {
+      if err.is::<PreconditionFailedError>() {
+        return Self::PreconditionFailed;
+      }
+      if err.is::<RecordNotFoundError>() {
+        return Self::NotFound;
+      }
+    }
     return Self::TokioRusqlite(err.into());
   }
 }
@@ -110,6 +122,100 @@ impl From<crate::records::files::FileError> for QueryError {
 
 type FileMetadataContents = Vec<(FileUpload, Vec<u8>)>;
 
+/// Precondition on a record's current contents, e.g. matching an If-Match ETag.
+pub(crate) type Precondition = Box<dyn FnOnce(&trailbase_sqlite::Row) -> bool + Send>;
+
+/// Checks and statements executed within the same transaction as a write, i.e. atomically.
+#[derive(Default)]
+pub(crate) struct WriteHooks {
+  /// Checked against the record right before writing. The write fails with
+  /// [QueryError::PreconditionFailed] if it doesn't hold or the record doesn't exist.
+  pub precondition: Option<Precondition>,
+  /// Attributes audit trail entries written by the write to the user making the change.
+  pub audit: Option<AuditAttribution>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Precondition failed")]
+struct PreconditionFailedError;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Record not found")]
+struct RecordNotFoundError;
+
+/// Fails with [QueryError::NotFound] if the record doesn't exist or was soft-deleted, i.e. its
+/// `soft_delete_column` is set.
+fn check_not_soft_deleted(
+  tx: &rusqlite::Transaction<'_>,
+  table_name: &str,
+  pk_column: &str,
+  pk_value: &Value,
+  soft_delete_column: Option<&str>,
+) -> Result<(), trailbase_sqlite::Error> {
+  let Some(soft_delete_column) = soft_delete_column else {
+    return Ok(());
+  };
+
+  let exists: bool = tx.query_row(
+    &format!(
+      r#"SELECT EXISTS(SELECT 1 FROM "{table_name}" WHERE "{pk_column}" = $1 AND "{soft_delete_column}" IS NULL)"#
+    ),
+    [pk_value],
+    |row| row.get(0),
+  )?;
+  if !exists {
+    return Err(trailbase_sqlite::Error::Other(Box::new(
+      RecordNotFoundError,
+    )));
+  }
+  return Ok(());
+}
+
+fn check_precondition(
+  tx: &rusqlite::Transaction<'_>,
+  table_name: &str,
+  pk_column: &str,
+  pk_value: &Value,
+  precondition: Option<Precondition>,
+) -> Result<(), trailbase_sqlite::Error> {
+  let Some(precondition) = precondition else {
+    return Ok(());
+  };
+
+  let mut stmt = tx.prepare(&format!(
+    r#"SELECT * FROM "{table_name}" WHERE "{pk_column}" = $1"#
+  ))?;
+  let mut rows = stmt.query([pk_value])?;
+
+  let holds = match rows.next()? {
+    Some(row) => precondition(&trailbase_sqlite::Row::from_row(row, None)?),
+    None => false,
+  };
+  if !holds {
+    return Err(trailbase_sqlite::Error::Other(Box::new(
+      PreconditionFailedError,
+    )));
+  }
+  return Ok(());
+}
+
+/// Runs the given write, attributing any audit trail entries it produces.
+fn with_audit_attribution<T>(
+  tx: &rusqlite::Transaction<'_>,
+  audit: Option<&AuditAttribution>,
+  write: impl FnOnce() -> Result<T, trailbase_sqlite::Error>,
+) -> Result<T, trailbase_sqlite::Error> {
+  let Some(audit) = audit else {
+    return write();
+  };
+
+  let watermark = audit.watermark(tx)?;
+  let result = write()?;
+  audit.attribute(tx, watermark)?;
+
+  return Ok(result);
+}
+
 // JSON type use to represent rows. Note that we use a map to represent rows sparsely.
 pub type JsonRow = serde_json::Map<String, serde_json::Value>;
 
@@ -329,19 +435,30 @@ impl Params {
 pub(crate) struct SelectQueryBuilder;
 
 impl SelectQueryBuilder {
+  /// Reads a single record. `groups` are exposed to `current_user_in_group()`, e.g. for views.
   pub(crate) async fn run(
     state: &AppState,
     table_name: &str,
     pk_column: &str,
     pk_value: Value,
+    groups: Vec<String>,
   ) -> Result<Option<trailbase_sqlite::Row>, trailbase_sqlite::Error> {
-    return state
-      .conn()
-      .query_row(
-        &format!(r#"SELECT * FROM "{table_name}" WHERE "{pk_column}" = $1"#),
-        [pk_value],
-      )
-      .await;
+    let query = format!(r#"SELECT * FROM "{table_name}" WHERE "{pk_column}" = $1"#);
+
+    return time_query(
+      "select",
+      state.conn().call(move |conn| {
+        return trailbase_extension::with_current_user_groups(&groups, || {
+          let mut stmt = conn.prepare(&query)?;
+          let mut rows = stmt.query([pk_value])?;
+          if let Some(row) = rows.next()? {
+            return Ok(Some(trailbase_sqlite::Row::from_row(row, None)?));
+          }
+          return Ok(None);
+        });
+      }),
+    )
+    .await;
   }
 }
 
@@ -421,6 +538,7 @@ impl InsertQueryBuilder {
     params: Params,
     conflict_resolution: Option<ConflictResolutionStrategy>,
     return_column_name: Option<&str>,
+    audit: Option<AuditAttribution>,
   ) -> Result<trailbase_sqlite::Row, QueryError> {
     let (query, named_params, mut files) =
       Self::build_insert_query(params, conflict_resolution, return_column_name)?;
@@ -434,7 +552,27 @@ impl InsertQueryBuilder {
       }
     }
 
-    let row = match state.conn().query_row(&query, named_params).await {
+    let insert = state.conn().call(move |conn| {
+      let tx = conn.transaction()?;
+
+      let row = with_audit_attribution(&tx, audit.as_ref(), || {
+        let mut stmt = tx.prepare(&query)?;
+        use trailbase_sqlite::Params;
+        named_params.bind(&mut stmt)?;
+
+        let mut rows = stmt.raw_query();
+        return match rows.next()? {
+          Some(row) => Ok(Some(trailbase_sqlite::Row::from_row(row, None)?)),
+          None => Ok(None),
+        };
+      })?;
+
+      tx.commit()?;
+
+      return Ok(row);
+    });
+
+    let row = match time_query("insert", insert).await {
       Ok(Some(row)) => row,
       Ok(None) => {
         return Err(QueryError::Sql(rusqlite::Error::QueryReturnedNoRows.into()));
@@ -457,6 +595,45 @@ impl InsertQueryBuilder {
     return Ok(row);
   }
 
+  /// Inserts multiple records in a single transaction, i.e. either all or none get inserted.
+  /// Returns the number of inserted records.
+  pub(crate) async fn run_bulk(
+    conn: &trailbase_sqlite::Connection,
+    records: Vec<Params>,
+  ) -> Result<usize, QueryError> {
+    let mut statements: Vec<(String, NamedParams)> = Vec::with_capacity(records.len());
+    for params in records {
+      if !params.files.is_empty() {
+        return Err(QueryError::Precondition("Bulk inserts don't support files"));
+      }
+
+      let (query, named_params, _files) = Self::build_insert_query(params, None, None)?;
+      statements.push((query, named_params));
+    }
+
+    let count = time_query(
+      "insert",
+      conn.call(move |conn| {
+        let tx = conn.transaction()?;
+
+        let count = statements.len();
+        for (query, named_params) in statements {
+          let mut stmt = tx.prepare(&query)?;
+          use trailbase_sqlite::Params;
+          named_params.bind(&mut stmt)?;
+          stmt.raw_execute()?;
+        }
+
+        tx.commit()?;
+
+        return Ok(count);
+      }),
+    )
+    .await?;
+
+    return Ok(count);
+  }
+
   fn build_insert_query(
     params: Params,
     conflict_resolution: Option<ConflictResolutionStrategy>,
@@ -526,16 +703,23 @@ impl InsertQueryBuilder {
 pub(crate) struct UpdateQueryBuilder;
 
 impl UpdateQueryBuilder {
+  /// Updates a single record. With `soft_delete_column`, soft-deleted records are treated as
+  /// missing, i.e. the update fails with [QueryError::NotFound].
   pub(crate) async fn run(
     state: &AppState,
     metadata: &TableMetadata,
     mut params: Params,
     pk_column: &str,
     pk_value: Value,
+    soft_delete_column: Option<&str>,
+    hooks: WriteHooks,
   ) -> Result<(), QueryError> {
     let table_name = metadata.name();
     assert_eq!(params.table_name, *table_name);
-    if params.column_names().is_empty() {
+    if params.column_names().is_empty()
+      && hooks.precondition.is_none()
+      && soft_delete_column.is_none()
+    {
       return Ok(());
     }
 
@@ -556,6 +740,8 @@ impl UpdateQueryBuilder {
       params: Params,
       pk_column: &str,
       pk_value: Value,
+      soft_delete_column: Option<&str>,
+      hooks: WriteHooks,
     ) -> Result<Option<trailbase_sqlite::Row>, QueryError> {
       let setters: String = {
         assert_eq!(params.col_names.len(), params.named_params.len());
@@ -567,10 +753,20 @@ impl UpdateQueryBuilder {
 
       let pk_column = pk_column.to_string();
       let table_name = table_name.to_string();
+      let soft_delete_column = soft_delete_column.map(|col| col.to_string());
       let files_row = conn
         .call(move |conn| {
           let tx = conn.transaction()?;
 
+          check_not_soft_deleted(
+            &tx,
+            &table_name,
+            &pk_column,
+            &pk_value,
+            soft_delete_column.as_deref(),
+          )?;
+          check_precondition(&tx, &table_name, &pk_column, &pk_value, hooks.precondition)?;
+
           // First, fetch updated file column contents so we can delete the files after updating the
           // column.
           let files_row = if params.file_col_names.is_empty() {
@@ -594,14 +790,16 @@ impl UpdateQueryBuilder {
           };
 
           // Update the column.
-          {
-            let mut stmt = tx.prepare(&format!(
-              r#"UPDATE "{table_name}" SET {setters} WHERE "{pk_column}" = :{pk_column}"#
-            ))?;
-            use trailbase_sqlite::Params;
-            params.named_params.bind(&mut stmt)?;
-
-            stmt.raw_execute()?;
+          if !params.column_names().is_empty() {
+            with_audit_attribution(&tx, hooks.audit.as_ref(), || {
+              let mut stmt = tx.prepare(&format!(
+                r#"UPDATE "{table_name}" SET {setters} WHERE "{pk_column}" = :{pk_column}"#
+              ))?;
+              use trailbase_sqlite::Params;
+              params.named_params.bind(&mut stmt)?;
+
+              return Ok(stmt.raw_execute()?);
+            })?;
           }
 
           tx.commit()?;
@@ -613,7 +811,20 @@ impl UpdateQueryBuilder {
       return Ok(files_row);
     }
 
-    let files_row = match row_update(state.conn(), table_name, params, pk_column, pk_value).await {
+    let files_row = match time_query(
+      "update",
+      row_update(
+        state.conn(),
+        table_name,
+        params,
+        pk_column,
+        pk_value,
+        soft_delete_column,
+        hooks,
+      ),
+    )
+    .await
+    {
       Ok(files_row) => files_row,
       Err(err) => {
         if !files.is_empty() {
@@ -638,6 +849,87 @@ impl UpdateQueryBuilder {
 
     return Ok(());
   }
+
+  /// Updates multiple records in a single transaction. Returns for each record whether it was
+  /// updated. With `fail_fast`, a missing record rolls back the entire transaction. With
+  /// `soft_delete_column`, soft-deleted records count as missing.
+  pub(crate) async fn run_bulk(
+    state: &AppState,
+    metadata: &TableMetadata,
+    records: Vec<(Params, Value)>,
+    pk_column: &str,
+    soft_delete_column: Option<&str>,
+    fail_fast: bool,
+    audit: Option<AuditAttribution>,
+  ) -> Result<Vec<bool>, QueryError> {
+    let table_name = metadata.name().to_string();
+    let pk_column = pk_column.to_string();
+    let soft_delete_predicate = soft_delete_column
+      .map(|col| format!(r#" AND "{col}" IS NULL"#))
+      .unwrap_or_default();
+
+    let mut statements: Vec<(String, NamedParams)> = Vec::with_capacity(records.len());
+    for (mut params, pk_value) in records {
+      assert_eq!(params.table_name, *table_name);
+      if !params.files.is_empty() {
+        return Err(QueryError::Precondition("Bulk updates don't support files"));
+      }
+
+      // NOTE: Always setting the pk even for otherwise empty updates lets us detect missing records.
+      params.push_param(pk_column.clone(), pk_value);
+
+      let setters: String = std::iter::zip(&params.col_names, &params.named_params)
+        .map(|(col_name, (placeholder, _value))| format!(r#""{col_name}" = {placeholder}"#))
+        .join(", ");
+
+      statements.push((
+        format!(
+          r#"UPDATE "{table_name}" SET {setters} WHERE "{pk_column}" = :{pk_column}{soft_delete_predicate}"#
+        ),
+        params.named_params,
+      ));
+    }
+
+    let result = state
+      .conn()
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+
+        let updated = with_audit_attribution(&tx, audit.as_ref(), || {
+          let mut updated = Vec::with_capacity(statements.len());
+          for (query, named_params) in statements {
+            let mut stmt = tx.prepare(&query)?;
+            use trailbase_sqlite::Params;
+            named_params.bind(&mut stmt)?;
+
+            let success = match stmt.raw_execute() {
+              Ok(rows_affected) => rows_affected > 0,
+              Err(err) if fail_fast => return Err(err.into()),
+              Err(_) => false,
+            };
+            if !success && fail_fast {
+              // Dropping the transaction rolls back all prior updates.
+              return Err(rusqlite::Error::QueryReturnedNoRows.into());
+            }
+            updated.push(success);
+          }
+          return Ok(updated);
+        })?;
+
+        tx.commit()?;
+
+        return Ok(updated);
+      })
+      .await;
+
+    return match result {
+      Ok(updated) => Ok(updated),
+      Err(trailbase_sqlite::Error::Rusqlite(rusqlite::Error::QueryReturnedNoRows)) => {
+        Err(QueryError::NotFound)
+      }
+      Err(err) => Err(err.into()),
+    };
+  }
 }
 
 pub(crate) struct DeleteQueryBuilder;
@@ -648,17 +940,36 @@ impl DeleteQueryBuilder {
     metadata: &TableMetadata,
     pk_column: &str,
     pk_value: Value,
+    hooks: WriteHooks,
   ) -> Result<(), QueryError> {
-    let table_name = metadata.name();
+    let table_name = metadata.name().to_string();
+    let pk_column = pk_column.to_string();
+
+    let row = time_query(
+      "delete",
+      state.conn().call(move |conn| {
+        let tx = conn.transaction()?;
+
+        check_precondition(&tx, &table_name, &pk_column, &pk_value, hooks.precondition)?;
+
+        let row = with_audit_attribution(&tx, hooks.audit.as_ref(), || {
+          let mut stmt = tx.prepare(&format!(
+            r#"DELETE FROM "{table_name}" WHERE "{pk_column}" = $1 RETURNING *"#
+          ))?;
+          let mut rows = stmt.query([pk_value])?;
+          return match rows.next()? {
+            Some(row) => Ok(Some(trailbase_sqlite::Row::from_row(row, None)?)),
+            None => Ok(None),
+          };
+        })?;
 
-    let row = state
-      .conn()
-      .query_row(
-        &format!(r#"DELETE FROM "{table_name}" WHERE "{pk_column}" = $1 RETURNING *"#),
-        [pk_value],
-      )
-      .await?
-      .ok_or_else(|| QueryError::Sql(rusqlite::Error::QueryReturnedNoRows.into()))?;
+        tx.commit()?;
+
+        return Ok(row);
+      }),
+    )
+    .await?
+    .ok_or_else(|| QueryError::Sql(rusqlite::Error::QueryReturnedNoRows.into()))?;
 
     // Finally, delete files.
     delete_files_in_row(state, metadata, row).await?;
@@ -667,6 +978,53 @@ impl DeleteQueryBuilder {
   }
 }
 
+pub(crate) struct SoftDeleteQueryBuilder;
+
+impl SoftDeleteQueryBuilder {
+  /// Sets or clears the given deletion-marker column. Files are retained in both cases.
+  pub(crate) async fn run(
+    state: &AppState,
+    metadata: &TableMetadata,
+    deleted_column: &str,
+    pk_column: &str,
+    pk_value: Value,
+    deleted: bool,
+    hooks: WriteHooks,
+  ) -> Result<(), QueryError> {
+    let table_name = metadata.name().to_string();
+    let pk_column = pk_column.to_string();
+    let (value, condition) = if deleted {
+      ("UNIXEPOCH()", "IS NULL")
+    } else {
+      ("NULL", "IS NOT NULL")
+    };
+    let query = format!(
+      r#"UPDATE "{table_name}" SET "{deleted_column}" = {value} WHERE "{pk_column}" = $1 AND "{deleted_column}" {condition}"#
+    );
+
+    let rows_affected = state
+      .conn()
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+
+        check_precondition(&tx, &table_name, &pk_column, &pk_value, hooks.precondition)?;
+        let rows_affected = with_audit_attribution(&tx, hooks.audit.as_ref(), || {
+          return Ok(tx.execute(&query, [pk_value])?);
+        })?;
+
+        tx.commit()?;
+
+        return Ok(rows_affected);
+      })
+      .await?;
+    if rows_affected == 0 {
+      return Err(QueryError::NotFound);
+    }
+
+    return Ok(());
+  }
+}
+
 async fn write_file(
   store: &dyn ObjectStore,
   metadata: &FileUpload,
@@ -6,13 +6,16 @@ use indoc::formatdoc;
 use itertools::Itertools;
 use serde::Serialize;
 use std::borrow::Cow;
-use trailbase_sqlite::Value;
+use trailbase_sqlite::{Params as _, Rows, Value};
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::listing::{
-  build_filter_where_clause, limit_or_default, parse_query, Order, QueryParseResult, WhereClause,
+  build_filter_where_clause, has_full_text_filter, limit_or_default, lookup_full_text_index,
+  parse_query, Order, QueryParseResult, WhereClause, WhereClauseError,
 };
+use crate::metrics::time_query;
+use crate::records::record_api::{access_rule_groups, user_claims_value, SOFT_DELETE_COLUMN};
 use crate::records::sql_to_json::rows_to_json;
 use crate::records::{Permission, RecordError};
 use crate::util::uuid_to_b64;
@@ -61,17 +64,33 @@ pub async fn list_records_handler(
     limit,
     order,
     count,
+    search,
+    include_score,
+    include_deleted,
     ..
   } = parse_query(raw_url_query.as_deref()).map_err(|_err| {
     return RecordError::BadRequest("Invalid query");
   })?;
 
+  // Only look up the FTS index if needed to filter.
+  let full_text_index = if has_full_text_filter(filter_params.as_ref()) {
+    lookup_full_text_index(state.conn(), api.table_name()).await?
+  } else {
+    None
+  };
+
   // Where clause contains column filters and cursor depending on what's present.
   let WhereClause {
     mut clause,
     mut params,
-  } = build_filter_where_clause(metadata, filter_params)
-    .map_err(|_err| RecordError::BadRequest("Invalid filter params"))?;
+  } = build_filter_where_clause(metadata, filter_params, full_text_index.as_ref()).map_err(
+    |err| match err {
+      WhereClauseError::FullTextNotSupported(_) => {
+        RecordError::BadRequest("Full-text search not supported")
+      }
+      _ => RecordError::BadRequest("Invalid filter params"),
+    },
+  )?;
 
   // User properties
   params.extend_from_slice(&[
@@ -81,7 +100,13 @@ pub async fn list_records_handler(
     ),
     (
       Cow::Borrowed(":__user_id"),
-      user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+      user
+        .as_ref()
+        .map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
+    ),
+    (
+      Cow::Borrowed(":__user_claims"),
+      user_claims_value(user.as_ref()),
     ),
   ]);
 
@@ -94,6 +119,39 @@ pub async fn list_records_handler(
     clause = format!("({read_access}) AND ({clause})");
   }
 
+  if api.soft_delete() {
+    if include_deleted.unwrap_or(false) {
+      // Only users who could have deleted records may see them.
+      api.check_table_level_access(Permission::Delete, user.as_ref())?;
+    } else {
+      clause = format!("({clause}) AND _ROW_.{SOFT_DELETE_COLUMN} IS NULL");
+    }
+  }
+
+  // Full-text search relies on a "<table>_fts" FTS5 virtual table indexing the table's rows.
+  let fts_table_name = match search {
+    Some(search) => {
+      let fts_table_name = format!("{}_fts", api.table_name());
+      if state.table_metadata().get(&fts_table_name).is_none() {
+        return Err(RecordError::BadRequest("Full-text search not supported"));
+      }
+
+      params.push((Cow::Borrowed(":__search"), Value::Text(search)));
+      clause = format!(
+        r#"({clause}) AND _ROW_.rowid IN (SELECT rowid FROM "{fts_table_name}" WHERE "{fts_table_name}" MATCH :__search)"#
+      );
+      Some(fts_table_name)
+    }
+    None => None,
+  };
+
+  let score_column = match (&fts_table_name, include_score.unwrap_or(false)) {
+    (Some(fts_table_name), true) => format!(
+      r#", (SELECT bm25("{fts_table_name}") FROM "{fts_table_name}" WHERE "{fts_table_name}" MATCH :__search AND rowid = _ROW_.rowid) AS __score"#
+    ),
+    _ => "".to_string(),
+  };
+
   let clause_with_cursor = match cursor {
     Some(cursor) => {
       params.push((Cow::Borrowed(":cursor"), Value::Blob(cursor.to_vec())));
@@ -124,16 +182,16 @@ pub async fn list_records_handler(
         SELECT COUNT(*) AS _value_
         FROM
           '{table_name}' as _ROW_,
-          (SELECT :__user_id AS id) AS _USER_
+          (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_
         WHERE
           {clause}
       )
 
-      SELECT _ROW_.*, total_count._value_
+      SELECT _ROW_.*{score_column}, total_count._value_
       FROM
         total_count,
         '{table_name}' as _ROW_,
-        (SELECT :__user_id AS id) AS _USER_
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_
       WHERE
         {clause_with_cursor}
       ORDER BY
@@ -145,10 +203,10 @@ pub async fn list_records_handler(
   } else {
     formatdoc!(
       r#"
-      SELECT _ROW_.*
+      SELECT _ROW_.*{score_column}
       FROM
         '{table_name}' as _ROW_,
-        (SELECT :__user_id AS id) AS _USER_
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_
       WHERE
         {clause_with_cursor}
       ORDER BY
@@ -159,7 +217,19 @@ pub async fn list_records_handler(
     )
   };
 
-  let rows = state.conn().query(&query, params).await?;
+  // Access rules may check group membership using `current_user_in_group()`.
+  let groups = access_rule_groups(user.as_ref());
+  let rows = time_query(
+    "list",
+    state.conn().call(move |conn| {
+      return trailbase_extension::with_current_user_groups(&groups, || {
+        let mut stmt = conn.prepare(&query)?;
+        params.bind(&mut stmt)?;
+        return Ok(Rows::from_rows(stmt.raw_query())?);
+      });
+    }),
+  )
+  .await?;
   let Some(last_row) = rows.last() else {
     // Rows are empty:
     return Ok(Json(ListResponse {
@@ -191,10 +261,33 @@ pub async fn list_records_handler(
     None
   };
 
-  let records = rows_to_json(metadata, rows, |col_name| !col_name.starts_with("_"))
+  // NOTE: The score is extracted separately, since "_"-prefixed columns are omitted below.
+  let scores: Option<Vec<serde_json::Value>> = rows
+    .column_names()
+    .iter()
+    .position(|name| *name == "__score")
+    .map(|score_index| {
+      rows
+        .iter()
+        .map(|row| match row[score_index] {
+          rusqlite::types::Value::Real(score) => serde_json::json!(score),
+          _ => serde_json::Value::Null,
+        })
+        .collect()
+    });
+
+  let mut records = rows_to_json(metadata, rows, |col_name| !col_name.starts_with("_"))
     .await
     .map_err(|err| RecordError::Internal(err.into()))?;
 
+  if let Some(scores) = scores {
+    for (record, score) in records.iter_mut().zip(scores) {
+      if let serde_json::Value::Object(ref mut record) = record {
+        record.insert("__score".to_string(), score);
+      }
+    }
+  }
+
   return Ok(Json(ListResponse {
     cursor,
     records,
@@ -491,6 +584,190 @@ mod tests {
     }
   }
 
+  #[tokio::test]
+  async fn test_record_api_list_row_level_rules() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await.unwrap();
+    let room = add_room(conn, "room0").await.unwrap();
+    let password = "Secret!1!!";
+
+    // Rules are compiled against the schema, i.e. unknown columns are rejected up-front.
+    assert!(add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls::default(),
+      AccessRules {
+        read: Some("missing_column = _USER_ID_".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .is_err());
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![PermissionFlag::Read, PermissionFlag::Delete],
+        ..Default::default()
+      },
+      AccessRules {
+        read: Some("_owner = _USER_ID_".to_string()),
+        write: Some("_owner = _USER_ID_".to_string()),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    let mut users = vec![];
+    for email in ["owner_x@test.com", "owner_y@test.com"] {
+      let user_id = create_user_for_test(&state, email, password)
+        .await
+        .unwrap()
+        .into_bytes();
+      let message_id = send_message(conn, user_id, room, email).await.unwrap();
+      let tokens = login_with_password(&state, email, password).await.unwrap();
+      users.push((email, message_id, tokens.auth_token));
+    }
+
+    let api = state.lookup_record_api("messages_api").unwrap();
+    for (email, message_id, auth_token) in &users {
+      // Each owner only sees their own messages.
+      let records = list_records(&state, Some(auth_token), None)
+        .await
+        .unwrap()
+        .records;
+      assert_eq!(records.len(), 1);
+      assert_eq!(records[0]["data"], *email);
+
+      // ...and can only delete their own messages.
+      let user = User::from_auth_token(&state, auth_token).unwrap();
+      for (_, other_message_id, _) in &users {
+        let result = api
+          .check_record_level_access(
+            Permission::Delete,
+            Some(&Value::Blob(other_message_id.to_vec())),
+            None,
+            Some(&user),
+          )
+          .await;
+        assert_eq!(result.is_ok(), other_message_id == message_id);
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_record_api_list_full_text_search() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE article (
+            id           INTEGER PRIMARY KEY,
+            title        TEXT NOT NULL,
+            body         TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+
+    for statement in crate::admin::table::create_fts_index_statements(
+      "article",
+      &["title".to_string(), "body".to_string()],
+    ) {
+      conn.execute(&statement, ()).await.unwrap();
+    }
+    state.table_metadata().invalidate_all().await.unwrap();
+
+    add_record_api(
+      &state,
+      "articles_api",
+      "article",
+      Acls {
+        world: vec![PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    for (title, body) in [
+      ("Rust", "Fearless concurrency with the borrow checker."),
+      (
+        "SQLite",
+        "A small and fast embedded database with full-text search.",
+      ),
+      ("Search", "Full-text search in Rust on top of SQLite."),
+    ] {
+      conn
+        .execute(
+          "INSERT INTO article (title, body) VALUES ($1, $2)",
+          trailbase_sqlite::params!(title.to_string(), body.to_string()),
+        )
+        .await
+        .unwrap();
+    }
+
+    let list = |query: &str| {
+      list_records_handler(
+        State(state.clone()),
+        Path("articles_api".to_string()),
+        RawQuery(Some(query.to_string())),
+        None,
+      )
+    };
+
+    let response = list("search=rust").await.unwrap().0;
+    let titles: Vec<_> = response
+      .records
+      .iter()
+      .map(|r| r["title"].as_str().unwrap())
+      .collect();
+    assert_eq!(titles, vec!["Search", "Rust"]);
+    assert!(response.records[0].get("__score").is_none());
+
+    // Search merges with other filters.
+    let response = list("search=rust&title=Rust").await.unwrap().0;
+    assert_eq!(response.records.len(), 1);
+
+    let response = list("search=sqlite&include_score=true").await.unwrap().0;
+    assert_eq!(response.records.len(), 2);
+    for record in &response.records {
+      assert!(record["__score"].is_f64(), "{record:?}");
+    }
+
+    // Column-scoped full-text filters.
+    let response = list("title[fulltext]=sqlite").await.unwrap().0;
+    assert_eq!(response.records.len(), 1);
+    assert_eq!(response.records[0]["title"], "SQLite");
+
+    let response = list("body[fulltext]=rust").await.unwrap().0;
+    assert_eq!(response.records.len(), 1);
+    assert_eq!(response.records[0]["title"], "Search");
+
+    assert!(matches!(
+      list("id[fulltext]=1").await,
+      Err(RecordError::BadRequest(_))
+    ));
+
+    // Changes are reflected in the index.
+    conn
+      .execute("DELETE FROM article WHERE title = 'Rust'", ())
+      .await
+      .unwrap();
+    let response = list("search=rust").await.unwrap().0;
+    assert_eq!(response.records.len(), 1);
+  }
+
   async fn list_records(
     state: &AppState,
     auth_token: Option<&str>,
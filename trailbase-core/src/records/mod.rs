@@ -1,27 +1,38 @@
 use axum::{
+  http::Method,
+  middleware,
   routing::{delete, get, patch, post},
   Router,
 };
 use utoipa::OpenApi;
 
+mod audit;
 pub(crate) mod create_record;
 pub(crate) mod delete_record;
+mod encoding;
 mod error;
+mod etag;
 pub(crate) mod files;
+mod import_export;
+mod import_records;
 mod json_schema;
 pub mod json_to_sql;
 mod list_records;
+pub(crate) mod quota;
 pub(crate) mod read_record;
 mod record_api;
 pub mod sql_to_json;
 pub(crate) mod subscribe;
 pub mod test_utils;
 mod update_record;
+pub(crate) mod upload;
 mod validate;
 
+pub(crate) use audit::{install_audit_trails, record_json_object};
 pub(crate) use error::RecordError;
+pub use import_export::{export_table, import_table, DataFormat, TransferError};
 pub use record_api::RecordApi;
-pub(crate) use validate::validate_record_api_config;
+pub(crate) use validate::{validate_record_api_access_rules, validate_record_api_config};
 
 use crate::config::proto::{PermissionFlag, RecordApiConfig};
 use crate::config::ConfigError;
@@ -36,19 +47,74 @@ use crate::AppState;
     read_record::get_uploaded_files_from_record_handler,
     list_records::list_records_handler,
     create_record::create_record_handler,
+    import_records::import_csv_handler,
     update_record::update_record_handler,
+    update_record::update_bulk_handler,
     delete_record::delete_record_handler,
+    delete_record::restore_record_handler,
+    audit::history_handler,
     json_schema::json_schema_handler,
+    upload::init_upload_handler,
+    upload::upload_chunk_handler,
+    upload::upload_progress_handler,
+    upload::complete_upload_handler,
   ),
-  components(schemas(create_record::CreateRecordResponse))
+  components(schemas(
+    create_record::CreateRecordResponse,
+    update_record::UpdateBulkResponse,
+    import_records::ImportResponse,
+    import_records::ImportRowError,
+    audit::HistoryResponse,
+    upload::InitUploadRequest,
+    upload::InitUploadResponse,
+    upload::UploadProgress,
+    upload::UploadStatus
+  ))
 )]
 pub(super) struct RecordOpenApi;
 
+/// Returns the record API addressed by the given request path, if any.
+pub(crate) fn record_api_from_path(state: &AppState, path: &str) -> Option<RecordApi> {
+  let name = path
+    .strip_prefix('/')?
+    .strip_prefix(RECORD_API_PATH)?
+    .strip_prefix('/')?
+    .split('/')
+    .next()?;
+  return state.lookup_record_api(name);
+}
+
+/// Returns the request body limit configured for the record API, if the request addresses one of
+/// its create or update endpoints.
+pub(crate) fn record_api_body_limit(
+  state: &AppState,
+  method: &Method,
+  path: &str,
+) -> Option<usize> {
+  let mut segments = path
+    .strip_prefix('/')?
+    .strip_prefix(RECORD_API_PATH)?
+    .strip_prefix('/')?
+    .split('/');
+  let api = state.lookup_record_api(segments.next()?)?;
+  let limit = api.max_body_size_bytes()?;
+
+  let is_create_or_update = match (segments.next(), segments.next()) {
+    // Create and bulk update.
+    (None, _) => *method == Method::POST || *method == Method::PATCH,
+    // Update.
+    (Some(_record), None) => *method == Method::PATCH,
+    _ => false,
+  };
+  return is_create_or_update.then_some(limit);
+}
+
 pub(crate) fn router() -> Router<AppState> {
   return Router::new()
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}"),
-      get(read_record::read_record_handler),
+      get(read_record::read_record_handler)
+        .route_layer(middleware::from_fn(encoding::response_encoding_middleware)),
     )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}"),
@@ -62,9 +128,22 @@ pub(crate) fn router() -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}"),
       delete(delete_record::delete_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/restore"),
+      post(delete_record::restore_record_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/history"),
+      get(audit::history_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}"),
-      get(list_records::list_records_handler),
+      get(list_records::list_records_handler)
+        .route_layer(middleware::from_fn(encoding::response_encoding_middleware)),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}"),
+      patch(update_record::update_bulk_handler),
     )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/file/{{column_name}}"),
@@ -74,6 +153,26 @@ pub(crate) fn router() -> Router<AppState> {
       &format!("/{RECORD_API_PATH}/{{name}}/{{record}}/files/{{column_name}}/{{file_index}}"),
       get(read_record::get_uploaded_files_from_record_handler),
     )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/import"),
+      post(import_records::import_csv_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/upload/init"),
+      post(upload::init_upload_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/upload/{{session_id}}"),
+      patch(upload::upload_chunk_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/upload/{{session_id}}/progress"),
+      get(upload::upload_progress_handler),
+    )
+    .route(
+      &format!("/{RECORD_API_PATH}/{{name}}/upload/{{session_id}}/complete"),
+      post(upload::complete_upload_handler),
+    )
     .route(
       &format!("/{RECORD_API_PATH}/{{name}}/schema"),
       get(json_schema::json_schema_handler),
@@ -110,6 +209,7 @@ pub struct AccessRules {
   pub update: Option<String>,
   pub delete: Option<String>,
   pub schema: Option<String>,
+  pub write: Option<String>,
 }
 
 // NOTE: used in integration test.
@@ -135,6 +235,15 @@ pub async fn add_record_api(
     update_access_rule: access_rules.update,
     delete_access_rule: access_rules.delete,
     schema_access_rule: access_rules.schema,
+    write_access_rule: access_rules.write,
+    soft_delete: None,
+    audit_trail: None,
+    cors_allowed_origins: vec![],
+    quota: None,
+    send_email_on_create: None,
+    notify_on_change: None,
+    group_ids: vec![],
+    max_body_size_bytes: None,
   });
 
   return state.validate_and_update_config(config, None).await;
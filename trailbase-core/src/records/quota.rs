@@ -0,0 +1,233 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use trailbase_sqlite::params;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::records::{RecordApi, RecordError};
+
+/// Cached record counts are re-queried after this long. In-between, counts are only updated for
+/// records created through the API, thus quotas may briefly lag behind deletions.
+const QUOTA_COUNT_TTL: Duration = Duration::from_secs(5);
+
+/// Per API, per user (`None` for the total) record count.
+type CountKey = (String, Option<Uuid>);
+
+/// Cached record counts used to enforce record API quotas without counting on every insert.
+#[derive(Default)]
+pub(crate) struct QuotaCounts {
+  counts: Mutex<HashMap<CountKey, (Instant, u64)>>,
+}
+
+impl QuotaCounts {
+  fn get(&self, key: &CountKey) -> Option<u64> {
+    return self
+      .counts
+      .lock()
+      .get(key)
+      .and_then(|(at, count)| (at.elapsed() < QUOTA_COUNT_TTL).then_some(*count));
+  }
+
+  fn insert(&self, key: CountKey, count: u64) {
+    let mut counts = self.counts.lock();
+    counts.retain(|_, (at, _)| at.elapsed() < QUOTA_COUNT_TTL);
+    counts.insert(key, (Instant::now(), count));
+  }
+
+  fn increment(&self, key: &CountKey, n: u64) {
+    if let Some((_, count)) = self.counts.lock().get_mut(key) {
+      *count += n;
+    }
+  }
+}
+
+/// Returns [RecordError::TooManyRequests] if creating another record would exceed the API's
+/// quota. Per-user quotas only apply to authenticated users.
+pub(crate) async fn check_create_quota(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+) -> Result<(), RecordError> {
+  return check_bulk_create_quota(state, api, user, 1).await;
+}
+
+/// Like [check_create_quota] but for creating `n` records at once, e.g. when importing.
+pub(crate) async fn check_bulk_create_quota(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+  n: u64,
+) -> Result<(), RecordError> {
+  let Some(quota) = api.quota() else {
+    return Ok(());
+  };
+
+  if let (Some(max), Some(user)) = (quota.max_records_per_user, user) {
+    let count = cached_count(state, api, Some(user)).await?;
+    if count + n > max {
+      return Err(RecordError::TooManyRequests);
+    }
+  }
+
+  if let Some(max) = quota.max_total_records {
+    let count = cached_count(state, api, None).await?;
+    if count + n > max {
+      return Err(RecordError::TooManyRequests);
+    }
+  }
+
+  return Ok(());
+}
+
+/// Accounts for a record created after passing [check_create_quota].
+pub(crate) fn record_created(state: &AppState, api: &RecordApi, user: Option<&User>) {
+  records_created(state, api, user, 1);
+}
+
+/// Accounts for `n` records created after passing [check_bulk_create_quota].
+pub(crate) fn records_created(state: &AppState, api: &RecordApi, user: Option<&User>, n: u64) {
+  if api.quota().is_none() || n == 0 {
+    return;
+  }
+
+  let counts = state.quota_counts();
+  counts.increment(&(api.api_name().to_string(), None), n);
+  if let Some(user) = user {
+    counts.increment(&(api.api_name().to_string(), Some(user.uuid)), n);
+  }
+}
+
+async fn cached_count(
+  state: &AppState,
+  api: &RecordApi,
+  user: Option<&User>,
+) -> Result<u64, RecordError> {
+  let key: CountKey = (api.api_name().to_string(), user.map(|u| u.uuid));
+  if let Some(count) = state.quota_counts().get(&key) {
+    return Ok(count);
+  }
+
+  let table_name = api.table_name();
+  let row = match user {
+    Some(user) => {
+      let column = api
+        .table_metadata()
+        .and_then(|m| m.user_id_columns.first().map(|i| &m.schema.columns[*i]))
+        .ok_or_else(|| RecordError::Internal("Quota requires user id column".into()))?;
+
+      state
+        .conn()
+        .query_row(
+          &format!(
+            r#"SELECT COUNT(*) FROM "{table_name}" WHERE "{column}" = $1"#,
+            column = column.name
+          ),
+          params!(user.uuid.into_bytes()),
+        )
+        .await?
+    }
+    None => {
+      state
+        .conn()
+        .query_row(&format!(r#"SELECT COUNT(*) FROM "{table_name}""#), ())
+        .await?
+    }
+  };
+
+  let count = row
+    .ok_or_else(|| RecordError::Internal("Missing count".into()))?
+    .get::<i64>(0)
+    .map_err(|err| RecordError::Internal(err.into()))? as u64;
+
+  state.quota_counts().insert(key, count);
+  return Ok(count);
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::{Path, Query, State};
+  use axum::http::StatusCode;
+  use axum::response::IntoResponse;
+
+  use super::*;
+  use crate::admin::user::create_user_for_test;
+  use crate::app_state::test_state;
+  use crate::auth::api::login::login_with_password;
+  use crate::config::proto::{PermissionFlag, RecordApiQuota};
+  use crate::extract::Either;
+  use crate::records::create_record::{create_record_handler, CreateRecordQuery};
+  use crate::records::test_utils::*;
+  use crate::records::{add_record_api, AccessRules, Acls};
+  use crate::util::id_to_b64;
+
+  #[tokio::test]
+  async fn test_max_records_per_user() {
+    let state = test_state(None).await.unwrap();
+    create_chat_message_app_tables(&state).await.unwrap();
+    let room = add_room(state.conn(), "room0").await.unwrap();
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![PermissionFlag::Create],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().quota = Some(RecordApiQuota {
+      max_records_per_user: Some(3),
+      max_total_records: None,
+    });
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let password = "Secret!1!!";
+    let mut users = vec![];
+    for email in ["user_a@test.com", "user_b@test.com"] {
+      let id = create_user_for_test(&state, email, password)
+        .await
+        .unwrap()
+        .into_bytes();
+      let tokens = login_with_password(&state, email, password).await.unwrap();
+      users.push((id, tokens.auth_token));
+    }
+
+    let create = |(user, token): &([u8; 16], String)| {
+      let state = state.clone();
+      let json = serde_json::json!({
+        "_owner": id_to_b64(user),
+        "room": id_to_b64(&room),
+      });
+      let token = token.clone();
+      return async move {
+        create_record_handler(
+          State(state.clone()),
+          Path("messages_api".to_string()),
+          Query(CreateRecordQuery::default()),
+          User::from_auth_token(&state, &token),
+          Either::Json(json_row_from_value(json).unwrap()),
+        )
+        .await
+      };
+    };
+
+    for _ in 0..3 {
+      create(&users[0]).await.unwrap();
+    }
+
+    let err = create(&users[0]).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::TOO_MANY_REQUESTS);
+
+    create(&users[1]).await.unwrap();
+  }
+}
@@ -1,29 +1,43 @@
 use axum::{
-  extract::{Path, State},
+  extract::{Path, Query, State},
+  http::{header, HeaderName},
   response::Response,
   Json,
 };
 
+use serde::Deserialize;
+use utoipa::IntoParams;
+
 use crate::app_state::AppState;
 use crate::auth::user::User;
+use crate::records::etag::record_etag;
 use crate::records::files::read_file_into_response;
 use crate::records::json_to_sql::{GetFileQueryBuilder, GetFilesQueryBuilder, SelectQueryBuilder};
+use crate::records::record_api::{access_rule_groups, SOFT_DELETE_COLUMN};
 use crate::records::sql_to_json::row_to_json;
 use crate::records::{Permission, RecordError};
 
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct ReadRecordQuery {
+  /// Read soft-deleted records. Requires delete permissions.
+  pub include_deleted: Option<bool>,
+}
+
 /// Read record.
 #[utoipa::path(
   get,
   path = "/:name/:record",
+  params(ReadRecordQuery),
   responses(
-    (status = 200, description = "Record contents.", body = serde_json::Value)
+    (status = 200, description = "Record contents. The ETag header can be passed as If-Match on update and delete.", body = serde_json::Value)
   )
 )]
 pub async fn read_record_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
+  Query(query): Query<ReadRecordQuery>,
   user: Option<User>,
-) -> Result<Json<serde_json::Value>, RecordError> {
+) -> Result<([(HeaderName, String); 1], Json<serde_json::Value>), RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
   };
@@ -39,16 +53,33 @@ pub async fn read_record_handler(
     api.table_name(),
     &api.record_pk_column().name,
     record_id,
+    access_rule_groups(user.as_ref()),
   )
   .await?
   else {
     return Err(RecordError::RecordNotFound);
   };
 
-  return Ok(Json(
-    row_to_json(api.metadata(), &row, |col_name| !col_name.starts_with("_"))
-      .map_err(|err| RecordError::Internal(err.into()))?,
-  ));
+  if api.soft_delete() && is_soft_deleted(&row) {
+    if !query.include_deleted.unwrap_or(false) {
+      return Err(RecordError::RecordNotFound);
+    }
+    api.check_table_level_access(Permission::Delete, user.as_ref())?;
+  }
+
+  let record = row_to_json(api.metadata(), &row, |col_name| !col_name.starts_with("_"))
+    .map_err(|err| RecordError::Internal(err.into()))?;
+
+  return Ok(([(header::ETAG, record_etag(&record))], Json(record)));
+}
+
+pub(crate) fn is_soft_deleted(row: &trailbase_sqlite::Row) -> bool {
+  return row
+    .column_names()
+    .iter()
+    .position(|name| *name == SOFT_DELETE_COLUMN)
+    .and_then(|index| row.get_value(index))
+    .is_some_and(|value| *value != trailbase_sqlite::Value::Null);
 }
 
 type GetUploadedFileFromRecordPath = Path<(
@@ -162,6 +193,7 @@ pub async fn get_uploaded_files_from_record_handler(
 #[cfg(test)]
 mod test {
   use axum::extract::{Path, Query, State};
+  use axum::http::HeaderMap;
   use axum::Json;
   use trailbase_sqlite::{schema::FileUpload, schema::FileUploadInput};
 
@@ -171,7 +203,7 @@ mod test {
   use crate::auth::api::login::login_with_password;
   use crate::auth::user::User;
   use crate::config::proto::PermissionFlag;
-  use crate::constants::USER_TABLE;
+  use crate::constants::{GROUPS_TABLE, GROUP_MEMBERS_TABLE, USER_TABLE};
   use crate::extract::Either;
   use crate::records::create_record::{
     create_record_handler, CreateRecordQuery, CreateRecordResponse,
@@ -268,6 +300,7 @@ mod test {
       assert!(read_record_handler(
         State(state.clone()),
         Path(("messages_api".to_string(), id_to_b64(&message_id),)),
+        Query(ReadRecordQuery::default()),
         None
       )
       .await
@@ -278,6 +311,7 @@ mod test {
         let response = read_record_handler(
           State(state.clone()),
           Path(("messages_api".to_string(), id_to_b64(&message_id))),
+          Query(ReadRecordQuery::default()),
           User::from_auth_token(&state, &user_x_token.auth_token),
         )
         .await;
@@ -289,6 +323,7 @@ mod test {
         let response = read_record_handler(
           State(state.clone()),
           Path(("messages_api".to_string(), id_to_b64(&message_id))),
+          Query(ReadRecordQuery::default()),
           User::from_auth_token(&state, &user_y_token.auth_token),
         )
         .await;
@@ -304,6 +339,7 @@ mod test {
       let response = read_record_handler(
         State(state.clone()),
         Path(("messages_api".to_string(), id_to_b64(&message_id))),
+        Query(ReadRecordQuery::default()),
         User::from_auth_token(&state, &user_y_token.auth_token),
       )
       .await;
@@ -402,8 +438,13 @@ mod test {
 
     let record_path = Path((API_NAME.to_string(), create_response.id.clone()));
 
-    let Json(value) =
-      read_record_handler(State(state.clone()), Path(record_path.clone()), None).await?;
+    let (_, Json(value)) = read_record_handler(
+      State(state.clone()),
+      Path(record_path.clone()),
+      Query(ReadRecordQuery::default()),
+      None,
+    )
+    .await?;
 
     let serde_json::Value::Object(map) = value else {
       panic!("Not a map");
@@ -429,9 +470,14 @@ mod test {
     let body = axum::body::to_bytes(read_response.into_body(), usize::MAX).await?;
     assert_eq!(body.to_vec(), bytes);
 
-    let _ = delete_record_handler(State(state.clone()), Path(record_path.clone()), None)
-      .await
-      .unwrap();
+    let _ = delete_record_handler(
+      State(state.clone()),
+      Path(record_path.clone()),
+      None,
+      HeaderMap::new(),
+    )
+    .await
+    .unwrap();
 
     let mut dir_cnt = 0;
     let mut read_dir = tokio::fs::read_dir(state.data_dir().uploads_path()).await?;
@@ -494,7 +540,13 @@ mod test {
 
     let record_path = Path((API_NAME.to_string(), resp.id.clone()));
 
-    let Json(value) = read_record_handler(State(state.clone()), record_path, None).await?;
+    let (_, Json(value)) = read_record_handler(
+      State(state.clone()),
+      record_path,
+      Query(ReadRecordQuery::default()),
+      None,
+    )
+    .await?;
 
     let serde_json::Value::Object(map) = value else {
       panic!("Not a map");
@@ -581,6 +633,7 @@ mod test {
     let response = read_record_handler(
       State(state.clone()),
       Path(("messages_api".to_string(), id_to_b64(&message_id))),
+      Query(ReadRecordQuery::default()),
       User::from_auth_token(&state, &user_x_token.auth_token),
     )
     .await;
@@ -588,4 +641,74 @@ mod test {
 
     return Ok(());
   }
+
+  #[tokio::test]
+  async fn test_read_record_from_group_filtered_view() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await?;
+    conn
+      .execute(
+        "CREATE VIEW staff_message_view AS SELECT * FROM message WHERE current_user_in_group('staff')",
+        (),
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "staff_messages_api",
+      "staff_message_view",
+      Acls {
+        authenticated: vec![PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let room = add_room(conn, "room0").await?;
+    let password = "Secret!1!!";
+    let staff = create_user_for_test(&state, "staff@test.com", password).await?;
+    create_user_for_test(&state, "other@test.com", password).await?;
+
+    let user_conn = state.user_conn();
+    user_conn
+      .execute(
+        &format!("INSERT INTO '{GROUPS_TABLE}' (id, name) VALUES ('staff', 'Staff')"),
+        (),
+      )
+      .await?;
+    user_conn
+      .execute(
+        &format!("INSERT INTO '{GROUP_MEMBERS_TABLE}' (group_id, user) VALUES ('staff', $1)"),
+        trailbase_sqlite::params!(staff.into_bytes()),
+      )
+      .await?;
+
+    let message_id = send_message(conn, staff.into_bytes(), room, "staff only").await?;
+
+    let read = |email: &'static str| {
+      let state = state.clone();
+      async move {
+        let tokens = login_with_password(&state, email, password).await.unwrap();
+        return read_record_handler(
+          State(state.clone()),
+          Path(("staff_messages_api".to_string(), id_to_b64(&message_id))),
+          Query(ReadRecordQuery::default()),
+          User::from_auth_token(&state, &tokens.auth_token),
+        )
+        .await;
+      }
+    };
+
+    assert!(read("staff@test.com").await.is_ok());
+    assert!(matches!(
+      read("other@test.com").await,
+      Err(RecordError::RecordNotFound)
+    ));
+
+    return Ok(());
+  }
 }
@@ -5,13 +5,22 @@ use std::sync::Arc;
 use trailbase_sqlite::{NamedParamRef, NamedParams, NamedParamsRef, Params as _, Value};
 
 use crate::auth::user::User;
-use crate::config::proto::{ConflictResolutionStrategy, RecordApiConfig};
+use crate::config::proto::{
+  ConflictResolutionStrategy, RecordApiConfig, RecordApiEmailOnCreate, RecordApiNotifyOnChange,
+  RecordApiQuota,
+};
 use crate::records::json_to_sql::{LazyParams, Params};
 use crate::records::{Permission, RecordError};
 use crate::schema::{Column, ColumnDataType};
 use crate::table_metadata::{TableMetadata, TableOrViewMetadata, ViewMetadata};
 use crate::util::{assert_uuidv7, b64_to_id};
 
+/// Column marking records as deleted for APIs configured with `soft_delete`.
+pub(crate) const SOFT_DELETE_COLUMN: &str = "deleted_at";
+
+/// Placeholder in access rules for the authenticated user's id, short-hand for `_USER_.id`.
+const USER_ID_PLACEHOLDER: &str = "_USER_ID_";
+
 enum RecordApiMetadata {
   Table(TableMetadata),
   View(ViewMetadata),
@@ -60,6 +69,14 @@ struct RecordApiState {
   acl: [u8; 2],
   insert_conflict_resolution_strategy: Option<ConflictResolutionStrategy>,
   insert_autofill_missing_user_id_columns: bool,
+  soft_delete: bool,
+  audit_trail: bool,
+  cors_allowed_origins: Vec<String>,
+  quota: Option<RecordApiQuota>,
+  send_email_on_create: Option<RecordApiEmailOnCreate>,
+  notify_on_change: Option<RecordApiNotifyOnChange>,
+  group_ids: Vec<String>,
+  max_body_size_bytes: Option<usize>,
 
   create_access_rule: Option<String>,
   create_access_query: Option<String>,
@@ -156,31 +173,50 @@ impl RecordApi {
       return Err(format!("RecordApi misses name: {config:?}"));
     };
 
-    let read_access_query = config.read_access_rule.as_ref().map(|rule| {
+    let expand = |rule: &Option<String>| rule.as_deref().map(expand_access_rule);
+    let write_access_rule = expand(&config.write_access_rule);
+    let read_access_rule = expand(&config.read_access_rule);
+    let schema_access_rule = expand(&config.schema_access_rule);
+    // Explicit create, update and delete rules take precedence over the generic write rule.
+    let create_access_rule = expand(&config.create_access_rule);
+    let update_access_rule = expand(&config.update_access_rule);
+    let delete_access_rule = expand(&config.delete_access_rule).or(write_access_rule.clone());
+
+    let read_access_query = read_access_rule.as_ref().map(|rule| {
       build_read_delete_schema_query(metadata.table_name(), &record_pk_column.name, rule)
     });
 
-    let delete_access_query = config.delete_access_rule.as_ref().map(|rule| {
+    let delete_access_query = delete_access_rule.as_ref().map(|rule| {
       build_read_delete_schema_query(metadata.table_name(), &record_pk_column.name, rule)
     });
 
-    let schema_access_query = config.schema_access_rule.as_ref().map(|rule| {
+    let schema_access_query = schema_access_rule.as_ref().map(|rule| {
       build_read_delete_schema_query(metadata.table_name(), &record_pk_column.name, rule)
     });
 
-    let create_access_query = config.create_access_rule.as_ref().and_then(|rule| {
-      if let RecordApiMetadata::Table(ref m) = metadata {
-        return Some(build_create_access_query(m, rule));
+    // Unlike create and update rules, which can reference both `_REQ_` and `_ROW_`, the write rule
+    // is evaluated against a single `_ROW_`: the new record on create and both the existing and the
+    // updated record on update. This lets the same rule, e.g. "owner = _USER_ID_", apply to all
+    // writes without letting owners hand records over to others.
+    let create_access_query = match (&metadata, &create_access_rule, &write_access_rule) {
+      (RecordApiMetadata::Table(m), Some(rule), _) => {
+        Some(build_create_access_query(m, rule, "_REQ_"))
       }
-      return None;
-    });
+      (RecordApiMetadata::Table(m), None, Some(rule)) => {
+        Some(build_create_access_query(m, rule, "_ROW_"))
+      }
+      _ => None,
+    };
 
-    let update_access_query = config.update_access_rule.as_ref().and_then(|rule| {
-      if let RecordApiMetadata::Table(ref m) = metadata {
-        return Some(build_update_access_query(m, &record_pk_column.name, rule));
+    let update_access_query = match (&metadata, &update_access_rule, &write_access_rule) {
+      (RecordApiMetadata::Table(m), Some(rule), _) => {
+        Some(build_update_access_query(m, &record_pk_column.name, rule))
       }
-      return None;
-    });
+      (RecordApiMetadata::Table(m), None, Some(rule)) => Some(
+        build_write_rule_update_access_query(m, &record_pk_column.name, rule),
+      ),
+      _ => None,
+    };
 
     return Ok(RecordApi {
       state: Arc::new(RecordApiState {
@@ -197,6 +233,14 @@ impl RecordApi {
         insert_autofill_missing_user_id_columns: config
           .autofill_missing_user_id_columns
           .unwrap_or(false),
+        soft_delete: config.soft_delete.unwrap_or(false),
+        audit_trail: config.audit_trail.unwrap_or(false),
+        cors_allowed_origins: config.cors_allowed_origins,
+        quota: config.quota,
+        send_email_on_create: config.send_email_on_create,
+        notify_on_change: config.notify_on_change,
+        group_ids: config.group_ids,
+        max_body_size_bytes: config.max_body_size_bytes.map(|size| size as usize),
 
         // Access control lists.
         acl: [
@@ -206,19 +250,19 @@ impl RecordApi {
         // Access rules.
         //
         // Create:
-        create_access_rule: config.create_access_rule,
+        create_access_rule: create_access_rule.or(write_access_rule.clone()),
         create_access_query,
 
-        read_access_rule: config.read_access_rule,
+        read_access_rule,
         read_access_query,
 
-        update_access_rule: config.update_access_rule,
+        update_access_rule: update_access_rule.or(write_access_rule),
         update_access_query,
 
-        delete_access_rule: config.delete_access_rule,
+        delete_access_rule,
         delete_access_query,
 
-        schema_access_rule: config.schema_access_rule,
+        schema_access_rule,
         schema_access_query,
       }),
     });
@@ -266,6 +310,20 @@ impl RecordApi {
     return &self.state.record_pk_column;
   }
 
+  /// All access queries, e.g. to check that they compile against the current schema.
+  pub(crate) fn access_queries(&self) -> impl Iterator<Item = &str> {
+    return [
+      &self.state.create_access_query,
+      &self.state.read_access_query,
+      &self.state.update_access_query,
+      &self.state.delete_access_query,
+      &self.state.schema_access_query,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|query| query.as_str());
+  }
+
   #[inline]
   pub fn access_rule(&self, p: Permission) -> &Option<String> {
     return match p {
@@ -298,6 +356,51 @@ impl RecordApi {
     return self.state.insert_conflict_resolution_strategy;
   }
 
+  /// Whether records are only marked as deleted using the [SOFT_DELETE_COLUMN].
+  #[inline]
+  pub fn soft_delete(&self) -> bool {
+    return self.state.soft_delete;
+  }
+
+  /// Whether changes are recorded in the table's audit trail, see [crate::records::audit].
+  #[inline]
+  pub fn audit_trail(&self) -> bool {
+    return self.state.audit_trail;
+  }
+
+  /// Origins overriding the server's global CORS policy for this API. Empty if not overridden.
+  #[inline]
+  pub fn cors_allowed_origins(&self) -> &[String] {
+    return &self.state.cors_allowed_origins;
+  }
+
+  /// Limits on the number of records that can be created through this API.
+  #[inline]
+  pub fn quota(&self) -> Option<&RecordApiQuota> {
+    return self.state.quota.as_ref();
+  }
+
+  #[inline]
+  pub fn send_email_on_create(&self) -> Option<&RecordApiEmailOnCreate> {
+    return self.state.send_email_on_create.as_ref();
+  }
+
+  #[inline]
+  pub fn notify_on_change(&self) -> Option<&RecordApiNotifyOnChange> {
+    return self.state.notify_on_change.as_ref();
+  }
+
+  #[inline]
+  pub fn group_ids(&self) -> &[String] {
+    return &self.state.group_ids;
+  }
+
+  /// Request body limit for creating and updating records, if overriding the global default.
+  #[inline]
+  pub fn max_body_size_bytes(&self) -> Option<usize> {
+    return self.state.max_body_size_bytes;
+  }
+
   /// Check if the given user (if any) can access a record given the request and the operation.
   pub async fn check_record_level_access(
     &self,
@@ -314,11 +417,16 @@ impl RecordApi {
     };
     let access_query = access_query.clone();
     let params = self.build_named_params(p, record_id, request_params, user)?;
+    let groups = access_rule_groups(user);
 
     match self
       .state
       .conn
-      .call(move |conn| Self::query_access(conn, &access_query, params))
+      .call(move |conn| {
+        return trailbase_extension::with_current_user_groups(&groups, || {
+          Self::query_access(conn, &access_query, params)
+        });
+      })
       .await
     {
       Ok(allowed) => {
@@ -355,7 +463,10 @@ impl RecordApi {
 
     let (query, params) = build_query_and_params_for_record_read(access_rule, user, record);
 
-    match Self::query_access_ref(conn, &query, &params) {
+    let groups = access_rule_groups(user);
+    match trailbase_extension::with_current_user_groups(&groups, || {
+      Self::query_access_ref(conn, &query, &params)
+    }) {
       Ok(allowed) => {
         if allowed {
           return Ok(());
@@ -378,7 +489,17 @@ impl RecordApi {
     p: Permission,
     user: Option<&User>,
   ) -> Result<(), RecordError> {
-    if (user.is_some() && self.has_access(Entity::Authenticated, p))
+    // Users with a pending forced password reset are locked out until they change their password.
+    if user.is_some_and(|user| user.must_change_password) {
+      return Err(RecordError::Forbidden);
+    }
+
+    let in_group = |user: &User| {
+      let group_ids = &self.state.group_ids;
+      return group_ids.is_empty() || user.groups.iter().any(|g| group_ids.contains(g));
+    };
+
+    if (user.is_some_and(in_group) && self.has_access(Entity::Authenticated, p))
       || self.has_access(Entity::World, p)
     {
       return Ok(());
@@ -450,9 +571,10 @@ impl RecordApi {
             .expect("params for update & create")
             .params()
             .map_err(|err| RecordError::Internal(err.into()))?,
+          p == Permission::Update,
         )
       }
-      Permission::Read | Permission::Delete | Permission::Schema => NamedParams::with_capacity(2),
+      Permission::Read | Permission::Delete | Permission::Schema => NamedParams::with_capacity(3),
     };
 
     params.extend_from_slice(&[
@@ -460,6 +582,7 @@ impl RecordApi {
         Cow::Borrowed(":__user_id"),
         user.map_or(Value::Null, |u| Value::Blob(u.uuid.into())),
       ),
+      (Cow::Borrowed(":__user_claims"), user_claims_value(user)),
       (
         Cow::Borrowed(":__record_id"),
         record_id.map_or(Value::Null, |id| id.clone()),
@@ -501,6 +624,10 @@ fn build_query_and_params_for_record_read<'a>(
       rusqlite::types::ToSqlOutput::Owned(Value::Blob(u.uuid.into()))
     }),
   ));
+  params.push((
+    Cow::Borrowed(":__user_claims"),
+    rusqlite::types::ToSqlOutput::Owned(user_claims_value(user)),
+  ));
 
   // Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
   let query = indoc::formatdoc!(
@@ -508,7 +635,7 @@ fn build_query_and_params_for_record_read<'a>(
         SELECT
           ({access_rule})
         FROM
-          (SELECT :__user_id AS id) AS _USER_,
+          (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
           (SELECT {row}) AS _ROW_
       "#
   );
@@ -516,6 +643,60 @@ fn build_query_and_params_for_record_read<'a>(
   return (query, params);
 }
 
+/// Substitutes the `_USER_ID_` placeholder in the given access rule.
+///
+/// Only whole tokens are substituted, i.e. string literals, quoted identifiers and identifiers
+/// merely containing the placeholder are left untouched.
+fn expand_access_rule(rule: &str) -> String {
+  let is_identifier_char = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80;
+
+  let bytes = rule.as_bytes();
+  let mut expanded = String::with_capacity(rule.len());
+  let mut quote: Option<u8> = None;
+  let mut last = 0;
+  let mut i = 0;
+  while i < bytes.len() {
+    let b = bytes[i];
+    if let Some(q) = quote {
+      // Escaped quotes, e.g. 'it''s', simply close and re-open the literal.
+      if b == q {
+        quote = None;
+      }
+    } else if matches!(b, b'\'' | b'"' | b'`') {
+      quote = Some(b);
+    } else if b == b'[' {
+      quote = Some(b']');
+    } else if rule[i..].starts_with(USER_ID_PLACEHOLDER)
+      && (i == 0 || !is_identifier_char(bytes[i - 1]))
+      && bytes
+        .get(i + USER_ID_PLACEHOLDER.len())
+        .is_none_or(|b| !is_identifier_char(*b))
+    {
+      expanded.push_str(&rule[last..i]);
+      expanded.push_str("_USER_.id");
+      i += USER_ID_PLACEHOLDER.len();
+      last = i;
+      continue;
+    }
+    i += 1;
+  }
+  expanded.push_str(&rule[last..]);
+
+  return expanded;
+}
+
+/// Groups of the given user (if any) exposed to access rules through `current_user_in_group()`.
+pub(crate) fn access_rule_groups(user: Option<&User>) -> Vec<String> {
+  return user.map(|u| u.groups.clone()).unwrap_or_default();
+}
+
+/// Custom claims of the given user as JSON text, exposed to access rules as `_USER_.claims`.
+pub(crate) fn user_claims_value(user: Option<&User>) -> Value {
+  return user
+    .and_then(|u| u.custom_claims.as_ref())
+    .map_or(Value::Null, |claims| Value::Text(claims.to_string()));
+}
+
 /// Build access query for record reads, deletes and query access.
 ///
 /// Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
@@ -529,16 +710,20 @@ fn build_read_delete_schema_query(
       SELECT
         ({access_rule})
       FROM
-        (SELECT :__user_id AS id) AS _USER_,
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
         (SELECT * FROM "{table_name}" WHERE "{pk_column_name}" = :__record_id) AS _ROW_
     "#
   );
 }
 
-/// Build access query for record creation.
+/// Build access query for record creation, exposing the request as `alias`.
 ///
 /// Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
-fn build_create_access_query(table_metadata: &TableMetadata, create_access_rule: &str) -> String {
+fn build_create_access_query(
+  table_metadata: &TableMetadata,
+  create_access_rule: &str,
+  alias: &str,
+) -> String {
   let column_sub_select = format!(
     "SELECT {placeholders}",
     placeholders = table_metadata
@@ -554,8 +739,8 @@ fn build_create_access_query(table_metadata: &TableMetadata, create_access_rule:
       SELECT
         ({create_access_rule})
       FROM
-        (SELECT :__user_id AS id) AS _USER_,
-        ({column_sub_select}) AS _REQ_
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
+        ({column_sub_select}) AS {alias}
     "#,
   );
 }
@@ -585,15 +770,62 @@ fn build_update_access_query(
       SELECT
         ({update_access_rule})
       FROM
-        (SELECT :__user_id AS id) AS _USER_,
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
         ({column_sub_select}) AS _REQ_,
         (SELECT * FROM "{table_name}" WHERE "{pk_column_name}" = :__record_id) AS _ROW_
     "#,
   );
 }
 
+/// Build access query for record updates based on the generic write rule, which must hold for both
+/// the existing record and the updated record, i.e. the existing record merged with the request.
+///
+/// Assumes access_rule is an expression: https://www.sqlite.org/syntax/expr.html
+fn build_write_rule_update_access_query(
+  table_metadata: &TableMetadata,
+  pk_column_name: &str,
+  write_access_rule: &str,
+) -> String {
+  let table_name = table_metadata.name();
+
+  let merged_columns = table_metadata
+    .schema
+    .columns
+    .iter()
+    .map(|col| {
+      format!(
+        r#"CASE WHEN :__present_{name} THEN :{name} ELSE _ROW_."{name}" END AS '{name}'"#,
+        name = col.name
+      )
+    })
+    .join(", ");
+
+  return indoc::formatdoc!(
+    r#"
+      SELECT
+        ({write_access_rule}) AND (
+          SELECT
+            ({write_access_rule})
+          FROM
+            (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
+            (SELECT {merged_columns} FROM "{table_name}" AS _ROW_ WHERE "{pk_column_name}" = :__record_id) AS _ROW_
+        )
+      FROM
+        (SELECT :__user_id AS id, :__user_claims AS claims) AS _USER_,
+        (SELECT * FROM "{table_name}" WHERE "{pk_column_name}" = :__record_id) AS _ROW_
+    "#,
+  );
+}
+
 /// Build SQL named parameters from request fields.
-fn build_request_params(table_metadata: &TableMetadata, request_params: &Params) -> NamedParams {
+///
+/// With `with_presence`, additionally binds `:__present_<column>` flags telling explicit NULLs
+/// apart from fields missing on the request.
+fn build_request_params(
+  table_metadata: &TableMetadata,
+  request_params: &Params,
+  with_presence: bool,
+) -> NamedParams {
   // NOTE: This has gotten pretty wild. We cannot have access queries access missing _REQ_.props.
   // So we need to inject an explicit NULL value for all missing fields on the request.
   // Can we make this cheaper, either by pre-processing the access query or improving construction?
@@ -616,6 +848,22 @@ fn build_request_params(table_metadata: &TableMetadata, request_params: &Params)
     named_params[col_index].1 = request_params.named_params()[param_index].1.clone();
   }
 
+  if with_presence {
+    let presence: NamedParams = table_metadata
+      .schema
+      .columns
+      .iter()
+      .map(|c| {
+        let present = request_params.column_names().contains(&c.name);
+        return (
+          Cow::Owned(format!(":__present_{}", c.name)),
+          Value::Integer(present as i64),
+        );
+      })
+      .collect();
+    named_params.extend(presence);
+  }
+
   return named_params;
 }
 
@@ -638,7 +886,7 @@ enum Entity {
 
 #[cfg(test)]
 mod tests {
-  use super::convert_acl;
+  use super::{convert_acl, expand_access_rule};
   use crate::{config::proto::PermissionFlag, records::Permission};
 
   fn has_access(flags: u8, p: Permission) -> bool {
@@ -670,4 +918,26 @@ mod tests {
       assert!(has_access(acl, Permission::Update), "ACL: {acl}");
     }
   }
+
+  #[test]
+  fn test_expand_access_rule() {
+    assert_eq!(expand_access_rule("_USER_ID_"), "_USER_.id");
+    assert_eq!(
+      expand_access_rule("owner = _USER_ID_ OR (editor=_USER_ID_)"),
+      "owner = _USER_.id OR (editor=_USER_.id)"
+    );
+    // Literals, quoted identifiers and other identifiers are left alone.
+    assert_eq!(
+      expand_access_rule("note = '_USER_ID_' AND \"_USER_ID_\" = [_USER_ID_]"),
+      "note = '_USER_ID_' AND \"_USER_ID_\" = [_USER_ID_]"
+    );
+    assert_eq!(
+      expand_access_rule("x_USER_ID_ = _USER_ID_x"),
+      "x_USER_ID_ = _USER_ID_x"
+    );
+    assert_eq!(
+      expand_access_rule("note = 'it''s _USER_ID_' AND owner = _USER_ID_"),
+      "note = 'it''s _USER_ID_' AND owner = _USER_.id"
+    );
+  }
 }
@@ -1,20 +1,22 @@
 use async_channel::WeakReceiver;
 use axum::{
-  extract::{Path, State},
+  extract::{Path, Query, State},
+  http::HeaderMap,
   response::sse::{Event, KeepAlive, Sse},
 };
 use futures_util::Stream;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use pin_project_lite::pin_project;
 use rusqlite::hooks::{Action, PreUpdateCase};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
 use std::sync::{
   atomic::{AtomicI64, Ordering},
   Arc,
 };
 use std::task::{Context, Poll};
+use std::time::Duration;
 use trailbase_sqlite::connection::{extract_record_values, extract_row_id};
 
 use crate::auth::user::User;
@@ -68,6 +70,8 @@ pin_project! {
   #[must_use = "streams do nothing unless polled"]
   struct AutoCleanupEventStream {
     cleanup: CleanupSubscription,
+    /// Missed events to be sent ahead of new ones to re-connecting clients.
+    replay: VecDeque<Event>,
 
     #[pin]
     receiver: async_channel::Receiver<Event>,
@@ -79,12 +83,18 @@ impl Stream for AutoCleanupEventStream {
 
   fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
     let mut this = self.project();
+    if let Some(event) = this.replay.pop_front() {
+      return Poll::Ready(Some(Ok(event)));
+    }
+
     let res = futures_util::ready!(this.receiver.as_mut().poll_next(cx));
     Poll::Ready(res.map(Ok))
   }
 
   fn size_hint(&self) -> (usize, Option<usize>) {
-    self.receiver.size_hint()
+    let (lower, upper) = self.receiver.size_hint();
+    let replay = self.replay.len();
+    (lower + replay, upper.map(|upper| upper + replay))
   }
 }
 
@@ -128,6 +138,17 @@ pub struct Subscription {
   sender: async_channel::Sender<Event>,
 }
 
+/// Recently brokered event kept around for re-connecting clients.
+#[derive(Clone)]
+struct BufferedEvent {
+  id: i64,
+  table_name: String,
+  rowid: i64,
+  /// Column names and values needed to re-check access for the re-connecting user.
+  record: Vec<(String, rusqlite::types::Value)>,
+  event: Event,
+}
+
 /// Internal, shareable state of the cloneable SubscriptionManager.
 struct ManagerState {
   /// SQLite connection to monitor.
@@ -143,6 +164,18 @@ struct ManagerState {
 
   /// Map from table name to table subscriptions.
   table_subscriptions: RwLock<HashMap<String, Vec<Subscription>>>,
+
+  /// Source of monotonically increasing event ids, sent as SSE "id" field.
+  event_counter: AtomicI64,
+  /// Ring buffer of the most recent events for replaying via "Last-Event-ID" or `since_id`.
+  ///
+  /// NOTE: Events are only observed while the preupdate hook is installed, i.e. while there's at
+  /// least one subscription to the table. Replay is thus best effort.
+  replay_buffer: Mutex<VecDeque<BufferedEvent>>,
+  replay_buffer_size: usize,
+  /// Interval at which keep-alive comments are sent on otherwise idle connections. Zero disables
+  /// keep-alives.
+  keepalive_secs: u64,
 }
 
 impl ManagerState {
@@ -219,6 +252,8 @@ impl SubscriptionManager {
     conn: trailbase_sqlite::Connection,
     table_metadata: TableMetadataCache,
     record_apis: Computed<Vec<(String, RecordApi)>, crate::config::proto::Config>,
+    keepalive_secs: u64,
+    replay_buffer_size: usize,
   ) -> Self {
     return Self {
       state: Arc::new(ManagerState {
@@ -228,10 +263,85 @@ impl SubscriptionManager {
 
         record_subscriptions: RwLock::new(HashMap::new()),
         table_subscriptions: RwLock::new(HashMap::new()),
+
+        event_counter: AtomicI64::new(0),
+        replay_buffer: Mutex::new(VecDeque::with_capacity(replay_buffer_size)),
+        replay_buffer_size,
+        keepalive_secs,
       }),
     };
   }
 
+  fn keep_alive(&self) -> Option<KeepAlive> {
+    return match self.state.keepalive_secs {
+      0 => None,
+      secs => Some(
+        KeepAlive::new()
+          .interval(Duration::from_secs(secs))
+          .text("keepalive"),
+      ),
+    };
+  }
+
+  /// Returns buffered events newer than `since_id` the given user is allowed to read, for
+  /// the given table or, if `row_id` is set, record.
+  async fn replay_events(
+    &self,
+    api_name: String,
+    table_name: &str,
+    row_id: Option<i64>,
+    user: Option<User>,
+    since_id: Option<i64>,
+  ) -> Result<VecDeque<Event>, RecordError> {
+    let Some(since_id) = since_id else {
+      return Ok(VecDeque::new());
+    };
+
+    let candidates: Vec<BufferedEvent> = self
+      .state
+      .replay_buffer
+      .lock()
+      .iter()
+      .filter(|ev| {
+        ev.id > since_id && ev.table_name == table_name && row_id.is_none_or(|id| id == ev.rowid)
+      })
+      .cloned()
+      .collect();
+    if candidates.is_empty() {
+      return Ok(VecDeque::new());
+    }
+
+    let state = self.state.clone();
+    return Ok(
+      self
+        .state
+        .conn
+        .call(move |conn| {
+          let Some(api) = state.lookup_record_api(&api_name) else {
+            return Ok(VecDeque::new());
+          };
+
+          return Ok(
+            candidates
+              .into_iter()
+              .filter(|ev| {
+                let record: Vec<(&str, rusqlite::types::ValueRef<'_>)> = ev
+                  .record
+                  .iter()
+                  .map(|(name, value)| (name.as_str(), value.into()))
+                  .collect();
+                return api
+                  .check_record_level_read_access(conn, Permission::Read, &record, user.as_ref())
+                  .is_ok();
+              })
+              .map(|ev| ev.event)
+              .collect(),
+          );
+        })
+        .await?,
+    );
+  }
+
   #[cfg(test)]
   pub fn num_record_subscriptions(&self) -> usize {
     let mut count: usize = 0;
@@ -356,10 +466,32 @@ impl SubscriptionManager {
         RecordAction::Update => DbEvent::Update(Some(json_value)),
       };
 
-      let Ok(event) = Event::default().json_data(db_event) else {
+      let event_id = s.event_counter.fetch_add(1, Ordering::SeqCst) + 1;
+      let Ok(event) = Event::default()
+        .id(event_id.to_string())
+        .json_data(db_event)
+      else {
         return;
       };
 
+      if s.replay_buffer_size > 0 {
+        let mut buffer = s.replay_buffer.lock();
+        if buffer.len() >= s.replay_buffer_size {
+          buffer.pop_front();
+        }
+        buffer.push_back(BufferedEvent {
+          id: event_id,
+          table_name: table_name.to_string(),
+          rowid,
+          record: record_values
+            .iter()
+            .enumerate()
+            .map(|(idx, v)| (table_metadata.schema.columns[idx].name.clone(), v.clone()))
+            .collect(),
+          event: event.clone(),
+        });
+      }
+
       event
     };
 
@@ -512,6 +644,7 @@ impl SubscriptionManager {
     api: RecordApi,
     record: trailbase_sqlite::Value,
     user: Option<User>,
+    since_id: Option<i64>,
   ) -> Result<AutoCleanupEventStream, RecordError> {
     let table_name = api.table_name().to_string();
     let pk_column = &api.record_pk_column().name;
@@ -543,7 +676,7 @@ impl SubscriptionManager {
         subscription_id,
         record_api_name: api.api_name().to_string(),
         // record_id: Some(record),
-        user,
+        user: user.clone(),
         sender,
       });
 
@@ -554,6 +687,18 @@ impl SubscriptionManager {
       self.add_hook().await.unwrap();
     }
 
+    // NOTE: Events brokered concurrently may be both replayed and received, i.e. clients may
+    // see duplicate ids.
+    let replay = self
+      .replay_events(
+        api.api_name().to_string(),
+        &table_name,
+        Some(row_id),
+        user,
+        since_id,
+      )
+      .await?;
+
     return Ok(AutoCleanupEventStream {
       cleanup: CleanupSubscription {
         receiver: receiver.downgrade(),
@@ -564,6 +709,7 @@ impl SubscriptionManager {
           sub_id: subscription_id,
         },
       },
+      replay,
       receiver,
     });
   }
@@ -573,6 +719,7 @@ impl SubscriptionManager {
     app_state: AppState,
     api: RecordApi,
     user: Option<User>,
+    since_id: Option<i64>,
   ) -> Result<AutoCleanupEventStream, RecordError> {
     let state = &self.state;
     let table_name = api.table_name().to_string();
@@ -587,7 +734,7 @@ impl SubscriptionManager {
       m.push(Subscription {
         subscription_id,
         record_api_name: api.api_name().to_string(),
-        user,
+        user: user.clone(),
         sender,
       });
 
@@ -598,6 +745,16 @@ impl SubscriptionManager {
       self.add_hook().await.unwrap();
     }
 
+    let replay = self
+      .replay_events(
+        api.api_name().to_string(),
+        &table_name,
+        None,
+        user,
+        since_id,
+      )
+      .await?;
+
     return Ok(AutoCleanupEventStream {
       cleanup: CleanupSubscription {
         receiver: receiver.downgrade(),
@@ -608,42 +765,59 @@ impl SubscriptionManager {
           sub_id: subscription_id,
         },
       },
+      replay,
       receiver,
     });
   }
 }
 
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscribeQuery {
+  /// Id of the last received event. Buffered events newer than this will be replayed. Takes
+  /// precedence over the "Last-Event-ID" header sent by re-connecting EventSources.
+  since_id: Option<i64>,
+}
+
 pub async fn add_subscription_sse_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
+  Query(query): Query<SubscribeQuery>,
+  headers: HeaderMap,
   user: Option<User>,
 ) -> Result<Sse<impl Stream<Item = SseEvent>>, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
   };
 
-  if record == "*" {
-    api.check_table_level_access(Permission::Read, user.as_ref())?;
+  let since_id = query.since_id.or_else(|| {
+    headers
+      .get("Last-Event-ID")
+      .and_then(|id| id.to_str().ok()?.parse().ok())
+  });
+  let manager = state.subscription_manager();
 
-    let receiver = state
-      .subscription_manager()
-      .add_table_subscription(state.clone(), api, user)
-      .await?;
+  let receiver = if record == "*" {
+    api.check_table_level_access(Permission::Read, user.as_ref())?;
 
-    return Ok(Sse::new(receiver).keep_alive(KeepAlive::default()));
+    manager
+      .add_table_subscription(state.clone(), api, user, since_id)
+      .await?
   } else {
     let record_id = api.id_to_sql(&record)?;
     api
       .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
       .await?;
 
-    let receiver = state
-      .subscription_manager()
-      .add_record_subscription(state.clone(), api, record_id, user)
-      .await?;
+    manager
+      .add_record_subscription(state.clone(), api, record_id, user, since_id)
+      .await?
+  };
 
-    return Ok(Sse::new(receiver).keep_alive(KeepAlive::default()));
-  }
+  let sse = Sse::new(receiver);
+  return Ok(match manager.keep_alive() {
+    Some(keep_alive) => sse.keep_alive(keep_alive),
+    None => sse,
+  });
 }
 
 #[cfg(test)]
@@ -664,9 +838,8 @@ async fn decode_sse_json_event(event: Event) -> serde_json::Value {
 
   let str = String::from_utf8_lossy(&bytes);
   let x = str
-    .strip_prefix("data: ")
-    .unwrap()
-    .strip_suffix("\n\n")
+    .lines()
+    .find_map(|line| line.strip_prefix("data: "))
     .unwrap();
   return serde_json::from_str(x).unwrap();
 }
@@ -762,6 +935,7 @@ mod tests {
         api,
         trailbase_sqlite::Value::Integer(0),
         None,
+        None,
       )
       .await
       .unwrap();
@@ -828,7 +1002,7 @@ mod tests {
 
     {
       let stream = manager
-        .add_table_subscription(state.clone(), api, None)
+        .add_table_subscription(state.clone(), api, None, None)
         .await
         .unwrap();
 
@@ -898,6 +1072,60 @@ mod tests {
     assert_eq!(0, manager.num_table_subscriptions());
   }
 
+  #[tokio::test]
+  async fn replay_missed_events_test() {
+    let state = setup_world_readable().await;
+    let conn = state.conn().clone();
+
+    let manager = state.subscription_manager();
+    let api = state.lookup_record_api("api_name").unwrap();
+
+    let stream = manager
+      .add_table_subscription(state.clone(), api.clone(), None, None)
+      .await
+      .unwrap();
+
+    for id in 0..3 {
+      conn
+        .execute(
+          "INSERT INTO test (id, text) VALUES ($1, 'foo')",
+          params!(id),
+        )
+        .await
+        .unwrap();
+      stream.receiver.recv().await.unwrap();
+    }
+
+    // A client re-connecting after having received the first event, gets the other two replayed.
+    let resumed = manager
+      .add_table_subscription(state.clone(), api.clone(), None, Some(1))
+      .await
+      .unwrap();
+    assert_eq!(resumed.replay.len(), 2);
+
+    let mut ids = vec![];
+    for event in resumed.replay.iter().cloned() {
+      match decode_db_event(event).await {
+        DbEvent::Insert(Some(value)) => ids.push(value["id"].clone()),
+        x => panic!("Expected insert, got: {x:?}"),
+      }
+    }
+    assert_eq!(ids, vec![serde_json::json!(1), serde_json::json!(2)]);
+
+    // Record subscriptions only get the record's events replayed.
+    let resumed = manager
+      .add_record_subscription(
+        state.clone(),
+        api,
+        trailbase_sqlite::Value::Integer(2),
+        None,
+        Some(0),
+      )
+      .await
+      .unwrap();
+    assert_eq!(resumed.replay.len(), 1);
+  }
+
   #[tokio::test]
   async fn subscription_lifecycle_test() {
     let state = setup_world_readable().await;
@@ -921,6 +1149,8 @@ mod tests {
     let sse = add_subscription_sse_handler(
       State(state.clone()),
       Path(("api_name".to_string(), record_id_raw.to_string())),
+      Query(SubscribeQuery::default()),
+      HeaderMap::new(),
       None,
     )
     .await;
@@ -1092,6 +1322,7 @@ mod tests {
           state.clone(),
           api.clone(),
           User::from_auth_token(&state, &user_x_token.auth_token),
+          None,
         )
         .await
         .unwrap();
@@ -1101,6 +1332,7 @@ mod tests {
           state.clone(),
           api.clone(),
           User::from_auth_token(&state, &user_y_token.auth_token),
+          None,
         )
         .await
         .unwrap();
@@ -1188,6 +1420,7 @@ mod tests {
         api,
         trailbase_sqlite::Value::Integer(record_id),
         user_x,
+        None,
       )
       .await
       .unwrap();
@@ -1,26 +1,49 @@
-use axum::extract::{Path, State};
+use axum::extract::{Json, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::app_state::AppState;
 use crate::auth::user::User;
 use crate::extract::Either;
-use crate::records::json_to_sql::{JsonRow, LazyParams, UpdateQueryBuilder};
+use crate::push::notify_record_change;
+use crate::records::audit::AuditAttribution;
+use crate::records::create_record::wants_representation;
+use crate::records::etag::if_match_precondition;
+use crate::records::json_to_sql::{
+  JsonRow, LazyParams, QueryError, SelectQueryBuilder, UpdateQueryBuilder, WriteHooks,
+};
+use crate::records::record_api::{access_rule_groups, SOFT_DELETE_COLUMN};
+use crate::records::sql_to_json::row_to_json;
 use crate::records::{Permission, RecordError};
 
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct UpdateRecordQuery {
+  /// If set to "representation", the response will contain the updated record.
+  #[serde(rename = "return")]
+  pub return_: Option<String>,
+}
+
 /// Update existing record.
 #[utoipa::path(
   patch,
   path = "/:name/:record",
+  params(UpdateRecordQuery),
   request_body = serde_json::Value,
   responses(
-    (status = 200, description = "Successful update.")
+    (status = 200, description = "Successful update. Contains the updated record if requested.", body = serde_json::Value),
+    (status = 412, description = "Record was modified concurrently, i.e. If-Match doesn't match its ETag.")
   )
 )]
 pub async fn update_record_handler(
   State(state): State<AppState>,
   Path((api_name, record)): Path<(String, String)>,
+  Query(update_record_query): Query<UpdateRecordQuery>,
   user: Option<User>,
+  headers: HeaderMap,
   either_request: Either<JsonRow>,
-) -> Result<(), RecordError> {
+) -> Result<Response, RecordError> {
   let Some(api) = state.lookup_record_api(&api_name) else {
     return Err(RecordError::ApiNotFound);
   };
@@ -31,12 +54,20 @@ pub async fn update_record_handler(
 
   let record_id = api.id_to_sql(&record)?;
 
+  let return_record = wants_representation(update_record_query.return_.as_deref())?;
+  if return_record {
+    // Fail early rather than after the update if the user cannot read the table at all.
+    api.check_table_level_access(Permission::Read, user.as_ref())?;
+  }
+
   let (request, multipart_files) = match either_request {
     Either::Json(value) => (value, None),
     Either::Multipart(value, files) => (value, Some(files)),
     Either::Form(value) => (value, None),
   };
 
+  let precondition = if_match_precondition(&api, &headers)?;
+
   let mut lazy_params = LazyParams::new(table_metadata, request, multipart_files);
   api
     .check_record_level_access(
@@ -54,17 +85,181 @@ pub async fn update_record_handler(
       .consume()
       .map_err(|err| RecordError::Internal(err.into()))?,
     &api.record_pk_column().name,
+    record_id.clone(),
+    api.soft_delete().then_some(SOFT_DELETE_COLUMN),
+    WriteHooks {
+      precondition,
+      audit: AuditAttribution::new(&api, user.as_ref()),
+    },
+  )
+  .await
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::PreconditionFailed => RecordError::PreconditionFailed,
+    err => RecordError::Internal(err.into()),
+  })?;
+
+  notify_record_change(&state, &api, record_id.clone()).await?;
+
+  if !return_record {
+    return Ok(().into_response());
+  }
+
+  api
+    .check_record_level_access(Permission::Read, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  let Some(row) = SelectQueryBuilder::run(
+    &state,
+    api.table_name(),
+    &api.record_pk_column().name,
     record_id,
+    access_rule_groups(user.as_ref()),
+  )
+  .await?
+  else {
+    return Err(RecordError::RecordNotFound);
+  };
+
+  return Ok(
+    Json(
+      row_to_json(api.metadata(), &row, |col_name| !col_name.starts_with("_"))
+        .map_err(|err| RecordError::Internal(err.into()))?,
+    )
+    .into_response(),
+  );
+}
+
+/// Upper bound on the number of records updated in a single bulk request.
+const MAX_BULK_UPDATE_SIZE: usize = 1024;
+
+#[derive(Clone, Debug, Default, Deserialize, IntoParams)]
+pub struct UpdateBulkQuery {
+  /// Whether to roll back all updates if any record fails to update. Defaults to true.
+  pub fail_fast: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateBulkResponse {
+  /// Number of successfully updated records.
+  pub updated: usize,
+  /// Ids of records that could not be updated. Only populated if `fail_fast` is false.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub failed: Vec<String>,
+}
+
+/// Update multiple records in a single transaction. Each record must contain its primary key.
+#[utoipa::path(
+  patch,
+  path = "/:name",
+  params(UpdateBulkQuery),
+  request_body = Vec<serde_json::Value>,
+  responses(
+    (status = 200, description = "All records updated.", body = UpdateBulkResponse),
+    (status = 207, description = "Some records failed to update.", body = UpdateBulkResponse),
+  )
+)]
+pub async fn update_bulk_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  Query(update_bulk_query): Query<UpdateBulkQuery>,
+  user: Option<User>,
+  Json(request): Json<Vec<JsonRow>>,
+) -> Result<Response, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+
+  let table_metadata = api
+    .table_metadata()
+    .ok_or_else(|| RecordError::ApiRequiresTable)?;
+
+  if request.len() > MAX_BULK_UPDATE_SIZE {
+    return Err(RecordError::BadRequest("Too many records"));
+  }
+
+  let fail_fast = update_bulk_query.fail_fast.unwrap_or(true);
+  let pk_column_name = &api.record_pk_column().name;
+
+  let mut failed: Vec<String> = vec![];
+  let mut records = Vec::with_capacity(request.len());
+  for mut row in request {
+    let id = match row.remove(pk_column_name) {
+      Some(serde_json::Value::String(id)) => id,
+      Some(serde_json::Value::Number(id)) => id.to_string(),
+      _ => return Err(RecordError::BadRequest("Missing or invalid record id")),
+    };
+    let record_id = api.id_to_sql(&id)?;
+
+    let mut lazy_params = LazyParams::new(table_metadata, row, None);
+    let access = api
+      .check_record_level_access(
+        Permission::Update,
+        Some(&record_id),
+        Some(&mut lazy_params),
+        user.as_ref(),
+      )
+      .await;
+
+    let params = match (access, lazy_params.consume()) {
+      (Ok(()), Ok(params)) => params,
+      (Err(err), _) if fail_fast => return Err(err),
+      (_, Err(_)) if fail_fast => return Err(RecordError::BadRequest("Parameter conversion")),
+      _ => {
+        failed.push(id);
+        continue;
+      }
+    };
+
+    records.push((id, params, record_id));
+  }
+
+  let (ids, records): (Vec<(String, trailbase_sqlite::Value)>, Vec<_>) = records
+    .into_iter()
+    .map(|(id, params, record_id)| ((id, record_id.clone()), (params, record_id)))
+    .unzip();
+
+  let updated = UpdateQueryBuilder::run_bulk(
+    &state,
+    table_metadata,
+    records,
+    pk_column_name,
+    api.soft_delete().then_some(SOFT_DELETE_COLUMN),
+    fail_fast,
+    AuditAttribution::new(&api, user.as_ref()),
   )
   .await
-  .map_err(|err| RecordError::Internal(err.into()))?;
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    QueryError::Precondition(msg) => RecordError::BadRequest(msg),
+    err => RecordError::Internal(err.into()),
+  })?;
 
-  return Ok(());
+  for ((id, record_id), success) in std::iter::zip(ids, &updated) {
+    if !success {
+      failed.push(id);
+      continue;
+    }
+    notify_record_change(&state, &api, record_id).await?;
+  }
+
+  let response = UpdateBulkResponse {
+    updated: updated.iter().filter(|success| **success).count(),
+    failed,
+  };
+  let status = if response.failed.is_empty() {
+    StatusCode::OK
+  } else {
+    StatusCode::MULTI_STATUS
+  };
+
+  return Ok((status, Json(response)).into_response());
 }
 
 #[cfg(test)]
 mod test {
   use axum::extract::Query;
+  use axum::http::header;
   use trailbase_sqlite::params;
 
   use super::*;
@@ -77,12 +272,13 @@ mod test {
   use crate::records::create_record::{
     create_record_handler, CreateRecordQuery, CreateRecordResponse,
   };
+  use crate::records::read_record::{read_record_handler, ReadRecordQuery};
   use crate::records::test_utils::*;
   use crate::records::*;
   use crate::test::unpack_json_response;
   use crate::util::{b64_to_id, id_to_b64, query_one_row};
 
-  #[tokio::test]
+  #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
   async fn test_record_api_update() -> Result<(), anyhow::Error> {
     let state = test_state(None).await?;
     let conn = state.conn();
@@ -162,7 +358,9 @@ mod test {
       let update_response = update_record_handler(
         State(state.clone()),
         Path(("messages_api".to_string(), b64_id.clone())),
+        Query(UpdateRecordQuery::default()),
         User::from_auth_token(&state, &user_x_token.auth_token),
+        HeaderMap::new(),
         Either::Json(json_row_from_value(update_json).unwrap()),
       )
       .await;
@@ -187,7 +385,9 @@ mod test {
       let update_response = update_record_handler(
         State(state.clone()),
         Path(("messages_api".to_string(), b64_id.clone())),
+        Query(UpdateRecordQuery::default()),
         User::from_auth_token(&state, &user_y_token.auth_token),
+        HeaderMap::new(),
         Either::Json(json_row_from_value(update_json).unwrap()),
       )
       .await;
@@ -195,6 +395,311 @@ mod test {
       assert!(update_response.is_err(), "{b64_id} {update_response:?}");
     }
 
+    {
+      // Two concurrent updates based on the same read: only one of them may succeed.
+      let (headers, _) = read_record_handler(
+        State(state.clone()),
+        Path(("messages_api".to_string(), b64_id.clone())),
+        Query(ReadRecordQuery::default()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+      )
+      .await?;
+      let etag = headers[0].1.clone();
+
+      let mut if_match = HeaderMap::new();
+      if_match.insert(header::IF_MATCH, etag.parse()?);
+
+      let update = |text: &'static str| {
+        update_record_handler(
+          State(state.clone()),
+          Path(("messages_api".to_string(), b64_id.clone())),
+          Query(UpdateRecordQuery::default()),
+          User::from_auth_token(&state, &user_x_token.auth_token),
+          if_match.clone(),
+          Either::Json(json_row_from_value(serde_json::json!({"data": text})).unwrap()),
+        )
+      };
+
+      // Race both writers: exactly one of them wins.
+      let (first, second) = tokio::join!(
+        tokio::spawn(update("first concurrent update")),
+        tokio::spawn(update("second concurrent update"))
+      );
+      let (winner, loser) = match (first?, second?) {
+        (Ok(_), Err(err)) => ("first concurrent update", err),
+        (Err(err), Ok(_)) => ("second concurrent update", err),
+        (first, second) => panic!("Expected exactly one winner: {first:?}, {second:?}"),
+      };
+      assert!(
+        matches!(loser, RecordError::PreconditionFailed),
+        "{loser:?}"
+      );
+
+      let message_text: String = query_one_row(
+        conn,
+        "SELECT data FROM message WHERE id = $1",
+        params!(b64_to_id(&b64_id)?),
+      )
+      .await?
+      .get(0)?;
+      assert_eq!(winner, message_text);
+    }
+
+    return Ok(());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_update_write_rule() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    create_chat_message_app_tables(&state).await?;
+    let room = add_room(conn, "room0").await?;
+    let password = "Secret!1!!";
+
+    add_record_api(
+      &state,
+      "messages_api",
+      "message",
+      Acls {
+        authenticated: vec![PermissionFlag::Read, PermissionFlag::Update],
+        ..Default::default()
+      },
+      AccessRules {
+        write: Some("_owner = _USER_ID_".to_string()),
+        ..Default::default()
+      },
+    )
+    .await?;
+
+    let user_x_email = "user_x@test.com";
+    let user_x = create_user_for_test(&state, user_x_email, password)
+      .await?
+      .into_bytes();
+    let user_x_token = login_with_password(&state, user_x_email, password).await?;
+
+    let user_y = create_user_for_test(&state, "user_y@test.com", password)
+      .await?
+      .into_bytes();
+
+    let message_id = send_message(conn, user_x, room, "user_x message").await?;
+    let b64_id = id_to_b64(&message_id);
+
+    let update = |json: serde_json::Value| {
+      update_record_handler(
+        State(state.clone()),
+        Path(("messages_api".to_string(), b64_id.clone())),
+        Query(UpdateRecordQuery::default()),
+        User::from_auth_token(&state, &user_x_token.auth_token),
+        HeaderMap::new(),
+        Either::Json(json_row_from_value(json).unwrap()),
+      )
+    };
+
+    // The owner can modify their own message.
+    update(serde_json::json!({"data": "updated"})).await?;
+
+    // ...but cannot hand it over to somebody else.
+    assert!(matches!(
+      update(serde_json::json!({"_owner": id_to_b64(&user_y)})).await,
+      Err(RecordError::Forbidden)
+    ));
+
+    let owner: [u8; 16] = query_one_row(
+      conn,
+      "SELECT _owner FROM message WHERE id = $1",
+      params!(message_id),
+    )
+    .await?
+    .get(0)?;
+    assert_eq!(owner, user_x);
+
+    return Ok(());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_update_bulk() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE product (
+            id           INTEGER PRIMARY KEY,
+            price        INTEGER NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await?;
+    for _ in 0..10 {
+      conn
+        .execute("INSERT INTO product (price) VALUES (100)", ())
+        .await?;
+    }
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "products_api",
+      "product",
+      Acls {
+        world: vec![PermissionFlag::Update],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let update_bulk = |fail_fast: Option<bool>, records: Vec<serde_json::Value>| {
+      update_bulk_handler(
+        State(state.clone()),
+        Path("products_api".to_string()),
+        Query(UpdateBulkQuery { fail_fast }),
+        None,
+        Json(
+          records
+            .into_iter()
+            .map(|r| json_row_from_value(r).unwrap())
+            .collect(),
+        ),
+      )
+    };
+    let prices = || async {
+      let rows = conn
+        .query("SELECT price FROM product ORDER BY id", ())
+        .await?;
+      return rows
+        .iter()
+        .map(|row| row.get::<i64>(0))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(anyhow::Error::from);
+    };
+
+    let response: UpdateBulkResponse = unpack_json_response(
+      update_bulk(
+        None,
+        (1..=10)
+          .map(|id| serde_json::json!({"id": id, "price": 200}))
+          .collect(),
+      )
+      .await?,
+    )
+    .await?;
+    assert_eq!(response.updated, 10);
+    assert!(response.failed.is_empty());
+    assert_eq!(prices().await?, vec![200; 10]);
+
+    // A non-existent id rolls back the entire transaction.
+    let response = update_bulk(
+      Some(true),
+      vec![
+        serde_json::json!({"id": 1, "price": 300}),
+        serde_json::json!({"id": 11, "price": 300}),
+      ],
+    )
+    .await;
+    assert!(matches!(response, Err(RecordError::RecordNotFound)));
+    assert_eq!(prices().await?, vec![200; 10]);
+
+    // Without fail-fast, existing records are updated and missing ones reported.
+    let response = update_bulk(
+      Some(false),
+      vec![
+        serde_json::json!({"id": 1, "price": 300}),
+        serde_json::json!({"id": 11, "price": 300}),
+      ],
+    )
+    .await?;
+    assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+    let response: UpdateBulkResponse = unpack_json_response(response).await?;
+    assert_eq!(response.updated, 1);
+    assert_eq!(response.failed, vec!["11".to_string()]);
+    assert_eq!(prices().await?[0], 300);
+
+    return Ok(());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_update_soft_deleted() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    let conn = state.conn();
+
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE note (
+            id           INTEGER PRIMARY KEY,
+            text         TEXT NOT NULL,
+            deleted_at   INTEGER
+          ) STRICT;
+          INSERT INTO note (text, deleted_at) VALUES ('deleted', UNIXEPOCH()), ('live', NULL);
+        "#,
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    add_record_api(
+      &state,
+      "notes_api",
+      "note",
+      Acls {
+        world: vec![PermissionFlag::Update],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let mut config = state.get_config();
+    config.record_apis.last_mut().unwrap().soft_delete = Some(true);
+    state.validate_and_update_config(config, None).await?;
+
+    let update = |id: &str| {
+      update_record_handler(
+        State(state.clone()),
+        Path(("notes_api".to_string(), id.to_string())),
+        Query(UpdateRecordQuery::default()),
+        None,
+        HeaderMap::new(),
+        Either::Json(json_row_from_value(serde_json::json!({"text": "changed"})).unwrap()),
+      )
+    };
+    let texts = || async {
+      let rows = conn.query("SELECT text FROM note ORDER BY id", ()).await?;
+      return rows
+        .iter()
+        .map(|row| row.get::<String>(0))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(anyhow::Error::from);
+    };
+
+    // Like reads, updates don't see soft-deleted records.
+    assert!(matches!(
+      update("1").await,
+      Err(RecordError::RecordNotFound)
+    ));
+    update("2").await?;
+    assert_eq!(texts().await?, vec!["deleted", "changed"]);
+
+    let response = update_bulk_handler(
+      State(state.clone()),
+      Path("notes_api".to_string()),
+      Query(UpdateBulkQuery {
+        fail_fast: Some(false),
+      }),
+      None,
+      Json(vec![
+        json_row_from_value(serde_json::json!({"id": 1, "text": "bulk"})).unwrap(),
+        json_row_from_value(serde_json::json!({"id": 2, "text": "bulk"})).unwrap(),
+      ]),
+    )
+    .await?;
+    let response: UpdateBulkResponse = unpack_json_response(response).await?;
+    assert_eq!(response.updated, 1);
+    assert_eq!(response.failed, vec!["1".to_string()]);
+    assert_eq!(texts().await?, vec!["deleted", "bulk"]);
+
     return Ok(());
   }
 }
@@ -0,0 +1,557 @@
+use axum::body::Bytes;
+use axum::extract::{Json, Path, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use trailbase_sqlite::schema::FileUploadInput;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::auth::user::User;
+use crate::rand::generate_random_string;
+use crate::records::audit::AuditAttribution;
+use crate::records::json_to_sql::{
+  JsonRow, LazyParams, QueryError, UpdateQueryBuilder, WriteHooks,
+};
+use crate::records::record_api::SOFT_DELETE_COLUMN;
+use crate::records::{Permission, RecordError};
+use crate::table_metadata::JsonColumnMetadata;
+
+/// Upper bound on the size of files uploaded in chunks. Chunks are buffered in memory until the
+/// upload is completed.
+const MAX_UPLOAD_SIZE: u64 = 256 * 1024 * 1024;
+/// Upload sessions, which haven't been completed within this period, get dropped.
+const UPLOAD_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+  /// Still expecting more chunks.
+  Pending,
+  /// All bytes were received and the upload can be completed.
+  Uploaded,
+}
+
+/// State of a chunked file upload into a record's `std.FileUpload` column.
+pub(crate) struct MultipartUploadSession {
+  pub id: String,
+  pub total_bytes: u64,
+  pub uploaded_bytes: AtomicU64,
+
+  api_name: String,
+  record: String,
+  column: String,
+  filename: Option<String>,
+  content_type: Option<String>,
+  user: Option<Uuid>,
+  created: Instant,
+  data: Mutex<Vec<u8>>,
+}
+
+impl MultipartUploadSession {
+  pub fn status(&self) -> UploadStatus {
+    if self.uploaded_bytes.load(Ordering::Acquire) < self.total_bytes {
+      return UploadStatus::Pending;
+    }
+    return UploadStatus::Uploaded;
+  }
+
+  fn progress(&self) -> UploadProgress {
+    return UploadProgress {
+      uploaded_bytes: self.uploaded_bytes.load(Ordering::Acquire),
+      total_bytes: self.total_bytes,
+      status: self.status(),
+    };
+  }
+
+  /// Appends the chunk, which is expected to start right where the previous one ended.
+  fn append(&self, range: &ContentRange, chunk: &[u8]) -> Result<(), RecordError> {
+    if range.total != self.total_bytes {
+      return Err(RecordError::BadRequest("Mismatching total size"));
+    }
+    if range.end - range.start + 1 != chunk.len() as u64 {
+      return Err(RecordError::BadRequest("Mismatching chunk size"));
+    }
+
+    let mut data = self.data.lock();
+    if range.start != data.len() as u64 {
+      return Err(RecordError::BadRequest("Unexpected chunk offset"));
+    }
+    data.extend_from_slice(chunk);
+    self
+      .uploaded_bytes
+      .store(data.len() as u64, Ordering::Release);
+
+    return Ok(());
+  }
+}
+
+/// In-flight chunked uploads by session id.
+#[derive(Default)]
+pub(crate) struct UploadSessions {
+  sessions: Mutex<HashMap<String, Arc<MultipartUploadSession>>>,
+}
+
+impl UploadSessions {
+  fn insert(&self, session: MultipartUploadSession) {
+    let mut sessions = self.sessions.lock();
+    sessions.retain(|_id, s| s.created.elapsed() < UPLOAD_SESSION_TTL);
+    sessions.insert(session.id.clone(), Arc::new(session));
+  }
+
+  fn get(&self, id: &str) -> Option<Arc<MultipartUploadSession>> {
+    return self.sessions.lock().get(id).cloned();
+  }
+
+  fn remove(&self, id: &str) -> Option<Arc<MultipartUploadSession>> {
+    return self.sessions.lock().remove(id);
+  }
+}
+
+/// Parsed `Content-Range: bytes {start}-{end}/{total}` header. End is inclusive.
+#[derive(Debug, PartialEq)]
+struct ContentRange {
+  start: u64,
+  end: u64,
+  total: u64,
+}
+
+fn parse_content_range(headers: &HeaderMap) -> Result<ContentRange, RecordError> {
+  const INVALID: RecordError = RecordError::BadRequest("Invalid Content-Range");
+
+  let value = headers
+    .get(header::CONTENT_RANGE)
+    .ok_or(RecordError::BadRequest("Missing Content-Range"))?
+    .to_str()
+    .map_err(|_| INVALID)?;
+
+  let (start, rest) = value
+    .strip_prefix("bytes ")
+    .and_then(|v| v.split_once('-'))
+    .ok_or(INVALID)?;
+  let (end, total) = rest.split_once('/').ok_or(INVALID)?;
+
+  let range = ContentRange {
+    start: start.trim().parse().map_err(|_| INVALID)?,
+    end: end.trim().parse().map_err(|_| INVALID)?,
+    total: total.trim().parse().map_err(|_| INVALID)?,
+  };
+  if range.start > range.end || range.end >= range.total {
+    return Err(INVALID);
+  }
+  return Ok(range);
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct InitUploadRequest {
+  /// Id of the record to upload the file to.
+  pub record: String,
+  /// Name of the record's `std.FileUpload` column.
+  pub column: String,
+  pub filename: Option<String>,
+  pub content_type: Option<String>,
+  /// Size of the entire file in bytes.
+  pub total_bytes: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct InitUploadResponse {
+  pub session_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct UploadProgress {
+  pub uploaded_bytes: u64,
+  pub total_bytes: u64,
+  pub status: UploadStatus,
+}
+
+/// Start a chunked upload of a file into an existing record's file column.
+#[utoipa::path(
+  post,
+  path = "/:name/upload/init",
+  request_body = InitUploadRequest,
+  responses(
+    (status = 200, description = "Upload session created.", body = InitUploadResponse)
+  )
+)]
+pub async fn init_upload_handler(
+  State(state): State<AppState>,
+  Path(api_name): Path<String>,
+  user: Option<User>,
+  Json(request): Json<InitUploadRequest>,
+) -> Result<Json<InitUploadResponse>, RecordError> {
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  let table_metadata = api
+    .table_metadata()
+    .ok_or_else(|| RecordError::ApiRequiresTable)?;
+
+  let Some((_column, column_metadata)) = table_metadata.column_by_name(&request.column) else {
+    return Err(RecordError::BadRequest("Unknown column"));
+  };
+  match &column_metadata.json {
+    Some(JsonColumnMetadata::SchemaName(name)) if name == "std.FileUpload" => {}
+    _ => return Err(RecordError::BadRequest("Expected std.FileUpload column")),
+  };
+
+  if request.total_bytes == 0 || request.total_bytes > MAX_UPLOAD_SIZE {
+    return Err(RecordError::BadRequest("Invalid upload size"));
+  }
+
+  // Fail early rather than after uploading all the data. The access is re-checked on completion.
+  let record_id = api.id_to_sql(&request.record)?;
+  api
+    .check_record_level_access(Permission::Update, Some(&record_id), None, user.as_ref())
+    .await?;
+
+  let session_id = generate_random_string(32);
+  state.upload_sessions().insert(MultipartUploadSession {
+    id: session_id.clone(),
+    total_bytes: request.total_bytes,
+    uploaded_bytes: AtomicU64::new(0),
+    api_name,
+    record: request.record,
+    column: request.column,
+    filename: request.filename,
+    content_type: request.content_type,
+    user: user.map(|u| u.uuid),
+    created: Instant::now(),
+    data: Mutex::new(vec![]),
+  });
+
+  return Ok(Json(InitUploadResponse { session_id }));
+}
+
+fn lookup_session(
+  state: &AppState,
+  api_name: &str,
+  session_id: &str,
+  user: Option<&User>,
+) -> Result<Arc<MultipartUploadSession>, RecordError> {
+  let Some(session) = state.upload_sessions().get(session_id) else {
+    return Err(RecordError::RecordNotFound);
+  };
+  if session.api_name != api_name || session.user != user.map(|u| u.uuid) {
+    return Err(RecordError::Forbidden);
+  }
+  return Ok(session);
+}
+
+/// Upload the next chunk of a file. Chunks need to be uploaded in order.
+#[utoipa::path(
+  patch,
+  path = "/:name/upload/:session_id",
+  request_body(content = Vec<u8>, description = "Chunk described by the Content-Range header", content_type = "application/octet-stream"),
+  responses(
+    (status = 200, description = "Chunk received.", body = UploadProgress)
+  )
+)]
+pub async fn upload_chunk_handler(
+  State(state): State<AppState>,
+  Path((api_name, session_id)): Path<(String, String)>,
+  user: Option<User>,
+  headers: HeaderMap,
+  body: Bytes,
+) -> Result<Json<UploadProgress>, RecordError> {
+  let session = lookup_session(&state, &api_name, &session_id, user.as_ref())?;
+  let range = parse_content_range(&headers)?;
+
+  session.append(&range, &body)?;
+
+  return Ok(Json(session.progress()));
+}
+
+/// Get the progress of a chunked upload.
+#[utoipa::path(
+  get,
+  path = "/:name/upload/:session_id/progress",
+  responses(
+    (status = 200, description = "Upload progress.", body = UploadProgress)
+  )
+)]
+pub async fn upload_progress_handler(
+  State(state): State<AppState>,
+  Path((api_name, session_id)): Path<(String, String)>,
+  user: Option<User>,
+) -> Result<Json<UploadProgress>, RecordError> {
+  let session = lookup_session(&state, &api_name, &session_id, user.as_ref())?;
+  return Ok(Json(session.progress()));
+}
+
+/// Complete a chunked upload storing the file in the record's column.
+#[utoipa::path(
+  post,
+  path = "/:name/upload/:session_id/complete",
+  responses(
+    (status = 200, description = "File stored.")
+  )
+)]
+pub async fn complete_upload_handler(
+  State(state): State<AppState>,
+  Path((api_name, session_id)): Path<(String, String)>,
+  user: Option<User>,
+) -> Result<Response, RecordError> {
+  let session = lookup_session(&state, &api_name, &session_id, user.as_ref())?;
+  if session.status() != UploadStatus::Uploaded {
+    return Err(RecordError::BadRequest("Upload incomplete"));
+  }
+
+  let Some(api) = state.lookup_record_api(&api_name) else {
+    return Err(RecordError::ApiNotFound);
+  };
+  let table_metadata = api
+    .table_metadata()
+    .ok_or_else(|| RecordError::ApiRequiresTable)?;
+  let record_id = api.id_to_sql(&session.record)?;
+
+  // Consume the session. Concurrent chunks will be rejected, since all bytes were received.
+  let Some(session) = state.upload_sessions().remove(&session.id) else {
+    return Err(RecordError::RecordNotFound);
+  };
+  let file = FileUploadInput {
+    name: Some(session.column.clone()),
+    filename: session.filename.clone(),
+    content_type: session.content_type.clone(),
+    data: std::mem::take(&mut *session.data.lock()),
+  };
+
+  let mut lazy_params = LazyParams::new(table_metadata, JsonRow::new(), Some(vec![file]));
+  api
+    .check_record_level_access(
+      Permission::Update,
+      Some(&record_id),
+      Some(&mut lazy_params),
+      user.as_ref(),
+    )
+    .await?;
+
+  UpdateQueryBuilder::run(
+    &state,
+    table_metadata,
+    lazy_params
+      .consume()
+      .map_err(|err| RecordError::Internal(err.into()))?,
+    &api.record_pk_column().name,
+    record_id,
+    api.soft_delete().then_some(SOFT_DELETE_COLUMN),
+    WriteHooks {
+      precondition: None,
+      audit: AuditAttribution::new(&api, user.as_ref()),
+    },
+  )
+  .await
+  .map_err(|err| match err {
+    QueryError::NotFound => RecordError::RecordNotFound,
+    err => RecordError::Internal(err.into()),
+  })?;
+
+  return Ok(().into_response());
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::extract::Query;
+  use axum::http::HeaderValue;
+
+  use super::*;
+  use crate::app_state::*;
+  use crate::config::proto::PermissionFlag;
+  use crate::extract::Either;
+  use crate::records::create_record::{
+    create_record_handler, CreateRecordQuery, CreateRecordResponse,
+  };
+  use crate::records::read_record::get_uploaded_file_from_record_handler;
+  use crate::records::test_utils::*;
+  use crate::records::*;
+  use crate::test::unpack_json_response;
+
+  #[test]
+  fn test_parse_content_range() {
+    let parse = |value: &str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::CONTENT_RANGE, HeaderValue::from_str(value).unwrap());
+      return parse_content_range(&headers);
+    };
+
+    assert_eq!(
+      parse("bytes 0-99/1000").unwrap(),
+      ContentRange {
+        start: 0,
+        end: 99,
+        total: 1000
+      }
+    );
+    assert!(parse("bytes 0-1000/1000").is_err());
+    assert!(parse("bytes 10-5/1000").is_err());
+    assert!(parse("bytes */1000").is_err());
+    assert!(parse("0-99/1000").is_err());
+    assert!(parse_content_range(&HeaderMap::new()).is_err());
+  }
+
+  #[tokio::test]
+  async fn test_chunked_upload() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+    state
+      .conn()
+      .execute(
+        r#"CREATE TABLE test_table (
+          id           BLOB PRIMARY KEY NOT NULL CHECK(is_uuid_v7(id)) DEFAULT(uuid_v7()),
+          file         TEXT CHECK(jsonschema('std.FileUpload', file))
+        ) STRICT"#,
+        (),
+      )
+      .await?;
+    state.table_metadata().invalidate_all().await?;
+
+    const API_NAME: &str = "test_api";
+    add_record_api(
+      &state,
+      API_NAME,
+      "test_table",
+      Acls {
+        world: vec![
+          PermissionFlag::Create,
+          PermissionFlag::Read,
+          PermissionFlag::Update,
+        ],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await?;
+
+    let record: CreateRecordResponse = unpack_json_response(
+      create_record_handler(
+        State(state.clone()),
+        Path(API_NAME.to_string()),
+        Query(CreateRecordQuery::default()),
+        None,
+        Either::Json(JsonRow::new()),
+      )
+      .await?,
+    )
+    .await?;
+
+    const TOTAL: usize = 1024 * 1024;
+    const CHUNK: usize = 256 * 1024;
+    let data: Vec<u8> = (0..TOTAL).map(|i| (i % 251) as u8).collect();
+
+    let Json(InitUploadResponse { session_id }) = init_upload_handler(
+      State(state.clone()),
+      Path(API_NAME.to_string()),
+      None,
+      Json(InitUploadRequest {
+        record: record.id.clone(),
+        column: "file".to_string(),
+        filename: Some("data.bin".to_string()),
+        content_type: Some("application/octet-stream".to_string()),
+        total_bytes: TOTAL as u64,
+      }),
+    )
+    .await?;
+
+    let session_path = || Path((API_NAME.to_string(), session_id.clone()));
+    let upload_chunk = |start: usize, chunk: &[u8]| {
+      let mut headers = HeaderMap::new();
+      headers.insert(
+        header::CONTENT_RANGE,
+        HeaderValue::from_str(&format!(
+          "bytes {start}-{end}/{TOTAL}",
+          end = start + chunk.len() - 1
+        ))
+        .unwrap(),
+      );
+      return upload_chunk_handler(
+        State(state.clone()),
+        session_path(),
+        None,
+        headers,
+        Bytes::copy_from_slice(chunk),
+      );
+    };
+
+    for (index, chunk) in data.chunks(CHUNK).enumerate() {
+      assert!(
+        complete_upload_handler(State(state.clone()), session_path(), None)
+          .await
+          .is_err()
+      );
+
+      upload_chunk(index * CHUNK, chunk).await?;
+
+      let Json(progress) =
+        upload_progress_handler(State(state.clone()), session_path(), None).await?;
+      assert_eq!(progress.uploaded_bytes, ((index + 1) * CHUNK) as u64);
+      assert_eq!(progress.total_bytes, TOTAL as u64);
+    }
+
+    // Chunks are rejected once everything was received.
+    assert!(upload_chunk(0, &data[0..CHUNK]).await.is_err());
+
+    complete_upload_handler(State(state.clone()), session_path(), None).await?;
+
+    // The session is consumed on completion.
+    assert!(
+      upload_progress_handler(State(state.clone()), session_path(), None)
+        .await
+        .is_err()
+    );
+
+    let response = get_uploaded_file_from_record_handler(
+      State(state.clone()),
+      Path((API_NAME.to_string(), record.id, "file".to_string())),
+      None,
+    )
+    .await?;
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    assert_eq!(body.to_vec(), data);
+
+    return Ok(());
+  }
+
+  #[tokio::test]
+  async fn test_chunked_upload_out_of_order() -> Result<(), anyhow::Error> {
+    let state = test_state(None).await?;
+
+    let session = MultipartUploadSession {
+      id: "id".to_string(),
+      total_bytes: 10,
+      uploaded_bytes: AtomicU64::new(0),
+      api_name: "api".to_string(),
+      record: "record".to_string(),
+      column: "file".to_string(),
+      filename: None,
+      content_type: None,
+      user: None,
+      created: Instant::now(),
+      data: Mutex::new(vec![]),
+    };
+    let range = |start: u64, end: u64| ContentRange {
+      start,
+      end,
+      total: 10,
+    };
+
+    assert!(session.append(&range(5, 9), &[0; 5]).is_err());
+    assert!(session.append(&range(0, 4), &[0; 4]).is_err());
+    session.append(&range(0, 4), &[0; 5])?;
+    assert_eq!(session.status(), UploadStatus::Pending);
+    assert!(session.append(&range(0, 4), &[0; 5]).is_err());
+    session.append(&range(5, 9), &[0; 5])?;
+    assert_eq!(session.status(), UploadStatus::Uploaded);
+
+    state.upload_sessions().insert(session);
+    assert!(lookup_session(&state, "other_api", "id", None).is_err());
+    assert!(lookup_session(&state, "api", "id", None).is_ok());
+
+    return Ok(());
+  }
+}
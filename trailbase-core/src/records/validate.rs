@@ -1,4 +1,8 @@
+use crate::config::proto::PermissionFlag;
 use crate::config::{proto, ConfigError};
+use crate::constants::ADMIN_AUDIT_LOG_TABLE;
+use crate::email::is_valid_template_name;
+use crate::records::record_api::{RecordApi, SOFT_DELETE_COLUMN};
 use crate::table_metadata::{
   sqlite3_parse_into_statements, TableMetadataCache, TableOrViewMetadata,
 };
@@ -34,6 +38,19 @@ pub(crate) fn validate_record_api_config(
     return ierr("RecordApi config misses table name.");
   };
 
+  if table_name == ADMIN_AUDIT_LOG_TABLE {
+    let read_only = api_config
+      .acl_world
+      .iter()
+      .chain(&api_config.acl_authenticated)
+      .all(|flag| *flag == PermissionFlag::Read as i32 || *flag == PermissionFlag::Schema as i32);
+    if !read_only {
+      return Err(ConfigError::Invalid(format!(
+        "The admin audit log is immutable, api '{name}' may only grant read access."
+      )));
+    }
+  }
+
   if let Some(metadata) = tables.get(table_name) {
     if !metadata.schema.strict {
       return Err(ConfigError::Invalid(format!(
@@ -47,6 +64,52 @@ pub(crate) fn validate_record_api_config(
         metadata.schema
       )));
     }
+
+    let per_user_quota = api_config
+      .quota
+      .as_ref()
+      .is_some_and(|q| q.max_records_per_user.is_some());
+    if per_user_quota && metadata.user_id_columns.is_empty() {
+      return Err(ConfigError::Invalid(format!(
+        "Table for api '{name}' requires a user id column for per-user quotas."
+      )));
+    }
+
+    if let Some(ref email) = api_config.send_email_on_create {
+      let to_field = email.to_field.as_deref().unwrap_or_default();
+      if metadata.column_by_name(to_field).is_none() {
+        return Err(ConfigError::Invalid(format!(
+          "Email 'to_field' for api '{name}' is not a column: '{to_field}'"
+        )));
+      }
+
+      let template = email.template.as_deref().unwrap_or_default();
+      if !is_valid_template_name(template) {
+        return Err(ConfigError::Invalid(format!(
+          "Invalid email template for api '{name}': '{template}'"
+        )));
+      }
+    }
+
+    if let Some(ref notify) = api_config.notify_on_change {
+      let to_user_field = notify.to_user_field.as_deref().unwrap_or_default();
+      let is_user_id_column = metadata
+        .column_index_by_name(to_user_field)
+        .is_some_and(|index| metadata.user_id_columns.contains(&index));
+      if !is_user_id_column {
+        return Err(ConfigError::Invalid(format!(
+          "Notification 'to_user_field' for api '{name}' is not a user id column: '{to_user_field}'"
+        )));
+      }
+    }
+
+    if api_config.soft_delete.unwrap_or(false)
+      && metadata.column_by_name(SOFT_DELETE_COLUMN).is_none()
+    {
+      return Err(ConfigError::Invalid(format!(
+        "Table for api '{name}' requires a '{SOFT_DELETE_COLUMN}' column for soft deletion."
+      )));
+    }
   } else if let Some(metadata) = tables.get_view(table_name) {
     if metadata.schema.temporary {
       return Err(ConfigError::Invalid(format!(
@@ -61,6 +124,36 @@ pub(crate) fn validate_record_api_config(
       )));
     }
 
+    if api_config.soft_delete.unwrap_or(false) {
+      return Err(ConfigError::Invalid(format!(
+        "View for api '{name}' does not support soft deletion."
+      )));
+    }
+
+    if api_config.audit_trail.unwrap_or(false) {
+      return Err(ConfigError::Invalid(format!(
+        "View for api '{name}' does not support audit trails."
+      )));
+    }
+
+    if api_config.quota.is_some() {
+      return Err(ConfigError::Invalid(format!(
+        "View for api '{name}' does not support quotas."
+      )));
+    }
+
+    if api_config.send_email_on_create.is_some() {
+      return Err(ConfigError::Invalid(format!(
+        "View for api '{name}' does not support emails on create."
+      )));
+    }
+
+    if api_config.notify_on_change.is_some() {
+      return Err(ConfigError::Invalid(format!(
+        "View for api '{name}' does not support push notifications."
+      )));
+    }
+
     let Some(ref _columns) = metadata.schema.columns else {
       return Err(ConfigError::Invalid(format!(
         "View for api '{name}' is not a \"simple\" view, i.e. the column types couldn't be inferred and thus type-safety cannot be guaranteed."
@@ -72,12 +165,27 @@ pub(crate) fn validate_record_api_config(
     )));
   }
 
+  for origin in &api_config.cors_allowed_origins {
+    if origin.is_empty() || axum::http::HeaderValue::from_str(origin).is_err() {
+      return Err(ConfigError::Invalid(format!(
+        "Invalid CORS origin for api '{name}': '{origin}'"
+      )));
+    }
+  }
+
+  if api_config.max_body_size_bytes == Some(0) {
+    return Err(ConfigError::Invalid(format!(
+      "Invalid max body size for api '{name}': must be positive"
+    )));
+  }
+
   let rules = [
     &api_config.create_access_rule,
     &api_config.read_access_rule,
     &api_config.update_access_rule,
     &api_config.delete_access_rule,
     &api_config.schema_access_rule,
+    &api_config.write_access_rule,
   ];
   for rule in rules.into_iter().flatten() {
     let map = |err| ConfigError::Invalid(format!("'{rule}' not a valid SQL expression: {err}"));
@@ -94,3 +202,38 @@ pub(crate) fn validate_record_api_config(
 
   return Ok(name.clone());
 }
+
+/// Compiles the access queries of all configured record APIs against the current schema using
+/// `EXPLAIN`. Unlike the syntax check above, this also catches rules referencing unknown tables or
+/// columns, which would otherwise only fail at request time.
+pub(crate) async fn validate_record_api_access_rules(
+  conn: &trailbase_sqlite::Connection,
+  tables: &TableMetadataCache,
+  config: &proto::Config,
+) -> Result<(), ConfigError> {
+  for api_config in &config.record_apis {
+    let Some(ref table_name) = api_config.table_name else {
+      continue;
+    };
+
+    let api = if let Some(metadata) = tables.get(table_name) {
+      RecordApi::from_table(conn.clone(), (*metadata).clone(), api_config.clone())
+    } else if let Some(metadata) = tables.get_view(table_name) {
+      RecordApi::from_view(conn.clone(), (*metadata).clone(), api_config.clone())
+    } else {
+      continue;
+    }
+    .map_err(ConfigError::Invalid)?;
+
+    for query in api.access_queries() {
+      if let Err(err) = conn.explain(query, ()).await {
+        return Err(ConfigError::Invalid(format!(
+          "Invalid access rule for api '{}': {err}",
+          api.api_name()
+        )));
+      }
+    }
+  }
+
+  return Ok(());
+}
@@ -0,0 +1,148 @@
+use axum::extract::Request;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+
+use crate::constants::HEADER_REQUEST_ID;
+
+/// Upper bound for client-provided request ids. Longer ones get replaced.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+  static REQUEST_ID: RequestId;
+}
+
+/// Id correlating a request with its response and log entry. Client-provided ids, i.e. via the
+/// "X-Request-ID" header, are respected if they're sane. Otherwise a UUIDv7 is generated.
+#[derive(Clone, Debug)]
+pub(crate) struct RequestId(pub String);
+
+impl RequestId {
+  /// Returns the id of the request currently being handled, if any.
+  pub(crate) fn current() -> Option<RequestId> {
+    return REQUEST_ID.try_with(|id| id.clone()).ok();
+  }
+}
+
+fn sanitize(value: &HeaderValue) -> Option<String> {
+  let value = value.to_str().ok()?.trim();
+  if value.is_empty()
+    || value.len() > MAX_REQUEST_ID_LENGTH
+    || !value
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+  {
+    return None;
+  }
+  return Some(value.to_string());
+}
+
+/// Middleware attaching a [RequestId] to the request's extensions, e.g. for the logging span, and
+/// to the response as "X-Request-ID" header.
+///
+/// NOTE: Needs to be installed outside the tracing layer for the id to be logged.
+pub(crate) async fn request_id_middleware(mut req: Request, next: Next) -> Response {
+  let request_id = RequestId(
+    req
+      .headers()
+      .get(HEADER_REQUEST_ID)
+      .and_then(sanitize)
+      .unwrap_or_else(|| uuid::Uuid::now_v7().to_string()),
+  );
+  req.extensions_mut().insert(request_id.clone());
+
+  let header = HeaderValue::from_str(&request_id.0);
+  let mut response = REQUEST_ID.scope(request_id, next.run(req)).await;
+  if let Ok(header) = header {
+    response.headers_mut().insert(HEADER_REQUEST_ID, header);
+  }
+  return response;
+}
+
+/// Builds a JSON error response, i.e. `{ "error": "...", "request_id": "..." }`, allowing clients
+/// to correlate errors with server logs.
+pub(crate) fn error_response(status: StatusCode, message: Option<String>) -> Response {
+  let error = message.unwrap_or_else(|| {
+    status
+      .canonical_reason()
+      .unwrap_or_else(|| status.as_str())
+      .to_string()
+  });
+
+  return (
+    status,
+    Json(serde_json::json!({
+      "error": error,
+      "request_id": RequestId::current().map(|id| id.0),
+    })),
+  )
+    .into_response();
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::routing::get;
+  use axum::{middleware, Router};
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::records::RecordError;
+
+  #[tokio::test]
+  async fn test_request_id_header() {
+    let router = Router::new()
+      .route("/ok", get(|| async { "Ok" }))
+      .route(
+        "/err",
+        get(|| async { Err::<(), _>(RecordError::RecordNotFound) }),
+      )
+      .layer(middleware::from_fn(request_id_middleware));
+
+    let request = |path: &str, request_id: Option<&str>| {
+      let mut builder = Request::builder().uri(path);
+      if let Some(request_id) = request_id {
+        builder = builder.header(HEADER_REQUEST_ID, request_id);
+      }
+      return builder.body(Body::empty()).unwrap();
+    };
+    let request_id = |response: &Response| -> String {
+      return response
+        .headers()
+        .get(HEADER_REQUEST_ID)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    };
+
+    let response = router.clone().oneshot(request("/ok", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(uuid::Uuid::parse_str(&request_id(&response)).is_ok());
+
+    let response = router.clone().oneshot(request("/err", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let id = request_id(&response);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+      .await
+      .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["request_id"], id);
+    assert_eq!(body["error"], "Not Found");
+
+    // Sane client-provided ids are echoed back, others get replaced.
+    let response = router
+      .clone()
+      .oneshot(request("/ok", Some("client-id-1")))
+      .await
+      .unwrap();
+    assert_eq!(request_id(&response), "client-id-1");
+
+    let response = router
+      .clone()
+      .oneshot(request("/err", Some("<script>")))
+      .await
+      .unwrap();
+    assert_ne!(request_id(&response), "<script>");
+  }
+}
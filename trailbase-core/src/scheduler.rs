@@ -1,28 +1,139 @@
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::future::BoxFuture;
 use log::*;
+use parking_lot::{Mutex, RwLock};
 use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
 use trailbase_sqlite::params;
 
 use crate::app_state::AppState;
 use crate::constants::{DEFAULT_REFRESH_TOKEN_TTL, LOGS_RETENTION_DEFAULT, SESSION_TABLE};
 
+type JobCallback = Arc<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Validates the given cron expression and returns its next `count` run times.
+pub(crate) fn preview_next(
+  schedule: &str,
+  count: usize,
+) -> Result<Vec<DateTime<Utc>>, cron::error::Error> {
+  let schedule = cron::Schedule::from_str(schedule)?;
+  return Ok(schedule.upcoming(Utc).take(count).collect());
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum JobSchedule {
+  Interval(Duration),
+  Cron(Box<cron::Schedule>),
+}
+
+impl JobSchedule {
+  pub(crate) fn cron(expression: &str) -> Result<Self, cron::error::Error> {
+    return Ok(Self::Cron(Box::new(cron::Schedule::from_str(expression)?)));
+  }
+
+  fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    return match self {
+      Self::Interval(period) => Some(time + *period),
+      Self::Cron(schedule) => schedule.after(&time).next(),
+    };
+  }
+}
+
+impl std::fmt::Display for JobSchedule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::Interval(period) => write!(f, "every {}s", period.num_seconds()),
+      Self::Cron(schedule) => write!(f, "{}", schedule.source()),
+    };
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct JobStatus {
+  pub last_run_at: Option<DateTime<Utc>>,
+  pub next_run_at: Option<DateTime<Utc>>,
+  pub error_count: u64,
+  pub last_error: Option<String>,
+}
+
+pub(crate) struct Job {
+  pub name: String,
+  pub schedule: JobSchedule,
+  callback: JobCallback,
+  status: Mutex<JobStatus>,
+}
+
+impl Job {
+  /// Runs the job immediately, independent of its schedule.
+  pub(crate) async fn run(&self) -> Result<(), String> {
+    let result = (self.callback)().await;
+
+    let mut status = self.status.lock();
+    status.last_run_at = Some(Utc::now());
+    if let Err(ref err) = result {
+      warn!("Job '{}' failed: {err}", self.name);
+      status.error_count += 1;
+      status.last_error = Some(err.clone());
+    }
+    return result;
+  }
+
+  pub(crate) fn status(&self) -> JobStatus {
+    return self.status.lock().clone();
+  }
+}
+
+/// Registry of all scheduled jobs, e.g. for inspecting and triggering them from the admin API.
 #[derive(Default)]
-pub struct AbortOnDrop {
-  handles: Vec<tokio::task::AbortHandle>,
+pub(crate) struct JobRegistry {
+  jobs: RwLock<Vec<Arc<Job>>>,
 }
 
-impl AbortOnDrop {
-  fn add_periodic_task<F, Fut>(&mut self, period: Duration, f: F)
+impl JobRegistry {
+  pub(crate) fn new_job<F, Fut>(&self, name: &str, schedule: JobSchedule, f: F) -> Arc<Job>
   where
     F: 'static + Sync + Send + Fn() -> Fut,
-    Fut: Sync + Send + Future,
+    Fut: 'static + Send + Future<Output = Result<(), String>>,
   {
+    let job = Arc::new(Job {
+      name: name.to_string(),
+      schedule,
+      callback: Arc::new(move || Box::pin(f())),
+      status: Mutex::new(JobStatus::default()),
+    });
+
+    let mut jobs = self.jobs.write();
+    jobs.retain(|j| j.name != name);
+    jobs.push(job.clone());
+    return job;
+  }
+
+  pub(crate) fn jobs(&self) -> Vec<Arc<Job>> {
+    return self.jobs.read().clone();
+  }
+
+  pub(crate) fn get(&self, name: &str) -> Option<Arc<Job>> {
+    return self.jobs.read().iter().find(|j| j.name == name).cloned();
+  }
+}
+
+#[derive(Default)]
+pub struct AbortOnDrop {
+  handles: Vec<tokio::task::AbortHandle>,
+}
+
+impl AbortOnDrop {
+  fn add_job(&mut self, job: Arc<Job>) {
     let handle = tokio::spawn(async move {
-      let period = period.to_std().unwrap();
-      let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
       loop {
-        interval.tick().await;
-        f().await;
+        let Some(next) = job.schedule.next_after(Utc::now()) else {
+          return;
+        };
+        job.status.lock().next_run_at = Some(next);
+
+        tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
+        let _ = job.run().await;
       }
     });
 
@@ -40,10 +151,16 @@ impl Drop for AbortOnDrop {
 
 pub(super) fn start_periodic_tasks(app_state: &AppState) -> AbortOnDrop {
   let mut tasks = AbortOnDrop::default();
+  let jobs = app_state.jobs();
 
-  tasks.add_periodic_task(Duration::seconds(60), || async {
-    info!("alive");
-  });
+  tasks.add_job(jobs.new_job(
+    "heartbeat",
+    JobSchedule::Interval(Duration::seconds(60)),
+    || async {
+      info!("alive");
+      Ok(())
+    },
+  ));
 
   // Backup job.
   let conn = app_state.conn().clone();
@@ -52,27 +169,30 @@ pub(super) fn start_periodic_tasks(app_state: &AppState) -> AbortOnDrop {
     .access_config(|c| c.server.backup_interval_sec)
     .map_or(Duration::zero(), Duration::seconds);
   if !backup_interval.is_zero() {
-    tasks.add_periodic_task(backup_interval, move || {
-      let conn = conn.clone();
-      let backup_file = backup_file.clone();
+    tasks.add_job(jobs.new_job(
+      "backup",
+      JobSchedule::Interval(backup_interval),
+      move || {
+        let conn = conn.clone();
+        let backup_file = backup_file.clone();
 
-      async move {
-        let result = conn
-          .call(|conn| {
-            return Ok(conn.backup(
-              rusqlite::DatabaseName::Main,
-              backup_file,
-              /* progress= */ None,
-            )?);
-          })
-          .await;
-
-        match result {
-          Ok(_) => info!("Backup complete"),
-          Err(err) => error!("Backup failed: {err}"),
-        };
-      }
-    });
+        async move {
+          conn
+            .call(|conn| {
+              return Ok(conn.backup(
+                rusqlite::DatabaseName::Main,
+                backup_file,
+                /* progress= */ None,
+              )?);
+            })
+            .await
+            .map_err(|err| format!("Backup failed: {err}"))?;
+
+          info!("Backup complete");
+          return Ok(());
+        }
+      },
+    ));
   }
 
   // Logs cleaner.
@@ -82,60 +202,135 @@ pub(super) fn start_periodic_tasks(app_state: &AppState) -> AbortOnDrop {
     .map_or(LOGS_RETENTION_DEFAULT, Duration::seconds);
 
   if !retention.is_zero() {
-    tasks.add_periodic_task(Duration::hours(2), move || {
-      let logs_conn = logs_conn.clone();
+    tasks.add_job(jobs.new_job(
+      "logs_cleaner",
+      JobSchedule::Interval(Duration::hours(2)),
+      move || {
+        let logs_conn = logs_conn.clone();
 
-      tokio::spawn(async move {
-        let timestamp = (Utc::now() - retention).timestamp();
-        match logs_conn
-          .execute("DELETE FROM _logs WHERE created < $1", params!(timestamp))
-          .await
-        {
-          Ok(_) => info!("Successfully pruned logs"),
-          Err(err) => warn!("Failed to clean up old logs: {err}"),
-        };
-      })
-    });
+        async move {
+          let timestamp = (Utc::now() - retention).timestamp();
+          logs_conn
+            .execute("DELETE FROM _logs WHERE created < $1", params!(timestamp))
+            .await
+            .map_err(|err| format!("Failed to clean up old logs: {err}"))?;
+
+          info!("Successfully pruned logs");
+          return Ok(());
+        }
+      },
+    ));
   }
 
   // Refresh token cleaner.
   let state = app_state.clone();
-  tasks.add_periodic_task(Duration::hours(12), move || {
-    let state = state.clone();
-
-    tokio::spawn(async move {
-      let refresh_token_ttl = state
-        .access_config(|c| c.auth.refresh_token_ttl_sec)
-        .map_or(DEFAULT_REFRESH_TOKEN_TTL, Duration::seconds);
-
-      let timestamp = (Utc::now() - refresh_token_ttl).timestamp();
-
-      match state
-        .user_conn()
-        .execute(
-          &format!("DELETE FROM '{SESSION_TABLE}' WHERE updated < $1"),
-          params!(timestamp),
-        )
-        .await
-      {
-        Ok(count) => info!("Successfully pruned {count} old sessions."),
-        Err(err) => warn!("Failed to clean up sessions: {err}"),
-      };
-    })
-  });
+  tasks.add_job(jobs.new_job(
+    "session_cleaner",
+    JobSchedule::Interval(Duration::hours(12)),
+    move || {
+      let state = state.clone();
+
+      async move {
+        let refresh_token_ttl = state
+          .access_config(|c| c.auth.refresh_token_ttl_sec)
+          .map_or(DEFAULT_REFRESH_TOKEN_TTL, Duration::seconds);
+
+        let timestamp = (Utc::now() - refresh_token_ttl).timestamp();
+
+        let count = state
+          .user_conn()
+          .execute(
+            &format!("DELETE FROM '{SESSION_TABLE}' WHERE updated < $1"),
+            params!(timestamp),
+          )
+          .await
+          .map_err(|err| format!("Failed to clean up sessions: {err}"))?;
+
+        info!("Successfully pruned {count} old sessions.");
+        return Ok(());
+      }
+    },
+  ));
 
   // Optimizer
   let conn = app_state.conn().clone();
-  tasks.add_periodic_task(Duration::hours(24), move || {
-    let conn = conn.clone();
+  tasks.add_job(jobs.new_job(
+    "optimizer",
+    JobSchedule::Interval(Duration::hours(24)),
+    move || {
+      let conn = conn.clone();
 
-    tokio::spawn(async move {
-      match conn.execute("PRAGMA optimize", ()).await {
-        Ok(_) => info!("Successfully ran query optimizer"),
-        Err(err) => warn!("query optimizer failed: {err}"),
-      };
-    })
-  });
+      async move {
+        conn
+          .execute("PRAGMA optimize", ())
+          .await
+          .map_err(|err| format!("query optimizer failed: {err}"))?;
+
+        info!("Successfully ran query optimizer");
+        return Ok(());
+      }
+    },
+  ));
+
+  // Webhook deliveries.
+  let conn = app_state.conn().clone();
+  let client = crate::webhooks::delivery_client();
+  tasks.add_job(jobs.new_job(
+    "webhook_delivery",
+    JobSchedule::Interval(Duration::seconds(5)),
+    move || {
+      let conn = conn.clone();
+      let client = client.clone();
+
+      async move {
+        let count = crate::webhooks::deliver_pending_webhooks(&conn, &client)
+          .await
+          .map_err(|err| format!("Webhook delivery failed: {err}"))?;
+
+        if count > 0 {
+          debug!("Delivered {count} webhooks");
+        }
+        return Ok(());
+      }
+    },
+  ));
+
+  // External OAuth provider access token refresh.
+  let state = app_state.clone();
+  tasks.add_job(jobs.new_job(
+    "oauth_token_refresh",
+    JobSchedule::Interval(Duration::minutes(5)),
+    move || {
+      let state = state.clone();
+
+      async move {
+        let count = crate::auth::oauth::provider_tokens::refresh_expiring_oauth_tokens(&state)
+          .await
+          .map_err(|err| format!("OAuth token refresh failed: {err}"))?;
+
+        if count > 0 {
+          debug!("Refreshed {count} OAuth tokens");
+        }
+        return Ok(());
+      }
+    },
+  ));
 
   return tasks;
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_preview_next() {
+    let runs = preview_next("0 */5 * * * *", 3).unwrap();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs[1] - runs[0], Duration::minutes(5));
+    assert_eq!(runs[2] - runs[1], Duration::minutes(5));
+
+    assert!(preview_next("not a schedule", 3).is_err());
+    assert!(JobSchedule::cron("0 61 * * * *").is_err());
+  }
+}
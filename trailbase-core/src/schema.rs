@@ -11,6 +11,8 @@ use ts_rs::TS;
 pub enum SchemaError {
   #[error("Missing ObjectName")]
   MissingName,
+  #[error("Invalid identifier: {0}")]
+  InvalidIdentifier(String),
   #[error("Precondition failed: {0}")]
   Precondition(Box<dyn std::error::Error + Send + Sync>),
 }
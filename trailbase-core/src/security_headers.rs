@@ -0,0 +1,172 @@
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+
+use crate::rand::generate_random_string;
+
+/// Placeholder in CSP policies, which is substituted with the response's [CspNonce].
+pub(crate) const CSP_NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// Default policy of the auth UI's HTML pages unless overridden by the server's `csp_policy`.
+pub(crate) const AUTH_UI_CSP_POLICY: &str =
+  "default-src 'self'; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'unsafe-inline'";
+
+const CSP_NONCE_LENGTH: usize = 24;
+
+/// Nonce allow-listing a response's inline scripts. Pages embedding the nonce into their script
+/// tags attach it to the response's extensions for the CSP header to match.
+#[derive(Clone, Debug)]
+pub(crate) struct CspNonce(pub String);
+
+impl CspNonce {
+  pub(crate) fn generate() -> Self {
+    return CspNonce(generate_random_string(CSP_NONCE_LENGTH));
+  }
+
+  /// Returns the given policy with the placeholder replaced by this nonce.
+  pub(crate) fn apply(&self, policy: &str) -> Option<HeaderValue> {
+    return HeaderValue::from_str(&policy.replace(CSP_NONCE_PLACEHOLDER, &self.0)).ok();
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SecurityHeaders {
+  /// Content-Security-Policy for all HTML responses.
+  pub csp_policy: Option<String>,
+  /// Max age in seconds for the Strict-Transport-Security header.
+  pub hsts_max_age: Option<u64>,
+}
+
+/// Middleware injecting the configured Content-Security-Policy into HTML responses and
+/// Strict-Transport-Security into all responses.
+pub(crate) async fn security_headers_middleware(
+  State(opts): State<Arc<SecurityHeaders>>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let mut response = next.run(request).await;
+
+  if let Some(ref policy) = opts.csp_policy {
+    let is_html = response
+      .headers()
+      .get(header::CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .is_some_and(|content_type| content_type.starts_with("text/html"));
+
+    if is_html {
+      // Responses without their own nonce get a fresh one, i.e. inline scripts are disallowed.
+      let header = match response.extensions().get::<CspNonce>() {
+        Some(nonce) => nonce.apply(policy),
+        None => CspNonce::generate().apply(policy),
+      };
+
+      match header {
+        Some(header) => {
+          response
+            .headers_mut()
+            .insert(header::CONTENT_SECURITY_POLICY, header);
+        }
+        None => log::warn!("Invalid CSP policy: {policy}"),
+      };
+    }
+  }
+
+  if let Some(max_age) = opts.hsts_max_age {
+    response.headers_mut().insert(
+      header::STRICT_TRANSPORT_SECURITY,
+      HeaderValue::from_str(&format!("max-age={max_age}")).expect("valid header"),
+    );
+  }
+
+  return response;
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::http::StatusCode;
+  use axum::{middleware, Router};
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::auth::auth_ui_router;
+
+  #[tokio::test]
+  async fn test_auth_ui_csp_nonce() {
+    let state = test_state(None).await.unwrap();
+
+    let router = |opts: SecurityHeaders| {
+      return auth_ui_router()
+        .layer(middleware::from_fn_with_state(
+          Arc::new(opts),
+          security_headers_middleware,
+        ))
+        .with_state(state.clone());
+    };
+    let login = |router: Router, uri: &'static str| async move {
+      let response = router
+        .oneshot(axum::http::Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+      assert_eq!(response.status(), StatusCode::OK);
+
+      let csp = response
+        .headers()
+        .get(header::CONTENT_SECURITY_POLICY)
+        .map(|value| value.to_str().unwrap().to_string());
+      let hsts = response
+        .headers()
+        .get(header::STRICT_TRANSPORT_SECURITY)
+        .map(|value| value.to_str().unwrap().to_string());
+      let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+      return (csp, hsts, String::from_utf8(body.to_vec()).unwrap());
+    };
+
+    let nonce_of = |csp: &str| -> String {
+      const NEEDLE: &str = "'nonce-";
+      let start = csp.find(NEEDLE).unwrap() + NEEDLE.len();
+      return csp[start..].split('\'').next().unwrap().to_string();
+    };
+
+    // The auth UI comes with a default policy.
+    let (csp, hsts, html) = login(router(SecurityHeaders::default()), "/_/auth/login").await;
+    let csp = csp.unwrap();
+    assert!(csp.starts_with("default-src 'self'"), "{csp}");
+    assert_eq!(hsts, None);
+    let nonce = nonce_of(&csp);
+    assert_eq!(nonce.len(), CSP_NONCE_LENGTH);
+    assert!(html.contains(&format!("<script nonce=\"{nonce}\"")));
+
+    // A configured policy takes precedence but shares the page's nonce.
+    let (csp, hsts, html) = login(
+      router(SecurityHeaders {
+        csp_policy: Some("default-src 'none'; script-src 'nonce-{nonce}'".to_string()),
+        hsts_max_age: Some(3600),
+      }),
+      "/_/auth/login",
+    )
+    .await;
+    let csp = csp.unwrap();
+    assert!(csp.starts_with("default-src 'none'"), "{csp}");
+    assert_eq!(hsts.as_deref(), Some("max-age=3600"));
+    let other_nonce = nonce_of(&csp);
+    assert_ne!(other_nonce, nonce);
+    assert!(html.contains(&format!("<script nonce=\"{other_nonce}\"")));
+
+    // Scripts injected through query parameters are escaped and never receive the nonce.
+    let (csp, _hsts, html) = login(
+      router(SecurityHeaders::default()),
+      "/_/auth/login?alert=%3Cscript%3Ealert(1)%3C%2Fscript%3E&redirect_to=%22%3E%3Cscript%3E",
+    )
+    .await;
+    let nonce = nonce_of(&csp.unwrap());
+    assert!(html.contains(&format!("<script nonce=\"{nonce}\"")));
+    assert!(!html.contains("<script>"), "{html}");
+    assert!(html.contains("&lt;script&gt;"), "{html}");
+  }
+}
@@ -0,0 +1,109 @@
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const HTML_CONTENT_TYPE: &str = "text/html; charset=utf-8";
+
+/// Status codes, for which "<code>.html" in the public directory is picked up automatically.
+const DEFAULT_ERROR_PAGE_CODES: [u16; 3] = [403, 404, 500];
+
+/// Custom HTML error pages keyed by status code.
+#[derive(Clone, Debug, Default)]
+pub(super) struct ErrorPages(HashMap<StatusCode, Bytes>);
+
+impl ErrorPages {
+  /// Loads "403.html", "404.html" and "500.html" from the public directory, if present, as well
+  /// as explicitly configured pages, which take precedence.
+  pub(super) async fn load(
+    public_dir: Option<&Path>,
+    custom: &HashMap<u16, PathBuf>,
+  ) -> Result<Self, std::io::Error> {
+    let mut pages = HashMap::<StatusCode, Bytes>::new();
+
+    if let Some(public_dir) = public_dir {
+      for code in DEFAULT_ERROR_PAGE_CODES {
+        let path = public_dir.join(format!("{code}.html"));
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+          pages.insert(to_status(code)?, tokio::fs::read(&path).await?.into());
+        }
+      }
+    }
+
+    for (code, path) in custom {
+      pages.insert(to_status(*code)?, tokio::fs::read(path).await?.into());
+    }
+
+    return Ok(Self(pages));
+  }
+
+  pub(super) fn is_empty(&self) -> bool {
+    return self.0.is_empty();
+  }
+
+  pub(super) fn response(&self, status: StatusCode) -> Option<Response> {
+    let page = self.0.get(&status)?;
+    return Some(
+      (
+        status,
+        [(header::CONTENT_TYPE, HTML_CONTENT_TYPE)],
+        page.clone(),
+      )
+        .into_response(),
+    );
+  }
+}
+
+fn to_status(code: u16) -> Result<StatusCode, std::io::Error> {
+  return StatusCode::from_u16(code).map_err(|_| {
+    std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!("Invalid status code: {code}"),
+    )
+  });
+}
+
+/// Replaces non-HTML error responses with the matching custom error page for clients asking for
+/// HTML, e.g. browsers navigating to a record or auth API.
+pub(super) async fn error_pages_middleware(
+  State(pages): State<Arc<ErrorPages>>,
+  req: Request,
+  next: Next,
+) -> Response {
+  let accepts_html = req
+    .headers()
+    .get(header::ACCEPT)
+    .and_then(|h| h.to_str().ok())
+    .is_some_and(|accept| accept.contains("text/html"));
+
+  let response = next.run(req).await;
+  if !accepts_html || response.status().is_success() {
+    return response;
+  }
+
+  let is_html = response
+    .headers()
+    .get(header::CONTENT_TYPE)
+    .and_then(|h| h.to_str().ok())
+    .is_some_and(|content_type| content_type.starts_with("text/html"));
+  if is_html {
+    return response;
+  }
+
+  let Some(page) = pages.0.get(&response.status()) else {
+    return response;
+  };
+
+  // Preserve other headers, e.g. cookies.
+  let (mut parts, _body) = response.into_parts();
+  parts.headers.insert(
+    header::CONTENT_TYPE,
+    HeaderValue::from_static(HTML_CONTENT_TYPE),
+  );
+  parts.headers.remove(header::CONTENT_LENGTH);
+  return Response::from_parts(parts, Body::from(page.clone()));
+}
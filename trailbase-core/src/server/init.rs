@@ -1,13 +1,19 @@
 use log::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 use crate::app_state::{build_objectstore, AppState, AppStateArgs};
 use crate::auth::jwt::{JwtHelper, JwtHelperError};
 use crate::config::load_or_init_config_textproto;
 use crate::constants::USER_TABLE;
-use crate::migrations::{apply_logs_migrations, apply_main_migrations};
+use crate::js::RuntimeOptions;
+use crate::migrations::{
+  apply_logs_migrations, apply_main_migrations, new_unique_migration_filename,
+};
 use crate::rand::generate_random_string;
+use crate::rate_limit::RateLimitConfig;
+use crate::records::validate_record_api_access_rules;
 use crate::server::DataDir;
 use crate::table_metadata::TableMetadataCache;
 
@@ -39,12 +45,31 @@ pub enum InitError {
   ScriptError(String),
   #[error("ObjectStore error: {0}")]
   ObjectStore(#[from] object_store::Error),
+  #[error("OpenTelemetry error: {0}")]
+  OpenTelemetry(#[from] opentelemetry::trace::TraceError),
+  #[error("Webhook error: {0}")]
+  Webhook(#[from] crate::webhooks::WebhookError),
+  #[error("Client IP error: {0}")]
+  ClientIp(String),
+  #[error("Virtual host error: {0}")]
+  VirtualHost(String),
+  #[error("Public dir error: {0}")]
+  PublicDir(String),
 }
 
 #[derive(Default)]
 pub struct InitArgs {
   pub dev: bool,
+  /// SQL files or glob patterns applied as migrations when initializing a new database.
+  pub first_init_sql: Vec<PathBuf>,
   pub js_runtime_threads: Option<usize>,
+  pub js_heap_limit_mb: Option<u32>,
+  pub js_stack_size_kb: Option<u32>,
+  pub js_timeout_ms: Option<u64>,
+  pub rate_limit: Option<RateLimitConfig>,
+  pub rate_limit_routes: Vec<(String, RateLimitConfig)>,
+  pub sse_keepalive_secs: u64,
+  pub sse_replay_buffer_size: usize,
 }
 
 pub async fn init_app_state(
@@ -68,6 +93,11 @@ pub async fn init_app_state(
     let mut conn = trailbase_sqlite::connect_sqlite(Some(data_dir.main_db_path()), None)?;
     let new_db = apply_main_migrations(&mut conn, Some(data_dir.migrations_path()))?;
 
+    if new_db && !args.first_init_sql.is_empty() {
+      write_first_init_migrations(&args.first_init_sql, &data_dir.migrations_path())?;
+      apply_main_migrations(&mut conn, Some(data_dir.migrations_path()))?;
+    }
+
     (trailbase_sqlite::Connection::from_conn(conn)?, new_db)
   };
 
@@ -75,6 +105,7 @@ pub async fn init_app_state(
 
   // Read config or write default one.
   let config = load_or_init_config_textproto(&data_dir, &table_metadata).await?;
+  validate_record_api_access_rules(&conn, &table_metadata, &config).await?;
 
   debug!("Initializing JSON schemas from config");
   trailbase_sqlite::schema::set_user_schemas(
@@ -105,6 +136,9 @@ pub async fn init_app_state(
       .collect(),
   )?;
 
+  crate::records::install_audit_trails(&conn, &table_metadata, &config).await?;
+  crate::webhooks::install_all_webhook_triggers(&conn, &table_metadata).await?;
+
   let jwt = JwtHelper::init_from_path(&data_dir).await?;
 
   // Init geoip if present.
@@ -113,7 +147,8 @@ pub async fn init_app_state(
     debug!("Failed to load maxmind geoip DB '{geoip_db_path:?}': {err}");
   }
 
-  let object_store = build_objectstore(&data_dir, config.server.s3_storage_config.as_ref())?;
+  let (object_store, url_signer) =
+    build_objectstore(&data_dir, config.server.s3_storage_config.as_ref())?;
 
   // Write out the latest .js/.d.ts runtime files.
   #[cfg(feature = "v8")]
@@ -129,7 +164,17 @@ pub async fn init_app_state(
     logs_conn,
     jwt,
     object_store,
-    js_runtime_threads: args.js_runtime_threads,
+    url_signer,
+    js_runtime: RuntimeOptions {
+      n_threads: args.js_runtime_threads,
+      heap_limit_mb: args.js_heap_limit_mb,
+      stack_size_kb: args.js_stack_size_kb,
+      timeout: args.js_timeout_ms.map(Duration::from_millis),
+    },
+    rate_limit: args.rate_limit,
+    rate_limit_routes: args.rate_limit_routes,
+    sse_keepalive_secs: args.sse_keepalive_secs,
+    sse_replay_buffer_size: args.sse_replay_buffer_size,
   });
 
   if new_db {
@@ -182,6 +227,52 @@ pub async fn init_app_state(
   return Ok((new_db, app_state));
 }
 
+/// Copies the given SQL files into the migrations directory, preserving their order, so they get
+/// applied and recorded like any other user migration.
+fn write_first_init_migrations(
+  patterns: &[PathBuf],
+  migrations_path: &Path,
+) -> Result<(), InitError> {
+  for pattern in patterns {
+    let pattern = pattern.to_string_lossy();
+    let paths = glob::glob(&pattern)
+      .map_err(|err| InitError::CustomInit(format!("Invalid pattern '{pattern}': {err}")))?
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|err| InitError::CustomInit(err.to_string()))?;
+
+    if paths.is_empty() {
+      return Err(InitError::CustomInit(format!(
+        "No SQL files matching: {pattern}"
+      )));
+    }
+
+    for path in paths {
+      let sql = std::fs::read_to_string(&path)?;
+
+      // Refinery requires migration names to only consist of word characters.
+      let suffix: String = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+          if c.is_alphanumeric() || c == '_' {
+            c
+          } else {
+            '_'
+          }
+        })
+        .collect();
+
+      let filename = new_unique_migration_filename(&suffix);
+      debug!("Writing first-init migration {filename} from {path:?}");
+      std::fs::write(migrations_path.join(filename), sql)?;
+    }
+  }
+
+  return Ok(());
+}
+
 fn init_logs_db(data_dir: &DataDir) -> Result<rusqlite::Connection, InitError> {
   let conn = trailbase_sqlite::connect_sqlite(data_dir.logs_db_path().into(), None)?;
 
@@ -1,15 +1,20 @@
+mod error_pages;
 mod init;
 mod serve;
+mod virtual_hosts;
 
 use axum::extract::{DefaultBodyLimit, Request, State};
-use axum::handler::HandlerWithoutStateExt;
-use axum::http::{HeaderValue, StatusCode};
+use axum::http::{request::Parts, HeaderValue, StatusCode};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{RequestExt, Router};
+use axum_client_ip::SecureClientIpSource;
+use opentelemetry_sdk::trace::TracerProvider;
 use rust_embed::RustEmbed;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::net::{Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::signal;
 use tokio::task::JoinSet;
@@ -18,8 +23,18 @@ use tokio_rustls::{
   rustls::ServerConfig,
   TlsAcceptor,
 };
+use tower::Layer as _;
 use tower_cookies::CookieManagerLayer;
-use tower_http::{cors, limit::RequestBodyLimitLayer, services::ServeDir, trace::TraceLayer};
+use tower_http::{
+  compression::{CompressionLayer, CompressionLevel},
+  cors,
+  decompression::RequestDecompressionLayer,
+  limit::RequestBodyLimitLayer,
+  services::ServeDir,
+  trace::TraceLayer,
+};
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
 
 use crate::admin;
 use crate::app_state::AppState;
@@ -28,29 +43,68 @@ use crate::auth::util::is_admin;
 use crate::auth::{self, AuthError, User};
 use crate::constants::{ADMIN_API_PATH, HEADER_CSRF_TOKEN};
 use crate::data_dir::DataDir;
+use crate::extract::parse_client_ip_source;
 use crate::logging;
+use crate::metrics;
+use crate::openapi;
+use crate::otel;
+use crate::rate_limit::{self, RateLimitConfig};
 use crate::records;
+use crate::request_id;
 use crate::scheduler;
+use crate::security_headers::{security_headers_middleware, SecurityHeaders};
 
+use error_pages::ErrorPages;
 pub use init::{init_app_state, InitArgs, InitError};
+use virtual_hosts::StaticFiles;
+pub use virtual_hosts::VirtualHostConfig;
 
 /// A set of options to configure serving behaviors. Changing any of these options
 /// requires a server restart, which makes them a natural fit for being exposed as command line
 /// arguments.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ServerOptions {
   /// Optional path to static assets that will be served at the HTTP root.
   pub data_dir: DataDir,
 
+  /// SQL files, optionally glob patterns, applied as migrations in the given order when a new
+  /// data directory is initialized. The files are copied into the data directory's migrations and
+  /// thus recorded in the migration history like any other migration.
+  pub on_first_init_sql: Option<Vec<PathBuf>>,
+
   // Address the HTTP server binds to (Default: localhost:4000).
   pub address: String,
 
   // Optional address of the admin UI + API.
   pub admin_address: Option<String>,
 
+  /// Only accept IPv6 connections on IPv6 addresses, i.e. sets `IPV6_V6ONLY`. Otherwise, binding
+  /// the unspecified IPv6 address "[::]" accepts both IPv4 and IPv6 connections (dual-stack),
+  /// while "0.0.0.0" only ever accepts IPv4 connections.
+  pub ipv6_only: bool,
+
+  /// Optional Unix domain socket path the HTTP server binds to instead of `address`, e.g. to sit
+  /// behind a reverse proxy. Incompatible with TLS.
+  pub unix_socket: Option<PathBuf>,
+
   /// Optional path to static assets that will be served at the HTTP root.
   pub public_dir: Option<PathBuf>,
 
+  /// Additional static assets served at the given path prefixes, e.g. ("/docs", "./docs").
+  pub extra_public_dirs: Vec<(String, PathBuf)>,
+
+  /// Max age in seconds of the "Cache-Control" header set on successfully served static assets.
+  pub static_cache_max_age: Option<u64>,
+
+  /// HTML pages served for the given HTTP status codes instead of the default error responses to
+  /// clients accepting HTML. "403.html", "404.html" and "500.html" in the `public_dir` are picked
+  /// up automatically.
+  pub custom_error_pages: HashMap<u16, PathBuf>,
+
+  /// Virtual hosts serving their own static assets from the same server, e.g. for multi-tenant
+  /// deployments. APIs and the database are shared.
+  pub virtual_hosts: Vec<VirtualHostConfig>,
+
   /// Enabling dev mode allows free-for-all access to admin APIs. This can be useful to develop the
   /// UI behind a different server preventing auth cookie passing.
   ///
@@ -66,11 +120,104 @@ pub struct ServerOptions {
 
   /// Number of V8 worker threads. If set to None, default of num available cores will be used.
   pub js_runtime_threads: Option<usize>,
+  /// Maximum V8 heap size per isolate in MB. Exceeding it fails the respective JS call rather
+  /// than the entire process.
+  pub js_heap_limit_mb: Option<u32>,
+  /// V8 stack size in KB, e.g. to allow for deeper recursion.
+  pub js_stack_size_kb: Option<u32>,
+  /// Timeout after which JS HTTP handlers are interrupted and a 504 Gateway Timeout is returned.
+  pub js_timeout_ms: Option<u64>,
 
   /// TLS certificate path.
   pub tls_cert: Option<CertificateDer<'static>>,
   /// TLS key path.
   pub tls_key: Option<PrivateKeyDer<'static>>,
+
+  /// Optional rate limit applied to all routes.
+  pub rate_limit: Option<RateLimitConfig>,
+  /// Rate limits overriding `rate_limit` for routes starting with the given path prefixes, e.g.
+  /// ("/api/auth/v1/login", ...). The longest matching prefix applies.
+  pub rate_limit_routes: Vec<(String, RateLimitConfig)>,
+
+  /// Compress responses and accept compressed request bodies (gzip, br, zstd).
+  pub enable_compression: bool,
+  /// Response compression level. 0 selects each algorithm's default.
+  pub compression_level: u32,
+
+  /// Export Prometheus metrics at "/api/metrics".
+  pub enable_metrics: bool,
+
+  /// Serve the OpenAPI spec at "/openapi.json" and "/openapi.yaml". Defaults to `dev`.
+  pub enable_openapi: Option<bool>,
+
+  /// OTLP/HTTP endpoint to export traces to, e.g. "http://localhost:4318/v1/traces". Spans are
+  /// only exported if the subscriber includes [Server::otel_layer].
+  pub otlp_endpoint: Option<String>,
+
+  /// Interval in seconds for sending keep-alives on realtime subscriptions. Zero disables them.
+  pub sse_keepalive_secs: u64,
+  /// Number of recent realtime events buffered for replaying to re-connecting subscribers.
+  pub sse_replay_buffer_size: usize,
+
+  /// Log (truncated) request bodies. Only takes effect in `dev` mode to prevent accidentally
+  /// logging PII in production.
+  pub log_request_bodies: bool,
+  /// Log (truncated) response bodies. Only takes effect in `dev` mode.
+  pub log_response_bodies: bool,
+
+  /// Content-Security-Policy for all HTML responses. Occurrences of "{nonce}" are replaced with a
+  /// per-request nonce, which the auth UI also attaches to its scripts. Otherwise, the auth UI
+  /// uses a default policy.
+  pub csp_policy: Option<String>,
+  /// If set, responses include a `Strict-Transport-Security` header with the given max age in
+  /// seconds.
+  pub hsts_max_age: Option<u64>,
+
+  /// Source of client IP addresses, e.g. for login lockouts and rate limits. Defaults to the
+  /// connection's peer address ("ConnectInfo"). Behind a trusted reverse proxy, set it to the
+  /// header the proxy provides, e.g. "RightmostXForwardedFor" or "XRealIp".
+  pub client_ip_source: Option<String>,
+}
+
+impl Default for ServerOptions {
+  fn default() -> Self {
+    return Self {
+      data_dir: DataDir::default(),
+      on_first_init_sql: None,
+      address: String::default(),
+      admin_address: None,
+      ipv6_only: false,
+      unix_socket: None,
+      public_dir: None,
+      extra_public_dirs: vec![],
+      static_cache_max_age: None,
+      custom_error_pages: HashMap::new(),
+      virtual_hosts: vec![],
+      dev: false,
+      disable_auth_ui: false,
+      cors_allowed_origins: vec![],
+      js_runtime_threads: None,
+      js_heap_limit_mb: None,
+      js_stack_size_kb: None,
+      js_timeout_ms: None,
+      tls_cert: None,
+      tls_key: None,
+      rate_limit: None,
+      rate_limit_routes: vec![],
+      enable_compression: true,
+      compression_level: 0,
+      enable_metrics: false,
+      enable_openapi: None,
+      otlp_endpoint: None,
+      sse_keepalive_secs: 30,
+      sse_replay_buffer_size: 128,
+      log_request_bodies: false,
+      log_response_bodies: false,
+      csp_policy: None,
+      hsts_max_age: None,
+      client_ip_source: None,
+    };
+  }
 }
 
 pub struct Server {
@@ -79,11 +226,15 @@ pub struct Server {
   // Routers.
   main_router: (String, Router),
   admin_router: Option<(String, Router)>,
+  ipv6_only: bool,
+  unix_socket: Option<PathBuf>,
 
   /// TLS certificate path.
   pub tls_cert: Option<CertificateDer<'static>>,
   /// TLS key path.
   pub tls_key: Option<PrivateKeyDer<'static>>,
+
+  tracer_provider: Option<TracerProvider>,
 }
 
 impl Server {
@@ -112,12 +263,46 @@ impl Server {
     let version_info = rustc_tools_util::get_version_info!();
     log::info!("Initializing server {version_info}");
 
+    if (opts.log_request_bodies || opts.log_response_bodies) && !opts.dev {
+      log::warn!("Ignoring request/response body logging outside of dev mode");
+    }
+
+    for (prefix, _) in &opts.extra_public_dirs {
+      if !prefix.starts_with('/') || prefix.trim_end_matches('/').is_empty() {
+        return Err(InitError::PublicDir(format!(
+          "invalid prefix '{prefix}', expected e.g. '/docs'. Use `public_dir` for the root"
+        )));
+      }
+    }
+
+    parse_client_ip_source(opts.client_ip_source.as_deref()).map_err(InitError::ClientIp)?;
+
+    for host in &opts.virtual_hosts {
+      if host.domain.is_empty() {
+        return Err(InitError::VirtualHost("empty domain".to_string()));
+      }
+      if host.data_dir.is_some() {
+        return Err(InitError::VirtualHost(format!(
+          "{}: separate data directories are not supported yet",
+          host.domain
+        )));
+      }
+    }
+
     let (new_data_dir, state) = init::init_app_state(
       opts.data_dir.clone(),
       opts.public_dir.clone(),
       InitArgs {
         dev: opts.dev,
+        first_init_sql: opts.on_first_init_sql.clone().unwrap_or_default(),
         js_runtime_threads: opts.js_runtime_threads,
+        js_heap_limit_mb: opts.js_heap_limit_mb,
+        js_stack_size_kb: opts.js_stack_size_kb,
+        js_timeout_ms: opts.js_timeout_ms,
+        rate_limit: opts.rate_limit.clone(),
+        rate_limit_routes: opts.rate_limit_routes.clone(),
+        sse_keepalive_secs: opts.sse_keepalive_secs,
+        sse_replay_buffer_size: opts.sse_replay_buffer_size,
       },
     )
     .await?;
@@ -144,12 +329,20 @@ impl Server {
     let main_router = Self::build_main_router(&state, &opts, custom_routes).await;
     let admin_router = Self::build_independent_admin_router(&state, &opts);
 
+    let tracer_provider = match opts.otlp_endpoint {
+      Some(ref endpoint) => Some(otel::init_tracer_provider(endpoint)?),
+      None => None,
+    };
+
     Ok(Self {
       state,
       main_router,
       admin_router,
+      ipv6_only: opts.ipv6_only,
+      unix_socket: opts.unix_socket,
       tls_key: opts.tls_key,
       tls_cert: opts.tls_cert,
+      tracer_provider,
     })
   }
 
@@ -157,11 +350,23 @@ impl Server {
     return &self.state;
   }
 
+  /// Tracing layer exporting spans to [ServerOptions::otlp_endpoint], if set.
+  pub fn otel_layer<S>(&self) -> Option<impl Layer<S>>
+  where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+  {
+    return self.tracer_provider.as_ref().map(otel::layer);
+  }
+
   pub fn router(&self) -> &Router<()> {
     return &self.main_router.1;
   }
 
   pub async fn serve(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if self.unix_socket.is_some() && (self.tls_key.is_some() || self.tls_cert.is_some()) {
+      return Err("TLS is not supported when listening on a Unix socket".into());
+    }
+
     let _raii_tasks = scheduler::start_periodic_tasks(&self.state);
 
     // NOTE: We panic if  a key/cert that was explicitly specified cannot be loaded.
@@ -186,81 +391,105 @@ impl Server {
     let mut set = JoinSet::new();
     {
       let (addr, router) = self.main_router.clone();
-      let (tls_key, tls_cert) = (tls_key.as_ref().map(|k| k.clone_key()), tls_cert.clone());
+      let unix_socket = self.unix_socket.clone();
+      // NOTE: Certificates from the data directory are ignored for Unix sockets.
+      let (tls_key, tls_cert) = match unix_socket {
+        Some(_) => (None, None),
+        None => (tls_key.as_ref().map(|k| k.clone_key()), tls_cert.clone()),
+      };
 
-      set.spawn(async move { Self::start_listen(&addr, router, tls_key, tls_cert).await });
+      let ipv6_only = self.ipv6_only;
+      set.spawn(async move {
+        Self::start_listen(&addr, ipv6_only, unix_socket, router, tls_key, tls_cert).await
+      });
     }
 
     if let Some((addr, router)) = self.admin_router.clone() {
-      set.spawn(async move { Self::start_listen(&addr, router, tls_key, tls_cert).await });
+      let ipv6_only = self.ipv6_only;
+      set.spawn(async move {
+        Self::start_listen(&addr, ipv6_only, None, router, tls_key, tls_cert).await
+      });
     }
 
-    log::info!(
-      "listening on http://{addr} 🚀 (Admin UI http://{admin_addr}/_/admin/)",
-      addr = self.main_router.0,
-      admin_addr = self
-        .admin_router
-        .as_ref()
-        .map_or_else(|| &self.main_router.0, |(addr, _)| addr)
-    );
+    match (&self.unix_socket, &self.admin_router) {
+      (Some(unix_socket), Some((admin_addr, _))) => {
+        log::info!("listening on unix:{unix_socket:?} 🚀 (Admin UI http://{admin_addr}/_/admin/)")
+      }
+      (Some(unix_socket), None) => {
+        log::info!("listening on unix:{unix_socket:?} 🚀 (Admin UI at /_/admin/)")
+      }
+      (None, _) => log::info!(
+        "listening on http://{addr} 🚀 (Admin UI http://{admin_addr}/_/admin/)",
+        addr = self.main_router.0,
+        admin_addr = self
+          .admin_router
+          .as_ref()
+          .map_or_else(|| &self.main_router.0, |(addr, _)| addr)
+      ),
+    };
 
     set.join_all().await;
 
+    if self.tracer_provider.is_some() {
+      // Flush pending spans.
+      opentelemetry::global::shutdown_tracer_provider();
+    }
+
     return Ok(());
   }
 
   async fn start_listen(
     addr: &str,
+    ipv6_only: bool,
+    unix_socket: Option<PathBuf>,
     router: Router<()>,
     tls_key: Option<PrivateKeyDer<'static>>,
     tls_cert: Option<CertificateDer<'static>>,
   ) {
-    match (tls_key, tls_cert) {
-      (Some(key), Some(cert)) => {
-        let tcp_listener = match tokio::net::TcpListener::bind(addr).await {
-          Ok(listener) => listener,
-          Err(err) => {
-            log::error!("Failed to listen on: {addr}: {err}");
-            std::process::exit(1);
-          }
-        };
-
-        let server_config = ServerConfig::builder()
-          .with_no_client_auth()
-          .with_single_cert(vec![cert], key)
-          .expect("Failed to build server config");
-
-        let listener = serve::TlsListener {
-          listener: tcp_listener,
-          acceptor: TlsAcceptor::from(Arc::new(server_config)),
-        };
-
-        if let Err(err) = serve::serve(listener, router.clone())
-          .with_graceful_shutdown(shutdown_signal())
-          .await
-        {
-          log::error!("Failed to start server: {err}");
-          std::process::exit(1);
-        }
+    let listener = match ListenerKind::bind(
+      addr,
+      ipv6_only,
+      unix_socket.as_deref(),
+      tls_key.zip(tls_cert),
+    )
+    .await
+    {
+      Ok(listener) => listener,
+      Err(err) => {
+        log::error!("Failed to listen on: {addr}: {err}");
+        std::process::exit(1);
       }
-      _ => {
-        let listener = match tokio::net::TcpListener::bind(addr).await {
-          Ok(listener) => listener,
-          Err(err) => {
-            log::error!("Failed to listen on: {addr}: {err}");
-            std::process::exit(1);
-          }
-        };
+    };
 
-        if let Err(err) = serve::serve(listener, router.clone())
+    let result = match listener {
+      ListenerKind::Tcp(listener) => {
+        serve::serve(
+          listener,
+          router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+      }
+      ListenerKind::Tls(listener) => {
+        serve::serve(
+          listener,
+          router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+      }
+      #[cfg(unix)]
+      ListenerKind::Unix(listener) => {
+        serve::serve(listener, router)
           .with_graceful_shutdown(shutdown_signal())
           .await
-        {
-          log::error!("Failed to start server: {err}");
-          std::process::exit(1);
-        }
       }
     };
+
+    if let Err(err) = result {
+      log::error!("Failed to start server: {err}");
+      std::process::exit(1);
+    }
   }
 
   fn build_admin_router(state: &AppState) -> Router<AppState> {
@@ -324,17 +553,64 @@ impl Server {
       router = router.merge(custom_router);
     }
 
-    if let Some(public_dir) = &opts.public_dir {
+    if opts.enable_metrics {
+      router = router
+        .route("/api/metrics", get(metrics::metrics_handler))
+        .route_layer(middleware::from_fn(metrics::route_labels_middleware));
+    }
+
+    if opts.enable_openapi.unwrap_or(opts.dev) {
+      router = router
+        .route("/openapi.json", get(openapi::openapi_json_handler))
+        .route("/openapi.yaml", get(openapi::openapi_yaml_handler));
+    }
+
+    for public_dir in opts
+      .public_dir
+      .iter()
+      .chain(
+        opts
+          .virtual_hosts
+          .iter()
+          .filter_map(|h| h.public_dir.as_ref()),
+      )
+      .chain(opts.extra_public_dirs.iter().map(|(_, dir)| dir))
+    {
       if !tokio::fs::try_exists(public_dir).await.unwrap_or(false) {
         panic!("--public_dir={public_dir:?} path does not exist.")
       }
+    }
 
-      async fn handle_404() -> (StatusCode, &'static str) {
-        (StatusCode::NOT_FOUND, "Not found")
-      }
+    for (prefix, dir) in &opts.extra_public_dirs {
+      router = router.nest_service(
+        prefix.trim_end_matches('/'),
+        virtual_hosts::cache_control_layer(opts.static_cache_max_age).layer(ServeDir::new(dir)),
+      );
+    }
 
-      router = router
-        .fallback_service(ServeDir::new(public_dir).not_found_service(handle_404.into_service()));
+    let error_pages = Arc::new(
+      ErrorPages::load(opts.public_dir.as_deref(), &opts.custom_error_pages)
+        .await
+        .unwrap_or_else(|err| panic!("Failed to load custom error pages: {err}")),
+    );
+
+    if let Some(static_files) = StaticFiles::new(
+      opts.public_dir.as_ref(),
+      &opts.virtual_hosts,
+      error_pages.clone(),
+      opts.static_cache_max_age,
+    ) {
+      let static_files = Arc::new(static_files);
+      router = router.fallback(move |req: Request| async move {
+        return static_files.serve(req).await;
+      });
+    }
+
+    if !error_pages.is_empty() {
+      router = router.layer(middleware::from_fn_with_state(
+        error_pages,
+        error_pages::error_pages_middleware,
+      ));
     }
 
     return (
@@ -348,9 +624,33 @@ impl Server {
     opts: &ServerOptions,
     router: Router<AppState>,
   ) -> Router<()> {
-    return router
+    let mut router = router
+      .layer(middleware::from_fn_with_state(
+        state.clone(),
+        rate_limit::rate_limit_middleware,
+      ))
       .layer(CookieManagerLayer::new())
-      .layer(build_cors(opts))
+      .layer(build_cors(state, opts))
+      .layer(middleware::from_fn_with_state(
+        Arc::new(SecurityHeaders {
+          csp_policy: opts.csp_policy.clone(),
+          hsts_max_age: opts.hsts_max_age,
+        }),
+        security_headers_middleware,
+      ));
+
+    if opts.dev && (opts.log_request_bodies || opts.log_response_bodies) {
+      // Needs to be inside the TraceLayer below to record bodies into its request span.
+      router = router.layer(middleware::from_fn_with_state(
+        logging::BodyLogging {
+          request: opts.log_request_bodies,
+          response: opts.log_response_bodies,
+        },
+        logging::body_logging_middleware,
+      ));
+    }
+
+    let router = router
       .layer(
         // This declares: **what information** is logged at what level in to events and spans.
         TraceLayer::new_for_http()
@@ -358,13 +658,154 @@ impl Server {
           .on_request(logging::sqlite_logger_on_request)
           .on_response(logging::sqlite_logger_on_response),
       )
-      // Default is only 2MB Increase to 10MB.
+      .layer(middleware::from_fn(request_id::request_id_middleware))
+      // Default is only 2MB Increase to 10MB unless overridden by a record API.
       .layer(DefaultBodyLimit::disable())
-      .layer(RequestBodyLimitLayer::new(10 * 1024 * 1024))
+      .layer(middleware::from_fn_with_state(
+        state.clone(),
+        body_limit_middleware,
+      ))
+      .layer(
+        parse_client_ip_source(opts.client_ip_source.as_deref())
+          .unwrap_or(SecureClientIpSource::ConnectInfo)
+          .into_extension(),
+      );
+
+    if !opts.enable_compression {
+      return router.with_state(state.clone());
+    }
+
+    let level = match opts.compression_level {
+      0 => CompressionLevel::Default,
+      level => CompressionLevel::Precise(level as i32),
+    };
+
+    return router
+      .layer(RequestDecompressionLayer::new())
+      .layer(CompressionLayer::new().quality(level))
       .with_state(state.clone());
   }
 }
 
+enum ListenerKind {
+  Tcp(tokio::net::TcpListener),
+  Tls(serve::TlsListener),
+  #[cfg(unix)]
+  Unix(tokio::net::UnixListener),
+}
+
+impl ListenerKind {
+  async fn bind(
+    addr: &str,
+    ipv6_only: bool,
+    unix_socket: Option<&Path>,
+    tls: Option<(PrivateKeyDer<'static>, CertificateDer<'static>)>,
+  ) -> Result<Self, std::io::Error> {
+    if let Some(path) = unix_socket {
+      if tls.is_some() {
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::InvalidInput,
+          "TLS is not supported for Unix sockets",
+        ));
+      }
+      return Self::bind_unix(path);
+    }
+
+    let listener = bind_tcp(addr, ipv6_only).await?;
+    let Some((key, cert)) = tls else {
+      return Ok(Self::Tcp(listener));
+    };
+
+    let server_config = ServerConfig::builder()
+      .with_no_client_auth()
+      .with_single_cert(vec![cert], key)
+      .expect("Failed to build server config");
+
+    return Ok(Self::Tls(serve::TlsListener {
+      listener,
+      acceptor: TlsAcceptor::from(Arc::new(server_config)),
+    }));
+  }
+
+  #[cfg(unix)]
+  fn bind_unix(path: &Path) -> Result<Self, std::io::Error> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // Remove stale sockets, e.g. left behind by a crash, which would otherwise fail binding.
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+      std::fs::remove_file(path)?;
+    }
+    return Ok(Self::Unix(tokio::net::UnixListener::bind(path)?));
+  }
+
+  #[cfg(not(unix))]
+  fn bind_unix(_path: &Path) -> Result<Self, std::io::Error> {
+    return Err(std::io::Error::new(
+      std::io::ErrorKind::Unsupported,
+      "Unix sockets are not supported on this platform",
+    ));
+  }
+}
+
+/// Parses socket addresses including IPv6 addresses with ("[::1]:4000") and without brackets
+/// ("::1:4000"), in which case the last segment is always taken to be the port.
+fn parse_socket_addr(addr: &str) -> Option<SocketAddr> {
+  if let Ok(addr) = addr.parse::<SocketAddr>() {
+    return Some(addr);
+  }
+
+  let (ip, port) = addr.rsplit_once(':')?;
+  return Some(SocketAddr::new(
+    ip.parse::<Ipv6Addr>().ok()?.into(),
+    port.parse().ok()?,
+  ));
+}
+
+/// Binds a TCP listener to the given address, which may also be a "host:port" to be resolved,
+/// e.g. "localhost:4000".
+async fn bind_tcp(addr: &str, ipv6_only: bool) -> Result<tokio::net::TcpListener, std::io::Error> {
+  let addrs: Vec<SocketAddr> = match parse_socket_addr(addr) {
+    Some(addr) => vec![addr],
+    None => tokio::net::lookup_host(addr).await?.collect(),
+  };
+
+  let mut last_err = None;
+  for addr in addrs {
+    match bind_tcp_socket(addr, ipv6_only) {
+      Ok(listener) => return Ok(listener),
+      Err(err) => last_err = Some(err),
+    }
+  }
+
+  return Err(last_err.unwrap_or_else(|| {
+    std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!("could not resolve address: {addr}"),
+    )
+  }));
+}
+
+fn bind_tcp_socket(
+  addr: SocketAddr,
+  ipv6_only: bool,
+) -> Result<tokio::net::TcpListener, std::io::Error> {
+  use socket2::{Domain, Protocol, Socket, Type};
+
+  let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+  if addr.is_ipv6() {
+    // NOTE: Set explicitly, since the default is platform-dependent.
+    socket.set_only_v6(ipv6_only)?;
+  }
+  // Same as tokio's TcpListener::bind.
+  #[cfg(not(windows))]
+  socket.set_reuse_address(true)?;
+  socket.set_nonblocking(true)?;
+  socket.bind(&addr.into())?;
+  socket.listen(1024)?;
+
+  return tokio::net::TcpListener::from_std(socket.into());
+}
+
 fn has_indepenedent_admin_router(opts: &ServerOptions) -> bool {
   return match opts.admin_address {
     None => false,
@@ -377,6 +818,26 @@ async fn healthcheck_handler() -> Response {
   return (StatusCode::OK, "Ok").into_response();
 }
 
+/// Global request body limit, unless overridden for a record API's create and update endpoints.
+const DEFAULT_MAX_BODY_SIZE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Limits the request body size to the matched record API's `max_body_size_bytes`, if any, or
+/// [DEFAULT_MAX_BODY_SIZE_BYTES] otherwise. Oversized requests are rejected with 413.
+async fn body_limit_middleware(
+  State(state): State<AppState>,
+  request: Request,
+  next: Next,
+) -> Response {
+  let limit = records::record_api_body_limit(&state, request.method(), request.uri().path())
+    .unwrap_or(DEFAULT_MAX_BODY_SIZE_BYTES);
+
+  let service = RequestBodyLimitLayer::new(limit).layer(next);
+  return match tower::ServiceExt::oneshot(service, request).await {
+    Ok(response) => response.map(axum::body::Body::new),
+    Err(err) => match err {},
+  };
+}
+
 /// Assert that the caller is an admin and provides a valid CSRF token. Unlike the access to the
 /// HTML/js assets, this one errors.
 ///
@@ -406,38 +867,112 @@ async fn assert_admin_api_access(
     return Err(AuthError::BadRequest("invalid CSRF token"));
   }
 
-  return Ok(next.run(req).await);
+  return Ok(admin::audit_admin_request(&state, &user, req, next).await);
+}
+
+/// Outcome of matching a request's origin against the configured CORS origins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CorsOriginMatch {
+  Denied,
+  /// Allowed only by a "*" wildcard.
+  Wildcard,
+  /// Allowed by an explicitly listed origin, which also permits credentials.
+  Explicit,
 }
 
-fn build_cors(opts: &ServerOptions) -> cors::CorsLayer {
+fn match_cors_origin<'a>(
+  origins: impl IntoIterator<Item = &'a [u8]>,
+  origin: &HeaderValue,
+) -> CorsOriginMatch {
+  let mut result = CorsOriginMatch::Denied;
+  for o in origins {
+    if o == origin.as_bytes() {
+      return CorsOriginMatch::Explicit;
+    }
+    if o == b"*" {
+      result = CorsOriginMatch::Wildcard;
+    }
+  }
+  return result;
+}
+
+fn build_cors(state: &AppState, opts: &ServerOptions) -> cors::CorsLayer {
   if opts.dev {
     return cors::CorsLayer::very_permissive();
   }
 
-  let origin_strs = &opts.cors_allowed_origins;
-  let wildcard = origin_strs.iter().any(|s| s == "*");
-
-  let origins = if wildcard {
+  if opts.cors_allowed_origins.iter().any(|s| s == "*") {
     log::info!("CORS: allow any origin");
-    // cors::AllowOrigin::any()
-    cors::AllowOrigin::mirror_request()
-  } else {
-    cors::AllowOrigin::list(origin_strs.iter().filter_map(|o| {
-      match HeaderValue::from_str(o.as_str()) {
+  }
+
+  fn parse_origins(origin_strs: &[String]) -> Vec<HeaderValue> {
+    return origin_strs
+      .iter()
+      .filter_map(|o| match HeaderValue::from_str(o.as_str()) {
         Ok(value) => Some(value),
         Err(err) => {
           log::error!("Invalid CORS origin {o}: {err}");
           None
         }
+      })
+      .collect();
+  }
+
+  let origins = parse_origins(&opts.cors_allowed_origins);
+  let host_origins: HashMap<String, Vec<HeaderValue>> = opts
+    .virtual_hosts
+    .iter()
+    .filter(|host| !host.cors_origins.is_empty())
+    .map(|host| {
+      (
+        host.domain.to_lowercase(),
+        parse_origins(&host.cors_origins),
+      )
+    })
+    .collect();
+
+  let state = state.clone();
+  let check = Arc::new(
+    move |origin: &HeaderValue, parts: &Parts| -> CorsOriginMatch {
+      // Record APIs with their own origins override the global policy.
+      if let Some(api) = records::record_api_from_path(&state, parts.uri.path()) {
+        if !api.cors_allowed_origins().is_empty() {
+          return match_cors_origin(
+            api.cors_allowed_origins().iter().map(|o| o.as_bytes()),
+            origin,
+          );
+        }
       }
-    }))
+
+      let host_origins = virtual_hosts::request_host(&parts.headers, &parts.uri)
+        .and_then(|host| host_origins.get(&host))
+        .into_iter()
+        .flatten();
+
+      return match_cors_origin(
+        origins.iter().chain(host_origins).map(|o| o.as_bytes()),
+        origin,
+      );
+    },
+  );
+
+  let allow_origin = {
+    let check = check.clone();
+    cors::AllowOrigin::predicate(move |origin, parts| {
+      return check(origin, parts) != CorsOriginMatch::Denied;
+    })
   };
 
-  // Cannot combine `Access-Control-Allow-Credentials: true` with `Access-Control-Allow-Methods: *`
+  // NOTE: Browsers reject credentials for wildcards, i.e. `Access-Control-Allow-Credentials: true`
+  // cannot be combined with `Access-Control-Allow-Methods: *`, thus methods are mirrored.
+  let allow_credentials = cors::AllowCredentials::predicate(move |origin, parts| {
+    return check(origin, parts) == CorsOriginMatch::Explicit;
+  });
+
   return cors::CorsLayer::new()
-    .allow_methods(cors::Any)
-    // .allow_credentials(wildcard)
-    .allow_origin(origins);
+    .allow_methods(cors::AllowMethods::mirror_request())
+    .allow_credentials(allow_credentials)
+    .allow_origin(allow_origin);
 }
 
 async fn shutdown_signal() {
@@ -496,3 +1031,208 @@ async fn shutdown_signal() {
 #[derive(RustEmbed, Clone)]
 #[folder = "js/admin/dist/"]
 struct AdminAssets;
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::http::{header, Method};
+  use axum::routing::post;
+  use tower::ServiceExt;
+
+  use super::*;
+  use crate::app_state::test_state;
+  use crate::constants::{AVATAR_TABLE, RECORD_API_PATH};
+
+  #[test]
+  fn test_parse_socket_addr() {
+    assert_eq!(
+      parse_socket_addr("127.0.0.1:4000"),
+      Some("127.0.0.1:4000".parse().unwrap())
+    );
+    assert_eq!(
+      parse_socket_addr("[::1]:4000"),
+      Some("[::1]:4000".parse().unwrap())
+    );
+    assert_eq!(
+      parse_socket_addr("::1:4000"),
+      Some("[::1]:4000".parse().unwrap())
+    );
+    assert_eq!(
+      parse_socket_addr(":::4000"),
+      Some("[::]:4000".parse().unwrap())
+    );
+
+    assert_eq!(parse_socket_addr("localhost:4000"), None);
+    assert_eq!(parse_socket_addr("::1"), None);
+  }
+
+  #[cfg(target_os = "linux")]
+  #[tokio::test]
+  async fn test_ipv6_listener() {
+    let Ok(listener) = bind_tcp("[::1]:0", false).await else {
+      // IPv6 not available, e.g. in some CI environments.
+      return;
+    };
+    let port = listener.local_addr().unwrap().port();
+
+    let router = Router::new().route("/api/healthcheck", get(healthcheck_handler));
+    tokio::spawn(async move { serve::serve(listener, router).await.unwrap() });
+
+    let response = reqwest::get(format!("http://[::1]:{port}/api/healthcheck"))
+      .await
+      .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    // Dual-stack vs IPv6 only.
+    let dual_stack = bind_tcp("[::]:0", false).await.unwrap();
+    let port = dual_stack.local_addr().unwrap().port();
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+      .await
+      .is_ok());
+
+    let ipv6_only = bind_tcp("[::]:0", true).await.unwrap();
+    let port = ipv6_only.local_addr().unwrap().port();
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+      .await
+      .is_err());
+    assert!(tokio::net::TcpStream::connect(("::1", port)).await.is_ok());
+  }
+
+  #[tokio::test]
+  async fn test_record_api_cors() {
+    let state = test_state(None).await.unwrap();
+
+    let mut config = state.get_config();
+    let api = config
+      .record_apis
+      .iter_mut()
+      .find(|api| api.name.as_deref() == Some(AVATAR_TABLE))
+      .unwrap();
+    api.cors_allowed_origins = vec!["http://allowed.example.com".to_string()];
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let opts = ServerOptions {
+      cors_allowed_origins: vec!["http://global.example.com".to_string()],
+      ..Default::default()
+    };
+    let record_path = format!("/{RECORD_API_PATH}/{AVATAR_TABLE}/some_id");
+    let router = Router::new()
+      .route(&record_path, get(|| async { "record" }))
+      .route("/api/healthcheck", get(healthcheck_handler))
+      .layer(build_cors(&state, &opts));
+
+    let preflight = |path: &str, origin: &str| {
+      return Request::builder()
+        .method(Method::OPTIONS)
+        .uri(path)
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .body(Body::empty())
+        .unwrap();
+    };
+    let allowed = |response: &Response| {
+      return response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .map(|v| v.to_str().unwrap().to_string());
+    };
+
+    let response = router
+      .clone()
+      .oneshot(preflight(&record_path, "http://allowed.example.com"))
+      .await
+      .unwrap();
+    assert_eq!(
+      allowed(&response).as_deref(),
+      Some("http://allowed.example.com")
+    );
+    assert_eq!(
+      response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+        .unwrap(),
+      "true"
+    );
+
+    // The record API's origins override the global ones.
+    for origin in ["http://denied.example.com", "http://global.example.com"] {
+      let response = router
+        .clone()
+        .oneshot(preflight(&record_path, origin))
+        .await
+        .unwrap();
+      assert_eq!(allowed(&response), None, "{origin}");
+    }
+
+    // Other routes are subject to the global policy.
+    let response = router
+      .clone()
+      .oneshot(preflight("/api/healthcheck", "http://global.example.com"))
+      .await
+      .unwrap();
+    assert_eq!(
+      allowed(&response).as_deref(),
+      Some("http://global.example.com")
+    );
+    let response = router
+      .clone()
+      .oneshot(preflight("/api/healthcheck", "http://allowed.example.com"))
+      .await
+      .unwrap();
+    assert_eq!(allowed(&response), None);
+  }
+
+  #[tokio::test]
+  async fn test_record_api_body_limit() {
+    let state = test_state(None).await.unwrap();
+
+    let mut config = state.get_config();
+    let api = config
+      .record_apis
+      .iter_mut()
+      .find(|api| api.name.as_deref() == Some(AVATAR_TABLE))
+      .unwrap();
+    api.max_body_size_bytes = Some(1024);
+    state
+      .validate_and_update_config(config, None)
+      .await
+      .unwrap();
+
+    let create_path = format!("/{RECORD_API_PATH}/{AVATAR_TABLE}");
+    let import_path = format!("/{RECORD_API_PATH}/{AVATAR_TABLE}/import");
+    let echo_len = post(|body: String| async move { body.len().to_string() });
+    let router = Router::new()
+      .route(&create_path, echo_len.clone())
+      .route(&import_path, echo_len)
+      .layer(middleware::from_fn_with_state(
+        state.clone(),
+        body_limit_middleware,
+      ))
+      .with_state(state.clone());
+
+    let send = |path: &str, len: usize| {
+      let router = router.clone();
+      let request = Request::builder()
+        .method(Method::POST)
+        .uri(path)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+          serde_json::to_vec(&serde_json::json!({"data": "x".repeat(len - 11)})).unwrap(),
+        ))
+        .unwrap();
+      return async move { router.oneshot(request).await.unwrap().status() };
+    };
+
+    assert_eq!(
+      send(&create_path, 2048).await,
+      StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(send(&create_path, 512).await, StatusCode::OK);
+
+    // Other endpoints remain subject to the global limit.
+    assert_eq!(send(&import_path, 2048).await, StatusCode::OK);
+  }
+}
@@ -63,6 +63,26 @@ impl Listener for TcpListener {
   }
 }
 
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+  type Io = tokio::net::UnixStream;
+  type Addr = tokio::net::unix::SocketAddr;
+
+  async fn accept(&mut self) -> io::Result<(Self::Io, Self::Addr)> {
+    loop {
+      match Self::accept(self).await {
+        Ok(tup) => return Ok(tup),
+        Err(e) => handle_accept_error(e).await,
+      }
+    }
+  }
+
+  #[inline]
+  fn local_addr(&self) -> io::Result<Self::Addr> {
+    Self::local_addr(self)
+  }
+}
+
 async fn handle_accept_error(e: io::Error) {
   if is_connection_error(&e) {
     return;
@@ -312,6 +332,12 @@ impl axum::extract::connect_info::Connected<IncomingStream<'_, TcpListener>> for
   }
 }
 
+impl axum::extract::connect_info::Connected<IncomingStream<'_, TlsListener>> for SocketAddr {
+  fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+    *stream.remote_addr()
+  }
+}
+
 /// Serve future with graceful shutdown enabled.
 #[must_use = "futures must be awaited or polled"]
 pub struct WithGracefulShutdown<L, M, S, F> {
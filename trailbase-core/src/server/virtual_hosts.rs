@@ -0,0 +1,165 @@
+use axum::extract::Request;
+use axum::handler::HandlerWithoutStateExt;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::Layer;
+use tower_http::services::ServeDir;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_service::Service;
+
+use crate::data_dir::DataDir;
+use crate::server::error_pages::ErrorPages;
+
+/// Configuration of a virtual host, i.e. settings applied to requests based on their "Host"
+/// header.
+#[derive(Clone, Debug, Default)]
+pub struct VirtualHostConfig {
+  /// Domain matched against the request's host ignoring the port, e.g. "tenant1.app.com".
+  pub domain: String,
+
+  /// Static assets served at the HTTP root for this host instead of [super::ServerOptions::public_dir].
+  pub public_dir: Option<PathBuf>,
+
+  /// Separate data directory for this host.
+  ///
+  /// NOTE: Not yet supported, i.e. all virtual hosts currently share the default database.
+  pub data_dir: Option<DataDir>,
+
+  /// Origins allowed for CORS requests to this host in addition to
+  /// [super::ServerOptions::cors_allowed_origins].
+  pub cors_origins: Vec<String>,
+}
+
+/// Serves static files from the public directory of the request's virtual host or the default
+/// public directory otherwise.
+pub(super) struct StaticFiles {
+  default_dir: Option<PathBuf>,
+  host_dirs: HashMap<String, PathBuf>,
+  error_pages: Arc<ErrorPages>,
+  cache_max_age: Option<u64>,
+}
+
+impl StaticFiles {
+  /// Returns `None` if there aren't any static files to serve.
+  pub(super) fn new(
+    default_dir: Option<&PathBuf>,
+    virtual_hosts: &[VirtualHostConfig],
+    error_pages: Arc<ErrorPages>,
+    cache_max_age: Option<u64>,
+  ) -> Option<Self> {
+    let host_dirs: HashMap<String, PathBuf> = virtual_hosts
+      .iter()
+      .filter_map(|host| Some((host.domain.to_lowercase(), host.public_dir.clone()?)))
+      .collect();
+
+    if default_dir.is_none() && host_dirs.is_empty() {
+      return None;
+    }
+
+    return Some(Self {
+      default_dir: default_dir.cloned(),
+      host_dirs,
+      error_pages,
+      cache_max_age,
+    });
+  }
+
+  pub(super) async fn serve(&self, req: Request) -> Response {
+    let dir = request_host(req.headers(), req.uri())
+      .and_then(|host| self.host_dirs.get(&host))
+      .or(self.default_dir.as_ref());
+
+    let Some(dir) = dir else {
+      return not_found(&self.error_pages);
+    };
+
+    let error_pages = self.error_pages.clone();
+    let handle_404 = move || async move { not_found(&error_pages) };
+    let mut service = cache_control_layer(self.cache_max_age)
+      .layer(ServeDir::new(dir).not_found_service(handle_404.into_service()));
+
+    return match service.call(req).await {
+      Ok(response) => response.into_response(),
+      Err(err) => match err {},
+    };
+  }
+}
+
+/// Sets a "Cache-Control" header on successful responses of static files, unless `max_age` is
+/// `None`.
+pub(super) fn cache_control_layer<B>(
+  max_age: Option<u64>,
+) -> SetResponseHeaderLayer<impl Fn(&axum::http::Response<B>) -> Option<HeaderValue> + Clone> {
+  let value =
+    max_age.and_then(|max_age| HeaderValue::from_str(&format!("public, max-age={max_age}")).ok());
+
+  return SetResponseHeaderLayer::if_not_present(
+    header::CACHE_CONTROL,
+    move |response: &axum::http::Response<B>| {
+      let status = response.status();
+      if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+        return None;
+      }
+      return value.clone();
+    },
+  );
+}
+
+fn not_found(error_pages: &ErrorPages) -> Response {
+  return error_pages
+    .response(StatusCode::NOT_FOUND)
+    .unwrap_or_else(|| (StatusCode::NOT_FOUND, "Not found").into_response());
+}
+
+/// Returns the lower-cased host of the request without port, preferring the "Host" header over
+/// the URI's authority (HTTP/2).
+pub(super) fn request_host(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+  let host = headers
+    .get(header::HOST)
+    .and_then(|h| h.to_str().ok())
+    .or_else(|| uri.host())?;
+
+  let host = match host.rsplit_once(':') {
+    // Don't mistake IPv6 addresses, e.g. "[::1]", for ports.
+    Some((host, port)) if !port.contains(']') => host,
+    _ => host,
+  };
+  return Some(host.to_lowercase());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_request_host() {
+    let host = |value: &str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(header::HOST, value.parse().unwrap());
+      return request_host(&headers, &Uri::from_static("/"));
+    };
+
+    assert_eq!(host("Tenant1.App.com"), Some("tenant1.app.com".to_string()));
+    assert_eq!(
+      host("tenant1.app.com:4000"),
+      Some("tenant1.app.com".to_string())
+    );
+    assert_eq!(host("[::1]:4000"), Some("[::1]".to_string()));
+    assert_eq!(host("[::1]"), Some("[::1]".to_string()));
+
+    assert_eq!(
+      request_host(
+        &HeaderMap::new(),
+        &Uri::from_static("https://tenant2.app.com/path")
+      ),
+      Some("tenant2.app.com".to_string())
+    );
+    assert_eq!(
+      request_host(&HeaderMap::new(), &Uri::from_static("/")),
+      None
+    );
+  }
+}
@@ -0,0 +1,311 @@
+use itertools::Itertools;
+
+use crate::schema::SchemaError;
+
+/// Sort order for [SqlTemplate::order_by].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+  Asc,
+  Desc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+  Select,
+  Insert,
+  Update,
+  Delete,
+}
+
+/// Small builder for the simple SELECT, INSERT, UPDATE and DELETE statements we construct
+/// throughout, which takes care of quoting identifiers.
+///
+/// Table and column names are validated when building and limited to alphanumeric characters
+/// and underscores. Table names may be qualified with a database name, e.g. "main.table".
+/// Conditions passed to `where_` and `join` are inserted verbatim.
+///
+/// INSERT and UPDATE statements use named placeholders derived from the column names, e.g.
+/// `:col`.
+#[derive(Clone, Debug)]
+pub struct SqlTemplate<'a> {
+  kind: Kind,
+  table: &'a str,
+  columns: Vec<&'a str>,
+  joins: Vec<(&'a str, &'a str)>,
+  where_clause: Option<&'a str>,
+  order_by: Vec<(&'a str, Order)>,
+  limit: Option<usize>,
+  returning: Vec<&'a str>,
+}
+
+impl<'a> SqlTemplate<'a> {
+  fn new(kind: Kind, table: &'a str) -> Self {
+    return Self {
+      kind,
+      table,
+      columns: vec![],
+      joins: vec![],
+      where_clause: None,
+      order_by: vec![],
+      limit: None,
+      returning: vec![],
+    };
+  }
+
+  /// Selects the given columns or all columns if none are given.
+  pub fn select(table: &'a str) -> Self {
+    return Self::new(Kind::Select, table);
+  }
+
+  /// Inserts values for the given columns or default values if none are given.
+  pub fn insert(table: &'a str) -> Self {
+    return Self::new(Kind::Insert, table);
+  }
+
+  /// Updates the given columns.
+  pub fn update(table: &'a str) -> Self {
+    return Self::new(Kind::Update, table);
+  }
+
+  pub fn delete(table: &'a str) -> Self {
+    return Self::new(Kind::Delete, table);
+  }
+
+  pub fn columns(mut self, columns: &[&'a str]) -> Self {
+    self.columns.extend_from_slice(columns);
+    return self;
+  }
+
+  pub fn join(mut self, table: &'a str, on: &'a str) -> Self {
+    self.joins.push((table, on));
+    return self;
+  }
+
+  pub fn where_(mut self, condition: &'a str) -> Self {
+    self.where_clause = Some(condition);
+    return self;
+  }
+
+  pub fn order_by(mut self, column: &'a str, order: Order) -> Self {
+    self.order_by.push((column, order));
+    return self;
+  }
+
+  pub fn limit(mut self, limit: usize) -> Self {
+    self.limit = Some(limit);
+    return self;
+  }
+
+  /// Returns the given columns. "*" returns all columns.
+  pub fn returning(mut self, columns: &[&'a str]) -> Self {
+    self.returning.extend_from_slice(columns);
+    return self;
+  }
+
+  pub fn build(&self) -> Result<String, SchemaError> {
+    let table = quote_qualified(self.table)?;
+
+    let mut sql = match self.kind {
+      Kind::Select => {
+        let columns = if self.columns.is_empty() {
+          "*".to_string()
+        } else {
+          self
+            .columns
+            .iter()
+            .map(|c| quote_column(c))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", ")
+        };
+        format!("SELECT {columns} FROM {table}")
+      }
+      Kind::Insert => {
+        if self.columns.is_empty() {
+          format!("INSERT INTO {table} DEFAULT VALUES")
+        } else {
+          let columns = self
+            .columns
+            .iter()
+            .map(|c| quote(c))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", ");
+          let placeholders = self.columns.iter().map(|c| format!(":{c}")).join(", ");
+          format!("INSERT INTO {table} ({columns}) VALUES ({placeholders})")
+        }
+      }
+      Kind::Update => {
+        if self.columns.is_empty() {
+          return Err(SchemaError::Precondition("UPDATE without columns".into()));
+        }
+        let setters = self
+          .columns
+          .iter()
+          .map(|c| Ok(format!("{} = :{c}", quote(c)?)))
+          .collect::<Result<Vec<_>, SchemaError>>()?
+          .join(", ");
+        format!("UPDATE {table} SET {setters}")
+      }
+      Kind::Delete => format!("DELETE FROM {table}"),
+    };
+
+    for (other, on) in &self.joins {
+      sql.push_str(&format!(" JOIN {} ON {on}", quote_qualified(other)?));
+    }
+
+    if let Some(condition) = self.where_clause {
+      sql.push_str(&format!(" WHERE {condition}"));
+    }
+
+    if !self.order_by.is_empty() {
+      let order_by = self
+        .order_by
+        .iter()
+        .map(|(column, order)| {
+          let order = match order {
+            Order::Asc => "ASC",
+            Order::Desc => "DESC",
+          };
+          return Ok(format!("{} {order}", quote(column)?));
+        })
+        .collect::<Result<Vec<_>, SchemaError>>()?
+        .join(", ");
+      sql.push_str(&format!(" ORDER BY {order_by}"));
+    }
+
+    if let Some(limit) = self.limit {
+      sql.push_str(&format!(" LIMIT {limit}"));
+    }
+
+    if !self.returning.is_empty() {
+      let returning = self
+        .returning
+        .iter()
+        .map(|c| quote_column(c))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", ");
+      sql.push_str(&format!(" RETURNING {returning}"));
+    }
+
+    return Ok(sql);
+  }
+}
+
+fn quote(identifier: &str) -> Result<String, SchemaError> {
+  let valid = !identifier.is_empty() && identifier.chars().all(|c| c.is_alphanumeric() || c == '_');
+  if !valid {
+    return Err(SchemaError::InvalidIdentifier(identifier.to_string()));
+  }
+  return Ok(format!(r#""{identifier}""#));
+}
+
+/// Like [quote] but passes through "*".
+fn quote_column(column: &str) -> Result<String, SchemaError> {
+  if column == "*" {
+    return Ok(column.to_string());
+  }
+  return quote(column);
+}
+
+fn quote_qualified(name: &str) -> Result<String, SchemaError> {
+  return match name.split_once('.') {
+    Some((database, table)) => Ok(format!("{}.{}", quote(database)?, quote(table)?)),
+    None => quote(name),
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_select() {
+    assert_eq!(
+      SqlTemplate::select("table").build().unwrap(),
+      r#"SELECT * FROM "table""#
+    );
+    assert_eq!(
+      SqlTemplate::select("main.table")
+        .columns(&["col1", "col2"])
+        .where_("id = ?1")
+        .order_by("col1", Order::Asc)
+        .order_by("col2", Order::Desc)
+        .limit(100)
+        .build()
+        .unwrap(),
+      r#"SELECT "col1", "col2" FROM "main"."table" WHERE id = ?1 ORDER BY "col1" ASC, "col2" DESC LIMIT 100"#
+    );
+    assert_eq!(
+      SqlTemplate::select("post")
+        .join("_user", "post.owner = _user.id")
+        .build()
+        .unwrap(),
+      r#"SELECT * FROM "post" JOIN "_user" ON post.owner = _user.id"#
+    );
+  }
+
+  #[test]
+  fn test_insert() {
+    assert_eq!(
+      SqlTemplate::insert("table")
+        .columns(&["col1", "col2"])
+        .returning(&["id"])
+        .build()
+        .unwrap(),
+      r#"INSERT INTO "table" ("col1", "col2") VALUES (:col1, :col2) RETURNING "id""#
+    );
+    assert_eq!(
+      SqlTemplate::insert("table").build().unwrap(),
+      r#"INSERT INTO "table" DEFAULT VALUES"#
+    );
+  }
+
+  #[test]
+  fn test_update() {
+    assert_eq!(
+      SqlTemplate::update("table")
+        .columns(&["col1", "col2"])
+        .where_(r#""id" = :id"#)
+        .returning(&["*"])
+        .build()
+        .unwrap(),
+      r#"UPDATE "table" SET "col1" = :col1, "col2" = :col2 WHERE "id" = :id RETURNING *"#
+    );
+    assert!(SqlTemplate::update("table").build().is_err());
+  }
+
+  #[test]
+  fn test_delete() {
+    assert_eq!(
+      SqlTemplate::delete("table")
+        .where_(r#""id" = $1"#)
+        .returning(&["*"])
+        .build()
+        .unwrap(),
+      r#"DELETE FROM "table" WHERE "id" = $1 RETURNING *"#
+    );
+  }
+
+  #[test]
+  fn test_invalid_identifiers() {
+    for name in ["", "ta\"ble", "table; DROP TABLE x", "a b", "a.b.c", "*"] {
+      assert!(
+        matches!(
+          SqlTemplate::select(name).build(),
+          Err(SchemaError::InvalidIdentifier(_))
+        ),
+        "{name}"
+      );
+    }
+
+    assert!(matches!(
+      SqlTemplate::select("table").columns(&["col\"; --"]).build(),
+      Err(SchemaError::InvalidIdentifier(_))
+    ));
+    assert!(matches!(
+      SqlTemplate::select("table")
+        .order_by("*", Order::Asc)
+        .build(),
+      Err(SchemaError::InvalidIdentifier(_))
+    ));
+  }
+}
@@ -12,7 +12,9 @@ use thiserror::Error;
 use trailbase_sqlite::params;
 
 use crate::constants::{SQLITE_SCHEMA_TABLE, USER_TABLE};
-use crate::schema::{Column, ColumnDataType, ColumnOption, ForeignKey, SchemaError, Table, View};
+use crate::schema::{
+  Column, ColumnDataType, ColumnOption, ForeignKey, SchemaError, Table, TableIndex, View,
+};
 
 // TODO: Can we merge this with trailbase_sqlite::schema::SchemaError?
 #[derive(Debug, Clone, Error)]
@@ -46,8 +48,8 @@ impl JsonColumnMetadata {
         return Ok(());
       }
       Self::Pattern(pattern) => {
-        let schema =
-          Validator::new(pattern).map_err(|err| JsonSchemaError::SchemaCompile(err.to_string()))?;
+        let schema = trailbase_sqlite::schema::compile_schema(pattern)
+          .map_err(|err| JsonSchemaError::SchemaCompile(err.to_string()))?;
         if !schema.is_valid(value) {
           Err(JsonSchemaError::Validation)
         } else {
@@ -79,7 +81,6 @@ pub struct TableMetadata {
   pub file_uploads_columns: Vec<usize>,
 
   // Only non-composite keys.
-  #[allow(unused)]
   foreign_ids: Vec<(usize, ForeignKey)>,
   // TODO: Add triggers once sqlparser supports a sqlite "CREATE TRIGGER" statements.
 }
@@ -173,6 +174,16 @@ impl TableMetadata {
     let index = self.column_index_by_name(key)?;
     return Some((&self.schema.columns[index], &self.metadata[index]));
   }
+
+  /// Returns both column-level and table-level foreign keys.
+  pub fn foreign_keys(&self) -> Vec<ForeignKey> {
+    return self
+      .foreign_ids
+      .iter()
+      .map(|(_index, fk)| fk.clone())
+      .chain(self.schema.foreign_keys.iter().cloned())
+      .collect();
+  }
 }
 
 /// A data class describing a sqlite View and future, additional meta data useful for TrailBase.
@@ -502,6 +513,13 @@ impl TableMetadataCache {
     self.state.views.read().get(view_name).cloned()
   }
 
+  /// Returns all tables ordered by name.
+  pub fn tables(&self) -> Vec<Arc<TableMetadata>> {
+    let mut tables: Vec<_> = self.state.tables.read().values().cloned().collect();
+    tables.sort_by(|a, b| a.name().cmp(b.name()));
+    return tables;
+  }
+
   pub async fn invalidate_all(&self) -> Result<(), TableLookupError> {
     debug!("Rebuilding TableMetadataCache");
     let (table_map, tables) = Self::build_tables(&self.state.conn).await?;
@@ -673,6 +691,29 @@ pub async fn lookup_and_parse_all_view_schemas(
   return Ok(views);
 }
 
+/// Looks up all indexes, excluding SQLite's internal auto-indexes, which don't have any SQL.
+pub async fn lookup_and_parse_all_index_schemas(
+  conn: &trailbase_sqlite::Connection,
+) -> Result<Vec<TableIndex>, TableLookupError> {
+  let rows = conn
+    .query(
+      &format!("SELECT sql FROM {SQLITE_SCHEMA_TABLE} WHERE type = 'index' AND sql IS NOT NULL"),
+      (),
+    )
+    .await?;
+
+  let mut indexes: Vec<TableIndex> = vec![];
+  for row in rows.iter() {
+    let sql: String = row.get(0)?;
+    let Some(stmt) = sqlite3_parse_into_statement(&sql)? else {
+      return Err(TableLookupError::Missing);
+    };
+    indexes.push(stmt.try_into()?);
+  }
+
+  return Ok(indexes);
+}
+
 /// Influeces the generated JSON schema. In `Insert` mode columns with default values will be
 /// optional.
 #[derive(Copy, Clone, Debug, Deserialize, Serialize)]
@@ -683,6 +724,60 @@ pub enum JsonSchemaMode {
   Select,
   /// Update mode.
   Update,
+  /// Like `Insert` but additionally translates well-known CHECK constraints into JSON schema
+  /// rules, e.g. `is_email(col)` into `"format": "email"`.
+  Strict,
+}
+
+/// Translates a column's CHECK constraint into additional JSON schema keywords where possible:
+///
+///  * `is_email(col)` and `is_url(col)` to `format`,
+///  * `col REGEXP '...'` or `regexp('...', col)` to `pattern`,
+///  * `col IN ('a', 'b')` to `enum`.
+fn check_to_json_schema_keywords(
+  column_name: &str,
+  check: &str,
+) -> serde_json::Map<String, serde_json::Value> {
+  const STRING: &str = r"'((?:[^']|'')*)'";
+  lazy_static! {
+    static ref STRING_RE: Regex = Regex::new(STRING).unwrap();
+  }
+
+  let unescape = |s: &str| s.replace("''", "'");
+  let col = format!(r#"[`"\[]?{}[`"\]]?"#, regex::escape(column_name));
+
+  let mut keywords = serde_json::Map::new();
+
+  for (function, format) in [("is_email", "email"), ("is_url", "uri")] {
+    let re = Regex::new(&format!(r"(?i){function}\s*\(\s*{col}\s*\)")).unwrap();
+    if re.is_match(check) {
+      keywords.insert("format".to_string(), format.into());
+    }
+  }
+
+  let pattern_re = Regex::new(&format!(
+    r"(?i)(?:{col}\s+REGEXP\s+{STRING})|(?:regexp\s*\(\s*{STRING}\s*,\s*{col}\s*\))"
+  ))
+  .unwrap();
+  if let Some(cap) = pattern_re.captures(check) {
+    if let Some(pattern) = cap.get(1).or_else(|| cap.get(2)) {
+      keywords.insert("pattern".to_string(), unescape(pattern.as_str()).into());
+    }
+  }
+
+  let enum_re = Regex::new(&format!(
+    r"(?i){col}\s+IN\s*\(((?:\s*{STRING}\s*,)*\s*{STRING}\s*)\)"
+  ))
+  .unwrap();
+  if let Some(cap) = enum_re.captures(check) {
+    let values: Vec<serde_json::Value> = STRING_RE
+      .captures_iter(&cap[1])
+      .map(|value| unescape(&value[1]).into())
+      .collect();
+    keywords.insert("enum".to_string(), values.into());
+  }
+
+  return keywords;
 }
 
 fn column_data_type_to_json_type(data_type: ColumnDataType) -> Value {
@@ -762,6 +857,7 @@ pub fn build_json_schema(
     let mut found_def = false;
     let mut not_null = false;
     let mut default = false;
+    let mut keywords = serde_json::Map::new();
 
     for opt in &col.options {
       match opt {
@@ -785,6 +881,10 @@ pub fn build_json_schema(
               }
             }
           }
+
+          if let JsonSchemaMode::Strict = mode {
+            keywords.extend(check_to_json_schema_keywords(&col.name, check));
+          }
         }
         ColumnOption::Unique { is_primary } => {
           // According to the SQL standard, PRIMARY KEY should always imply NOT NULL.
@@ -806,7 +906,7 @@ pub fn build_json_schema(
     }
 
     match mode {
-      JsonSchemaMode::Insert => {
+      JsonSchemaMode::Insert | JsonSchemaMode::Strict => {
         if not_null && !default {
           required_cols.push(col.name.clone());
         }
@@ -830,12 +930,11 @@ pub fn build_json_schema(
       continue;
     }
 
-    properties.insert(
-      col.name.clone(),
-      serde_json::json!({
-        "type": column_data_type_to_json_type(col.data_type),
-      }),
+    keywords.insert(
+      "type".to_string(),
+      column_data_type_to_json_type(col.data_type),
     );
+    properties.insert(col.name.clone(), serde_json::Value::Object(keywords));
   }
 
   let schema = serde_json::json!({
@@ -847,7 +946,8 @@ pub fn build_json_schema(
   });
 
   return Ok((
-    Validator::new(&schema).map_err(|err| JsonSchemaError::SchemaCompile(err.to_string()))?,
+    trailbase_sqlite::schema::compile_schema(&schema)
+      .map_err(|err| JsonSchemaError::SchemaCompile(err.to_string()))?,
     schema,
   ));
 }
@@ -1015,6 +1115,66 @@ mod tests {
     })));
   }
 
+  #[tokio::test]
+  async fn test_strict_json_schema() {
+    let state = test_state(None).await.unwrap();
+    let conn = state.conn();
+
+    conn
+      .execute(
+        r#"CREATE TABLE strict_table (
+            email    TEXT NOT NULL CHECK(is_email(email)),
+            code     TEXT CHECK(code REGEXP '^[a-z]+$'),
+            kind     TEXT CHECK(kind IN ('a', 'b', 'c')),
+            other    TEXT
+          ) STRICT"#,
+        (),
+      )
+      .await
+      .unwrap();
+
+    let table = lookup_and_parse_table_schema(conn, "strict_table")
+      .await
+      .unwrap();
+    let table_metadata = TableMetadata::new(table.clone(), &[table]);
+
+    let (_, insert_schema) = build_json_schema(
+      table_metadata.name(),
+      &table_metadata,
+      JsonSchemaMode::Insert,
+    )
+    .unwrap();
+    assert_eq!(
+      insert_schema["properties"]["email"],
+      json!({"type": "string"})
+    );
+
+    let (validator, schema) = build_json_schema(
+      table_metadata.name(),
+      &table_metadata,
+      JsonSchemaMode::Strict,
+    )
+    .unwrap();
+    assert_eq!(
+      schema["properties"]["email"],
+      json!({"type": "string", "format": "email"})
+    );
+    assert_eq!(
+      schema["properties"]["code"],
+      json!({"type": "string", "pattern": "^[a-z]+$"})
+    );
+    assert_eq!(
+      schema["properties"]["kind"],
+      json!({"type": "string", "enum": ["a", "b", "c"]})
+    );
+    assert_eq!(schema["properties"]["other"], json!({"type": "string"}));
+    assert_eq!(schema["required"], json!(["email"]));
+
+    assert!(validator.is_valid(&json!({"email": "foo@bar.com", "code": "abc", "kind": "a"})));
+    assert!(!validator.is_valid(&json!({"email": "foo@bar.com", "code": "ABC"})));
+    assert!(!validator.is_valid(&json!({"email": "foo@bar.com", "kind": "d"})));
+  }
+
   #[test]
   fn test_parse_alter_table() {
     let sql = "ALTER TABLE foo RENAME TO bar";
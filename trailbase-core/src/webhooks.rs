@@ -0,0 +1,276 @@
+use axum::http::header::CONTENT_TYPE;
+use hmac::{Hmac, Mac};
+use log::*;
+use sha2::Sha256;
+use std::time::Duration;
+use thiserror::Error;
+use trailbase_sqlite::{named_params, params};
+
+use crate::constants::{WEBHOOKS_TABLE, WEBHOOK_DELIVERIES_TABLE};
+use crate::records::record_json_object;
+use crate::table_metadata::{TableMetadata, TableMetadataCache};
+
+/// Header carrying the payload's signature, see [sign_payload].
+pub const HEADER_WEBHOOK_SIGNATURE: &str = "X-Webhook-Signature";
+/// Header carrying the operation that triggered the delivery, e.g. "INSERT".
+pub const HEADER_WEBHOOK_EVENT: &str = "X-Webhook-Event";
+/// Header carrying the delivery's unique id, e.g. for de-duplication by the receiver.
+pub const HEADER_WEBHOOK_DELIVERY: &str = "X-Webhook-Delivery";
+
+pub(crate) const WEBHOOK_EVENTS: [&str; 3] = ["INSERT", "UPDATE", "DELETE"];
+
+/// Deliveries are given up after this many failed attempts.
+const MAX_DELIVERY_ATTEMPTS: i64 = 5;
+/// Delay before the first retry, which doubles with every further attempt.
+const RETRY_BASE_DELAY_SEC: i64 = 30;
+const DELIVERY_BATCH_SIZE: i64 = 100;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+  #[error("TokioRusqlite error: {0}")]
+  TokioRusqlite(#[from] trailbase_sqlite::Error),
+  #[error("Rusqlite FromSql error: {0}")]
+  FromSql(#[from] rusqlite::types::FromSqlError),
+}
+
+/// Computes the signature sent along with webhook payloads: "sha256=" followed by the
+/// hex-encoded HMAC-SHA256 of the payload keyed with the webhook's secret.
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+  let mac = hmac_sha256(secret.as_bytes(), payload);
+  return format!(
+    "sha256={}",
+    mac.iter().map(|b| format!("{b:02x}")).collect::<String>()
+  );
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+  let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+  mac.update(message);
+  return mac.finalize().into_bytes().into();
+}
+
+fn trigger_name(table_name: &str, event: &str) -> String {
+  return format!(
+    "__webhooks__{table_name}__{event}_trigger",
+    event = event.to_lowercase()
+  );
+}
+
+/// Triggers enqueuing deliveries for all active webhooks subscribed to the table and operation.
+/// Webhooks are looked up when the trigger fires, thus the triggers only need to be re-created
+/// when the table's schema changes.
+fn webhook_trigger_statements(metadata: &TableMetadata) -> Vec<String> {
+  let table_name = &metadata.schema.name;
+  let new_record = record_json_object(metadata, "NEW");
+  let old_record = record_json_object(metadata, "OLD");
+
+  let mut statements = vec![];
+  for event in WEBHOOK_EVENTS {
+    let trigger = trigger_name(table_name, event);
+    let payload = match event {
+      "INSERT" => format!("'record', {new_record}"),
+      "UPDATE" => format!("'record', {new_record}, 'old_record', {old_record}"),
+      _ => format!("'record', {old_record}"),
+    };
+
+    statements.push(format!(r#"DROP TRIGGER IF EXISTS "{trigger}""#));
+    statements.push(format!(
+      r#"CREATE TRIGGER "{trigger}" AFTER {event} ON "{table_name}" BEGIN
+  INSERT INTO "{WEBHOOK_DELIVERIES_TABLE}" (webhook_id, event, payload)
+    SELECT id, '{event}', json_object('event', '{event}', 'table', '{table_name}', 'timestamp', UNIXEPOCH(), {payload})
+    FROM "{WEBHOOKS_TABLE}" AS w
+    WHERE w.active AND w.table_filter = '{table_name}' AND EXISTS (SELECT 1 FROM json_each(w.events) WHERE value = '{event}');
+END"#
+    ));
+  }
+  return statements;
+}
+
+/// (Re-)installs the delivery triggers on the given table.
+pub(crate) async fn install_webhook_triggers(
+  conn: &trailbase_sqlite::Connection,
+  metadata: &TableMetadata,
+) -> Result<(), trailbase_sqlite::Error> {
+  conn
+    .execute_batch(&webhook_trigger_statements(metadata).join(";\n"))
+    .await?;
+  return Ok(());
+}
+
+/// Removes the delivery triggers from the given table, e.g. after its last webhook was removed.
+pub(crate) async fn uninstall_webhook_triggers(
+  conn: &trailbase_sqlite::Connection,
+  table_name: &str,
+) -> Result<(), trailbase_sqlite::Error> {
+  let statements = WEBHOOK_EVENTS
+    .iter()
+    .map(|event| {
+      format!(
+        r#"DROP TRIGGER IF EXISTS "{trigger}""#,
+        trigger = trigger_name(table_name, event)
+      )
+    })
+    .collect::<Vec<_>>();
+  conn.execute_batch(&statements.join(";\n")).await?;
+  return Ok(());
+}
+
+/// (Re-)installs the delivery triggers for all tables with webhooks to pick up schema changes.
+pub(crate) async fn install_all_webhook_triggers(
+  conn: &trailbase_sqlite::Connection,
+  tables: &TableMetadataCache,
+) -> Result<(), WebhookError> {
+  let rows = conn
+    .query(
+      &format!("SELECT DISTINCT table_filter FROM '{WEBHOOKS_TABLE}'"),
+      (),
+    )
+    .await?;
+
+  for row in rows.iter() {
+    let table_name: String = row.get(0)?;
+    let Some(metadata) = tables.get(&table_name) else {
+      warn!("Skipping webhooks for missing table: {table_name}");
+      continue;
+    };
+    install_webhook_triggers(conn, &metadata).await?;
+  }
+
+  return Ok(());
+}
+
+/// HTTP client for webhook deliveries. Redirects aren't followed, since they'd forward signed
+/// payloads to endpoints other than the configured one.
+pub(crate) fn delivery_client() -> reqwest::Client {
+  // NOTE: Like `reqwest::Client::new()`, this only fails if the TLS backend cannot be initialized.
+  return reqwest::Client::builder()
+    .redirect(reqwest::redirect::Policy::none())
+    .build()
+    .expect("webhook delivery client");
+}
+
+/// Attempts all pending deliveries that are due and returns the number of successful ones.
+///
+/// Failed deliveries are retried with exponential backoff until they are given up after
+/// [MAX_DELIVERY_ATTEMPTS].
+pub(crate) async fn deliver_pending_webhooks(
+  conn: &trailbase_sqlite::Connection,
+  client: &reqwest::Client,
+) -> Result<usize, WebhookError> {
+  let rows = conn
+    .query(
+      &format!(
+        r#"
+          SELECT d.id, d.event, d.payload, d.attempts, w.url, w.secret
+          FROM "{WEBHOOK_DELIVERIES_TABLE}" AS d JOIN "{WEBHOOKS_TABLE}" AS w ON d.webhook_id = w.id
+          WHERE d.status = 'pending' AND d.next_attempt <= UNIXEPOCH()
+          ORDER BY d.id
+          LIMIT $1
+        "#
+      ),
+      params!(DELIVERY_BATCH_SIZE),
+    )
+    .await?;
+
+  let mut delivered: usize = 0;
+  for row in rows.iter() {
+    let id: i64 = row.get(0)?;
+    let event: String = row.get(1)?;
+    let payload: String = row.get(2)?;
+    let attempts: i64 = row.get::<i64>(3)? + 1;
+    let url: String = row.get(4)?;
+    let secret: String = row.get(5)?;
+
+    let result = client
+      .post(&url)
+      .timeout(DELIVERY_TIMEOUT)
+      .header(CONTENT_TYPE, "application/json")
+      .header(HEADER_WEBHOOK_EVENT, &event)
+      .header(HEADER_WEBHOOK_DELIVERY, id.to_string())
+      .header(
+        HEADER_WEBHOOK_SIGNATURE,
+        sign_payload(&secret, payload.as_bytes()),
+      )
+      .body(payload)
+      .send()
+      .await;
+
+    let (response_status, error) = match result {
+      Ok(response) if response.status().is_success() => {
+        conn
+          .execute(
+            &format!(
+              "UPDATE '{WEBHOOK_DELIVERIES_TABLE}' SET status = 'delivered', attempts = :attempts, response_status = :status, error = NULL WHERE id = :id"
+            ),
+            named_params! {
+              ":attempts": attempts,
+              ":status": response.status().as_u16() as i64,
+              ":id": id,
+            },
+          )
+          .await?;
+        delivered += 1;
+        continue;
+      }
+      Ok(response) => (
+        Some(response.status().as_u16() as i64),
+        format!("Unexpected status: {}", response.status()),
+      ),
+      Err(err) => (None, err.to_string()),
+    };
+
+    debug!("Webhook delivery {id} to {url} failed: {error}");
+
+    let status = if attempts >= MAX_DELIVERY_ATTEMPTS {
+      "failed"
+    } else {
+      "pending"
+    };
+    conn
+      .execute(
+        &format!(
+          "UPDATE '{WEBHOOK_DELIVERIES_TABLE}' SET status = :status, attempts = :attempts, next_attempt = UNIXEPOCH() + :delay, response_status = :response_status, error = :error WHERE id = :id"
+        ),
+        named_params! {
+          ":status": status,
+          ":attempts": attempts,
+          ":delay": RETRY_BASE_DELAY_SEC << (attempts - 1),
+          ":response_status": response_status,
+          ":error": error,
+          ":id": id,
+        },
+      )
+      .await?;
+  }
+
+  return Ok(delivered);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hmac_sha256() {
+    let hex = |bytes: [u8; 32]| bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+
+    // Test vectors from RFC 4231.
+    assert_eq!(
+      hex(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+      "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_eq!(
+      hex(hmac_sha256(
+        &[0xaa; 131],
+        b"Test Using Larger Than Block-Size Key - Hash Key First"
+      )),
+      "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+
+    assert_eq!(
+      sign_payload("Jefe", b"what do ya want for nothing?"),
+      "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+  }
+}
@@ -0,0 +1,117 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{Read, Write};
+
+use trailbase::config::proto::PermissionFlag;
+use trailbase::constants::RECORD_API_PATH;
+use trailbase::records::*;
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_compression() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+
+  let _ = runtime.block_on(async move {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let state = app.state();
+    state
+      .conn()
+      .execute_batch(
+        r#"
+          CREATE TABLE item (
+            id           INTEGER PRIMARY KEY,
+            text         TEXT NOT NULL
+          ) STRICT;
+        "#,
+      )
+      .await
+      .unwrap();
+    state.refresh_table_cache().await.unwrap();
+
+    add_record_api(
+      state,
+      "items_api",
+      "item",
+      Acls {
+        world: vec![PermissionFlag::Create, PermissionFlag::Read],
+        ..Default::default()
+      },
+      AccessRules::default(),
+    )
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    // Compressed request bodies get decompressed.
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+      .write_all(
+        serde_json::json!({
+          "text": "compressed",
+        })
+        .to_string()
+        .as_bytes(),
+      )
+      .unwrap();
+    let body = encoder.finish().unwrap();
+
+    let response = server
+      .post(&format!("/{RECORD_API_PATH}/items_api"))
+      .add_header("Content-Type", "application/json")
+      .add_header("Content-Encoding", "gzip")
+      .bytes(body.into())
+      .await;
+    assert_eq!(response.status_code(), StatusCode::OK, "{response:?}");
+
+    let text: String = state
+      .conn()
+      .query_row("SELECT text FROM item", ())
+      .await
+      .unwrap()
+      .unwrap()
+      .get(0)
+      .unwrap();
+    assert_eq!(text, "compressed");
+
+    // Large responses get compressed.
+    state
+      .conn()
+      .execute(
+        r#"
+          WITH RECURSIVE series(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM series WHERE n < 200)
+          INSERT INTO item (text) SELECT 'some longer text to make for a sizable response' FROM series
+        "#,
+        (),
+      )
+      .await
+      .unwrap();
+
+    let response = server
+      .get(&format!("/{RECORD_API_PATH}/items_api?limit=200"))
+      .add_header("Accept-Encoding", "gzip")
+      .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("Content-Encoding"), "gzip");
+
+    let mut decoded = String::new();
+    GzDecoder::new(response.as_bytes().as_ref())
+      .read_to_string(&mut decoded)
+      .unwrap();
+    let list: serde_json::Value = serde_json::from_str(&decoded).unwrap();
+    assert_eq!(list["records"].as_array().unwrap().len(), 200);
+  });
+}
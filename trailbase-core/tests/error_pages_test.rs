@@ -0,0 +1,101 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use std::collections::HashMap;
+
+use trailbase::config::proto::PermissionFlag;
+use trailbase::constants::RECORD_API_PATH;
+use trailbase::records::*;
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_custom_error_pages() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let public_dir = temp_dir::TempDir::new().unwrap();
+
+  let _ = runtime.block_on(async move {
+    std::fs::write(public_dir.child("index.html"), "<html>index</html>").unwrap();
+    std::fs::write(public_dir.child("404.html"), "<html>custom 404</html>").unwrap();
+    std::fs::write(
+      public_dir.child("forbidden.html"),
+      "<html>custom 403</html>",
+    )
+    .unwrap();
+
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      public_dir: Some(public_dir.path().to_path_buf()),
+      custom_error_pages: HashMap::from([(403, public_dir.child("forbidden.html"))]),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    let response = server.get("/index.html").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.text(), "<html>index</html>");
+
+    // Missing static files.
+    let response = server.get("/does/not/exist").await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(response.text(), "<html>custom 404</html>");
+    assert!(response
+      .header("Content-Type")
+      .to_str()
+      .unwrap()
+      .starts_with("text/html"));
+
+    let state = app.state();
+    state
+      .conn()
+      .execute_batch("CREATE TABLE item (id INTEGER PRIMARY KEY, text TEXT) STRICT")
+      .await
+      .unwrap();
+    state.refresh_table_cache().await.unwrap();
+
+    for (api_name, world) in [
+      ("public_items", vec![PermissionFlag::Read]),
+      ("private_items", vec![]),
+    ] {
+      add_record_api(
+        state,
+        api_name,
+        "item",
+        Acls {
+          world,
+          ..Default::default()
+        },
+        AccessRules::default(),
+      )
+      .await
+      .unwrap();
+    }
+
+    // API errors are only replaced for clients asking for HTML.
+    let response = server
+      .get(&format!("/{RECORD_API_PATH}/public_items/1"))
+      .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_ne!(response.text(), "<html>custom 404</html>");
+
+    let response = server
+      .get(&format!("/{RECORD_API_PATH}/public_items/1"))
+      .add_header("Accept", "text/html,application/xhtml+xml")
+      .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(response.text(), "<html>custom 404</html>");
+
+    let response = server
+      .get(&format!("/{RECORD_API_PATH}/private_items/1"))
+      .add_header("Accept", "text/html")
+      .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), "<html>custom 403</html>");
+  });
+}
@@ -0,0 +1,74 @@
+use trailbase::{DataDir, Server, ServerOptions};
+
+async fn count_tables(conn: &trailbase_sqlite::Connection) -> i64 {
+  return conn
+    .query_row(
+      "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('table_a', 'table_b')",
+      (),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .get(0)
+    .unwrap();
+}
+
+#[test]
+fn test_first_init_sql() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let sql_dir = temp_dir::TempDir::new().unwrap();
+
+  std::fs::write(
+    sql_dir.child("001_table_a.sql"),
+    "CREATE TABLE table_a (id INTEGER PRIMARY KEY) STRICT;",
+  )
+  .unwrap();
+  std::fs::write(
+    sql_dir.child("002_table-b.sql"),
+    "CREATE TABLE table_b (id INTEGER PRIMARY KEY, a INTEGER REFERENCES table_a(id)) STRICT;",
+  )
+  .unwrap();
+
+  runtime.block_on(async {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      on_first_init_sql: Some(vec![sql_dir.path().join("*.sql")]),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let conn = app.state().conn();
+    assert_eq!(count_tables(conn).await, 2);
+
+    let migrations: i64 = conn
+      .query_row(
+        "SELECT COUNT(*) FROM _schema_history WHERE name IN ('001_table_a', '002_table_b')",
+        (),
+      )
+      .await
+      .unwrap()
+      .unwrap()
+      .get(0)
+      .unwrap();
+    assert_eq!(migrations, 2);
+  });
+
+  // Re-initializing an existing data directory must neither fail nor re-run the files.
+  runtime.block_on(async {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      on_first_init_sql: Some(vec![sql_dir.path().join("*.sql")]),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(count_tables(app.state().conn()).await, 2);
+  });
+}
@@ -6,7 +6,10 @@ use tower_cookies::Cookie;
 use tracing_subscriber::prelude::*;
 use trailbase_sqlite::params;
 
-use trailbase::api::{create_user_handler, login_with_password, CreateUserRequest};
+use trailbase::api::{
+  create_api_key, create_user_handler, login_with_password, revoke_api_key, CreateUserRequest,
+  NewApiKey,
+};
 use trailbase::config::proto::PermissionFlag;
 use trailbase::constants::{COOKIE_AUTH_TOKEN, RECORD_API_PATH};
 use trailbase::records::*;
@@ -152,6 +155,40 @@ async fn test_record_apis() {
       assert_eq!(test_response.status_code(), StatusCode::OK);
     }
 
+    {
+      // User X can post using an API key until it's revoked.
+      let user_x_id = uuid::Uuid::from_bytes(user_x);
+      let NewApiKey { id, key } =
+        create_api_key(state.user_conn(), user_x_id, "ci".to_string(), None)
+          .await
+          .unwrap();
+
+      let post_with_api_key = || {
+        server
+          .post(&format!("/{RECORD_API_PATH}/messages_api"))
+          .add_header("Authorization", format!("Bearer {key}"))
+          .json(&serde_json::json!({
+            "_owner": id_to_b64(&user_x),
+            "room": id_to_b64(&room),
+            "data": "user_x message to room via API key",
+          }))
+      };
+
+      let test_response = post_with_api_key().await;
+      assert_eq!(
+        test_response.status_code(),
+        StatusCode::OK,
+        "{test_response:?}"
+      );
+
+      revoke_api_key(state.user_conn(), user_x_id, id)
+        .await
+        .unwrap();
+
+      let test_response = post_with_api_key().await;
+      assert_eq!(test_response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+
     {
       // Add a second record API for the same table
       add_record_api(
@@ -295,6 +332,7 @@ pub(crate) async fn create_user_for_test(
         password: password.to_string(),
         verified: true,
         admin: false,
+        force_password_reset: false,
       }),
     )
     .await?
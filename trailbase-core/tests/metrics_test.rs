@@ -0,0 +1,36 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_metrics() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+
+  let _ = runtime.block_on(async move {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      enable_metrics: true,
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    let response = server.get("/api/healthcheck").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = server.get("/api/metrics").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let body = response.text();
+    assert!(body.contains("trailbase_http_requests_total"), "{body}");
+    assert!(body.contains(r#"path="/api/healthcheck""#), "{body}");
+  });
+}
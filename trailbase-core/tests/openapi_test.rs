@@ -0,0 +1,40 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_openapi() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+
+  let _ = runtime.block_on(async move {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      enable_openapi: Some(true),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    let response = server.get("/openapi.json").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let spec: serde_json::Value = response.json();
+    assert!(spec["paths"]["/api/auth/v1/login"].is_object(), "{spec}");
+    assert!(
+      spec["components"]["securitySchemes"]["BearerAuth"].is_object(),
+      "{spec}"
+    );
+
+    let response = server.get("/openapi.yaml").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("/api/auth/v1/login"));
+  });
+}
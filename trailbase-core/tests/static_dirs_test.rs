@@ -0,0 +1,68 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_extra_public_dirs() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let public_dir = temp_dir::TempDir::new().unwrap();
+  let docs_dir = temp_dir::TempDir::new().unwrap();
+
+  let _ = runtime.block_on(async move {
+    std::fs::write(public_dir.child("index.html"), "<html>app</html>").unwrap();
+    std::fs::write(docs_dir.child("index.html"), "<html>docs</html>").unwrap();
+    std::fs::write(docs_dir.child("guide.html"), "<html>guide</html>").unwrap();
+
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      public_dir: Some(public_dir.path().to_path_buf()),
+      extra_public_dirs: vec![("/docs".to_string(), docs_dir.path().to_path_buf())],
+      static_cache_max_age: Some(3600),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    for (path, expected) in [
+      ("/index.html", "<html>app</html>"),
+      ("/docs/guide.html", "<html>guide</html>"),
+      ("/docs/", "<html>docs</html>"),
+    ] {
+      let response = server.get(path).await;
+      assert_eq!(response.status_code(), StatusCode::OK, "{path}");
+      assert_eq!(response.text(), expected, "{path}");
+      assert_eq!(
+        response.header("Cache-Control"),
+        "public, max-age=3600",
+        "{path}"
+      );
+    }
+
+    // Missing files aren't cached.
+    let response = server.get("/docs/missing.html").await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get("Cache-Control").is_none());
+
+    // APIs aren't affected.
+    let response = server.get("/api/healthcheck").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.headers().get("Cache-Control").is_none());
+
+    // Invalid prefixes are rejected.
+    assert!(Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      extra_public_dirs: vec![("/".to_string(), docs_dir.path().to_path_buf())],
+      ..Default::default()
+    })
+    .await
+    .is_err());
+  });
+}
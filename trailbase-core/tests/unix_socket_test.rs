@@ -0,0 +1,50 @@
+#![cfg(unix)]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+use trailbase::{DataDir, Server, ServerOptions};
+
+#[test]
+fn test_unix_socket() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let socket_path = data_dir.path().join("trail.sock");
+
+  let _ = runtime.block_on(async move {
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      unix_socket: Some(socket_path.clone()),
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let _server = tokio::spawn(async move { app.serve().await.unwrap() });
+
+    // NOTE: reqwest doesn't support Unix sockets, thus we speak plain HTTP/1.1.
+    let mut stream = None;
+    for _ in 0..100 {
+      if let Ok(s) = UnixStream::connect(&socket_path).await {
+        stream = Some(s);
+        break;
+      }
+      tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let mut stream = stream.expect("server not listening");
+
+    stream
+      .write_all(b"GET /api/healthcheck HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with("Ok"), "{response}");
+  });
+}
@@ -0,0 +1,94 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+use trailbase::{DataDir, Server, ServerOptions, VirtualHostConfig};
+
+#[test]
+fn test_virtual_hosts() {
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .enable_all()
+    .build()
+    .unwrap();
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let public_dirs: Vec<_> = (0..3).map(|_| temp_dir::TempDir::new().unwrap()).collect();
+
+  let _ = runtime.block_on(async move {
+    for (dir, name) in public_dirs.iter().zip(["default", "tenant1", "tenant2"]) {
+      std::fs::write(dir.child("index.html"), format!("<html>{name}</html>")).unwrap();
+    }
+
+    let app = Server::init(ServerOptions {
+      data_dir: DataDir(data_dir.path().to_path_buf()),
+      public_dir: Some(public_dirs[0].path().to_path_buf()),
+      virtual_hosts: vec![
+        VirtualHostConfig {
+          domain: "tenant1.app.com".to_string(),
+          public_dir: Some(public_dirs[1].path().to_path_buf()),
+          cors_origins: vec!["https://tenant1.org".to_string()],
+          ..Default::default()
+        },
+        VirtualHostConfig {
+          domain: "tenant2.app.com".to_string(),
+          public_dir: Some(public_dirs[2].path().to_path_buf()),
+          ..Default::default()
+        },
+      ],
+      ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let server = TestServer::new(app.router().clone()).unwrap();
+
+    for (host, expected) in [
+      ("tenant1.app.com", "<html>tenant1</html>"),
+      ("TENANT2.app.com:4000", "<html>tenant2</html>"),
+      ("other.app.com", "<html>default</html>"),
+    ] {
+      let response = server.get("/index.html").add_header("Host", host).await;
+      assert_eq!(response.status_code(), StatusCode::OK, "{host}");
+      assert_eq!(response.text(), expected, "{host}");
+    }
+
+    // APIs are shared.
+    let response = server
+      .get("/api/healthcheck")
+      .add_header("Host", "tenant1.app.com")
+      .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // CORS origins are per host.
+    let response = server
+      .get("/api/healthcheck")
+      .add_header("Host", "tenant1.app.com")
+      .add_header("Origin", "https://tenant1.org")
+      .await;
+    assert_eq!(
+      response.header("Access-Control-Allow-Origin"),
+      "https://tenant1.org"
+    );
+
+    let response = server
+      .get("/api/healthcheck")
+      .add_header("Host", "tenant2.app.com")
+      .add_header("Origin", "https://tenant1.org")
+      .await;
+    assert!(response
+      .headers()
+      .get("Access-Control-Allow-Origin")
+      .is_none());
+  });
+
+  let data_dir = temp_dir::TempDir::new().unwrap();
+  let result = runtime.block_on(Server::init(ServerOptions {
+    data_dir: DataDir(data_dir.path().to_path_buf()),
+    virtual_hosts: vec![VirtualHostConfig {
+      domain: "tenant1.app.com".to_string(),
+      data_dir: Some(DataDir(data_dir.path().join("tenant1"))),
+      ..Default::default()
+    }],
+    ..Default::default()
+  }));
+  assert!(result.is_err());
+}
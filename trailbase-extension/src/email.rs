@@ -0,0 +1,127 @@
+use rusqlite::functions::Context;
+use rusqlite::Error;
+use validator::ValidateEmail;
+
+#[inline]
+fn unpack_email_or_null<'a>(context: &'a Context<'_>) -> rusqlite::Result<Option<&'a str>> {
+  return match context.get_raw(0).as_str_or_null()? {
+    None => Ok(None),
+    Some(email) if email.validate_email() => Ok(Some(email)),
+    Some(email) => Err(Error::UserFunctionError(
+      format!("Invalid email: '{email}'").into(),
+    )),
+  };
+}
+
+/// Lower-cases the given address and, if requested, strips "+suffix" sub-addresses from the
+/// local part, e.g. "Foo+Spam@Gmail.com" becomes "foo@gmail.com".
+pub fn normalize_email(email: &str, strip_plus: bool) -> String {
+  let email = email.to_lowercase();
+  if !strip_plus {
+    return email;
+  }
+
+  return match email.rsplit_once('@') {
+    Some((local, domain)) => match local.split_once('+') {
+      Some((local, _suffix)) => format!("{local}@{domain}"),
+      None => email,
+    },
+    None => email,
+  };
+}
+
+pub(super) fn normalize_email_sqlite(context: &Context) -> rusqlite::Result<Option<String>> {
+  #[cfg(debug_assertions)]
+  if context.is_empty() || context.len() > 2 {
+    return Err(Error::InvalidParameterCount(context.len(), 2));
+  }
+
+  let strip_plus = match context.len() {
+    2 => context.get::<i64>(1)? != 0,
+    _ => false,
+  };
+
+  return Ok(unpack_email_or_null(context)?.map(|email| normalize_email(email, strip_plus)));
+}
+
+pub(super) fn domain_of_email(context: &Context) -> rusqlite::Result<Option<String>> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return Ok(
+    unpack_email_or_null(context)?
+      .and_then(|email| email.rsplit_once('@'))
+      .map(|(_local, domain)| domain.to_string()),
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  #[test]
+  fn test_normalize_email() {
+    let conn = crate::connect().unwrap();
+
+    let normalize = |query: &str, email: Option<&str>| -> rusqlite::Result<Option<String>> {
+      conn.query_row(query, [email], |row| row.get(0))
+    };
+
+    assert_eq!(
+      normalize("SELECT normalize_email($1)", Some("Foo.Bar@Example.COM")).unwrap(),
+      Some("foo.bar@example.com".to_string())
+    );
+    assert_eq!(
+      normalize("SELECT normalize_email($1)", Some("Foo+Spam@Gmail.com")).unwrap(),
+      Some("foo+spam@gmail.com".to_string())
+    );
+    assert_eq!(
+      normalize("SELECT normalize_email($1, 0)", Some("Foo+Spam@Gmail.com")).unwrap(),
+      Some("foo+spam@gmail.com".to_string())
+    );
+    assert_eq!(
+      normalize("SELECT normalize_email($1, 1)", Some("Foo+Spam@Gmail.com")).unwrap(),
+      Some("foo@gmail.com".to_string())
+    );
+    assert_eq!(
+      normalize("SELECT normalize_email($1, 1)", Some("foo@gmail.com")).unwrap(),
+      Some("foo@gmail.com".to_string())
+    );
+
+    assert_eq!(normalize("SELECT normalize_email($1)", None).unwrap(), None);
+    assert_eq!(
+      normalize("SELECT normalize_email($1, 1)", None).unwrap(),
+      None
+    );
+
+    assert!(normalize("SELECT normalize_email($1)", Some("not an email")).is_err());
+  }
+
+  #[test]
+  fn test_domain_of_email() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          email          TEXT,
+          email_domain   TEXT GENERATED ALWAYS AS (domain_of_email(email)) STORED
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    const QUERY: &str = "INSERT INTO test (email) VALUES ($1) RETURNING email_domain";
+    let insert = |email: Option<&str>| -> rusqlite::Result<Option<String>> {
+      conn.query_row(QUERY, [email], |row| row.get(0))
+    };
+
+    assert_eq!(
+      insert(Some("foo@example.com")).unwrap(),
+      Some("example.com".to_string())
+    );
+    assert_eq!(
+      insert(Some("Foo+Bar@Sub.Example.com")).unwrap(),
+      Some("Sub.Example.com".to_string())
+    );
+    assert_eq!(insert(None).unwrap(), None);
+    assert!(insert(Some("not an email")).is_err());
+  }
+}
@@ -0,0 +1,85 @@
+use rusqlite::functions::Context;
+use rusqlite::Error;
+use std::cell::RefCell;
+
+thread_local! {
+  static CURRENT_USER_GROUPS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with `groups` as the current user's groups, i.e. statements executed by `f` on this
+/// thread, for example queries against VIEWs, see them through `current_user_in_group()`.
+pub fn with_current_user_groups<T>(groups: &[String], f: impl FnOnce() -> T) -> T {
+  /// Restores the previous groups, even if `f` panics.
+  struct Reset(Option<Vec<String>>);
+
+  impl Drop for Reset {
+    fn drop(&mut self) {
+      let previous = self.0.take();
+      CURRENT_USER_GROUPS.with(|current| current.replace(previous));
+    }
+  }
+
+  let _reset = Reset(CURRENT_USER_GROUPS.with(|current| current.replace(Some(groups.to_vec()))));
+  return f();
+}
+
+/// Returns whether the current user, see [with_current_user_groups], is a member of the given
+/// group. Always false outside of a user's scope.
+pub(super) fn current_user_in_group(context: &Context) -> rusqlite::Result<bool> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  let Some(group_id) = context.get_raw(0).as_str_or_null()? else {
+    return Ok(false);
+  };
+
+  return Ok(CURRENT_USER_GROUPS.with(|current| {
+    current
+      .borrow()
+      .as_ref()
+      .is_some_and(|groups| groups.iter().any(|g| g == group_id))
+  }));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_current_user_in_group() {
+    let conn = crate::connect().unwrap();
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE project (id INTEGER PRIMARY KEY, team TEXT NOT NULL) STRICT;
+          INSERT INTO project (team) VALUES ('red'), ('blue'), ('red');
+
+          CREATE VIEW my_project AS SELECT * FROM project WHERE current_user_in_group(team);
+        "#,
+      )
+      .unwrap();
+
+    let count = || -> i64 {
+      return conn
+        .query_row("SELECT COUNT(*) FROM my_project", (), |row| row.get(0))
+        .unwrap();
+    };
+
+    assert_eq!(count(), 0);
+    assert_eq!(
+      with_current_user_groups(&["red".to_string()], || count()),
+      2
+    );
+    assert_eq!(
+      with_current_user_groups(&["red".to_string(), "blue".to_string()], || {
+        // Nested scopes replace rather than extend the outer groups.
+        assert_eq!(with_current_user_groups(&[], || count()), 0);
+        count()
+      }),
+      3
+    );
+    assert_eq!(count(), 0);
+  }
+}
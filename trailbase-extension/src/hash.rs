@@ -0,0 +1,125 @@
+use rusqlite::functions::Context;
+use rusqlite::types::ValueRef;
+use rusqlite::Error;
+use sha2::{Digest, Sha256};
+
+/// Returns the raw bytes of a TEXT or BLOB argument, or None for NULL.
+#[inline]
+fn unpack_bytes_or_null<'a>(context: &'a Context<'_>) -> rusqlite::Result<Option<&'a [u8]>> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return match context.get_raw(0) {
+    ValueRef::Null => Ok(None),
+    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => Ok(Some(bytes)),
+    arg => Err(Error::UserFunctionError(
+      format!("Expected TEXT or BLOB, got {}", arg.data_type()).into(),
+    )),
+  };
+}
+
+pub(super) fn hash_sha256(context: &Context) -> rusqlite::Result<Option<Vec<u8>>> {
+  return Ok(unpack_bytes_or_null(context)?.map(|bytes| Sha256::digest(bytes).to_vec()));
+}
+
+pub(super) fn hash_sha256_hex(context: &Context) -> rusqlite::Result<Option<String>> {
+  return Ok(unpack_bytes_or_null(context)?.map(|bytes| hex::encode(Sha256::digest(bytes))));
+}
+
+pub(super) fn hash_blake3(context: &Context) -> rusqlite::Result<Option<Vec<u8>>> {
+  return Ok(unpack_bytes_or_null(context)?.map(|bytes| blake3::hash(bytes).as_bytes().to_vec()));
+}
+
+pub(super) fn hash_blake3_hex(context: &Context) -> rusqlite::Result<Option<String>> {
+  return Ok(unpack_bytes_or_null(context)?.map(|bytes| blake3::hash(bytes).to_hex().to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+  use rusqlite::params;
+
+  const SHA256_EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+  const SHA256_ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+  const BLAKE3_EMPTY: &str = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262";
+  const BLAKE3_ABC: &str = "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85";
+
+  #[test]
+  fn test_hash_sha256() {
+    let conn = crate::connect().unwrap();
+
+    let hex_of = |query: &str, arg: rusqlite::types::Value| -> Option<String> {
+      conn.query_row(query, [arg], |row| row.get(0)).unwrap()
+    };
+
+    assert_eq!(
+      hex_of("SELECT hash_sha256_hex($1)", "".to_string().into()).unwrap(),
+      SHA256_EMPTY
+    );
+    assert_eq!(
+      hex_of("SELECT hash_sha256_hex($1)", "abc".to_string().into()).unwrap(),
+      SHA256_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hash_sha256_hex($1)", b"abc".to_vec().into()).unwrap(),
+      SHA256_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hex(hash_sha256($1))", "abc".to_string().into())
+        .unwrap()
+        .to_lowercase(),
+      SHA256_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hash_sha256_hex($1)", rusqlite::types::Value::Null),
+      None
+    );
+
+    let digest: Vec<u8> = conn
+      .query_row("SELECT hash_sha256($1)", params!(b"abc"), |row| row.get(0))
+      .unwrap();
+    assert_eq!(digest.len(), 32);
+
+    assert!(conn
+      .query_row("SELECT hash_sha256(42)", (), |row| row.get::<_, Vec<u8>>(0))
+      .is_err());
+  }
+
+  #[test]
+  fn test_hash_blake3() {
+    let conn = crate::connect().unwrap();
+
+    let hex_of = |query: &str, arg: rusqlite::types::Value| -> Option<String> {
+      conn.query_row(query, [arg], |row| row.get(0)).unwrap()
+    };
+
+    assert_eq!(
+      hex_of("SELECT hash_blake3_hex($1)", "".to_string().into()).unwrap(),
+      BLAKE3_EMPTY
+    );
+    assert_eq!(
+      hex_of("SELECT hash_blake3_hex($1)", "abc".to_string().into()).unwrap(),
+      BLAKE3_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hash_blake3_hex($1)", b"abc".to_vec().into()).unwrap(),
+      BLAKE3_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hex(hash_blake3($1))", "abc".to_string().into())
+        .unwrap()
+        .to_lowercase(),
+      BLAKE3_ABC
+    );
+    assert_eq!(
+      hex_of("SELECT hash_blake3($1)", rusqlite::types::Value::Null),
+      None
+    );
+
+    let digest: Vec<u8> = conn
+      .query_row("SELECT hash_blake3($1)", params!(b"abc"), |row| row.get(0))
+      .unwrap();
+    assert_eq!(digest.len(), 32);
+  }
+}
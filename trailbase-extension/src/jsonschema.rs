@@ -1,4 +1,4 @@
-use jsonschema::Validator;
+use jsonschema::{Retrieve, Uri, Validator};
 use lru::LruCache;
 use parking_lot::Mutex;
 use rusqlite::functions::Context;
@@ -25,7 +25,7 @@ impl SchemaEntry {
     schema: serde_json::Value,
     custom_validator: Option<CustomValidatorFn>,
   ) -> Result<Self, ValidationError> {
-    let validator = Validator::new(&schema)?;
+    let validator = compile_schema(&schema)?;
 
     return Ok(Self {
       schema,
@@ -38,6 +38,50 @@ impl SchemaEntry {
 static SCHEMA_REGISTRY: LazyLock<Mutex<HashMap<String, SchemaEntry>>> =
   LazyLock::new(|| Mutex::new(HashMap::<String, SchemaEntry>::new()));
 
+/// URI scheme for referencing other registered schemas by name, e.g.
+/// `{ "$ref": "schema://other-schema" }`.
+pub const SCHEMA_REF_SCHEME: &str = "schema";
+
+/// Resolves `$ref`s to registered schemas. Referenced schemas have to be registered before the
+/// referencing schema is compiled.
+struct RegistryRetriever;
+
+impl Retrieve for RegistryRetriever {
+  fn retrieve(
+    &self,
+    uri: &Uri<&str>,
+  ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    if uri.scheme().as_str() != SCHEMA_REF_SCHEME {
+      return Err(format!("Unsupported reference: {}", uri.as_str()).into());
+    }
+    let Some(name) = uri.authority().map(|a| a.as_str()) else {
+      return Err(format!("Missing schema name: {}", uri.as_str()).into());
+    };
+
+    // NOTE: URI hosts get normalized to lower case, thus fall back to case-insensitive matching.
+    let registry = SCHEMA_REGISTRY.lock();
+    let entry = registry.get(name).or_else(|| {
+      registry
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, entry)| entry)
+    });
+
+    return match entry {
+      Some(entry) => Ok(entry.schema.clone()),
+      None => Err(format!("Schema {name} not found").into()),
+    };
+  }
+}
+
+/// Compiles the given schema resolving references to registered schemas, see
+/// [SCHEMA_REF_SCHEME].
+pub fn compile_schema(schema: &serde_json::Value) -> Result<Validator, ValidationError> {
+  return jsonschema::options()
+    .with_retriever(RegistryRetriever)
+    .build(schema);
+}
+
 #[allow(unused)]
 fn cstr_to_string(ptr: *const ffi::c_char) -> String {
   assert!(!ptr.is_null());
@@ -178,7 +222,7 @@ pub(crate) fn jsonschema_matches(context: &Context) -> rusqlite::Result<bool> {
     None => {
       let schema = serde_json::from_str(pattern)
         .map_err(|err| Error::UserFunctionError(format!("Invalid JSON Schema: {err}").into()))?;
-      let validator = Validator::new(&schema).map_err(|err| {
+      let validator = compile_schema(&schema).map_err(|err| {
         Error::UserFunctionError(format!("Failed to compile Schema: {err}").into())
       })?;
 
@@ -8,6 +8,11 @@ pub mod jsonschema;
 pub mod maxminddb;
 pub mod password;
 
+pub use groups::with_current_user_groups;
+
+mod email;
+mod groups;
+mod hash;
 mod uuid;
 mod validators;
 
@@ -52,6 +57,58 @@ pub fn sqlite3_extension_init(db: rusqlite::Connection) -> rusqlite::Result<rusq
     password::hash_password_sqlite,
   )?;
 
+  // General-purpose digests, e.g. for content addressing or integrity checks.
+  db.create_scalar_function(
+    "hash_sha256",
+    1,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    hash::hash_sha256,
+  )?;
+  db.create_scalar_function(
+    "hash_sha256_hex",
+    1,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    hash::hash_sha256_hex,
+  )?;
+  db.create_scalar_function(
+    "hash_blake3",
+    1,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    hash::hash_blake3,
+  )?;
+  db.create_scalar_function(
+    "hash_blake3_hex",
+    1,
+    FunctionFlags::SQLITE_DETERMINISTIC | FunctionFlags::SQLITE_INNOCUOUS,
+    hash::hash_blake3_hex,
+  )?;
+
+  // Email utilities, e.g. for normalized UNIQUE constraints or generated columns.
+  db.create_scalar_function(
+    "normalize_email",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    email::normalize_email_sqlite,
+  )?;
+  db.create_scalar_function(
+    "normalize_email",
+    2,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    email::normalize_email_sqlite,
+  )?;
+  db.create_scalar_function(
+    "domain_of_email",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    email::domain_of_email,
+  )?;
+
   // Match column against given JSON schema, e.g. jsonschema_matches(col, '<schema>').
   db.create_scalar_function(
     "jsonschema_matches",
@@ -98,6 +155,46 @@ pub fn sqlite3_extension_init(db: rusqlite::Connection) -> rusqlite::Result<rusq
       | FunctionFlags::SQLITE_INNOCUOUS,
     validators::is_email,
   )?;
+  db.create_scalar_function(
+    "is_phone",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_phone,
+  )?;
+  db.create_scalar_function(
+    "is_url",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_url,
+  )?;
+  db.create_scalar_function(
+    "is_ip",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_ip,
+  )?;
+  db.create_scalar_function(
+    "is_ip4",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_ip4,
+  )?;
+  db.create_scalar_function(
+    "is_ip6",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_ip6,
+  )?;
   // NOTE: there's also https://sqlite.org/json1.html#jvalid
   db.create_scalar_function(
     "is_json",
@@ -107,6 +204,30 @@ pub fn sqlite3_extension_init(db: rusqlite::Connection) -> rusqlite::Result<rusq
       | FunctionFlags::SQLITE_INNOCUOUS,
     validators::is_json,
   )?;
+  db.create_scalar_function(
+    "is_credit_card",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_credit_card,
+  )?;
+  db.create_scalar_function(
+    "is_credit_card_type",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_credit_card_type,
+  )?;
+  db.create_scalar_function(
+    "is_iban",
+    1,
+    FunctionFlags::SQLITE_UTF8
+      | FunctionFlags::SQLITE_DETERMINISTIC
+      | FunctionFlags::SQLITE_INNOCUOUS,
+    validators::is_iban,
+  )?;
 
   db.create_scalar_function(
     "geoip_country",
@@ -115,6 +236,15 @@ pub fn sqlite3_extension_init(db: rusqlite::Connection) -> rusqlite::Result<rusq
     maxminddb::geoip_country,
   )?;
 
+  // NOTE: Not deterministic, since the result depends on the user on whose behalf a statement is
+  // run, see `with_current_user_groups`. Innocuous so that it can be used in VIEWs.
+  db.create_scalar_function(
+    "current_user_in_group",
+    1,
+    FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+    groups::current_user_in_group,
+  )?;
+
   return Ok(db);
 }
 
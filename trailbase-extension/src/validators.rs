@@ -4,7 +4,9 @@ use regex::Regex;
 use rusqlite::functions::Context;
 use rusqlite::types::ValueRef;
 use rusqlite::Error;
+use std::net::IpAddr;
 use std::num::NonZeroUsize;
+use std::str::FromStr;
 use std::sync::LazyLock;
 use validator::ValidateEmail;
 
@@ -76,6 +78,191 @@ pub(super) fn is_json(context: &Context) -> rusqlite::Result<bool> {
   };
 }
 
+/// Validates phone numbers in E.164 format, e.g. "+4915112345678".
+pub(super) fn is_phone(context: &Context) -> rusqlite::Result<bool> {
+  static PHONE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\+[1-9]\d{1,14}$").unwrap());
+
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return match context.get_raw(0).as_str_or_null()? {
+    None => Ok(true),
+    Some(str) => Ok(PHONE_RE.is_match(str)),
+  };
+}
+
+pub(super) fn is_url(context: &Context) -> rusqlite::Result<bool> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return match context.get_raw(0).as_str_or_null()? {
+    None => Ok(true),
+    Some(str) => Ok(url::Url::parse(str).is_ok()),
+  };
+}
+
+fn parse_ip(context: &Context) -> rusqlite::Result<Option<Option<IpAddr>>> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return Ok(
+    context
+      .get_raw(0)
+      .as_str_or_null()?
+      .map(|str| IpAddr::from_str(str).ok()),
+  );
+}
+
+pub(super) fn is_ip(context: &Context) -> rusqlite::Result<bool> {
+  return match parse_ip(context)? {
+    None => Ok(true),
+    Some(addr) => Ok(addr.is_some()),
+  };
+}
+
+pub(super) fn is_ip4(context: &Context) -> rusqlite::Result<bool> {
+  return match parse_ip(context)? {
+    None => Ok(true),
+    Some(addr) => Ok(matches!(addr, Some(IpAddr::V4(_)))),
+  };
+}
+
+pub(super) fn is_ip6(context: &Context) -> rusqlite::Result<bool> {
+  return match parse_ip(context)? {
+    None => Ok(true),
+    Some(addr) => Ok(matches!(addr, Some(IpAddr::V6(_)))),
+  };
+}
+
+/// Extracts the digits of a card number, allowing for space and hyphen separators.
+fn card_digits(number: &str) -> Option<Vec<u8>> {
+  let mut digits = Vec::with_capacity(number.len());
+  for c in number.chars() {
+    match c {
+      '0'..='9' => digits.push(c as u8 - b'0'),
+      ' ' | '-' => {}
+      _ => return None,
+    }
+  }
+
+  if !(12..=19).contains(&digits.len()) {
+    return None;
+  }
+  return Some(digits);
+}
+
+fn luhn(digits: &[u8]) -> bool {
+  let sum: u32 = digits
+    .iter()
+    .rev()
+    .enumerate()
+    .map(|(i, &d)| {
+      let d = d as u32;
+      if i % 2 == 1 {
+        if d > 4 {
+          2 * d - 9
+        } else {
+          2 * d
+        }
+      } else {
+        d
+      }
+    })
+    .sum();
+  return sum % 10 == 0;
+}
+
+/// Validates credit card numbers using the Luhn checksum.
+pub(super) fn is_credit_card(context: &Context) -> rusqlite::Result<bool> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return match context.get_raw(0).as_str_or_null()? {
+    None => Ok(true),
+    Some(str) => Ok(card_digits(str).is_some_and(|digits| luhn(&digits))),
+  };
+}
+
+/// Returns the card network, e.g. "visa", based on the issuer identification number or NULL if the
+/// number is invalid or the network unknown.
+pub(super) fn is_credit_card_type(context: &Context) -> rusqlite::Result<Option<&'static str>> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  let Some(str) = context.get_raw(0).as_str_or_null()? else {
+    return Ok(None);
+  };
+  let Some(digits) = card_digits(str).filter(|digits| luhn(digits)) else {
+    return Ok(None);
+  };
+
+  let prefix = |n: usize| -> u32 {
+    return digits[..n].iter().fold(0, |acc, &d| acc * 10 + d as u32);
+  };
+
+  return Ok(match (prefix(1), prefix(2), prefix(3), prefix(4)) {
+    (4, _, _, _) => Some("visa"),
+    (_, 34 | 37, _, _) => Some("amex"),
+    (_, 51..=55, _, _) | (_, _, _, 2221..=2720) => Some("mastercard"),
+    (_, 65, _, _) | (_, _, 644..=649, _) | (_, _, _, 6011) => Some("discover"),
+    (_, _, _, 3528..=3589) => Some("jcb"),
+    (_, 36 | 38 | 39, _, _) | (_, _, 300..=305, _) => Some("diners"),
+    (_, 62, _, _) => Some("unionpay"),
+    _ => None,
+  });
+}
+
+/// Validates International Bank Account Numbers (ISO 13616) using the ISO 7064 MOD 97-10 checksum.
+/// Spaces are ignored.
+pub(super) fn is_iban(context: &Context) -> rusqlite::Result<bool> {
+  #[cfg(debug_assertions)]
+  if context.len() != 1 {
+    return Err(Error::InvalidParameterCount(context.len(), 1));
+  }
+
+  return match context.get_raw(0).as_str_or_null()? {
+    None => Ok(true),
+    Some(str) => Ok(valid_iban(str)),
+  };
+}
+
+fn valid_iban(iban: &str) -> bool {
+  let iban: Vec<u8> = iban
+    .bytes()
+    .filter(|b| *b != b' ')
+    .map(|b| b.to_ascii_uppercase())
+    .collect();
+
+  if !(15..=34).contains(&iban.len())
+    || !iban[..2].iter().all(u8::is_ascii_uppercase)
+    || !iban[2..4].iter().all(u8::is_ascii_digit)
+    || !iban.iter().all(u8::is_ascii_alphanumeric)
+  {
+    return false;
+  }
+
+  // Move the country code and check digits to the end and replace letters with 10 to 35.
+  let mut remainder: u32 = 0;
+  for &b in iban[4..].iter().chain(&iban[..4]) {
+    remainder = if b.is_ascii_digit() {
+      (remainder * 10 + (b - b'0') as u32) % 97
+    } else {
+      (remainder * 100 + (b - b'A' + 10) as u32) % 97
+    };
+  }
+  return remainder == 1;
+}
+
 #[cfg(test)]
 mod tests {
   use rusqlite::params;
@@ -123,6 +310,177 @@ mod tests {
     assert!(conn.execute(QUERY, [""]).is_err());
   }
 
+  #[test]
+  fn test_is_phone() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          phone                  TEXT CHECK(is_phone(phone))
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    const QUERY: &str = "INSERT INTO test (phone) VALUES ($1)";
+    conn.execute(QUERY, ["+4915112345678"]).unwrap();
+    conn.execute(QUERY, ["+12"]).unwrap();
+    conn.execute(QUERY, [rusqlite::types::Value::Null]).unwrap();
+
+    for invalid in [
+      "",
+      "4915112345678",
+      "+0123456",
+      "+1",
+      "+1234567890123456",
+      "+49 151 12345678",
+      "+49abc",
+    ] {
+      assert!(conn.execute(QUERY, [invalid]).is_err(), "{invalid}");
+    }
+  }
+
+  #[test]
+  fn test_is_url() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          url                    TEXT CHECK(is_url(url))
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    const QUERY: &str = "INSERT INTO test (url) VALUES ($1)";
+    conn.execute(QUERY, ["https://trailbase.io"]).unwrap();
+    conn
+      .execute(QUERY, ["http://localhost:4000/api/records/v1/?a=b#c"])
+      .unwrap();
+    conn.execute(QUERY, ["mailto:foo@bar.com"]).unwrap();
+    conn.execute(QUERY, [rusqlite::types::Value::Null]).unwrap();
+
+    for invalid in ["", "trailbase.io", "/relative/path", "http://"] {
+      assert!(conn.execute(QUERY, [invalid]).is_err(), "{invalid}");
+    }
+  }
+
+  #[test]
+  fn test_is_ip() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          ip                     TEXT CHECK(is_ip(ip)),
+          ip4                    TEXT CHECK(is_ip4(ip4)),
+          ip6                    TEXT CHECK(is_ip6(ip6))
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    let insert = |col: &str, value: &str| -> rusqlite::Result<usize> {
+      conn.execute(&format!("INSERT INTO test ({col}) VALUES ($1)"), [value])
+    };
+
+    insert("ip", "127.0.0.1").unwrap();
+    insert("ip", "::1").unwrap();
+    insert("ip", "2001:db8::8a2e:370:7334").unwrap();
+    assert!(insert("ip", "").is_err());
+    assert!(insert("ip", "256.0.0.1").is_err());
+    assert!(insert("ip", "localhost").is_err());
+
+    insert("ip4", "192.168.0.1").unwrap();
+    assert!(insert("ip4", "::1").is_err());
+    assert!(insert("ip4", "1.2.3").is_err());
+
+    insert("ip6", "fe80::1").unwrap();
+    assert!(insert("ip6", "10.0.0.1").is_err());
+    assert!(insert("ip6", "fe80:::1").is_err());
+
+    conn
+      .execute(
+        "INSERT INTO test (ip, ip4, ip6) VALUES (NULL, NULL, NULL)",
+        (),
+      )
+      .unwrap();
+  }
+
+  #[test]
+  fn test_is_credit_card() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          card                   TEXT CHECK(is_credit_card(card))
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    const QUERY: &str = "INSERT INTO test (card) VALUES ($1)";
+    for valid in [
+      "4111111111111111",
+      "4111 1111 1111 1111",
+      "5555-5555-5555-4444",
+      "378282246310005",
+      "6011111111111117",
+    ] {
+      conn.execute(QUERY, [valid]).unwrap();
+    }
+    conn.execute(QUERY, [rusqlite::types::Value::Null]).unwrap();
+
+    for invalid in [
+      "",
+      "4111111111111112",
+      "411111111111111a",
+      "41111",
+      "4111111111111111111111",
+    ] {
+      assert!(conn.execute(QUERY, [invalid]).is_err(), "{invalid}");
+    }
+
+    let card_type = |number: &str| -> Option<String> {
+      return conn
+        .query_row("SELECT is_credit_card_type($1)", [number], |row| row.get(0))
+        .unwrap();
+    };
+    assert_eq!(card_type("4111111111111111").as_deref(), Some("visa"));
+    assert_eq!(card_type("5555555555554444").as_deref(), Some("mastercard"));
+    assert_eq!(card_type("2223003122003222").as_deref(), Some("mastercard"));
+    assert_eq!(card_type("378282246310005").as_deref(), Some("amex"));
+    assert_eq!(card_type("6011111111111117").as_deref(), Some("discover"));
+    assert_eq!(card_type("3530111333300000").as_deref(), Some("jcb"));
+    assert_eq!(card_type("30569309025904").as_deref(), Some("diners"));
+    assert_eq!(card_type("4111111111111112"), None);
+  }
+
+  #[test]
+  fn test_is_iban() {
+    let conn = crate::connect().unwrap();
+    let create_table = r#"
+        CREATE TABLE test (
+          iban                   TEXT CHECK(is_iban(iban))
+        ) STRICT;
+      "#;
+    conn.execute(create_table, ()).unwrap();
+
+    const QUERY: &str = "INSERT INTO test (iban) VALUES ($1)";
+    for valid in [
+      "GB82WEST12345698765432",
+      "GB82 WEST 1234 5698 7654 32",
+      "DE89370400440532013000",
+      "FR1420041010050500013M02606",
+      "NL91ABNA0417164300",
+      "gb82west12345698765432",
+    ] {
+      conn.execute(QUERY, [valid]).unwrap();
+    }
+    conn.execute(QUERY, [rusqlite::types::Value::Null]).unwrap();
+
+    for invalid in [
+      "",
+      "GB82WEST12345698765431",
+      "GB82WEST1234569876543!",
+      "1282WEST12345698765432",
+      "GB82",
+    ] {
+      assert!(conn.execute(QUERY, [invalid]).is_err(), "{invalid}");
+    }
+  }
+
   #[test]
   fn test_regexp() {
     let conn = crate::connect().unwrap();
@@ -0,0 +1,120 @@
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use std::collections::HashSet;
+
+/// Operation SQLite asks to authorize while preparing a statement, see
+/// [SQLite docs](https://www.sqlite.org/c3ref/set_authorizer.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuthorizerAction<'c> {
+  pub action: AuthAction<'c>,
+  /// Name of the database, e.g. "main", if applicable.
+  pub database_name: Option<&'c str>,
+  /// Inner-most trigger or view responsible for the access or `None` for top-level statements.
+  pub accessor: Option<&'c str>,
+}
+
+impl<'c> AuthorizerAction<'c> {
+  /// Returns the table read from, if any.
+  pub fn read_table(&self) -> Option<&'c str> {
+    return match self.action {
+      AuthAction::Read { table_name, .. } => Some(table_name),
+      _ => None,
+    };
+  }
+
+  /// Returns the table modified, if any, i.e. for inserts, updates, deletes as well as altering
+  /// and dropping tables.
+  pub fn write_table(&self) -> Option<&'c str> {
+    return match self.action {
+      AuthAction::Insert { table_name }
+      | AuthAction::Update { table_name, .. }
+      | AuthAction::Delete { table_name }
+      | AuthAction::AlterTable { table_name, .. }
+      | AuthAction::DropTable { table_name }
+      | AuthAction::DropTempTable { table_name } => Some(table_name),
+      _ => None,
+    };
+  }
+}
+
+impl<'c> From<AuthContext<'c>> for AuthorizerAction<'c> {
+  fn from(context: AuthContext<'c>) -> Self {
+    return Self {
+      action: context.action,
+      database_name: context.database_name,
+      accessor: context.accessor,
+    };
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorizationResult {
+  Allow,
+  /// Silently skip the operation, e.g. columns denied reading are read as NULL.
+  Ignore,
+  /// Fail preparing the statement with `SQLITE_AUTH`.
+  Deny,
+}
+
+impl From<AuthorizationResult> for Authorization {
+  fn from(result: AuthorizationResult) -> Self {
+    return match result {
+      AuthorizationResult::Allow => Self::Allow,
+      AuthorizationResult::Ignore => Self::Ignore,
+      AuthorizationResult::Deny => Self::Deny,
+    };
+  }
+}
+
+/// Builder for table-level authorizers to be installed with
+/// [`crate::Connection::set_authorizer`].
+///
+/// Table names are matched case-insensitively.
+#[derive(Clone, Debug, Default)]
+pub struct TablePolicy {
+  readable: Option<HashSet<String>>,
+  read_only: HashSet<String>,
+}
+
+impl TablePolicy {
+  pub fn new() -> Self {
+    return Self::default();
+  }
+
+  /// Restricts reads to the given tables, i.e. reading from any other table is denied. All tables
+  /// are readable by default. Can be called repeatedly to allow additional tables.
+  pub fn allow_read<S: AsRef<str>>(mut self, tables: impl IntoIterator<Item = S>) -> Self {
+    self
+      .readable
+      .get_or_insert_with(HashSet::new)
+      .extend(tables.into_iter().map(|t| t.as_ref().to_lowercase()));
+    return self;
+  }
+
+  /// Denies writes to the given tables, see [`AuthorizerAction::write_table`].
+  pub fn deny_write<S: AsRef<str>>(mut self, tables: impl IntoIterator<Item = S>) -> Self {
+    self
+      .read_only
+      .extend(tables.into_iter().map(|t| t.as_ref().to_lowercase()));
+    return self;
+  }
+
+  pub fn build(
+    self,
+  ) -> impl Fn(AuthorizerAction<'_>) -> AuthorizationResult + Send + Sync + 'static {
+    return move |action: AuthorizerAction<'_>| -> AuthorizationResult {
+      if let (Some(readable), Some(table)) = (&self.readable, action.read_table()) {
+        if !readable.contains(&table.to_lowercase()) {
+          return AuthorizationResult::Deny;
+        }
+      }
+
+      if let Some(table) = action.write_table() {
+        if self.read_only.contains(&table.to_lowercase()) {
+          return AuthorizationResult::Deny;
+        }
+      }
+
+      return AuthorizationResult::Allow;
+    };
+  }
+}
@@ -1,14 +1,20 @@
 use crossbeam_channel::{Receiver, Sender};
 use rusqlite::fallible_iterator::FallibleIterator;
-use rusqlite::hooks::{Action, PreUpdateCase};
+use rusqlite::hooks::{Action, AuthContext, Authorization, PreUpdateCase};
 use rusqlite::types::Value;
+use serde::Serialize;
 use std::{
   fmt::{self, Debug},
+  path::{Path, PathBuf},
   sync::Arc,
+  time::{Duration, Instant},
 };
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::sync::oneshot;
 
+use crate::authorizer::{AuthorizationResult, AuthorizerAction};
 use crate::error::Error;
+use crate::extension::{connect_sqlite_with_options, ConnectOptions};
 pub use crate::params::Params;
 use crate::rows::{columns, Column};
 pub use crate::rows::{Row, Rows};
@@ -36,6 +42,10 @@ macro_rules! named_params {
 /// The result returned on method calls in this crate.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Number of pages copied per backup step, i.e. between progress callbacks.
+const BACKUP_PAGES_PER_STEP: std::ffi::c_int = 128;
+const BACKUP_BUSY_BACKOFF: Duration = Duration::from_millis(250);
+
 type CallFn = Box<dyn FnOnce(&mut rusqlite::Connection) + Send + 'static>;
 
 enum Message {
@@ -47,13 +57,45 @@ enum Message {
 #[derive(Clone)]
 pub struct Connection {
   sender: Sender<Message>,
+  readonly: bool,
 }
 
 impl Connection {
   pub fn from_conn(conn: rusqlite::Connection) -> Result<Self> {
+    let readonly = conn.is_readonly(rusqlite::DatabaseName::Main)?;
+
     let (sender, receiver) = crossbeam_channel::unbounded::<Message>();
     std::thread::spawn(move || event_loop(conn, receiver));
-    return Ok(Self { sender });
+    return Ok(Self { sender, readonly });
+  }
+
+  /// Open a read-only connection to an existing SQLite database, e.g. a periodically copied
+  /// replica.
+  ///
+  /// # Failure
+  ///
+  /// Will return `Err` if the database does not exist or the underlying SQLite open call fails.
+  pub fn open_readonly(path: PathBuf) -> Result<Self> {
+    return Self::from_conn(connect_sqlite_with_options(
+      Some(path),
+      None,
+      ConnectOptions {
+        readonly: true,
+        ..Default::default()
+      },
+    )?);
+  }
+
+  /// Whether the connection rejects writes with [`Error::ReadOnly`].
+  pub fn is_readonly(&self) -> bool {
+    return self.readonly;
+  }
+
+  fn check_writable(&self) -> Result<()> {
+    if self.readonly {
+      return Err(Error::ReadOnly);
+    }
+    return Ok(());
   }
 
   /// Open a new connection to an in-memory SQLite database.
@@ -86,7 +128,19 @@ impl Connection {
       })))
       .map_err(|_| Error::ConnectionClosed)?;
 
-    receiver.await.map_err(|_| Error::ConnectionClosed)?
+    let result = receiver.await.map_err(|_| Error::ConnectionClosed)?;
+    if self.readonly {
+      // Writes can also sneak in through queries, e.g. "INSERT ... RETURNING".
+      return result.map_err(|err| match err {
+        Error::Rusqlite(ref inner)
+          if inner.sqlite_error_code() == Some(rusqlite::ErrorCode::ReadOnly) =>
+        {
+          Error::ReadOnly
+        }
+        err => err,
+      });
+    }
+    return result;
   }
 
   pub fn call_and_forget(&self, function: impl FnOnce(&rusqlite::Connection) + Send + 'static) {
@@ -146,6 +200,101 @@ impl Connection {
       .await;
   }
 
+  /// Query the first row with a deadline, e.g. to let interactive queries fail fast on a busy
+  /// database rather than waiting for the connection-wide busy timeout.
+  ///
+  /// The deadline covers both waiting for preceding calls and waiting for database locks. Returns
+  /// [`Error::Timeout`] when exceeded.
+  ///
+  /// NOTE: Any busy handler installed via [`Connection::set_busy_handler`] is replaced by the
+  /// previous busy timeout once the query completes.
+  pub async fn query_with_timeout<T>(
+    &self,
+    sql: &str,
+    params: impl Params + Send + 'static,
+    timeout: Duration,
+    f: impl FnOnce(&rusqlite::Row<'_>) -> std::result::Result<T, rusqlite::Error> + Send + 'static,
+  ) -> Result<Option<T>>
+  where
+    T: Send + 'static,
+  {
+    let deadline = Instant::now() + timeout;
+    let sql = sql.to_string();
+    let call = self.call(move |conn: &mut rusqlite::Connection| {
+      let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+        return Err(Error::Timeout);
+      };
+
+      let previous: u64 = conn.pragma_query_value(None, "busy_timeout", |row| row.get(0))?;
+      conn.busy_timeout(remaining)?;
+
+      let result = (|| -> rusqlite::Result<Option<T>> {
+        let mut stmt = conn.prepare(&sql)?;
+        params.bind(&mut stmt)?;
+        let mut rows = stmt.raw_query();
+        return match rows.next()? {
+          Some(row) => Ok(Some(f(row)?)),
+          None => Ok(None),
+        };
+      })();
+
+      conn.busy_timeout(Duration::from_millis(previous))?;
+
+      return result.map_err(|err| match err.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::DatabaseBusy) => Error::Timeout,
+        _ => err.into(),
+      });
+    });
+
+    return match tokio::time::timeout(timeout, call).await {
+      Ok(result) => result,
+      Err(_elapsed) => Err(Error::Timeout),
+    };
+  }
+
+  /// Install a custom busy handler, which is invoked with the number of prior invocations while
+  /// the database is locked and returns whether to keep waiting. Passing `None` removes any busy
+  /// handler including the busy timeout.
+  ///
+  /// NOTE: SQLite only supports a single busy handler, i.e. this replaces the default busy
+  /// timeout and vice versa.
+  pub async fn set_busy_handler(&self, handler: Option<fn(i32) -> bool>) -> Result<()> {
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        return Ok(conn.busy_handler(handler)?);
+      })
+      .await;
+  }
+
+  /// Install an authorizer, which is consulted for every operation while preparing statements
+  /// and may deny it, e.g. to restrict table access more robustly than by inspecting SQL. See
+  /// [`crate::authorizer::TablePolicy`] for table-level policies.
+  ///
+  /// NOTE: SQLite only supports a single authorizer, i.e. this replaces any previous one.
+  pub async fn set_authorizer(
+    &self,
+    f: impl Fn(AuthorizerAction<'_>) -> AuthorizationResult + Send + Sync + 'static,
+  ) -> Result<()> {
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        conn.authorizer(Some(move |context: AuthContext<'_>| {
+          return f(context.into()).into();
+        }));
+        return Ok(());
+      })
+      .await;
+  }
+
+  /// Remove the authorizer installed via [`Connection::set_authorizer`], if any.
+  pub async fn clear_authorizer(&self) -> Result<()> {
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        conn.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+        return Ok(());
+      })
+      .await;
+  }
+
   pub async fn query_values<T: serde::de::DeserializeOwned + Send + 'static>(
     &self,
     sql: &str,
@@ -167,8 +316,69 @@ impl Connection {
       .await;
   }
 
+  /// Returns the query plan of the given statement without executing it, see
+  /// [SQLite docs](https://www.sqlite.org/eqp.html).
+  pub async fn explain_query_plan(
+    &self,
+    sql: &str,
+    params: impl Params + Send + 'static,
+  ) -> Result<Vec<QueryPlanRow>> {
+    let sql = format!("EXPLAIN QUERY PLAN {sql}");
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        let mut stmt = conn.prepare(&sql)?;
+        params.bind(&mut stmt)?;
+        let mut rows = stmt.raw_query();
+
+        let mut plan = vec![];
+        while let Some(row) = rows.next()? {
+          plan.push(QueryPlanRow {
+            id: row.get(0)?,
+            parent: row.get(1)?,
+            detail: row.get(3)?,
+          });
+        }
+        return Ok(plan);
+      })
+      .await;
+  }
+
+  /// Returns the bytecode program of the given statement without executing it, see
+  /// [SQLite docs](https://www.sqlite.org/opcode.html).
+  pub async fn explain(
+    &self,
+    sql: &str,
+    params: impl Params + Send + 'static,
+  ) -> Result<Vec<ExplainRow>> {
+    let sql = format!("EXPLAIN {sql}");
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        let mut stmt = conn.prepare(&sql)?;
+        params.bind(&mut stmt)?;
+        let mut rows = stmt.raw_query();
+
+        let mut program = vec![];
+        while let Some(row) = rows.next()? {
+          program.push(ExplainRow {
+            addr: row.get(0)?,
+            opcode: row.get(1)?,
+            p1: row.get(2)?,
+            p2: row.get(3)?,
+            p3: row.get(4)?,
+            p4: row.get(5)?,
+            p5: row.get(6)?,
+            comment: row.get(7)?,
+          });
+        }
+        return Ok(program);
+      })
+      .await;
+  }
+
   /// Execute SQL statement.
   pub async fn execute(&self, sql: &str, params: impl Params + Send + 'static) -> Result<usize> {
+    self.check_writable()?;
+
     let sql = sql.to_string();
     return self
       .call(move |conn: &mut rusqlite::Connection| {
@@ -181,6 +391,8 @@ impl Connection {
 
   /// Batch execute SQL statements and return rows of last statement.
   pub async fn execute_batch(&self, sql: &str) -> Result<Option<Rows>> {
+    self.check_writable()?;
+
     let sql = sql.to_string();
     return self
       .call(move |conn: &mut rusqlite::Connection| {
@@ -212,6 +424,133 @@ impl Connection {
       .await;
   }
 
+  /// Checkpoints the WAL, i.e. moves its content into the main database file.
+  ///
+  /// Returns the number of pages in the WAL and the number of pages that were checkpointed. The
+  /// latter may be smaller if concurrent readers or writers prevented a complete checkpoint.
+  pub async fn checkpoint(&self, mode: WalCheckpointMode) -> Result<(u32, u32)> {
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        // NOTE: The first column is a "busy" flag, which is set when a RESTART or TRUNCATE
+        // checkpoint could not complete. This is reflected in the page counts.
+        return Ok(conn.query_row(
+          &format!("PRAGMA wal_checkpoint({})", mode.as_str()),
+          (),
+          |row| {
+            let log: i64 = row.get(1)?;
+            let checkpointed: i64 = row.get(2)?;
+            // NOTE: Both are -1 for databases that aren't in WAL mode.
+            return Ok((log.max(0) as u32, checkpointed.max(0) as u32));
+          },
+        )?);
+      })
+      .await;
+  }
+
+  /// Creates a consistent copy of the main database at `dest` using SQLite's online backup API,
+  /// i.e. it's safe to call while the database is being written to.
+  ///
+  /// The optional `progress` callback is invoked with the number of remaining and total pages
+  /// after every step.
+  pub async fn backup_to_path(
+    &self,
+    dest: &Path,
+    progress: Option<Box<dyn Fn(u32, u32) + Send>>,
+  ) -> Result<()> {
+    let dest = dest.to_path_buf();
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        let mut dest_conn = rusqlite::Connection::open(dest)?;
+        let backup = rusqlite::backup::Backup::new(conn, &mut dest_conn)?;
+
+        loop {
+          let result = backup.step(BACKUP_PAGES_PER_STEP)?;
+          if let Some(ref progress) = progress {
+            let p = backup.progress();
+            progress(p.remaining.max(0) as u32, p.pagecount.max(0) as u32);
+          }
+
+          match result {
+            rusqlite::backup::StepResult::Done => return Ok(()),
+            rusqlite::backup::StepResult::More => {}
+            // Busy or locked, i.e. back off and retry.
+            _ => std::thread::sleep(BACKUP_BUSY_BACKOFF),
+          }
+        }
+      })
+      .await;
+  }
+
+  /// Like [`Connection::backup_to_path`] but streams the backup to the given writer, e.g. stdout
+  /// or an upload.
+  ///
+  /// NOTE: The backup is staged in a temporary file, since SQLite's backup API requires a
+  /// database as destination.
+  pub async fn backup_to_writer(&self, mut writer: impl AsyncWrite + Send + Unpin) -> Result<()> {
+    let tmp =
+      std::env::temp_dir().join(format!("trailbase_backup_{}.sqlite", uuid::Uuid::new_v4()));
+
+    let result = async {
+      self.backup_to_path(&tmp, None).await?;
+
+      let mut file = tokio::fs::File::open(&tmp)
+        .await
+        .map_err(|err| Error::Other(err.into()))?;
+      tokio::io::copy(&mut file, &mut writer)
+        .await
+        .map_err(|err| Error::Other(err.into()))?;
+      writer
+        .flush()
+        .await
+        .map_err(|err| Error::Other(err.into()))?;
+      return Ok(());
+    }
+    .await;
+
+    let _ = tokio::fs::remove_file(&tmp).await;
+    return result;
+  }
+
+  /// Starts a named savepoint, which is rolled back when the returned guard is dropped without
+  /// being released. Savepoints can be nested within transactions and other savepoints.
+  ///
+  /// NOTE: Statements are executed on a single, shared connection. Any statement executed through
+  /// a clone of this connection while the savepoint is active becomes part of it.
+  pub async fn savepoint(&self, name: &str) -> Result<Savepoint> {
+    self.check_writable()?;
+    validate_savepoint_name(name)?;
+
+    self.execute(&format!("SAVEPOINT {name}"), ()).await?;
+
+    return Ok(Savepoint {
+      conn: self.clone(),
+      name: name.to_string(),
+      done: false,
+    });
+  }
+
+  /// Runs `f` within a named savepoint, which is released if `f` succeeds and rolled back
+  /// otherwise.
+  pub async fn execute_in_savepoint<F, T>(&self, name: &str, f: F) -> Result<T>
+  where
+    F: FnOnce(&rusqlite::Connection) -> std::result::Result<T, rusqlite::Error> + Send + 'static,
+    T: Send + 'static,
+  {
+    self.check_writable()?;
+    validate_savepoint_name(name)?;
+
+    let name = name.to_string();
+    return self
+      .call(move |conn: &mut rusqlite::Connection| {
+        // Rolls back on drop unless committed.
+        let savepoint = conn.savepoint_with_name(name)?;
+        let value = f(&savepoint)?;
+        savepoint.commit()?;
+        return Ok(value);
+      })
+      .await;
+  }
+
   /// Convenience API for (un)setting a new pre-update hook.
   pub async fn add_preupdate_hook(
     &self,
@@ -260,6 +599,130 @@ impl Connection {
   }
 }
 
+/// Node of a query plan as returned by [`Connection::explain_query_plan`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct QueryPlanRow {
+  pub id: i64,
+  /// Id of the parent node or zero for top-level nodes.
+  pub parent: i64,
+  /// Description of the step, e.g. "SCAN table" or "SEARCH table USING INDEX ...".
+  pub detail: String,
+}
+
+/// Bytecode instruction as returned by [`Connection::explain`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ExplainRow {
+  pub addr: i64,
+  pub opcode: String,
+  pub p1: i64,
+  pub p2: i64,
+  pub p3: i64,
+  pub p4: Option<String>,
+  pub p5: i64,
+  /// Only available if SQLite was compiled with `SQLITE_ENABLE_EXPLAIN_COMMENTS`.
+  pub comment: Option<String>,
+}
+
+/// Checkpoint modes, see [SQLite docs](https://www.sqlite.org/pragma.html#pragma_wal_checkpoint).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WalCheckpointMode {
+  /// Checkpoints as many frames as possible without waiting for readers or writers.
+  #[default]
+  Passive,
+  /// Waits for writers and readers of older snapshots to complete a full checkpoint.
+  Full,
+  /// Like `Full` but additionally waits for readers so that the WAL is restarted from the
+  /// beginning.
+  Restart,
+  /// Like `Restart` but additionally truncates the WAL file to zero bytes.
+  Truncate,
+}
+
+impl WalCheckpointMode {
+  fn as_str(&self) -> &'static str {
+    return match self {
+      Self::Passive => "PASSIVE",
+      Self::Full => "FULL",
+      Self::Restart => "RESTART",
+      Self::Truncate => "TRUNCATE",
+    };
+  }
+}
+
+/// Guard of a named savepoint created by [`Connection::savepoint`].
+pub struct Savepoint {
+  conn: Connection,
+  name: String,
+  done: bool,
+}
+
+impl Savepoint {
+  pub fn name(&self) -> &str {
+    return &self.name;
+  }
+
+  /// Releases the savepoint, i.e. keeps all changes made since its creation.
+  pub async fn release(mut self) -> Result<()> {
+    self.done = true;
+    self
+      .conn
+      .execute(&format!("RELEASE {}", self.name), ())
+      .await?;
+    return Ok(());
+  }
+
+  /// Rolls back all changes made since the savepoint's creation and ends it.
+  pub async fn rollback(mut self) -> Result<()> {
+    self.done = true;
+    self
+      .conn
+      .execute_batch(&format!(
+        "ROLLBACK TO {name}; RELEASE {name};",
+        name = self.name
+      ))
+      .await?;
+    return Ok(());
+  }
+}
+
+impl Drop for Savepoint {
+  fn drop(&mut self) {
+    if self.done {
+      return;
+    }
+
+    let name = self.name.clone();
+    self.conn.call_and_forget(move |conn| {
+      if let Err(err) = conn.execute_batch(&format!("ROLLBACK TO {name}; RELEASE {name};")) {
+        log::warn!("Failed to roll back savepoint '{name}': {err}");
+      }
+    });
+  }
+}
+
+impl Debug for Savepoint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Savepoint")
+      .field("name", &self.name)
+      .finish()
+  }
+}
+
+/// Savepoint names are interpolated into SQL and thus restricted to plain identifiers.
+fn validate_savepoint_name(name: &str) -> Result<()> {
+  let valid = name
+    .chars()
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+  if !valid {
+    return Err(Error::Other(
+      format!("Invalid savepoint name: '{name}'").into(),
+    ));
+  }
+  return Ok(());
+}
+
 impl Debug for Connection {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("Connection").finish()
@@ -3,6 +3,14 @@ pub enum Error {
   #[error("Connection closed error")]
   ConnectionClosed,
 
+  /// A write was attempted on a read-only connection.
+  #[error("Read-only connection")]
+  ReadOnly,
+
+  /// A call did not complete within its deadline.
+  #[error("Timeout")]
+  Timeout,
+
   /// An error occured while closing the SQLite connection.
   /// This `Error` variant contains the [`Connection`], which can be used to retry the close
   /// operation and the underlying [`rusqlite::Error`] that made it impossible to close the
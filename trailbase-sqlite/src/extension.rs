@@ -24,10 +24,30 @@ extern "C" fn init_trailbase_extensions(
   return status;
 }
 
-#[allow(unsafe_code)]
+/// Tunables applied on top of the default pragmas when opening a connection.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+  /// Number of WAL pages after which SQLite automatically checkpoints. Defaults to SQLite's
+  /// default of 1000 pages. Zero disables automatic checkpoints, i.e. checkpoints have to be
+  /// triggered explicitly, e.g. using [`crate::Connection::checkpoint`].
+  pub wal_autocheckpoint: Option<u32>,
+  /// Opens the database read-only. Requires a path to an existing database and skips all
+  /// pragmas that would modify the database file.
+  pub readonly: bool,
+}
+
 pub fn connect_sqlite(
   path: Option<PathBuf>,
   extensions: Option<Vec<PathBuf>>,
+) -> Result<rusqlite::Connection, Error> {
+  return connect_sqlite_with_options(path, extensions, ConnectOptions::default());
+}
+
+#[allow(unsafe_code)]
+pub fn connect_sqlite_with_options(
+  path: Option<PathBuf>,
+  extensions: Option<Vec<PathBuf>>,
+  options: ConnectOptions,
 ) -> Result<rusqlite::Connection, Error> {
   crate::schema::try_init_schemas();
 
@@ -38,11 +58,19 @@ pub fn connect_sqlite(
 
   let conn = trailbase_extension::sqlite3_extension_init(if let Some(p) = path {
     use rusqlite::OpenFlags;
-    let flags = OpenFlags::SQLITE_OPEN_READ_WRITE
-      | OpenFlags::SQLITE_OPEN_CREATE
-      | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let flags = if options.readonly {
+      OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    } else {
+      OpenFlags::SQLITE_OPEN_READ_WRITE
+        | OpenFlags::SQLITE_OPEN_CREATE
+        | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    };
 
     rusqlite::Connection::open_with_flags(p, flags)?
+  } else if options.readonly {
+    return Err(Error::Other(
+      "Read-only connections require a database path".into(),
+    ));
   } else {
     rusqlite::Connection::open_in_memory()?
   })?;
@@ -69,11 +97,20 @@ pub fn connect_sqlite(
 
   // NOTE: we're querying here since some pragmas return data.
   for pragma in CONFIG {
+    // NOTE: Switching the journal mode requires writing to the database.
+    if options.readonly && pragma.contains("journal_mode") {
+      continue;
+    }
+
     let mut stmt = conn.prepare(pragma)?;
     let mut rows = stmt.query([])?;
     rows.next()?;
   }
 
+  if let Some(pages) = options.wal_autocheckpoint {
+    conn.pragma_update(None, "wal_autocheckpoint", pages)?;
+  }
+
   if let Some(extensions) = extensions {
     for path in extensions {
       unsafe { conn.load_extension(path, None)? }
@@ -81,7 +118,9 @@ pub fn connect_sqlite(
   }
 
   // Initial optimize.
-  conn.execute("PRAGMA optimize = 0x10002", ())?;
+  if !options.readonly {
+    conn.execute("PRAGMA optimize = 0x10002", ())?;
+  }
 
   return Ok(conn);
 }
@@ -12,6 +12,7 @@
 
 mod extension;
 
+pub mod authorizer;
 pub mod connection;
 pub mod error;
 pub mod geoip;
@@ -19,9 +20,10 @@ pub mod params;
 mod rows;
 pub mod schema;
 
-pub use connection::Connection;
+pub use authorizer::{AuthorizationResult, AuthorizerAction, TablePolicy};
+pub use connection::{Connection, ExplainRow, QueryPlanRow, Savepoint, WalCheckpointMode};
 pub use error::Error;
-pub use extension::connect_sqlite;
+pub use extension::{connect_sqlite, connect_sqlite_with_options, ConnectOptions};
 pub use params::{NamedParamRef, NamedParams, NamedParamsRef, Params};
 pub use rows::{Row, Rows, ValueType};
 pub use rusqlite::types::Value;
@@ -16,6 +16,8 @@ pub enum SchemaError {
   BuiltinSchema,
   #[error("Missing name")]
   MissingName,
+  #[error("Invalid JSON: {0}")]
+  InvalidJson(Arc<serde_json::Error>),
 }
 
 /// File input schema used both for multipart-form uploads (in which case the name is mapped to
@@ -164,6 +166,12 @@ pub fn get_compiled_schema(name: &str) -> Option<Arc<Validator>> {
   trailbase_extension::jsonschema::get_compiled_schema(name)
 }
 
+/// Compiles the given schema resolving references to registered schemas.
+pub fn compile_schema(schema: &serde_json::Value) -> Result<Validator, SchemaError> {
+  return trailbase_extension::jsonschema::compile_schema(schema)
+    .map_err(|err| SchemaError::JsonSchema(Arc::new(err)));
+}
+
 pub fn get_schemas() -> Vec<Schema> {
   let builtins = builtin_schemas();
   return trailbase_extension::jsonschema::get_schemas()
@@ -195,6 +203,34 @@ pub fn set_user_schema(name: &str, pattern: Option<serde_json::Value>) -> Result
   return Ok(());
 }
 
+/// Registers a user schema from its JSON representation after validating it against the JSON
+/// Schema meta-schema.
+///
+/// Schemas may reference other registered schemas, e.g. `{ "$ref": "schema://other" }`, which
+/// have to be registered first.
+pub fn register_schema(name: &str, schema_json: &str) -> Result<(), SchemaError> {
+  if name.is_empty() {
+    return Err(SchemaError::MissingName);
+  }
+
+  let schema: serde_json::Value =
+    serde_json::from_str(schema_json).map_err(|err| SchemaError::InvalidJson(Arc::new(err)))?;
+  jsonschema::meta::validate(&schema)
+    .map_err(|err| SchemaError::JsonSchema(Arc::new(err.to_owned())))?;
+
+  return set_user_schema(name, Some(schema));
+}
+
+/// Lists the names of all registered schemas, including builtins.
+pub fn list_schemas() -> Vec<String> {
+  let mut names: Vec<String> = trailbase_extension::jsonschema::get_schemas()
+    .into_iter()
+    .map(|(name, _schema)| name)
+    .collect();
+  names.sort();
+  return names;
+}
+
 lazy_static! {
   static ref INIT: std::sync::Mutex<bool> = std::sync::Mutex::new(false);
 }
@@ -275,4 +311,65 @@ mod tests {
         .is_ok());
     }
   }
+
+  #[test]
+  fn test_schema_references() {
+    let conn = crate::connect_sqlite(None, None).unwrap();
+
+    register_schema(
+      "test.Address",
+      r#"{
+        "type": "object",
+        "properties": {
+          "city": { "type": "string" }
+        },
+        "required": ["city"]
+      }"#,
+    )
+    .unwrap();
+    register_schema(
+      "test.Person",
+      r#"{
+        "type": "object",
+        "properties": {
+          "name": { "type": "string" },
+          "address": { "$ref": "schema://test.Address" }
+        },
+        "required": ["name", "address"]
+      }"#,
+    )
+    .unwrap();
+
+    let names = list_schemas();
+    assert!(names.contains(&"test.Address".to_string()));
+    assert!(names.contains(&"test.Person".to_string()));
+
+    let valid = |value: serde_json::Value| -> bool {
+      return conn
+        .query_row(
+          "SELECT jsonschema('test.Person', ?1)",
+          [value.to_string()],
+          |row| row.get(0),
+        )
+        .unwrap();
+    };
+
+    assert!(valid(json!({
+      "name": "Alice",
+      "address": { "city": "Berlin" },
+    })));
+    assert!(!valid(json!({
+      "name": "Alice",
+      "address": { "city": 5 },
+    })));
+    assert!(!valid(json!({
+      "name": "Alice",
+      "address": {},
+    })));
+
+    // Dangling references and invalid schemas are rejected.
+    assert!(register_schema("test.Dangling", r#"{ "$ref": "schema://test.Missing" }"#).is_err());
+    assert!(register_schema("test.Invalid", r#"{ "type": 5 }"#).is_err());
+    assert!(register_schema("test.Invalid", "not json").is_err());
+  }
 }
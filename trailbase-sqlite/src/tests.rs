@@ -1,9 +1,14 @@
 use rusqlite::ffi;
-use rusqlite::hooks::PreUpdateCase;
+use rusqlite::hooks::{AuthAction, PreUpdateCase};
 use serde::Deserialize;
+use std::time::Duration;
 
 use crate::connection::extract_row_id;
-use crate::{named_params, params, Connection, Error, Value, ValueType};
+use crate::{
+  connect_sqlite, connect_sqlite_with_options, named_params, params, AuthorizationResult,
+  AuthorizerAction, ConnectOptions, Connection, Error, TablePolicy, Value, ValueType,
+  WalCheckpointMode,
+};
 use rusqlite::ErrorCode;
 
 #[tokio::test]
@@ -379,9 +384,411 @@ async fn test_hooks() {
   assert_eq!(text, "foo");
 }
 
+#[tokio::test]
+async fn test_savepoint() {
+  let conn = Connection::open_in_memory().unwrap();
+  conn
+    .execute("CREATE TABLE test (id INTEGER PRIMARY KEY)", ())
+    .await
+    .unwrap();
+
+  let count = || async {
+    let rows = conn.query("SELECT COUNT(*) FROM test", ()).await.unwrap();
+    return rows.0.first().unwrap().get::<i64>(0).unwrap();
+  };
+
+  // Rolled back explicitly.
+  let savepoint = conn.savepoint("sp0").await.unwrap();
+  conn
+    .execute("INSERT INTO test (id) VALUES (1)", ())
+    .await
+    .unwrap();
+  assert_eq!(count().await, 1);
+  savepoint.rollback().await.unwrap();
+  assert_eq!(count().await, 0);
+
+  // Rolled back on drop.
+  {
+    let _savepoint = conn.savepoint("sp1").await.unwrap();
+    conn
+      .execute("INSERT INTO test (id) VALUES (1)", ())
+      .await
+      .unwrap();
+  }
+  assert_eq!(count().await, 0);
+
+  // Nested savepoints: the inner one is rolled back, the outer one released.
+  let outer = conn.savepoint("outer").await.unwrap();
+  conn
+    .execute("INSERT INTO test (id) VALUES (1)", ())
+    .await
+    .unwrap();
+  let inner = conn.savepoint("inner").await.unwrap();
+  conn
+    .execute("INSERT INTO test (id) VALUES (2)", ())
+    .await
+    .unwrap();
+  inner.rollback().await.unwrap();
+  outer.release().await.unwrap();
+  assert_eq!(count().await, 1);
+
+  assert!(conn
+    .savepoint("invalid name; DROP TABLE test")
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_execute_in_savepoint() {
+  let conn = Connection::open_in_memory().unwrap();
+  conn
+    .execute("CREATE TABLE test (id INTEGER PRIMARY KEY)", ())
+    .await
+    .unwrap();
+
+  let result = conn
+    .execute_in_savepoint("sp", |conn| {
+      conn.execute("INSERT INTO test (id) VALUES (1)", ())?;
+      // Fails due to the primary key constraint and rolls back the first insertion.
+      conn.execute("INSERT INTO test (id) VALUES (1)", ())?;
+      return Ok(());
+    })
+    .await;
+  assert!(result.is_err());
+
+  let inserted = conn
+    .execute_in_savepoint("sp", |conn| {
+      return conn.execute("INSERT INTO test (id) VALUES (2)", ());
+    })
+    .await
+    .unwrap();
+  assert_eq!(inserted, 1);
+
+  let rows = conn.query("SELECT id FROM test", ()).await.unwrap();
+  assert_eq!(rows.0.len(), 1);
+  assert_eq!(rows.0.first().unwrap().get::<i64>(0), Ok(2));
+}
+
+#[tokio::test]
+async fn test_wal_checkpoint() {
+  let dir = temp_dir::TempDir::new().unwrap();
+  let path = dir.child("main.db");
+  let wal_path = dir.child("main.db-wal");
+
+  let conn = Connection::from_conn(
+    connect_sqlite_with_options(
+      Some(path),
+      None,
+      ConnectOptions {
+        // Only checkpoint explicitly.
+        wal_autocheckpoint: Some(0),
+        ..Default::default()
+      },
+    )
+    .unwrap(),
+  )
+  .unwrap();
+
+  conn
+    .execute("CREATE TABLE test (id INTEGER PRIMARY KEY, data BLOB)", ())
+    .await
+    .unwrap();
+
+  // Write roughly 2000 pages worth of data.
+  let page_size: i64 = conn.query("PRAGMA page_size", ()).await.unwrap().0[0]
+    .get(0)
+    .unwrap();
+  for _ in 0..20 {
+    conn
+      .execute(
+        r#"
+          WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 100)
+          INSERT INTO test (data) SELECT randomblob($1) FROM seq
+        "#,
+        params!(page_size),
+      )
+      .await
+      .unwrap();
+  }
+
+  let wal_size = std::fs::metadata(&wal_path).unwrap().len();
+  assert!(wal_size >= 2000 * page_size as u64, "{wal_size}");
+
+  let (log, checkpointed) = conn.checkpoint(WalCheckpointMode::Truncate).await.unwrap();
+  assert!(log >= 2000, "{log}");
+  assert_eq!(log, checkpointed);
+
+  assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+  let rows = conn.query("SELECT COUNT(*) FROM test", ()).await.unwrap();
+  assert_eq!(rows.0[0].get::<i64>(0), Ok(2000));
+}
+
+#[tokio::test]
+async fn test_readonly() {
+  let dir = temp_dir::TempDir::new().unwrap();
+  let path = dir.child("main.db");
+
+  {
+    let conn = Connection::from_conn(connect_sqlite(Some(path.clone()), None).unwrap()).unwrap();
+    assert!(!conn.is_readonly());
+    conn
+      .execute_batch(
+        r#"
+          CREATE TABLE test (id INTEGER PRIMARY KEY);
+          INSERT INTO test (id) VALUES (1);
+        "#,
+      )
+      .await
+      .unwrap();
+    conn.close().await.unwrap();
+  }
+
+  let conn = Connection::open_readonly(path).unwrap();
+  assert!(conn.is_readonly());
+
+  let rows = conn.query("SELECT id FROM test", ()).await.unwrap();
+  assert_eq!(rows.0[0].get::<i64>(0), Ok(1));
+
+  assert!(matches!(
+    conn.execute("INSERT INTO test (id) VALUES (2)", ()).await,
+    Err(Error::ReadOnly)
+  ));
+  // Writes through the query API are rejected by SQLite.
+  assert!(matches!(
+    conn
+      .query("INSERT INTO test (id) VALUES (2) RETURNING id", ())
+      .await,
+    Err(Error::ReadOnly)
+  ));
+  assert!(matches!(conn.savepoint("sp").await, Err(Error::ReadOnly)));
+
+  assert!(Connection::open_readonly(dir.child("missing.db")).is_err());
+}
+
+#[tokio::test]
+async fn test_query_with_timeout() {
+  let dir = temp_dir::TempDir::new().unwrap();
+  let path = dir.child("main.db");
+
+  // NOTE: Use a rollback journal, since in WAL mode writers don't block readers.
+  let conn = Connection::from_conn(rusqlite::Connection::open(&path).unwrap()).unwrap();
+  conn
+    .execute_batch(
+      r#"
+        PRAGMA busy_timeout = 5000;
+        CREATE TABLE test (id INTEGER PRIMARY KEY);
+        INSERT INTO test (id) VALUES (1);
+      "#,
+    )
+    .await
+    .unwrap();
+
+  let read = || {
+    conn.query_with_timeout(
+      "SELECT id FROM test",
+      (),
+      Duration::from_millis(100),
+      |row| row.get::<_, i64>(0),
+    )
+  };
+  assert_eq!(read().await.unwrap(), Some(1));
+
+  // Lock the database from another connection.
+  let locker = rusqlite::Connection::open(&path).unwrap();
+  locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+  let start = std::time::Instant::now();
+  assert!(matches!(read().await, Err(Error::Timeout)));
+  assert!(start.elapsed() < Duration::from_secs(5));
+
+  // The connection-wide busy timeout is restored.
+  let timeout: i64 = conn.query("PRAGMA busy_timeout", ()).await.unwrap().0[0]
+    .get(0)
+    .unwrap();
+  assert_eq!(timeout, 5000);
+
+  locker.execute_batch("ROLLBACK").unwrap();
+  assert_eq!(read().await.unwrap(), Some(1));
+
+  fn never_wait(_count: i32) -> bool {
+    return false;
+  }
+  conn
+    .set_busy_handler(Some(never_wait as fn(i32) -> bool))
+    .await
+    .unwrap();
+  locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+  assert!(conn.query("SELECT id FROM test", ()).await.is_err());
+  locker.execute_batch("ROLLBACK").unwrap();
+}
+
 // The rest is boilerplate, not really that important
 #[derive(Debug, thiserror::Error)]
 enum MyError {
   #[error("MySpecificError")]
   MySpecificError,
 }
+
+#[tokio::test]
+async fn test_explain() {
+  let conn = Connection::open_in_memory().unwrap();
+  conn
+    .execute_batch(
+      r#"
+        CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT NOT NULL, value INTEGER);
+        CREATE INDEX test__name_index ON test (name);
+      "#,
+    )
+    .await
+    .unwrap();
+
+  // Full table scan.
+  let plan = conn
+    .explain_query_plan("SELECT * FROM test WHERE name LIKE $1", params!("%foo%"))
+    .await
+    .unwrap();
+  assert_eq!(plan.len(), 1, "{plan:?}");
+  assert!(plan[0].detail.starts_with("SCAN"), "{plan:?}");
+  assert!(!plan[0].detail.contains("USING INDEX"), "{plan:?}");
+
+  let plan = conn
+    .explain_query_plan("SELECT * FROM test WHERE name = $1", params!("foo"))
+    .await
+    .unwrap();
+  assert!(
+    plan[0].detail.contains("USING INDEX test__name_index"),
+    "{plan:?}"
+  );
+
+  // Explaining doesn't execute the statement.
+  let program = conn
+    .explain("INSERT INTO test (name) VALUES ($1)", params!("foo"))
+    .await
+    .unwrap();
+  assert_eq!(program[0].opcode, "Init");
+  assert!(program.iter().any(|row| row.opcode == "Insert"));
+  let rows = conn.query("SELECT COUNT(*) FROM test", ()).await.unwrap();
+  assert_eq!(rows.0.first().unwrap().get::<i64>(0).unwrap(), 0);
+
+  assert!(conn
+    .explain_query_plan("SELECT * FROM missing", ())
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_backup() {
+  let dir = temp_dir::TempDir::new().unwrap();
+  let conn =
+    Connection::from_conn(connect_sqlite(Some(dir.child("main.db")), None).unwrap()).unwrap();
+  conn
+    .execute_batch(
+      r#"
+        CREATE TABLE test (id INTEGER PRIMARY KEY, data BLOB NOT NULL);
+        WITH RECURSIVE series(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM series WHERE n < 1024)
+        INSERT INTO test (data) SELECT randomblob(1024) FROM series;
+      "#,
+    )
+    .await
+    .unwrap();
+
+  let count = |conn: rusqlite::Connection| -> i64 {
+    return conn
+      .query_row("SELECT COUNT(*) FROM test", (), |row| row.get(0))
+      .unwrap();
+  };
+
+  let progress = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+  let backup_path = dir.child("backup.db");
+  {
+    let progress = progress.clone();
+    conn
+      .backup_to_path(
+        &backup_path,
+        Some(Box::new(move |remaining, total| {
+          progress.lock().unwrap().push((remaining, total));
+        })),
+      )
+      .await
+      .unwrap();
+  }
+  assert_eq!(
+    count(rusqlite::Connection::open(&backup_path).unwrap()),
+    1024
+  );
+
+  let progress = progress.lock().unwrap();
+  assert!(progress.len() > 1, "{progress:?}");
+  let (remaining, total) = *progress.last().unwrap();
+  assert_eq!(remaining, 0);
+  assert!(total > 0);
+
+  let mut buffer: Vec<u8> = vec![];
+  conn.backup_to_writer(&mut buffer).await.unwrap();
+  let stream_path = dir.child("stream.db");
+  std::fs::write(&stream_path, &buffer).unwrap();
+  assert_eq!(
+    count(rusqlite::Connection::open(&stream_path).unwrap()),
+    1024
+  );
+}
+
+#[tokio::test]
+async fn test_authorizer() {
+  let conn = Connection::open_in_memory().unwrap();
+  conn
+    .execute_batch(
+      r#"
+        CREATE TABLE _user (id INTEGER PRIMARY KEY, email TEXT);
+        CREATE TABLE post (id INTEGER PRIMARY KEY, body TEXT);
+        CREATE TABLE secret (id INTEGER PRIMARY KEY, value TEXT);
+      "#,
+    )
+    .await
+    .unwrap();
+
+  let policy = TablePolicy::new()
+    .allow_read(["_user", "post"])
+    .deny_write(["_user"])
+    .build();
+  assert_eq!(
+    policy(AuthorizerAction {
+      action: AuthAction::Insert {
+        table_name: "_USER"
+      },
+      database_name: Some("main"),
+      accessor: None,
+    }),
+    AuthorizationResult::Deny
+  );
+  conn.set_authorizer(policy).await.unwrap();
+
+  let is_denied = |result: Result<usize, Error>| match result {
+    Err(Error::Rusqlite(err)) => {
+      err.sqlite_error_code() == Some(ErrorCode::AuthorizationForStatementDenied)
+    }
+    _ => false,
+  };
+
+  assert!(is_denied(
+    conn
+      .execute("INSERT INTO _user (email) VALUES ('foo@bar.org')", ())
+      .await
+  ));
+  assert!(is_denied(conn.execute("DELETE FROM _user", ()).await));
+  conn
+    .execute("INSERT INTO post (body) VALUES ('hi')", ())
+    .await
+    .unwrap();
+
+  assert!(conn.query("SELECT email FROM _user", ()).await.is_ok());
+  assert!(conn.query("SELECT value FROM secret", ()).await.is_err());
+
+  conn.clear_authorizer().await.unwrap();
+  conn
+    .execute("INSERT INTO _user (email) VALUES ('foo@bar.org')", ())
+    .await
+    .unwrap();
+  assert!(conn.query("SELECT value FROM secret", ()).await.is_ok());
+}